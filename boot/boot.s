@@ -1,4 +1,6 @@
 .global _start
+.global stack_bottom
+.global stack_top
 
 .section .multiboot
 .align 4
@@ -9,7 +11,17 @@
 .section .text
 .align 4
 _start:
+    mov %eax, %esi         # Keep the Multiboot magic
+    mov $stack_bottom, %edi # Paint the stack for its usage watermark
+    mov $0x57AC57AC, %eax  # (STACK_PAINT in src/mm/kstack.rs)
+    mov $4096, %ecx        # 16 KB in dwords
+    cld
+    rep stosl
+    mov %esi, %eax
     mov $stack_top, %esp   # Setup stack
+    xor %ebp, %ebp         # End of the frame chain for stack traces
+    push %ebx              # Multiboot information structure
+    push %eax              # Multiboot magic
     call rust_main         # Call rust function
 
 halt:
@@ -0,0 +1,8 @@
+# Boot script, loaded by GRUB as a module (see grub.cfg) and run by the
+# shell before the first prompt.
+
+BOOT_SCRIPT=init.sh
+export BOOT_SCRIPT
+
+# Keep a copy of the boot messages in a named buffer: `cat boot.log`
+dmesg > boot.log
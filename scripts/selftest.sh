@@ -0,0 +1,73 @@
+# Shell self-test, embedded in the kernel: run it with `source selftest.sh`.
+
+FAILED=
+
+check() {
+    if [ "$1" = "$2" ]; then
+        return 0
+    fi
+    echo "FAIL: $3: got '$1', expected '$2'"
+    FAILED=yes
+    return 1
+}
+
+# Variables and quoting
+NAME=tacos
+check "$NAME" tacos "assignment"
+check "${NAME}!" "tacos!" "braces"
+check '$NAME' '$NAME' "single quotes"
+show_x() {
+    check "$X" 1 "prefix assignment"
+}
+X=1 show_x
+check "$X" "" "prefix assignment is temporary"
+
+# Exit status and lists
+true && check $? 0 "true"
+false || check $? 1 "false"
+false && check never ran "&& skips on failure"
+
+# if / elif / else and test
+if [ 3 -lt 2 ]; then R=a; elif [ 3 -eq 3 ]; then R=b; else R=c; fi
+check $R b "elif"
+if test -z "$UNSET_VAR" && [ ! -n "" ]; then R=empty; fi
+check $R empty "test -z / -n"
+
+# for loops, break and continue
+L=
+for x in 1 2 3 4 5; do
+    if [ $x = 2 ]; then continue; fi
+    if [ $x = 4 ]; then break; fi
+    L=$L$x
+done
+check $L 13 "for/break/continue"
+
+# while and until loops
+N=
+while [ "$N" != ... ]; do N=$N.; done
+check $N ... "while"
+until [ "$N" = "" ]; do N=; done
+check "$N" "" "until"
+
+# Functions, parameters and return
+args() {
+    check $# 2 "argument count"
+    check "$2" two "second argument"
+    return 3
+}
+args one two
+check $? 3 "return status"
+
+# Pipelines and redirection into builtins
+for w in pear apple fig; do echo $w; done | sort > selftest.out
+head -n 1 selftest.out | grep apple > selftest.first
+check $? 0 "pipeline"
+buffers -d selftest.out
+buffers -d selftest.first
+
+if [ -z "$FAILED" ]; then
+    echo "selftest: all checks passed"
+else
+    echo "selftest: some checks failed"
+    false
+fi
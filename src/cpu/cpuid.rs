@@ -0,0 +1,263 @@
+/// CPU identification through the `cpuid` instruction.
+///
+/// `detect` runs once at boot and caches the identification leaves;
+/// `has` then answers feature queries without executing `cpuid` again.
+/// CPUs older than the late 486 lack the instruction, in which case no
+/// feature is reported.
+
+use core::arch::asm;
+use core::arch::x86::{CpuidResult, __cpuid_count};
+
+const EFLAGS_ID: u32 = 1 << 21;
+
+/// Register holding a feature flag.
+#[derive(Copy, Clone)]
+enum Reg {
+    /// Leaf 1, EDX
+    Edx,
+    /// Leaf 1, ECX
+    Ecx,
+    /// Leaf 0x80000001, EDX
+    ExtEdx,
+    /// Leaf 0x80000001, ECX
+    ExtEcx,
+}
+
+/// A CPUID feature flag.
+pub struct Feature {
+    pub name: &'static str,
+    reg: Reg,
+    bit: u32,
+}
+
+const fn feature(name: &'static str, reg: Reg, bit: u32) -> Feature {
+    Feature { name, reg, bit }
+}
+
+pub const FPU: Feature = feature("fpu", Reg::Edx, 0);
+pub const VME: Feature = feature("vme", Reg::Edx, 1);
+pub const DE: Feature = feature("de", Reg::Edx, 2);
+pub const PSE: Feature = feature("pse", Reg::Edx, 3);
+pub const TSC: Feature = feature("tsc", Reg::Edx, 4);
+pub const MSR: Feature = feature("msr", Reg::Edx, 5);
+pub const PAE: Feature = feature("pae", Reg::Edx, 6);
+pub const MCE: Feature = feature("mce", Reg::Edx, 7);
+pub const CX8: Feature = feature("cx8", Reg::Edx, 8);
+pub const APIC: Feature = feature("apic", Reg::Edx, 9);
+pub const SEP: Feature = feature("sep", Reg::Edx, 11);
+pub const MTRR: Feature = feature("mtrr", Reg::Edx, 12);
+pub const PGE: Feature = feature("pge", Reg::Edx, 13);
+pub const MCA: Feature = feature("mca", Reg::Edx, 14);
+pub const CMOV: Feature = feature("cmov", Reg::Edx, 15);
+pub const PAT: Feature = feature("pat", Reg::Edx, 16);
+pub const PSE36: Feature = feature("pse36", Reg::Edx, 17);
+pub const CLFLUSH: Feature = feature("clflush", Reg::Edx, 19);
+pub const MMX: Feature = feature("mmx", Reg::Edx, 23);
+pub const FXSR: Feature = feature("fxsr", Reg::Edx, 24);
+pub const SSE: Feature = feature("sse", Reg::Edx, 25);
+pub const SSE2: Feature = feature("sse2", Reg::Edx, 26);
+pub const HTT: Feature = feature("htt", Reg::Edx, 28);
+pub const SSE3: Feature = feature("sse3", Reg::Ecx, 0);
+pub const SSSE3: Feature = feature("ssse3", Reg::Ecx, 9);
+pub const SSE4_1: Feature = feature("sse4_1", Reg::Ecx, 19);
+pub const SSE4_2: Feature = feature("sse4_2", Reg::Ecx, 20);
+pub const X2APIC: Feature = feature("x2apic", Reg::Ecx, 21);
+pub const POPCNT: Feature = feature("popcnt", Reg::Ecx, 23);
+pub const AES: Feature = feature("aes", Reg::Ecx, 25);
+pub const XSAVE: Feature = feature("xsave", Reg::Ecx, 26);
+pub const AVX: Feature = feature("avx", Reg::Ecx, 28);
+pub const HYPERVISOR: Feature = feature("hypervisor", Reg::Ecx, 31);
+pub const SYSCALL: Feature = feature("syscall", Reg::ExtEdx, 11);
+pub const NX: Feature = feature("nx", Reg::ExtEdx, 20);
+pub const LM: Feature = feature("lm", Reg::ExtEdx, 29);
+pub const LAHF_LM: Feature = feature("lahf_lm", Reg::ExtEcx, 0);
+
+/// Every known feature, in the order `cpuinfo` lists them.
+pub static FEATURES: &[Feature] = &[
+    FPU, VME, DE, PSE, TSC, MSR, PAE, MCE, CX8, APIC, SEP, MTRR, PGE, MCA, CMOV, PAT,
+    PSE36, CLFLUSH, MMX, FXSR, SSE, SSE2, HTT, SSE3, SSSE3, SSE4_1, SSE4_2, X2APIC,
+    POPCNT, AES, XSAVE, AVX, HYPERVISOR, SYSCALL, NX, LM, LAHF_LM,
+];
+
+/// Identification data read at boot.
+pub struct CpuInfo {
+    vendor: [u8; 12],
+    brand: [u8; 48],
+    brand_len: usize,
+    /// Highest standard leaf.
+    pub max_leaf: u32,
+    /// Highest extended leaf (0x8000_0000 and up), 0 if none.
+    pub max_ext_leaf: u32,
+    /// Display family (base + extended).
+    pub family: u32,
+    /// Display model (base + extended).
+    pub model: u32,
+    pub stepping: u32,
+    edx: u32,
+    ecx: u32,
+    ext_edx: u32,
+    ext_ecx: u32,
+}
+
+impl CpuInfo {
+    /// Vendor string, e.g. `GenuineIntel` or `AuthenticAMD`.
+    pub fn vendor(&self) -> &[u8] {
+        &self.vendor
+    }
+
+    /// Brand string, empty if the CPU has none.
+    pub fn brand(&self) -> &[u8] {
+        unsafe { self.brand.get_unchecked(..self.brand_len) }
+    }
+
+    /// Returns `true` if the vendor is `vendor`.
+    pub fn is_vendor(&self, vendor: &[u8; 12]) -> bool {
+        // Folded with `|` rather than `&&`: LLVM turns a chain of byte
+        // comparisons into a `memcmp` call, which the kernel does not link
+        let diff = self.vendor.iter().zip(vendor.iter()).fold(0, |acc, (a, b)| acc | (a ^ b));
+        diff == 0
+    }
+}
+
+static mut INFO: Option<CpuInfo> = None;
+
+/// Returns `true` if `cpuid` exists, i.e. EFLAGS.ID can be toggled.
+pub fn is_supported() -> bool {
+    let before: u32;
+    let after: u32;
+    unsafe {
+        asm!(
+            "pushfd",
+            "pop {before}",
+            "mov {after}, {before}",
+            "xor {after}, {id}",
+            "push {after}",
+            "popfd",
+            "pushfd",
+            "pop {after}",
+            // Restore the original flags
+            "push {before}",
+            "popfd",
+            before = out(reg) before,
+            after = out(reg) after,
+            id = const EFLAGS_ID,
+        );
+    }
+    (before ^ after) & EFLAGS_ID != 0
+}
+
+/// Executes `cpuid` for a leaf and sub-leaf.
+pub fn cpuid(leaf: u32, subleaf: u32) -> CpuidResult {
+    unsafe { __cpuid_count(leaf, subleaf) }
+}
+
+/// Reads the identification leaves and caches them for `info` and `has`.
+/// Returns `None` if the CPU has no `cpuid` instruction.
+pub fn detect() -> Option<&'static CpuInfo> {
+    if !is_supported() {
+        return None;
+    }
+
+    let leaf0 = cpuid(0, 0);
+    let mut vendor = [0u8; 12];
+    for (i, reg) in [leaf0.ebx, leaf0.edx, leaf0.ecx].iter().enumerate() {
+        for (j, &byte) in reg.to_le_bytes().iter().enumerate() {
+            unsafe { *vendor.get_unchecked_mut(i * 4 + j) = byte; }
+        }
+    }
+
+    let mut info = CpuInfo {
+        vendor,
+        brand: [0; 48],
+        brand_len: 0,
+        max_leaf: leaf0.eax,
+        max_ext_leaf: 0,
+        family: 0,
+        model: 0,
+        stepping: 0,
+        edx: 0,
+        ecx: 0,
+        ext_edx: 0,
+        ext_ecx: 0,
+    };
+
+    if info.max_leaf >= 1 {
+        let leaf1 = cpuid(1, 0);
+        let base_family = (leaf1.eax >> 8) & 0xF;
+        let base_model = (leaf1.eax >> 4) & 0xF;
+        info.stepping = leaf1.eax & 0xF;
+        info.family = base_family;
+        info.model = base_model;
+        if base_family == 0xF {
+            info.family += (leaf1.eax >> 20) & 0xFF;
+        }
+        if base_family == 0x6 || base_family == 0xF {
+            info.model += ((leaf1.eax >> 16) & 0xF) << 4;
+        }
+        info.edx = leaf1.edx;
+        info.ecx = leaf1.ecx;
+    }
+
+    let ext = cpuid(0x8000_0000, 0).eax;
+    if ext >= 0x8000_0000 {
+        info.max_ext_leaf = ext;
+    }
+    if info.max_ext_leaf >= 0x8000_0001 {
+        let leaf = cpuid(0x8000_0001, 0);
+        info.ext_edx = leaf.edx;
+        info.ext_ecx = leaf.ecx;
+    }
+    if info.max_ext_leaf >= 0x8000_0004 {
+        read_brand(&mut info);
+    }
+
+    unsafe {
+        INFO = Some(info);
+        (*core::ptr::addr_of!(INFO)).as_ref()
+    }
+}
+
+/// Brand string from leaves 0x80000002..4, without its padding.
+fn read_brand(info: &mut CpuInfo) {
+    let mut len = 0;
+    for leaf in 0x8000_0002..=0x8000_0004 {
+        let r = cpuid(leaf, 0);
+        for reg in [r.eax, r.ebx, r.ecx, r.edx] {
+            for byte in reg.to_le_bytes() {
+                unsafe { *info.brand.get_unchecked_mut(len) = byte; }
+                len += 1;
+            }
+        }
+    }
+    let mut start = 0;
+    while start < len && unsafe { *info.brand.get_unchecked(start) } == b' ' {
+        start += 1;
+    }
+    let mut end = start;
+    while end < len && unsafe { *info.brand.get_unchecked(end) } != 0 {
+        end += 1;
+    }
+    for i in start..end {
+        unsafe { *info.brand.get_unchecked_mut(i - start) = *info.brand.get_unchecked(i); }
+    }
+    info.brand_len = end - start;
+}
+
+/// Identification data, `None` before `detect` or without `cpuid`.
+pub fn info() -> Option<&'static CpuInfo> {
+    unsafe { (*core::ptr::addr_of!(INFO)).as_ref() }
+}
+
+/// Returns `true` if the CPU reports `feature`.
+pub fn has(feature: &Feature) -> bool {
+    let Some(info) = info() else {
+        return false;
+    };
+    let reg = match feature.reg {
+        Reg::Edx => info.edx,
+        Reg::Ecx => info.ecx,
+        Reg::ExtEdx => info.ext_edx,
+        Reg::ExtEcx => info.ext_ecx,
+    };
+    reg & (1 << feature.bit) != 0
+}
@@ -0,0 +1,260 @@
+/// Interrupt Descriptor Table: handlers for CPU exceptions and IRQs.
+///
+/// The 32 CPU exception vectors are all fatal for now. The stub of each
+/// vector (`global_asm!` below) pushes a dummy error code where the CPU
+/// pushes none, then the vector number, and calls `exception_handler`,
+/// which logs the exception at emerg level, flushes the console in panic
+/// mode and halts.
+///
+/// The 16 IRQ vectors follow, where `pic::init` remaps them. Their stubs
+/// save the registers, push the IRQ number and call `irq_handler`, which
+/// runs the driver of the line and acknowledges it to the PIC.
+///
+/// The double fault vector is a task gate instead, so that its handler
+/// runs on a stack of its own (see `gdt::tss`). It tells a kernel stack
+/// overflow, where the fault address or the stack pointer lies in a
+/// guard page (see `mm::kstack`), from other double faults.
+
+use core::arch::{asm, global_asm};
+
+use crate::cpu::{irq, regs};
+use crate::drivers::{mouse, pic};
+use crate::gdt::tss::{self, Tss};
+use crate::io::klog;
+use crate::mm::kstack::{self, Stack};
+use crate::pr_emerg;
+
+const EXCEPTIONS: usize = 32;
+const VECTORS: usize = EXCEPTIONS + pic::IRQS;
+const DOUBLE_FAULT: u32 = 8;
+const PAGE_FAULT: u32 = 14;
+
+const KERNEL_CODE_SELECTOR: u16 = 0x08;
+const KERNEL_DATA_SELECTOR: u32 = 0x10;
+const KERNEL_STACK_SELECTOR: u32 = 0x18;
+
+/// Type and attributes: present, DPL 0, 32-bit interrupt gate.
+const INTERRUPT_GATE: u8 = 0x8E;
+/// Type and attributes: present, DPL 0, task gate.
+const TASK_GATE: u8 = 0x85;
+
+const DOUBLE_FAULT_STACK_SIZE: usize = 8192;
+
+static EXCEPTION_NAMES: [&str; EXCEPTIONS] = [
+    "divide error", "debug", "non-maskable interrupt", "breakpoint",
+    "overflow", "bound range exceeded", "invalid opcode", "device not available",
+    "double fault", "coprocessor segment overrun", "invalid TSS", "segment not present",
+    "stack-segment fault", "general protection fault", "page fault", "reserved",
+    "x87 floating-point error", "alignment check", "machine check", "SIMD floating-point error",
+    "virtualization exception", "control protection exception", "reserved", "reserved",
+    "reserved", "reserved", "reserved", "reserved",
+    "hypervisor injection", "VMM communication", "security exception", "reserved",
+];
+
+/// 8-byte gate descriptor.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct IdtEntry {
+    offset_low: u16,
+    selector: u16,
+    zero: u8,
+    type_attr: u8,
+    offset_high: u16,
+}
+
+impl IdtEntry {
+    const fn missing() -> Self {
+        IdtEntry { offset_low: 0, selector: 0, zero: 0, type_attr: 0, offset_high: 0 }
+    }
+
+    fn interrupt_gate(handler: u32) -> Self {
+        IdtEntry {
+            offset_low: handler as u16,
+            selector: KERNEL_CODE_SELECTOR,
+            zero: 0,
+            type_attr: INTERRUPT_GATE,
+            offset_high: (handler >> 16) as u16,
+        }
+    }
+
+    fn task_gate(tss_selector: u16) -> Self {
+        IdtEntry { offset_low: 0, selector: tss_selector, zero: 0, type_attr: TASK_GATE, offset_high: 0 }
+    }
+}
+
+/// IDTR contents for `lidt`.
+#[repr(C, packed)]
+struct IdtPointer {
+    limit: u16,
+    base: u32,
+}
+
+/// What the stubs leave on the stack for `exception_handler`.
+#[repr(C)]
+struct ExceptionFrame {
+    vector: u32,
+    error_code: u32,
+    eip: u32,
+    cs: u32,
+    eflags: u32,
+}
+
+static mut IDT: [IdtEntry; VECTORS] = [IdtEntry::missing(); VECTORS];
+static mut DOUBLE_FAULT_STACK: [u8; DOUBLE_FAULT_STACK_SIZE] = [0; DOUBLE_FAULT_STACK_SIZE];
+
+extern "C" {
+    /// Addresses of the exception stubs, by vector, then of the IRQ stubs.
+    static exception_stubs: [u32; VECTORS];
+    fn double_fault_entry() -> !;
+}
+
+// Vectors 8, 10-14, 17, 21, 29 and 30 come with an error code.
+global_asm!(
+    ".macro exception_stub vector, error_code",
+    "exception_\\vector:",
+    ".if \\error_code == 0",
+    "    push 0",
+    ".endif",
+    "    push \\vector",
+    "    jmp exception_common",
+    ".endm",
+    ".irp vector, 0,1,2,3,4,5,6,7,9,15,16,18,19,20,22,23,24,25,26,27,28,31",
+    "exception_stub \\vector, 0",
+    ".endr",
+    ".irp vector, 8,10,11,12,13,14,17,21,29,30",
+    "exception_stub \\vector, 1",
+    ".endr",
+    "exception_common:",
+    "    push esp",
+    "    call {handler}",
+    // Entered by a task switch, with the error code on top of the stack
+    ".global double_fault_entry",
+    "double_fault_entry:",
+    "    call {double_fault}",
+    // IRQs return to the interrupted code: save every general register
+    ".macro irq_stub irq",
+    "irq_\\irq:",
+    "    pushad",
+    "    cld",
+    "    push \\irq",
+    "    call {irq_handler}",
+    "    add esp, 4",
+    "    popad",
+    "    iretd",
+    ".endm",
+    ".irp irq, 0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15",
+    "irq_stub \\irq",
+    ".endr",
+    ".section .rodata",
+    ".global exception_stubs",
+    ".align 4",
+    "exception_stubs:",
+    ".irp vector, 0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31",
+    "    .long exception_\\vector",
+    ".endr",
+    ".irp irq, 0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15",
+    "    .long irq_\\irq",
+    ".endr",
+    ".text",
+    handler = sym exception_handler,
+    double_fault = sym double_fault_handler,
+    irq_handler = sym irq_handler,
+);
+
+/// Installs the exception and IRQ handlers. Call after `gdt::init` and
+/// once paging is set up: the double fault task loads the current CR3.
+pub fn init() {
+    let stack_base = core::ptr::addr_of!(DOUBLE_FAULT_STACK) as usize;
+    kstack::paint(stack_base, DOUBLE_FAULT_STACK_SIZE);
+    kstack::register(Stack { name: "double-fault", base: stack_base, size: DOUBLE_FAULT_STACK_SIZE, guarded: false });
+
+    unsafe {
+        *core::ptr::addr_of_mut!(tss::DOUBLE_FAULT) = Tss {
+            cr3: regs::read_cr3(),
+            eip: double_fault_entry as usize as u32,
+            // Reserved bit 1 set, interrupts off
+            eflags: 0x2,
+            esp: (stack_base + DOUBLE_FAULT_STACK_SIZE) as u32,
+            cs: KERNEL_CODE_SELECTOR as u32,
+            ss: KERNEL_STACK_SELECTOR,
+            ds: KERNEL_DATA_SELECTOR,
+            es: KERNEL_DATA_SELECTOR,
+            fs: KERNEL_DATA_SELECTOR,
+            gs: KERNEL_DATA_SELECTOR,
+            ..tss::DOUBLE_FAULT
+        };
+
+        let idt = &mut *core::ptr::addr_of_mut!(IDT);
+        let stubs = &*core::ptr::addr_of!(exception_stubs);
+        for (entry, &stub) in idt.iter_mut().zip(stubs.iter()) {
+            *entry = IdtEntry::interrupt_gate(stub);
+        }
+        *idt.get_unchecked_mut(DOUBLE_FAULT as usize) = IdtEntry::task_gate(tss::DOUBLE_FAULT_SELECTOR);
+
+        let idt_ptr = IdtPointer {
+            limit: (core::mem::size_of::<[IdtEntry; VECTORS]>() - 1) as u16,
+            base: idt.as_ptr() as u32,
+        };
+        asm!("lidt [{}]", in(reg) &idt_ptr, options(nostack));
+    }
+}
+
+/// Flushes the log to the console and stops the CPU for good.
+fn die() -> ! {
+    klog::enter_panic_mode();
+    loop {
+        unsafe { asm!("cli", "hlt", options(nomem, nostack)); }
+    }
+}
+
+extern "C" fn exception_handler(frame: &ExceptionFrame) -> ! {
+    irq::enter();
+    let name = EXCEPTION_NAMES.get(frame.vector as usize).copied().unwrap_or("unknown exception");
+    pr_emerg!("{} (vector {}, error code {:#x}) at eip {:#010x}", name, frame.vector, frame.error_code, frame.eip);
+    if frame.vector == PAGE_FAULT {
+        let addr = regs::read_cr2() as usize;
+        match kstack::overflowed(addr) {
+            Some(stack) => pr_emerg!("kernel stack overflow: {:#010x} is in the guard page of the {} stack", addr, stack.name),
+            None => pr_emerg!(
+                "{} of {:#010x}: {}",
+                if frame.error_code & 0x2 != 0 { "write" } else { "read" },
+                addr,
+                if frame.error_code & 0x1 != 0 { "protection violation" } else { "page not present" }
+            ),
+        }
+    }
+    die()
+}
+
+extern "C" fn double_fault_handler(_error_code: u32) -> ! {
+    irq::enter();
+    // State of the faulting code, saved by the task switch
+    let faulting = unsafe { *core::ptr::addr_of!(tss::MAIN) };
+    let addr = regs::read_cr2() as usize;
+    let esp = faulting.esp as usize;
+    match kstack::overflowed(esp).or_else(|| kstack::overflowed(addr)) {
+        Some(stack) => pr_emerg!(
+            "kernel stack overflow on the {} stack: esp {:#010x}, fault address {:#010x}, eip {:#010x}",
+            stack.name, esp, addr, faulting.eip
+        ),
+        None => pr_emerg!("double fault at eip {:#010x}, esp {:#010x}", faulting.eip, esp),
+    }
+    die()
+}
+
+extern "C" fn irq_handler(irq: u32) {
+    let irq = irq as u8;
+    if pic::is_spurious(irq) {
+        // The master did raise IRQ2 for a spurious IRQ15
+        if irq == 15 {
+            pic::eoi(2);
+        }
+        return;
+    }
+    irq::enter();
+    if irq == mouse::IRQ {
+        mouse::handle_irq();
+    }
+    pic::eoi(irq);
+    irq::exit();
+}
@@ -0,0 +1,68 @@
+/// Interrupt flag control and interrupt-context tracking.
+///
+/// Interrupts are enabled once the drivers are set up; the only IRQ
+/// unmasked is the mouse's (IRQ12, see `idt::irq_handler`). This is the
+/// contract for code shared with handlers: data a handler may also touch
+/// is updated between `save_disable` and `restore`, and handlers wrap
+/// their body in `enter` / `exit` so that `in_interrupt` can tell code to
+/// defer slow work (printk's console output) to normal context.
+
+use core::arch::asm;
+
+use crate::cpu::regs;
+
+const EFLAGS_IF: u32 = 1 << 9;
+
+/// Nesting depth of interrupt handlers currently running.
+static mut DEPTH: u32 = 0;
+
+/// Returns `true` if maskable interrupts are enabled.
+pub fn enabled() -> bool {
+    regs::read_eflags() & EFLAGS_IF != 0
+}
+
+pub fn disable() {
+    unsafe { asm!("cli", options(nomem, nostack)); }
+}
+
+pub fn enable() {
+    unsafe { asm!("sti", options(nomem, nostack)); }
+}
+
+/// Disables interrupts and returns whether they were enabled, for
+/// `restore`. Pairs nest.
+pub fn save_disable() -> bool {
+    let was_enabled = enabled();
+    disable();
+    was_enabled
+}
+
+/// Re-enables interrupts if `save_disable` found them enabled.
+pub fn restore(was_enabled: bool) {
+    if was_enabled {
+        enable();
+    }
+}
+
+/// Runs `f` with interrupts disabled.
+pub fn without<T>(f: impl FnOnce() -> T) -> T {
+    let was_enabled = save_disable();
+    let result = f();
+    restore(was_enabled);
+    result
+}
+
+/// Marks the start of an interrupt handler.
+pub fn enter() {
+    unsafe { DEPTH += 1; }
+}
+
+/// Marks the end of an interrupt handler.
+pub fn exit() {
+    unsafe { DEPTH = DEPTH.saturating_sub(1); }
+}
+
+/// Returns `true` while an interrupt handler runs.
+pub fn in_interrupt() -> bool {
+    unsafe { DEPTH > 0 }
+}
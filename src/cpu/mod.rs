@@ -0,0 +1,46 @@
+pub mod cpuid;
+pub mod idt;
+pub mod irq;
+pub mod regs;
+
+pub use cpuid::has;
+
+use crate::{pr_warn, printkln};
+
+/// Identifies the CPU and enables the optional features it supports.
+///
+/// Must run before anything calls `cpuid::has` or `cpuid::info`.
+pub fn init() {
+    let Some(info) = cpuid::detect() else {
+        pr_warn!("cpu: no CPUID instruction, keeping the reset configuration");
+        return;
+    };
+    printkln!(
+        "cpu: {} family {} model {} stepping {}",
+        info.vendor(),
+        info.family,
+        info.model,
+        info.stepping
+    );
+
+    let mut cr0 = regs::read_cr0();
+    let mut cr4 = regs::read_cr4();
+    // 4 MiB pages and global pages, ready for when paging is turned on
+    if has(&cpuid::PSE) {
+        cr4 |= regs::CR4_PSE;
+        printkln!("cpu: enabled PSE");
+    }
+    if has(&cpuid::PGE) {
+        cr4 |= regs::CR4_PGE;
+        printkln!("cpu: enabled PGE");
+    }
+    // fxsave/fxrstor and SSE instructions. The kernel itself is built
+    // without SSE; this only stops them from faulting.
+    if has(&cpuid::FXSR) && has(&cpuid::SSE) {
+        cr0 = (cr0 & !regs::CR0_EM) | regs::CR0_MP;
+        cr4 |= regs::CR4_OSFXSR | regs::CR4_OSXMMEXCPT;
+        printkln!("cpu: enabled SSE");
+    }
+    regs::write_cr0(cr0);
+    regs::write_cr4(cr4);
+}
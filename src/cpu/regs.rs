@@ -0,0 +1,154 @@
+/// Control, flags, segment and descriptor-table registers.
+///
+/// Thin wrappers around the privileged `mov crN` / `sgdt` / `sidt`
+/// instructions, plus the bit names used to decode them.
+
+use core::arch::asm;
+
+pub const CR0_PE: u32 = 1 << 0;
+pub const CR0_MP: u32 = 1 << 1;
+pub const CR0_EM: u32 = 1 << 2;
+pub const CR0_TS: u32 = 1 << 3;
+pub const CR0_PG: u32 = 1 << 31;
+
+pub const CR4_PSE: u32 = 1 << 4;
+pub const CR4_PAE: u32 = 1 << 5;
+pub const CR4_PGE: u32 = 1 << 7;
+pub const CR4_OSFXSR: u32 = 1 << 9;
+pub const CR4_OSXMMEXCPT: u32 = 1 << 10;
+
+/// Names of the CR0 bits, for decoding.
+pub static CR0_BITS: &[(u32, &str)] = &[
+    (0, "PE"), (1, "MP"), (2, "EM"), (3, "TS"), (4, "ET"), (5, "NE"),
+    (16, "WP"), (18, "AM"), (29, "NW"), (30, "CD"), (31, "PG"),
+];
+
+/// Names of the CR4 bits, for decoding.
+pub static CR4_BITS: &[(u32, &str)] = &[
+    (0, "VME"), (1, "PVI"), (2, "TSD"), (3, "DE"), (4, "PSE"), (5, "PAE"),
+    (6, "MCE"), (7, "PGE"), (8, "PCE"), (9, "OSFXSR"), (10, "OSXMMEXCPT"),
+];
+
+/// Names of the EFLAGS bits, for decoding (IOPL is printed separately).
+pub static EFLAGS_BITS: &[(u32, &str)] = &[
+    (0, "CF"), (2, "PF"), (4, "AF"), (6, "ZF"), (7, "SF"), (8, "TF"), (9, "IF"),
+    (10, "DF"), (11, "OF"), (14, "NT"), (16, "RF"), (17, "VM"), (18, "AC"), (21, "ID"),
+];
+
+/// Contents of GDTR or IDTR.
+#[derive(Copy, Clone)]
+pub struct DescriptorTable {
+    pub base: u32,
+    pub limit: u16,
+}
+
+impl DescriptorTable {
+    /// Number of 8-byte descriptors covered by the limit.
+    pub fn entries(&self) -> u32 {
+        (self.limit as u32 + 1) / 8
+    }
+
+    fn from_bytes(buf: &[u8; 6]) -> Self {
+        let [l0, l1, b0, b1, b2, b3] = *buf;
+        DescriptorTable {
+            base: u32::from_le_bytes([b0, b1, b2, b3]),
+            limit: u16::from_le_bytes([l0, l1]),
+        }
+    }
+}
+
+pub fn read_cr0() -> u32 {
+    let value: u32;
+    unsafe { asm!("mov {}, cr0", out(reg) value, options(nomem, nostack)); }
+    value
+}
+
+pub fn write_cr0(value: u32) {
+    unsafe { asm!("mov cr0, {}", in(reg) value, options(nostack)); }
+}
+
+/// Address that caused the last page fault.
+pub fn read_cr2() -> u32 {
+    let value: u32;
+    unsafe { asm!("mov {}, cr2", out(reg) value, options(nomem, nostack)); }
+    value
+}
+
+pub fn read_cr3() -> u32 {
+    let value: u32;
+    unsafe { asm!("mov {}, cr3", out(reg) value, options(nomem, nostack)); }
+    value
+}
+
+/// Loads a page directory, flushing the non-global TLB entries.
+pub fn write_cr3(value: u32) {
+    unsafe { asm!("mov cr3, {}", in(reg) value, options(nostack)); }
+}
+
+/// Flushes the TLB entry of the page holding `addr`.
+pub fn invlpg(addr: usize) {
+    unsafe { asm!("invlpg [{}]", in(reg) addr, options(nostack)); }
+}
+
+pub fn read_cr4() -> u32 {
+    let value: u32;
+    unsafe { asm!("mov {}, cr4", out(reg) value, options(nomem, nostack)); }
+    value
+}
+
+pub fn write_cr4(value: u32) {
+    unsafe { asm!("mov cr4, {}", in(reg) value, options(nostack)); }
+}
+
+pub fn read_eflags() -> u32 {
+    let value: u32;
+    unsafe { asm!("pushfd", "pop {}", out(reg) value, options(nomem, preserves_flags)); }
+    value
+}
+
+/// Time-stamp counter: CPU cycles since reset. Requires `cpuid::TSC`.
+pub fn read_tsc() -> u64 {
+    let (low, high): (u32, u32);
+    unsafe { asm!("rdtsc", out("eax") low, out("edx") high, options(nomem, nostack, preserves_flags)); }
+    ((high as u64) << 32) | low as u64
+}
+
+/// Segment selectors, in the order CS, DS, ES, FS, GS, SS.
+pub fn read_selectors() -> [u16; 6] {
+    let (cs, ds, es, fs, gs, ss): (u16, u16, u16, u16, u16, u16);
+    unsafe {
+        asm!("mov {:x}, cs", out(reg) cs, options(nomem, nostack, preserves_flags));
+        asm!("mov {:x}, ds", out(reg) ds, options(nomem, nostack, preserves_flags));
+        asm!("mov {:x}, es", out(reg) es, options(nomem, nostack, preserves_flags));
+        asm!("mov {:x}, fs", out(reg) fs, options(nomem, nostack, preserves_flags));
+        asm!("mov {:x}, gs", out(reg) gs, options(nomem, nostack, preserves_flags));
+        asm!("mov {:x}, ss", out(reg) ss, options(nomem, nostack, preserves_flags));
+    }
+    [cs, ds, es, fs, gs, ss]
+}
+
+/// Reads back GDTR.
+pub fn sgdt() -> DescriptorTable {
+    let mut buf = [0u8; 6];
+    unsafe {
+        asm!(
+            "sgdt ({buf})",
+            buf = in(reg) buf.as_mut_ptr() as u32,
+            options(att_syntax, nostack)
+        );
+    }
+    DescriptorTable::from_bytes(&buf)
+}
+
+/// Reads back IDTR.
+pub fn sidt() -> DescriptorTable {
+    let mut buf = [0u8; 6];
+    unsafe {
+        asm!(
+            "sidt ({buf})",
+            buf = in(reg) buf.as_mut_ptr() as u32,
+            options(att_syntax, nostack)
+        );
+    }
+    DescriptorTable::from_bytes(&buf)
+}
@@ -1,50 +1,115 @@
-use crate::drivers::port;
+use crate::io::format::{Formatter, KDebug, KDisplay};
+use crate::kwrite;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyEvent {
     Char(char),
     Enter,
     Backspace,
-    Tab,            // not implemented yet
+    Tab,
     ArrowLeft,
     ArrowRight,
-    ArrowUp,        // not implemented yet
-    ArrowDown,      // not implemented yet
+    ArrowUp,
+    ArrowDown,
+    Home,
+    End,
+    Delete,
+    CtrlA,
     CtrlC,
+    CtrlE,
+    CtrlK,
+    CtrlL,
+    CtrlR,
+    CtrlU,
+    CtrlW,
+    CtrlY,
+    AltB,
+    AltF,
     Unknown,
 }
 
-static mut SHIFT_PRESSED: bool = false;
-static mut CTRL_PRESSED: bool = false;
+impl KeyEvent {
+    /// Variant name, and the key as written on the keyboard.
+    fn names(self) -> (&'static str, &'static str) {
+        match self {
+            KeyEvent::Char(_) => ("Char", ""),
+            KeyEvent::Enter => ("Enter", "Enter"),
+            KeyEvent::Backspace => ("Backspace", "Backspace"),
+            KeyEvent::Tab => ("Tab", "Tab"),
+            KeyEvent::ArrowLeft => ("ArrowLeft", "Left"),
+            KeyEvent::ArrowRight => ("ArrowRight", "Right"),
+            KeyEvent::ArrowUp => ("ArrowUp", "Up"),
+            KeyEvent::ArrowDown => ("ArrowDown", "Down"),
+            KeyEvent::Home => ("Home", "Home"),
+            KeyEvent::End => ("End", "End"),
+            KeyEvent::Delete => ("Delete", "Delete"),
+            KeyEvent::CtrlA => ("CtrlA", "Ctrl+A"),
+            KeyEvent::CtrlC => ("CtrlC", "Ctrl+C"),
+            KeyEvent::CtrlE => ("CtrlE", "Ctrl+E"),
+            KeyEvent::CtrlK => ("CtrlK", "Ctrl+K"),
+            KeyEvent::CtrlL => ("CtrlL", "Ctrl+L"),
+            KeyEvent::CtrlR => ("CtrlR", "Ctrl+R"),
+            KeyEvent::CtrlU => ("CtrlU", "Ctrl+U"),
+            KeyEvent::CtrlW => ("CtrlW", "Ctrl+W"),
+            KeyEvent::CtrlY => ("CtrlY", "Ctrl+Y"),
+            KeyEvent::AltB => ("AltB", "Alt+B"),
+            KeyEvent::AltF => ("AltF", "Alt+F"),
+            KeyEvent::Unknown => ("Unknown", "Unknown"),
+        }
+    }
+}
 
-pub fn get_key_event() -> Option<KeyEvent> {
-    if let Some(scancode) = read_scancode() {
-        handle_scancode(scancode)
-    } else {
-        None
+/// Key as written on the keyboard: `a`, `Enter`, `Ctrl+C`...
+impl KDisplay for KeyEvent {
+    fn fmt(&self, f: &mut Formatter) {
+        match *self {
+            KeyEvent::Char(c) => kwrite!(f, "{}", c),
+            key => f.write_str(key.names().1),
+        }
     }
 }
 
-#[inline(always)]
-fn read_scancode() -> Option<u8> {
-    let status = port::inb(0x64);
-    if status & 0x01 != 0 {
-        Some(port::inb(0x60))
-    } else {
-        None
+/// Variant name, like a derived `core::fmt::Debug`.
+impl KDebug for KeyEvent {
+    fn fmt(&self, f: &mut Formatter) {
+        match *self {
+            KeyEvent::Char(c) => kwrite!(f, "Char({:?})", c),
+            key => f.write_str(key.names().0),
+        }
     }
 }
 
-fn handle_scancode(scancode: u8) -> Option<KeyEvent> {
+static mut SHIFT_PRESSED: bool = false;
+static mut CTRL_PRESSED: bool = false;
+static mut ALT_PRESSED: bool = false;
+
+/// Translates a scancode (set 1) into a key event, tracking modifiers.
+pub fn handle_scancode(scancode: u8) -> Option<KeyEvent> {
     match scancode {
         0x2A | 0x36 => { unsafe { SHIFT_PRESSED = true }; None },   // Shift press
         0xAA | 0xB6 => { unsafe { SHIFT_PRESSED = false }; None },  // Shift release
         0x1D => { unsafe { CTRL_PRESSED = true }; None },           // Ctrl press
         0x9D => { unsafe { CTRL_PRESSED = false }; None },          // Ctrl release
+        0x38 => { unsafe { ALT_PRESSED = true }; None },            // Alt press
+        0xB8 => { unsafe { ALT_PRESSED = false }; None },           // Alt release
         _ => {
             if unsafe { CTRL_PRESSED } {
                 match scancode {
+                    0x1E => Some(KeyEvent::CtrlA),  // Ctrl+A (scancode for 'a')
                     0x2E => Some(KeyEvent::CtrlC),  // Ctrl+C (scancode for 'c')
+                    0x12 => Some(KeyEvent::CtrlE),  // Ctrl+E (scancode for 'e')
+                    0x25 => Some(KeyEvent::CtrlK),  // Ctrl+K (scancode for 'k')
+                    0x26 => Some(KeyEvent::CtrlL),  // Ctrl+L (scancode for 'l')
+                    0x13 => Some(KeyEvent::CtrlR),  // Ctrl+R (scancode for 'r')
+                    0x16 => Some(KeyEvent::CtrlU),  // Ctrl+U (scancode for 'u')
+                    0x11 => Some(KeyEvent::CtrlW),  // Ctrl+W (scancode for 'w')
+                    0x15 => Some(KeyEvent::CtrlY),  // Ctrl+Y (scancode for 'y')
+                    _ => None,
+                }
+            } else if unsafe { ALT_PRESSED } {
+                match scancode {
+                    0x30 => Some(KeyEvent::AltB),   // Alt+B (scancode for 'b')
+                    0x21 => Some(KeyEvent::AltF),   // Alt+F (scancode for 'f')
                     _ => None,
                 }
             } else {
@@ -95,10 +160,13 @@ const SCANCODE_MAP: [Option<KeyEvent>; 128] = {
     map[0x35] = Some(KeyEvent::Char('/'));
 
     map[0x39] = Some(KeyEvent::Char(' '));
+    map[0x47] = Some(KeyEvent::Home);
     map[0x48] = Some(KeyEvent::ArrowUp);
     map[0x4B] = Some(KeyEvent::ArrowLeft); 
     map[0x4D] = Some(KeyEvent::ArrowRight);
+    map[0x4F] = Some(KeyEvent::End);
     map[0x50] = Some(KeyEvent::ArrowDown);
+    map[0x53] = Some(KeyEvent::Delete);
 
     map
 };
@@ -137,10 +205,13 @@ const SHIFTED_SCANCODE_MAP: [Option<KeyEvent>; 128] = {
     map[0x35] = Some(KeyEvent::Char('?'));
     
     map[0x39] = Some(KeyEvent::Char(' '));
+    map[0x47] = Some(KeyEvent::Home);
     map[0x48] = Some(KeyEvent::ArrowUp);
     map[0x4B] = Some(KeyEvent::ArrowLeft);
     map[0x4D] = Some(KeyEvent::ArrowRight);
+    map[0x4F] = Some(KeyEvent::End);
     map[0x50] = Some(KeyEvent::ArrowDown);
+    map[0x53] = Some(KeyEvent::Delete);
 
     map
 };
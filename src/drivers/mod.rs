@@ -1,3 +1,8 @@
 pub mod keyboard;
+pub mod mouse;
+pub mod pic;
 pub mod port;
+pub mod ps2;
+pub mod rtc;
+pub mod timer;
 pub mod vga;
\ No newline at end of file
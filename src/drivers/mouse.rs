@@ -0,0 +1,194 @@
+/// PS/2 mouse driver.
+///
+/// The mouse streams 3-byte packets, or 4-byte packets once the
+/// IntelliMouse scroll wheel extension has been unlocked:
+///
+///   byte 0: Y ovf | X ovf | Y sign | X sign | 1 | middle | right | left
+///   byte 1: X movement (low 8 bits, 9th bit is X sign)
+///   byte 2: Y movement (low 8 bits, 9th bit is Y sign, positive = up)
+///   byte 3: Z movement (wheel, signed 4 bits) — 4-byte mode only
+///
+/// Once reporting is on, the controller raises IRQ12 for every byte.
+/// `handle_irq` reads it and feeds it to `handle_byte`; complete packets
+/// are queued for the shell loop, which takes them with `next_event`
+/// (drawing the pointer is no work for an interrupt handler).
+
+use crate::cpu::irq;
+use crate::drivers::{pic, ps2};
+use crate::{pr_warn, printkln};
+
+// Device commands
+const CMD_SET_DEFAULTS: u8 = 0xF6;
+const CMD_ENABLE_REPORTING: u8 = 0xF4;
+const CMD_SET_SAMPLE_RATE: u8 = 0xF3;
+const CMD_GET_ID: u8 = 0xF2;
+
+/// PIC line of the second PS/2 port.
+pub const IRQ: u8 = 12;
+
+/// Events decoded but not yet taken by `next_event`.
+const QUEUE_LEN: usize = 32;
+
+/// Device ID reported once the scroll wheel extension is active.
+const ID_INTELLIMOUSE: u8 = 0x03;
+
+// Packet byte 0 flags
+const PACKET_LEFT: u8 = 0x01;
+const PACKET_RIGHT: u8 = 0x02;
+const PACKET_MIDDLE: u8 = 0x04;
+const PACKET_ALWAYS_ONE: u8 = 0x08;
+const PACKET_X_SIGN: u8 = 0x10;
+const PACKET_Y_SIGN: u8 = 0x20;
+const PACKET_X_OVERFLOW: u8 = 0x40;
+const PACKET_Y_OVERFLOW: u8 = 0x80;
+
+/// Button bits in `MouseEvent::buttons`.
+pub const BUTTON_LEFT: u8 = PACKET_LEFT;
+pub const BUTTON_RIGHT: u8 = PACKET_RIGHT;
+pub const BUTTON_MIDDLE: u8 = PACKET_MIDDLE;
+
+/// A decoded mouse packet.
+///
+/// `dy` is positive when the mouse moves up, `dz` positive when the
+/// wheel scrolls down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    pub dx: i16,
+    pub dy: i16,
+    pub dz: i8,
+    pub buttons: u8,
+}
+
+static mut ENABLED: bool = false;
+static mut PACKET_SIZE: usize = 3;
+static mut PACKET: [u8; 4] = [0; 4];
+static mut PACKET_IDX: usize = 0;
+static mut QUEUE: [MouseEvent; QUEUE_LEN] = [MouseEvent { dx: 0, dy: 0, dz: 0, buttons: 0 }; QUEUE_LEN];
+static mut QUEUE_HEAD: usize = 0;
+static mut QUEUE_COUNT: usize = 0;
+
+/// Sends a command (and optional argument) to the mouse.
+fn send(cmd: u8) -> bool {
+    ps2::write_port2(cmd)
+}
+
+fn set_sample_rate(rate: u8) -> bool {
+    send(CMD_SET_SAMPLE_RATE) && send(rate)
+}
+
+/// Initializes the mouse on the second PS/2 port, unlocking the scroll
+/// wheel (4-byte packets) when supported.
+pub fn init() {
+    if !ps2::has_second_port() {
+        printkln!("PS/2 mouse: no second port, skipping");
+        return;
+    }
+    if !send(CMD_SET_DEFAULTS) {
+        pr_warn!("PS/2 mouse: no response");
+        return;
+    }
+
+    // Magic sample rate sequence 200, 100, 80 enables the wheel
+    set_sample_rate(200);
+    set_sample_rate(100);
+    set_sample_rate(80);
+    let wheel = send(CMD_GET_ID) && ps2::read_data() == Some(ID_INTELLIMOUSE);
+
+    if !send(CMD_ENABLE_REPORTING) {
+        pr_warn!("PS/2 mouse: failed to enable reporting");
+        return;
+    }
+
+    ps2::enable_port2_irq();
+    unsafe {
+        PACKET_SIZE = if wheel { 4 } else { 3 };
+        PACKET_IDX = 0;
+        ENABLED = true;
+    }
+    pic::unmask(IRQ);
+    printkln!("PS/2 mouse: enabled ({}-byte packets)", if wheel { 4_u32 } else { 3_u32 });
+}
+
+/// Returns `true` if the mouse has been successfully initialized.
+pub fn is_enabled() -> bool {
+    unsafe { ENABLED }
+}
+
+/// Accumulates one byte of a packet. Returns an event once a full
+/// packet has been received.
+pub fn handle_byte(byte: u8) -> Option<MouseEvent> {
+    unsafe {
+        if !ENABLED {
+            return None;
+        }
+        // Resynchronize: the first byte always has bit 3 set
+        if PACKET_IDX == 0 && byte & PACKET_ALWAYS_ONE == 0 {
+            return None;
+        }
+        let packet = &mut *core::ptr::addr_of_mut!(PACKET);
+        *packet.get_unchecked_mut(PACKET_IDX) = byte;
+        PACKET_IDX += 1;
+        if PACKET_IDX < PACKET_SIZE {
+            return None;
+        }
+        PACKET_IDX = 0;
+        Some(decode_packet(packet, PACKET_SIZE))
+    }
+}
+
+/// IRQ12 handler: decodes the pending byte and queues the event it
+/// completes. Events that find the queue full are dropped.
+pub fn handle_irq() {
+    let Some(event) = ps2::read_port2().and_then(handle_byte) else {
+        return;
+    };
+    unsafe {
+        if QUEUE_COUNT == QUEUE_LEN {
+            return;
+        }
+        let tail = (QUEUE_HEAD + QUEUE_COUNT) % QUEUE_LEN;
+        *(*core::ptr::addr_of_mut!(QUEUE)).get_unchecked_mut(tail) = event;
+        QUEUE_COUNT += 1;
+    }
+}
+
+/// Takes the oldest event queued by the IRQ12 handler.
+pub fn next_event() -> Option<MouseEvent> {
+    irq::without(|| unsafe {
+        if QUEUE_COUNT == 0 {
+            return None;
+        }
+        let event = *(*core::ptr::addr_of!(QUEUE)).get_unchecked(QUEUE_HEAD);
+        QUEUE_HEAD = (QUEUE_HEAD + 1) % QUEUE_LEN;
+        QUEUE_COUNT -= 1;
+        Some(event)
+    })
+}
+
+/// Decodes a complete 3- or 4-byte packet.
+fn decode_packet(packet: &[u8; 4], size: usize) -> MouseEvent {
+    let flags = packet[0];
+
+    let mut dx = packet[1] as i16 - (((flags & PACKET_X_SIGN) as i16) << 4);
+    let mut dy = packet[2] as i16 - (((flags & PACKET_Y_SIGN) as i16) << 3);
+    if flags & PACKET_X_OVERFLOW != 0 {
+        dx = 0;
+    }
+    if flags & PACKET_Y_OVERFLOW != 0 {
+        dy = 0;
+    }
+
+    let dz = if size == 4 {
+        // Sign-extend the low 4 bits
+        ((packet[3] << 4) as i8) >> 4
+    } else {
+        0
+    };
+
+    MouseEvent {
+        dx,
+        dy,
+        dz,
+        buttons: flags & (PACKET_LEFT | PACKET_RIGHT | PACKET_MIDDLE),
+    }
+}
@@ -0,0 +1,106 @@
+/// 8259 programmable interrupt controllers (master and slave).
+///
+/// The BIOS leaves IRQ0-7 on vectors 8-15, over the CPU exceptions, so
+/// `init` remaps the master to `IRQ_BASE` and the slave right after it
+/// (see https://wiki.osdev.org/8259_PIC). Every line starts masked except
+/// the cascade (IRQ2), which the slave needs to reach the CPU; drivers
+/// unmask their own line with `unmask`.
+///
+/// IRQ7 and IRQ15 can be spurious: the controller raises them when the
+/// request that started an acknowledge cycle went away. `is_spurious`
+/// tells them apart through the in-service register, and `eoi` must not
+/// be sent for a spurious IRQ7 (nor to the slave for a spurious IRQ15).
+
+use crate::drivers::port;
+
+const MASTER_COMMAND: u16 = 0x20;
+const MASTER_DATA: u16 = 0x21;
+const SLAVE_COMMAND: u16 = 0xA0;
+const SLAVE_DATA: u16 = 0xA1;
+/// Unused port, written to give the controllers time between commands.
+const WAIT_PORT: u16 = 0x80;
+
+/// ICW1: initialization, ICW4 follows.
+const ICW1_INIT: u8 = 0x11;
+/// ICW3: the slave is on the master's IRQ2 line / has cascade identity 2.
+const ICW3_MASTER: u8 = 1 << CASCADE;
+const ICW3_SLAVE: u8 = CASCADE;
+/// ICW4: 8086 mode.
+const ICW4_8086: u8 = 0x01;
+/// OCW3: read the in-service register on the next read of the command port.
+const OCW3_READ_ISR: u8 = 0x0B;
+const EOI: u8 = 0x20;
+
+/// Vector of IRQ0; IRQ8-15 follow IRQ0-7.
+pub const IRQ_BASE: u8 = 0x20;
+pub const IRQS: usize = 16;
+const CASCADE: u8 = 2;
+
+fn wait() {
+    port::outb(WAIT_PORT, 0);
+}
+
+/// Command and data ports of the controller handling `irq`, and the bit of
+/// `irq` in its registers.
+fn controller(irq: u8) -> (u16, u16, u8) {
+    if irq < 8 {
+        (MASTER_COMMAND, MASTER_DATA, 1 << irq)
+    } else {
+        (SLAVE_COMMAND, SLAVE_DATA, 1 << (irq - 8))
+    }
+}
+
+/// Remaps both controllers to `IRQ_BASE` and masks every line but the
+/// cascade. Call with interrupts disabled.
+pub fn init() {
+    port::outb(MASTER_COMMAND, ICW1_INIT);
+    wait();
+    port::outb(SLAVE_COMMAND, ICW1_INIT);
+    wait();
+    port::outb(MASTER_DATA, IRQ_BASE);
+    wait();
+    port::outb(SLAVE_DATA, IRQ_BASE + 8);
+    wait();
+    port::outb(MASTER_DATA, ICW3_MASTER);
+    wait();
+    port::outb(SLAVE_DATA, ICW3_SLAVE);
+    wait();
+    port::outb(MASTER_DATA, ICW4_8086);
+    wait();
+    port::outb(SLAVE_DATA, ICW4_8086);
+    wait();
+
+    port::outb(MASTER_DATA, !(1 << CASCADE));
+    port::outb(SLAVE_DATA, 0xFF);
+}
+
+/// Lets `irq` through to the CPU.
+pub fn unmask(irq: u8) {
+    let (_, data, bit) = controller(irq);
+    port::outb(data, port::inb(data) & !bit);
+}
+
+/// Blocks `irq`.
+pub fn mask(irq: u8) {
+    let (_, data, bit) = controller(irq);
+    port::outb(data, port::inb(data) | bit);
+}
+
+/// Returns `true` if `irq` is not actually being serviced: a spurious
+/// IRQ7 or IRQ15.
+pub fn is_spurious(irq: u8) -> bool {
+    if irq != 7 && irq != 15 {
+        return false;
+    }
+    let (command, _, bit) = controller(irq);
+    port::outb(command, OCW3_READ_ISR);
+    port::inb(command) & bit == 0
+}
+
+/// Signals the end of `irq` to the controllers that delivered it.
+pub fn eoi(irq: u8) {
+    if irq >= 8 {
+        port::outb(SLAVE_COMMAND, EOI);
+    }
+    port::outb(MASTER_COMMAND, EOI);
+}
@@ -20,4 +20,77 @@ pub fn outb(port: u16, val: u8) {
             in("al") val,
         );
     }
-}
\ No newline at end of file
+}
+
+pub fn inw(port: u16) -> u16 {
+    let mut ret: u16;
+    unsafe {
+        asm!(
+            "in ax, dx",    // lit un mot de 16 bits du port dans dx vers ax
+            out("ax") ret,
+            in("dx") port,
+        );
+    }
+    ret
+}
+
+pub fn outw(port: u16, val: u16) {
+    unsafe {
+        asm!(
+            "out dx, ax",    // envoie un mot de 16 bits de ax vers port dans dx
+            in("dx") port,
+            in("ax") val,
+        );
+    }
+}
+
+pub fn inl(port: u16) -> u32 {
+    let mut ret: u32;
+    unsafe {
+        asm!(
+            "in eax, dx",    // lit un double mot de 32 bits du port dans dx vers eax
+            out("eax") ret,
+            in("dx") port,
+        );
+    }
+    ret
+}
+
+pub fn outl(port: u16, val: u32) {
+    unsafe {
+        asm!(
+            "out dx, eax",    // envoie un double mot de 32 bits de eax vers port dans dx
+            in("dx") port,
+            in("eax") val,
+        );
+    }
+}
+
+/// Reads `buf.len()` 16-bit words from a port (ATA PIO data transfers).
+pub fn insw(port: u16, buf: &mut [u16]) {
+    unsafe {
+        asm!(
+            "rep insw",    // lit ecx mots du port dans dx vers [edi]
+            in("dx") port,
+            inout("edi") buf.as_mut_ptr() => _,
+            inout("ecx") buf.len() => _,
+            options(nostack, preserves_flags),
+        );
+    }
+}
+
+/// Writes every 16-bit word of `buf` to a port.
+pub fn outsw(port: u16, buf: &[u16]) {
+    unsafe {
+        asm!(
+            // esi est réservé par LLVM : on le sauvegarde le temps du transfert
+            "xchg esi, {src}",
+            "rep outsw",    // envoie ecx mots de [esi] vers le port dans dx
+            "xchg esi, {src}",
+            src = inout(reg) buf.as_ptr() => _,
+            in("dx") port,
+            inout("ecx") buf.len() => _,
+            options(nostack, preserves_flags, readonly),
+        );
+    }
+}
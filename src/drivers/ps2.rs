@@ -0,0 +1,251 @@
+/// 8042 PS/2 controller.
+///
+/// The controller multiplexes two devices (keyboard on port 1, mouse on
+/// port 2) over a single data port. Every byte read from `DATA_PORT`
+/// must therefore be routed by looking at the `AUX_DATA` status bit.
+///
+/// Init sequence (see https://wiki.osdev.org/%228042%22_PS/2_Controller):
+///   1. disable both ports so devices cannot interfere
+///   2. flush the output buffer
+///   3. clear IRQ bits in the configuration byte (translation is kept,
+///      the keyboard driver decodes scancode set 1)
+///   4. controller self-test (0xAA → 0x55)
+///   5. detect a second port (enable it, check its clock bit)
+///   6. interface tests on each port (0xAB / 0xA9 → 0x00)
+///   7. enable working ports
+///
+/// IRQ1 stays masked in the configuration byte and the keyboard is polled
+/// from the shell loop through `poll()`. The mouse driver turns IRQ12 on
+/// with `enable_port2_irq` once the device streams packets; from then on
+/// its bytes are read by the IRQ12 handler through `read_port2`, and
+/// `poll()` leaves them in the output buffer for it.
+
+use crate::drivers::port;
+use crate::{pr_err, printkln};
+
+pub const DATA_PORT: u16 = 0x60;
+pub const STATUS_PORT: u16 = 0x64;
+pub const COMMAND_PORT: u16 = 0x64;
+
+// Status register bits
+const STATUS_OUTPUT_FULL: u8 = 0x01;
+const STATUS_INPUT_FULL: u8 = 0x02;
+const STATUS_AUX_DATA: u8 = 0x20;
+
+// Controller commands
+const CMD_READ_CONFIG: u8 = 0x20;
+const CMD_WRITE_CONFIG: u8 = 0x60;
+const CMD_DISABLE_PORT2: u8 = 0xA7;
+const CMD_ENABLE_PORT2: u8 = 0xA8;
+const CMD_TEST_PORT2: u8 = 0xA9;
+const CMD_SELF_TEST: u8 = 0xAA;
+const CMD_TEST_PORT1: u8 = 0xAB;
+const CMD_DISABLE_PORT1: u8 = 0xAD;
+const CMD_ENABLE_PORT1: u8 = 0xAE;
+const CMD_WRITE_PORT2: u8 = 0xD4;
+
+// Configuration byte bits
+const CONFIG_PORT1_IRQ: u8 = 0x01;
+const CONFIG_PORT2_IRQ: u8 = 0x02;
+const CONFIG_PORT2_CLOCK_OFF: u8 = 0x20;
+const CONFIG_TRANSLATION: u8 = 0x40;
+
+const SELF_TEST_OK: u8 = 0x55;
+const PORT_TEST_OK: u8 = 0x00;
+
+/// Device acknowledge byte.
+pub const ACK: u8 = 0xFA;
+
+/// Busy-wait iterations before giving up on the controller.
+const TIMEOUT: usize = 100_000;
+
+static mut PORT1_OK: bool = false;
+static mut PORT2_OK: bool = false;
+static mut PORT2_IRQ: bool = false;
+
+/// A byte read from the controller, tagged with the device that sent it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ps2Byte {
+    Keyboard(u8),
+    Mouse(u8),
+}
+
+// ──────────────────────────────────────────────
+//  Low-level helpers
+// ──────────────────────────────────────────────
+
+/// Waits until the controller input buffer is empty (safe to write).
+fn wait_write() -> bool {
+    for _ in 0..TIMEOUT {
+        if port::inb(STATUS_PORT) & STATUS_INPUT_FULL == 0 {
+            return true;
+        }
+    }
+    false
+}
+
+/// Waits until the controller output buffer is full (data to read).
+fn wait_read() -> bool {
+    for _ in 0..TIMEOUT {
+        if port::inb(STATUS_PORT) & STATUS_OUTPUT_FULL != 0 {
+            return true;
+        }
+    }
+    false
+}
+
+/// Sends a command byte to the controller.
+fn send_command(cmd: u8) {
+    wait_write();
+    port::outb(COMMAND_PORT, cmd);
+}
+
+/// Reads a response byte, or `None` on timeout.
+pub fn read_data() -> Option<u8> {
+    if wait_read() {
+        Some(port::inb(DATA_PORT))
+    } else {
+        None
+    }
+}
+
+/// Writes a byte to the data port.
+fn write_data(val: u8) {
+    wait_write();
+    port::outb(DATA_PORT, val);
+}
+
+/// Discards everything pending in the output buffer.
+fn flush_output() {
+    while port::inb(STATUS_PORT) & STATUS_OUTPUT_FULL != 0 {
+        port::inb(DATA_PORT);
+    }
+}
+
+fn read_config() -> u8 {
+    send_command(CMD_READ_CONFIG);
+    read_data().unwrap_or(0)
+}
+
+fn write_config(config: u8) {
+    send_command(CMD_WRITE_CONFIG);
+    write_data(config);
+}
+
+// ──────────────────────────────────────────────
+//  Public API
+// ──────────────────────────────────────────────
+
+/// Initializes the 8042 controller and enables every port that passes its test.
+pub fn init() {
+    printkln!("Initializing PS/2 controller...");
+
+    send_command(CMD_DISABLE_PORT1);
+    send_command(CMD_DISABLE_PORT2);
+    flush_output();
+
+    let mut config = read_config();
+    config &= !(CONFIG_PORT1_IRQ | CONFIG_PORT2_IRQ);
+    config |= CONFIG_TRANSLATION;
+    write_config(config);
+
+    send_command(CMD_SELF_TEST);
+    if read_data() != Some(SELF_TEST_OK) {
+        pr_err!("PS/2: controller self-test failed");
+        return;
+    }
+    // Some controllers reset themselves during the self-test
+    write_config(config);
+
+    // A second port exists if its clock turns on once enabled
+    send_command(CMD_ENABLE_PORT2);
+    let dual = read_config() & CONFIG_PORT2_CLOCK_OFF == 0;
+    send_command(CMD_DISABLE_PORT2);
+
+    send_command(CMD_TEST_PORT1);
+    let port1 = read_data() == Some(PORT_TEST_OK);
+    let port2 = dual && {
+        send_command(CMD_TEST_PORT2);
+        read_data() == Some(PORT_TEST_OK)
+    };
+
+    if port1 {
+        send_command(CMD_ENABLE_PORT1);
+    }
+    if port2 {
+        send_command(CMD_ENABLE_PORT2);
+    }
+    flush_output();
+
+    unsafe {
+        PORT1_OK = port1;
+        PORT2_OK = port2;
+    }
+    printkln!("PS/2: port1 {}, port2 {}",
+        if port1 { "ok" } else { "unavailable" },
+        if port2 { "ok" } else { "unavailable" });
+}
+
+/// Returns `true` if the keyboard port passed its interface test.
+pub fn has_first_port() -> bool {
+    unsafe { PORT1_OK }
+}
+
+/// Returns `true` if the mouse port exists and passed its interface test.
+pub fn has_second_port() -> bool {
+    unsafe { PORT2_OK }
+}
+
+/// Sends a byte to the device on port 2 and waits for its ACK.
+pub fn write_port2(val: u8) -> bool {
+    send_command(CMD_WRITE_PORT2);
+    write_data(val);
+    read_data() == Some(ACK)
+}
+
+/// Has the controller raise IRQ12 for every byte from port 2. The port
+/// is stopped meanwhile so that no mouse byte is taken for the
+/// configuration byte; bytes pending then are dropped.
+pub fn enable_port2_irq() {
+    send_command(CMD_DISABLE_PORT2);
+    flush_output();
+    let config = read_config();
+    write_config(config | CONFIG_PORT2_IRQ);
+    send_command(CMD_ENABLE_PORT2);
+    unsafe { PORT2_IRQ = true; }
+}
+
+/// Reads one pending byte, if any, and tags it with its source device.
+/// Mouse bytes are left to the IRQ12 handler once `enable_port2_irq` ran.
+#[inline(always)]
+pub fn poll() -> Option<Ps2Byte> {
+    let status = port::inb(STATUS_PORT);
+    if status & STATUS_OUTPUT_FULL == 0 {
+        return None;
+    }
+    let aux = status & STATUS_AUX_DATA != 0;
+    if aux && unsafe { PORT2_IRQ } {
+        return None;
+    }
+    let data = port::inb(DATA_PORT);
+    if aux {
+        Some(Ps2Byte::Mouse(data))
+    } else {
+        Some(Ps2Byte::Keyboard(data))
+    }
+}
+
+/// Reads a pending byte from port 2, for the IRQ12 handler. Keyboard
+/// bytes are left in the output buffer.
+pub fn read_port2() -> Option<u8> {
+    let status = port::inb(STATUS_PORT);
+    if status & (STATUS_OUTPUT_FULL | STATUS_AUX_DATA) != STATUS_OUTPUT_FULL | STATUS_AUX_DATA {
+        return None;
+    }
+    Some(port::inb(DATA_PORT))
+}
+
+/// Waits for the controller to accept a command, then sends it.
+pub fn command(cmd: u8) {
+    send_command(cmd);
+}
@@ -0,0 +1,96 @@
+/// CMOS real-time clock.
+///
+/// The RTC keeps the wall-clock time in the CMOS, read through an index
+/// port (0x70) and a data port (0x71). Values are BCD or binary and the
+/// hour 12h or 24h depending on status register B. A read can race with
+/// the once-per-second update, so `read` waits for the update-in-progress
+/// flag to clear and reads until two passes agree.
+///
+/// The clock is assumed to hold UTC in the 2000s (the century register is
+/// not standard).
+
+use crate::drivers::port;
+use crate::klib::time::DateTime;
+
+const INDEX_PORT: u16 = 0x70;
+const DATA_PORT: u16 = 0x71;
+/// Set in the index to keep NMIs disabled while the CMOS is accessed.
+const NMI_DISABLE: u8 = 0x80;
+
+// Registers
+const REG_SECONDS: u8 = 0x00;
+const REG_MINUTES: u8 = 0x02;
+const REG_HOURS: u8 = 0x04;
+const REG_DAY: u8 = 0x07;
+const REG_MONTH: u8 = 0x08;
+const REG_YEAR: u8 = 0x09;
+const REG_STATUS_A: u8 = 0x0A;
+const REG_STATUS_B: u8 = 0x0B;
+
+const STATUS_A_UPDATING: u8 = 0x80;
+const STATUS_B_24H: u8 = 0x02;
+const STATUS_B_BINARY: u8 = 0x04;
+/// Set in the hour register for PM in 12-hour mode.
+const HOUR_PM: u8 = 0x80;
+
+/// Polls of status register A before giving up on an RTC that never
+/// finishes its update (or is absent).
+const MAX_POLLS: u32 = 1_000_000;
+
+fn read_reg(reg: u8) -> u8 {
+    port::outb(INDEX_PORT, NMI_DISABLE | reg);
+    port::inb(DATA_PORT)
+}
+
+/// Raw registers: seconds, minutes, hours, day, month, year.
+fn read_raw() -> Option<[u8; 6]> {
+    let mut polls = 0;
+    while read_reg(REG_STATUS_A) & STATUS_A_UPDATING != 0 {
+        polls += 1;
+        if polls == MAX_POLLS {
+            return None;
+        }
+    }
+    Some([REG_SECONDS, REG_MINUTES, REG_HOURS, REG_DAY, REG_MONTH, REG_YEAR].map(read_reg))
+}
+
+fn from_bcd(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0F)
+}
+
+/// Reads the current date and time, `None` if the RTC does not answer or
+/// holds an invalid date.
+pub fn read() -> Option<DateTime> {
+    let mut raw = read_raw()?;
+    loop {
+        let again = read_raw()?;
+        if again == raw {
+            break;
+        }
+        raw = again;
+    }
+
+    let status = read_reg(REG_STATUS_B);
+    let [mut second, mut minute, hours, mut day, mut month, mut year] = raw;
+    let pm = hours & HOUR_PM != 0;
+    let mut hour = hours & !HOUR_PM;
+    if status & STATUS_B_BINARY == 0 {
+        second = from_bcd(second);
+        minute = from_bcd(minute);
+        hour = from_bcd(hour);
+        day = from_bcd(day);
+        month = from_bcd(month);
+        year = from_bcd(year);
+    }
+    if status & STATUS_B_24H == 0 {
+        // 12 AM is midnight, 12 PM is noon
+        hour %= 12;
+        if pm {
+            hour += 12;
+        }
+    }
+
+    let valid = second < 60 && minute < 60 && hour < 24
+        && (1..=31).contains(&day) && (1..=12).contains(&month) && year < 100;
+    valid.then_some(DateTime { year: 2000 + year as u32, month, day, hour, minute, second })
+}
@@ -0,0 +1,114 @@
+/// Time since boot.
+///
+/// The PIT interrupt (IRQ0) stays masked, so there is no tick counter:
+/// the time comes from the CPU time stamp counter, whose rate `init`
+/// measures once against the PIT. Channel 2 (the speaker channel) runs a 10 ms one-shot
+/// whose output is polled through port 0x61, the way Linux calibrates its
+/// TSC. Without a TSC the clock stays at 0.
+///
+/// `init` also reads the RTC, so uptimes can be turned into wall-clock
+/// times (`dmesg -T`).
+
+use crate::cpu::{cpuid, regs};
+use crate::drivers::{port, rtc};
+use crate::klib::number;
+use crate::{pr_warn, printkln};
+
+/// PIT input clock.
+const PIT_HZ: u32 = 1_193_182;
+const PIT_CHANNEL2: u16 = 0x42;
+const PIT_COMMAND: u16 = 0x43;
+/// Channel 2, low then high byte, mode 0 (interrupt on terminal count).
+const PIT_CH2_ONESHOT: u8 = 0xB0;
+
+/// Speaker control: bit 0 gates channel 2, bit 1 drives the speaker,
+/// bit 5 reads back the channel 2 output.
+const SPEAKER_PORT: u16 = 0x61;
+const SPEAKER_GATE: u8 = 0x01;
+const SPEAKER_DATA: u8 = 0x02;
+const SPEAKER_OUT: u8 = 0x20;
+
+const CALIBRATION_MS: u32 = 10;
+/// Polls of the PIT output before giving up on the calibration.
+const MAX_POLLS: u32 = 10_000_000;
+
+static mut BOOT_TSC: u64 = 0;
+/// Microseconds per TSC tick, as a 32.32 fixed-point fraction (0: no clock).
+static mut US_PER_TICK: u32 = 0;
+static mut TSC_KHZ: u32 = 0;
+/// Unix time of `init`, if the RTC could be read.
+static mut BOOT_TIME: Option<u32> = None;
+
+/// TSC ticks during `CALIBRATION_MS` of PIT time.
+fn calibrate() -> Option<u32> {
+    let latch = PIT_HZ / 1000 * CALIBRATION_MS;
+    let speaker = port::inb(SPEAKER_PORT);
+    port::outb(SPEAKER_PORT, (speaker & !SPEAKER_DATA) | SPEAKER_GATE);
+    port::outb(PIT_COMMAND, PIT_CH2_ONESHOT);
+    port::outb(PIT_CHANNEL2, latch as u8);
+    port::outb(PIT_CHANNEL2, (latch >> 8) as u8);
+
+    let start = regs::read_tsc();
+    let mut polls = 0;
+    while port::inb(SPEAKER_PORT) & SPEAKER_OUT == 0 && polls < MAX_POLLS {
+        polls += 1;
+    }
+    let end = regs::read_tsc();
+    port::outb(SPEAKER_PORT, speaker);
+
+    let ticks = end.wrapping_sub(start);
+    if polls == MAX_POLLS || ticks >> 32 != 0 {
+        return None;
+    }
+    Some(ticks as u32)
+}
+
+/// Calibrates the TSC and records the boot time. Needs `cpu::init`.
+pub fn init() {
+    unsafe {
+        BOOT_TIME = rtc::read().map(|time| time.to_unix());
+    }
+    if !cpuid::has(&cpuid::TSC) {
+        pr_warn!("timer: no TSC, timestamps disabled");
+        return;
+    }
+    let Some(ticks) = calibrate() else {
+        pr_warn!("timer: PIT calibration failed, timestamps disabled");
+        return;
+    };
+    let us = CALIBRATION_MS * 1000;
+    // Below 1 MHz the fraction would not fit in 32 bits
+    if ticks <= us {
+        pr_warn!("timer: TSC too slow ({} ticks in {} ms)", ticks, CALIBRATION_MS);
+        return;
+    }
+    unsafe {
+        US_PER_TICK = number::div_rem_u64((us as u64) << 32, ticks).0 as u32;
+        TSC_KHZ = ticks / CALIBRATION_MS;
+        BOOT_TSC = regs::read_tsc();
+    }
+    printkln!("timer: TSC at {} kHz", tsc_khz());
+}
+
+/// Microseconds since `init`, 0 before it or without a TSC.
+pub fn uptime_us() -> u64 {
+    let (boot, per_tick) = unsafe { (BOOT_TSC, US_PER_TICK) };
+    if per_tick == 0 {
+        return 0;
+    }
+    // ticks * per_tick >> 32, split so every product is 32 x 32 bits
+    let ticks = regs::read_tsc().wrapping_sub(boot);
+    let high = (ticks >> 32) * per_tick as u64;
+    let low = ((ticks as u32 as u64) * per_tick as u64) >> 32;
+    high + low
+}
+
+/// Measured TSC frequency, 0 if unknown.
+pub fn tsc_khz() -> u32 {
+    unsafe { TSC_KHZ }
+}
+
+/// Unix time at `init`, `None` if the RTC could not be read.
+pub fn boot_time() -> Option<u32> {
+    unsafe { BOOT_TIME }
+}
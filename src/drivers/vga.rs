@@ -1,4 +1,5 @@
 use crate::drivers::port;
+use crate::klib::memory;
 
 pub const VGA_WIDTH: usize = 80;
 pub const VGA_HEIGHT: usize = 25;
@@ -44,6 +45,23 @@ pub fn draw_char_at(x: usize, y: usize, c: u8, color: u8) {
     }
 }
 
+/// Reads the character at the specified (x, y) position.
+pub fn read_char_at(x: usize, y: usize) -> u8 {
+    unsafe {
+        let offset = (y * VGA_WIDTH + x) * 2;
+        *VGA_BUFFER.offset(offset as isize)
+    }
+}
+
+/// Swaps foreground and background colors at the specified (x, y) position.
+pub fn invert_color_at(x: usize, y: usize) {
+    unsafe {
+        let offset = (y * VGA_WIDTH + x) * 2 + 1;
+        let color = *VGA_BUFFER.offset(offset as isize);
+        *VGA_BUFFER.offset(offset as isize) = color.rotate_left(4);
+    }
+}
+
 /// Clears the character at the specified (x, y) position.
 pub fn clear_char_at(x: usize, y: usize) {
     draw_char_at(x, y, b' ', DEFAULT_COLOR);
@@ -61,15 +79,8 @@ pub fn update_cursor(x: usize, y: usize) {
 /// Shifts all lines up by one, and clears the last line.
 pub fn scroll_buffer_up() {
     unsafe {
-        for row in 1..VGA_HEIGHT {
-            for col in 0..VGA_WIDTH {
-                let from = ((row * VGA_WIDTH + col) * 2) as isize;
-                let to = (((row - 1) * VGA_WIDTH + col) * 2) as isize;
-
-                *VGA_BUFFER.offset(to) = *VGA_BUFFER.offset(from);
-                *VGA_BUFFER.offset(to + 1) = *VGA_BUFFER.offset(from + 1);
-            }
-        }
+        let row_bytes = VGA_WIDTH * 2;
+        memory::memcpy_volatile(VGA_BUFFER, VGA_BUFFER.add(row_bytes), (VGA_HEIGHT - 1) * row_bytes);
 
         let last_line_offset = ((VGA_HEIGHT - 1) * VGA_WIDTH * 2) as isize;
         for col in 0..VGA_WIDTH {
@@ -46,7 +46,7 @@
 ///     1   (L)     Long mode (IA-32e only)     0 = disabled (protected mode), 1 = 64-bit code segment
 ///     0   (AVL)   Available for software      Ignored by the CPU
 /// 
-/// This GDT contains 7 entries at physical address 0x00000800:
+/// This GDT contains 9 entries at physical address 0x00000800:
 ///     0x00: Null descriptor (mandatory)
 ///     0x08: Kernel Code
 ///     0x10: Kernel Data
@@ -54,16 +54,21 @@
 ///     0x20: User Code
 ///     0x28: User Data
 ///     0x30: User Stack
+///     0x38: Main TSS (system segment, see `tss`)
+///     0x40: Double fault TSS
 
 use core::arch::asm;
-use crate::{printkln, println};
+use crate::cpu::regs;
+use crate::gdt::tss::{self, TSS_SIZE};
+use crate::io::format::{Formatter, KDebug, KDisplay};
+use crate::{kwrite, printkln, println};
 
 /// -----------------------
 /// GDT Constants
 /// -----------------------
 
 /// Number of GDT entries
-const GDT_ENTRIES: usize = 7;
+const GDT_ENTRIES: usize = 9;
 
 /// GDT physical address
 const GDT_BASE_ADDR: u32 = 0x00000800;
@@ -75,10 +80,14 @@ const KERNEL_STACK_ACCESS:  u8 = 0b1001_0110; // 0x96 — P=1, DPL=0, S=1, E=0,
 const USER_CODE_ACCESS:     u8 = 0b1111_1010; // 0xFA — P=1, DPL=3, S=1, E=1, RW=1
 const USER_DATA_ACCESS:     u8 = 0b1111_0010; // 0xF2 — P=1, DPL=3, S=1, E=0, RW=1
 const USER_STACK_ACCESS:    u8 = 0b1111_0110; // 0xF6 — P=1, DPL=3, S=1, E=0, DC=1, RW=1
+const TSS_ACCESS:           u8 = 0b1000_1001; // 0x89 — P=1, DPL=0, S=0, type=9 (32-bit TSS, available)
 
 /// Flags for 32-bit protected mode segments with 4KB granularity
 const FLAGS_32BIT_4K: u8 = 0b1100;
 
+/// Flags for TSS descriptors: limit in bytes
+const FLAGS_TSS: u8 = 0b0000;
+
 /// -----------------------
 /// GDT Data Structures
 /// -----------------------
@@ -153,14 +162,44 @@ impl GdtEntry {
     }
 }
 
+impl KDisplay for GdtEntry {
+    fn fmt(&self, f: &mut Formatter) {
+        let access = self.access;
+        kwrite!(
+            f,
+            "base={:#010x} limit={:#07x} access={:#04x} flags={:#x}",
+            self.base(),
+            self.limit(),
+            access,
+            self.flags()
+        );
+    }
+}
+
+impl KDebug for GdtEntry {
+    fn fmt(&self, f: &mut Formatter) {
+        let access = self.access;
+        kwrite!(
+            f,
+            "GdtEntry {{ base: {:#x}, limit: {:#x}, access: {:#b}, flags: {:#b} }}",
+            self.base(),
+            self.limit(),
+            access,
+            self.flags()
+        );
+    }
+}
+
 
 /// GDT initialization function
 ///
-/// Creates 7 segment descriptors, copies them to physical address 0x00000800,
-/// and reloads the GDTR and segment registers.
+/// Creates 9 segment descriptors, copies them to physical address 0x00000800,
+/// reloads the GDTR and segment registers, and loads the main TSS into TR.
 pub fn init() {
     printkln!("Initializing GDT...");
-    
+
+    let main_tss = core::ptr::addr_of!(tss::MAIN) as u32;
+    let double_fault_tss = core::ptr::addr_of!(tss::DOUBLE_FAULT) as u32;
     let gdt: [GdtEntry; GDT_ENTRIES] = [
         GdtEntry::null(),
         GdtEntry::new(0x00000000, 0xFFFFF, KERNEL_CODE_ACCESS, FLAGS_32BIT_4K),
@@ -169,6 +208,8 @@ pub fn init() {
         GdtEntry::new(0x00000000, 0xFFFFF, USER_CODE_ACCESS, FLAGS_32BIT_4K),
         GdtEntry::new(0x00000000, 0xFFFFF, USER_DATA_ACCESS, FLAGS_32BIT_4K),
         GdtEntry::new(0x00000000, 0xFFFFF, USER_STACK_ACCESS, FLAGS_32BIT_4K),
+        GdtEntry::new(main_tss, (TSS_SIZE - 1) as u32, TSS_ACCESS, FLAGS_TSS),
+        GdtEntry::new(double_fault_tss, (TSS_SIZE - 1) as u32, TSS_ACCESS, FLAGS_TSS),
     ];
 
     unsafe {
@@ -189,6 +230,8 @@ pub fn init() {
 
     unsafe {
         load_gdt(&gdt_ptr);
+        // The CPU saves the interrupted state there on a task switch
+        asm!("ltr {0:x}", in(reg) tss::MAIN_SELECTOR, options(nostack));
     }
 
     printkln!("GDT initialized successfully.");
@@ -250,24 +293,14 @@ unsafe fn load_gdt(gdt_ptr: &GdtPointer) {
 /// Prints the GDT contents in a human-readable format.
 pub fn print_gdt() {
     // Read back the actual GDTR to verify it's loaded correctly
-    let mut gdtr_buf: [u8; 6] = [0; 6];
-    unsafe {
-        asm!(
-            "sgdt ({buf})",
-            buf = in(reg) gdtr_buf.as_mut_ptr() as u32,
-            options(att_syntax, nostack)
-        );
-    }
-    let gdtr_limit = (gdtr_buf[0] as u16) | ((gdtr_buf[1] as u16) << 8);
-    let gdtr_base = (gdtr_buf[2] as u32)
-        | ((gdtr_buf[3] as u32) << 8)
-        | ((gdtr_buf[4] as u32) << 16)
-        | ((gdtr_buf[5] as u32) << 24);
-    let num_entries = ((gdtr_limit as u32) + 1) / 8;
+    let gdtr = regs::sgdt();
+    let gdtr_base = gdtr.base;
+    let gdtr_limit = gdtr.limit;
+    let num_entries = gdtr.entries();
 
     println!("=== Global Descriptor Table ===");
     println!(
-        "  GDTR: base={:#x}  limit={:#x}  ({} entries)",
+        "  GDTR: base={:#010x}  limit={:#06x}  ({} entries)",
         gdtr_base,
         gdtr_limit as u32,
         num_entries
@@ -283,6 +316,8 @@ pub fn print_gdt() {
         "User Code",
         "User Data",
         "User Stack",
+        "Main TSS",
+        "Double Fault TSS",
     ];
 
     for i in 0..GDT_ENTRIES {
@@ -299,7 +334,7 @@ pub fn print_gdt() {
         let flags = entry.flags();
 
         println!(
-            "  [{}]  {:#x}      {:#x}  {:#x}    {:#x}    {:#x}   {}",
+            "  [{}]  {:#06x}    {:#010x}  {:#07x}     {:#04x}    {:#x}    {}",
             i as u32,
             selector,
             base,
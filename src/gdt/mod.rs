@@ -1,4 +1,5 @@
 pub mod gdt;
+pub mod tss;
 
 pub use gdt::init;
 pub use gdt::print_gdt;
@@ -0,0 +1,65 @@
+/// Task State Segments.
+///
+/// TacOS does not switch tasks in hardware, but needs TSSs for one thing:
+/// a double fault must run on a known-good stack, since its usual cause
+/// is a kernel stack overflow that leaves no room for the exception frame.
+/// The IDT entry of the double fault is a task gate to `DOUBLE_FAULT`,
+/// which starts the handler on its own stack. The task switch saves the
+/// state of the faulting code in `MAIN`, the TSS loaded in TR, where the
+/// handler reads it.
+
+/// Selectors of the TSS descriptors in the GDT.
+pub const MAIN_SELECTOR: u16 = 0x38;
+pub const DOUBLE_FAULT_SELECTOR: u16 = 0x40;
+
+/// A 32-bit TSS. Selector fields hold the selector in their low 16 bits.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct Tss {
+    pub link: u32,
+    pub esp0: u32,
+    pub ss0: u32,
+    pub esp1: u32,
+    pub ss1: u32,
+    pub esp2: u32,
+    pub ss2: u32,
+    pub cr3: u32,
+    pub eip: u32,
+    pub eflags: u32,
+    pub eax: u32,
+    pub ecx: u32,
+    pub edx: u32,
+    pub ebx: u32,
+    pub esp: u32,
+    pub ebp: u32,
+    pub esi: u32,
+    pub edi: u32,
+    pub es: u32,
+    pub cs: u32,
+    pub ss: u32,
+    pub ds: u32,
+    pub fs: u32,
+    pub gs: u32,
+    pub ldt: u32,
+    pub trap: u16,
+    /// Offset of the I/O permission bitmap; the TSS size means none.
+    pub iomap_base: u16,
+}
+
+pub const TSS_SIZE: usize = core::mem::size_of::<Tss>();
+
+impl Tss {
+    const fn new() -> Self {
+        Tss {
+            link: 0, esp0: 0, ss0: 0, esp1: 0, ss1: 0, esp2: 0, ss2: 0,
+            cr3: 0, eip: 0, eflags: 0,
+            eax: 0, ecx: 0, edx: 0, ebx: 0, esp: 0, ebp: 0, esi: 0, edi: 0,
+            es: 0, cs: 0, ss: 0, ds: 0, fs: 0, gs: 0, ldt: 0,
+            trap: 0,
+            iomap_base: TSS_SIZE as u16,
+        }
+    }
+}
+
+pub static mut MAIN: Tss = Tss::new();
+pub static mut DOUBLE_FAULT: Tss = Tss::new();
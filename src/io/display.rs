@@ -73,6 +73,16 @@ pub fn new_line() {
     sync_cursor();
 }
 
+/// Blanks the whole screen and moves the cursor to the top-left corner.
+pub fn clear_screen() {
+    for y in 0..vga::VGA_HEIGHT {
+        for x in 0..vga::VGA_WIDTH {
+            vga::clear_char_at(x, y);
+        }
+    }
+    set_pos(0, 0);
+}
+
 // Syncs software cursor with VGA hardware.
 fn sync_cursor() {
     let (x, y) = get_pos();
@@ -0,0 +1,1218 @@
+//! Format string interpreter behind `print!` and `printk!`.
+//!
+//! Pure logic with no output device: `format` renders into any `Output`,
+//! so it can be unit-tested on the host. `print_engine` plugs the kernel
+//! sinks (VGA display, klog, shell streams) into it.
+//!
+//! Placeholders follow the `core::fmt` syntax:
+//!
+//!   {[argument][:[[fill]align][sign]['#']['0'][width]['.' precision][type]]}
+//!
+//!   argument   — nothing for the next argument, an index (`{0}`, `{1:x}`)
+//!                or the name of a `name = value` argument (`{addr:#x}`)
+//!   fill       — any ASCII character, used with an alignment (default ' ')
+//!   align      — '<' left, '>' right, '^' center
+//!                (default: left for text, right for numbers)
+//!   sign       — '+' prints a sign on non-negative numbers too
+//!   '#'        — alternate form: "0x", "0X", "0b" or "0o" prefix
+//!   '0'        — pad numbers with zeros after the sign and prefix
+//!   width      — minimum width in characters
+//!   precision  — maximum number of characters of a string
+//!   type       — x X b o p, ? for the debug form, or nothing for the default
+//!
+//! Examples: `{:08x}`, `{:#010x}`, `{:>10}`, `{:*^9}`, `{:+}`, `{:.3}`, `{:?}`.
+//! Unknown types fall back to the default representation.
+//!
+//! The printing macros validate the format string against their arguments
+//! at compile time (see `check`), so `format` never sees a malformed one.
+//!
+//! Kernel types become printable by implementing `KDisplay` (`{}`) and
+//! `KDebug` (`{:?}`, passed as `PrintArg::debug(&value)`).
+//!
+//! `kformat!` renders into a caller-provided buffer instead of a sink.
+//! `Formatter`, `BufWriter` and the kernel sinks also implement
+//! `core::fmt::Write`, for `no_std` crates written against it. With the
+//! `core-fmt` feature, `format_args!` output is accepted as an argument,
+//! which prints `#[derive(Debug)]` types: `{}` of `format_args!("{:?}", v)`.
+//! The feature is off by default because it links `core::fmt` into the
+//! kernel (`make link FEATURES=core-fmt`).
+
+use core::fmt;
+
+use crate::klib::number;
+
+/// Type-erased argument (like `va_arg`).
+#[derive(Copy, Clone)]
+pub enum PrintArg<'a> {
+    Str(&'a str),
+    Bytes(&'a [u8]),
+    Char(u8),
+    I32(i32),
+    U32(u32),
+    Usize(usize),
+    U64(u64),
+    I64(i64),
+    Bool(bool),
+    Ptr(*const u32),
+    Display(&'a dyn KDisplay),
+    Debug(&'a dyn KDebug),
+    #[cfg(feature = "core-fmt")]
+    Fmt(fmt::Arguments<'a>),
+}
+
+impl<'a> PrintArg<'a> {
+    /// Prints `value` with its `KDebug` form.
+    pub fn debug(value: &'a dyn KDebug) -> Self {
+        PrintArg::Debug(value)
+    }
+}
+
+#[cfg(feature = "core-fmt")]
+impl<'a> From<fmt::Arguments<'a>> for PrintArg<'a> {
+    fn from(v: fmt::Arguments<'a>) -> Self { PrintArg::Fmt(v) }
+}
+impl<'a> From<&'a str> for PrintArg<'a> {
+    fn from(v: &'a str) -> Self { PrintArg::Str(v) }
+}
+impl<'a> From<&'a [u8]> for PrintArg<'a> {
+    fn from(v: &'a [u8]) -> Self { PrintArg::Bytes(v) }
+}
+impl<'a> From<u8> for PrintArg<'a> {
+    fn from(v: u8) -> Self { PrintArg::U32(v as u32) }
+}
+impl<'a> From<u16> for PrintArg<'a> {
+    fn from(v: u16) -> Self { PrintArg::U32(v as u32) }
+}
+impl<'a> From<u32> for PrintArg<'a> {
+    fn from(v: u32) -> Self { PrintArg::U32(v) }
+}
+impl<'a> From<i8> for PrintArg<'a> {
+    fn from(v: i8) -> Self { PrintArg::I32(v as i32) }
+}
+impl<'a> From<i16> for PrintArg<'a> {
+    fn from(v: i16) -> Self { PrintArg::I32(v as i32) }
+}
+impl<'a> From<i32> for PrintArg<'a> {
+    fn from(v: i32) -> Self { PrintArg::I32(v) }
+}
+impl<'a> From<usize> for PrintArg<'a> {
+    fn from(v: usize) -> Self { PrintArg::Usize(v) }
+}
+impl<'a> From<u64> for PrintArg<'a> {
+    fn from(v: u64) -> Self { PrintArg::U64(v) }
+}
+impl<'a> From<i64> for PrintArg<'a> {
+    fn from(v: i64) -> Self { PrintArg::I64(v) }
+}
+impl<'a> From<bool> for PrintArg<'a> {
+    fn from(v: bool) -> Self { PrintArg::Bool(v) }
+}
+impl<'a> From<char> for PrintArg<'a> {
+    fn from(v: char) -> Self { PrintArg::Char(v as u8) }
+}
+impl<'a> From<&u32> for PrintArg<'a> {
+    fn from(v: &u32) -> Self { PrintArg::Ptr(v) }
+}
+impl<'a, T: KDisplay> From<&'a T> for PrintArg<'a> {
+    fn from(v: &'a T) -> Self { PrintArg::Display(v) }
+}
+
+// ──────────────────────────────────────────────
+//  Output — where the rendered text goes
+// ──────────────────────────────────────────────
+
+/// Destination of `format`.
+pub trait Output {
+    /// A byte drawn as is: digits, padding, `{}` arguments of type char.
+    fn raw(&mut self, c: u8);
+    /// Text whose control characters (`\n`, `\t`, ...) are interpreted:
+    /// string arguments and the literal parts of the format string.
+    fn text(&mut self, bytes: &[u8]);
+}
+
+/// Counts the bytes that would be written, to pad user types.
+struct Counter(usize);
+
+impl Output for Counter {
+    fn raw(&mut self, _: u8) {
+        self.0 += 1;
+    }
+
+    fn text(&mut self, bytes: &[u8]) {
+        self.0 += bytes.len();
+    }
+}
+
+/// `Output` that fills a caller-provided buffer. What does not fit is
+/// dropped but still counted, so the caller learns the size it needed.
+pub struct BufWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    needed: usize,
+}
+
+/// Returned by `BufWriter::finish` when the buffer was too small.
+#[derive(Debug, PartialEq)]
+pub struct Truncated<'a> {
+    /// The part that fit, cut at the end of the buffer.
+    pub written: &'a [u8],
+    /// Size of the complete output.
+    pub needed: usize,
+}
+
+impl<'a> BufWriter<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        BufWriter { buf, len: 0, needed: 0 }
+    }
+
+    /// Bytes written so far.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { self.buf.get_unchecked(..self.len) }
+    }
+
+    /// `true` once something did not fit.
+    pub fn is_truncated(&self) -> bool {
+        self.needed > self.len
+    }
+
+    /// Renders `fmt` with `args`, then `finish`es; see `kformat!`.
+    pub fn format(mut self, fmt: &str, args: &[PrintArg], names: &[&str]) -> Result<&'a [u8], Truncated<'a>> {
+        format(fmt, args, names, &mut self);
+        self.finish()
+    }
+
+    /// The written part of the buffer, or `Truncated` if output was lost.
+    pub fn finish(self) -> Result<&'a [u8], Truncated<'a>> {
+        let BufWriter { buf, len, needed } = self;
+        let written = unsafe { buf.get_unchecked(..len) };
+        if needed > len {
+            Err(Truncated { written, needed })
+        } else {
+            Ok(written)
+        }
+    }
+}
+
+impl Output for BufWriter<'_> {
+    fn raw(&mut self, c: u8) {
+        if self.len < self.buf.len() {
+            unsafe { *self.buf.get_unchecked_mut(self.len) = c; }
+            self.len += 1;
+        }
+        self.needed += 1;
+    }
+
+    fn text(&mut self, bytes: &[u8]) {
+        for &c in bytes {
+            self.raw(c);
+        }
+    }
+}
+
+impl fmt::Write for BufWriter<'_> {
+    /// Fails once the buffer is full, which stops `write!` early.
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.text(s.as_bytes());
+        if self.is_truncated() { Err(fmt::Error) } else { Ok(()) }
+    }
+}
+
+/// Renders into a byte buffer (an array or a slice), like `snprintf`:
+///
+///   let mut buf = [0u8; 32];
+///   match kformat!(buf, "{}x{}", w, h) {
+///       Ok(text) => ...,
+///       Err(Truncated { written, needed }) => ...,
+///   }
+///
+/// Evaluates to `Result<&[u8], Truncated>`; see `BufWriter::finish`.
+#[macro_export]
+macro_rules! kformat {
+    ($buf:expr, $fmt:expr $(, $($args:tt)*)?) => {
+        $crate::format_call!({ $crate::io::format::BufWriter::new(&mut $buf[..]).format } $fmt; [] [] $($($args)*)?)
+    };
+}
+
+// ──────────────────────────────────────────────
+//  KDisplay / KDebug — printing kernel types
+// ──────────────────────────────────────────────
+
+/// Where a `KDisplay` or `KDebug` implementation writes its text.
+pub struct Formatter<'a> {
+    out: &'a mut dyn Output,
+    alternate: bool,
+}
+
+impl Formatter<'_> {
+    /// Writes a string as is.
+    pub fn write_str(&mut self, s: &str) {
+        self.out.text(s.as_bytes());
+    }
+
+    /// Writes bytes as is.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.out.text(bytes);
+    }
+
+    /// Writes formatted text; see `kwrite!`.
+    pub fn write(&mut self, fmt: &str, args: &[PrintArg], names: &[&str]) {
+        format(fmt, args, names, self.out);
+    }
+
+    /// `true` for `{:#}` / `{:#?}`: an implementation may pick a longer form.
+    pub fn alternate(&self) -> bool {
+        self.alternate
+    }
+}
+
+impl fmt::Write for Formatter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.out.text(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// `{}` formatting for kernel types, like `core::fmt::Display`.
+///
+/// `print!("{}", &value)` works for any implementor. Width and alignment
+/// apply to the whole output of `fmt`.
+pub trait KDisplay {
+    fn fmt(&self, f: &mut Formatter);
+}
+
+/// `{:?}` formatting for kernel types, like `core::fmt::Debug`.
+///
+/// Values are passed as `PrintArg::debug(&value)`.
+pub trait KDebug {
+    fn fmt(&self, f: &mut Formatter);
+}
+
+/// `print!`-style output into a `Formatter`, for `KDisplay` / `KDebug`
+/// implementations.
+#[macro_export]
+macro_rules! kwrite {
+    ($f:expr, $fmt:expr $(, $($args:tt)*)?) => {
+        $crate::format_call!({ $f.write } $fmt; [] [] $($($args)*)?)
+    };
+}
+
+/// Checks a format string against its arguments at compile time, then
+/// calls `$call(fmt, &[args...], &[names...])`. Used by the printing
+/// macros; positional arguments come first, then `name = value` ones.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! format_call {
+    ({ $($call:tt)* } $fmt:expr; [$($pos:expr,)*] [$($name:ident = $val:expr,)*]) => {{
+        const _: () = $crate::io::format::check(
+            $fmt,
+            { let positional: &[&str] = &[$(stringify!($pos)),*]; positional.len() },
+            &[$(stringify!($name)),*],
+        );
+        $($call)*($fmt, &[
+            $($crate::io::format::PrintArg::from($pos),)*
+            $($crate::io::format::PrintArg::from($val),)*
+        ], &[$(stringify!($name)),*])
+    }};
+    ({ $($call:tt)* } $fmt:expr; [$($pos:tt)*] [$($named:tt)*] $name:ident = $val:expr $(, $($rest:tt)*)?) => {
+        $crate::format_call!({ $($call)* } $fmt; [$($pos)*] [$($named)* $name = $val,] $($($rest)*)?)
+    };
+    ({ $($call:tt)* } $fmt:expr; [$($pos:tt)*] [$($named:tt)*] $arg:expr $(, $($rest:tt)*)?) => {
+        $crate::format_call!({ $($call)* } $fmt; [$($pos)* $arg,] [$($named)*] $($($rest)*)?)
+    };
+}
+
+// ──────────────────────────────────────────────
+//  Spec — a parsed `{:...}` placeholder
+// ──────────────────────────────────────────────
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Kind {
+    Default,
+    Hex,
+    HexUpper,
+    Binary,
+    Octal,
+    Ptr,
+    Debug,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct Spec {
+    fill: u8,
+    align: Option<Align>,
+    plus: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    kind: Kind,
+}
+
+impl Spec {
+    const DEFAULT: Spec = Spec {
+        fill: b' ',
+        align: None,
+        plus: false,
+        alternate: false,
+        zero: false,
+        width: 0,
+        precision: None,
+        kind: Kind::Default,
+    };
+
+    /// Base, digit case and prefix of a number.
+    fn radix(&self) -> (u32, bool, &'static str) {
+        match (self.kind, self.alternate) {
+            (Kind::Default, _)      => (10, false, ""),
+            (Kind::Debug, _)        => (10, false, ""),
+            (Kind::Hex, false)      => (16, false, ""),
+            (Kind::Hex, true)       => (16, false, "0x"),
+            (Kind::HexUpper, false) => (16, true,  ""),
+            (Kind::HexUpper, true)  => (16, true,  "0X"),
+            (Kind::Binary, false)   => ( 2, false, ""),
+            (Kind::Binary, true)    => ( 2, false, "0b"),
+            (Kind::Octal, false)    => ( 8, false, ""),
+            (Kind::Octal, true)     => ( 8, false, "0o"),
+            (Kind::Ptr, _)          => (16, false, "0x"),
+        }
+    }
+}
+
+fn align_of(c: u8) -> Option<Align> {
+    match c {
+        b'<' => Some(Align::Left),
+        b'>' => Some(Align::Right),
+        b'^' => Some(Align::Center),
+        _ => None,
+    }
+}
+
+/// Reads a decimal number at `s[*i..]`, advancing `i`. `None` if there is
+/// no digit.
+fn parse_decimal(s: &[u8], i: &mut usize) -> Option<usize> {
+    let start = *i;
+    let mut n: usize = 0;
+    while let Some(&c) = s.get(*i) {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        n = n.saturating_mul(10).saturating_add((c - b'0') as usize);
+        *i += 1;
+    }
+    if *i == start { None } else { Some(n) }
+}
+
+/// Parses the text between `{` and `}`. Anything before the `:` is ignored.
+fn parse_spec(fmt: &[u8], start: usize, end: usize) -> Spec {
+    let mut spec = Spec::DEFAULT;
+    let content = unsafe { fmt.get_unchecked(start..end) };
+    let Some(colon) = content.iter().position(|&c| c == b':') else {
+        return spec;
+    };
+    let s = unsafe { content.get_unchecked(colon + 1..) };
+    let mut i = 0;
+
+    // [[fill]align]
+    if let Some(align) = s.get(1).and_then(|&c| align_of(c)) {
+        spec.fill = unsafe { *s.get_unchecked(0) };
+        spec.align = Some(align);
+        i = 2;
+    } else if let Some(align) = s.first().and_then(|&c| align_of(c)) {
+        spec.align = Some(align);
+        i = 1;
+    }
+    // [sign]['#']['0']
+    match s.get(i) {
+        Some(b'+') => {
+            spec.plus = true;
+            i += 1;
+        }
+        Some(b'-') => i += 1,
+        _ => {}
+    }
+    if s.get(i) == Some(&b'#') {
+        spec.alternate = true;
+        i += 1;
+    }
+    if s.get(i) == Some(&b'0') {
+        spec.zero = true;
+        i += 1;
+    }
+    // [width]['.' precision]
+    if let Some(width) = parse_decimal(s, &mut i) {
+        spec.width = width;
+    }
+    if s.get(i) == Some(&b'.') {
+        i += 1;
+        spec.precision = Some(parse_decimal(s, &mut i).unwrap_or(0));
+    }
+    // [type]
+    spec.kind = match s.get(i) {
+        Some(b'x') => Kind::Hex,
+        Some(b'X') => Kind::HexUpper,
+        Some(b'b') => Kind::Binary,
+        Some(b'o') => Kind::Octal,
+        Some(b'p') => Kind::Ptr,
+        Some(b'?') => Kind::Debug,
+        _ => Kind::Default,
+    };
+    spec
+}
+
+// ──────────────────────────────────────────────
+//  Padding
+// ──────────────────────────────────────────────
+
+fn fill(out: &mut dyn Output, c: u8, count: usize) {
+    for _ in 0..count {
+        out.raw(c);
+    }
+}
+
+/// Emits `body`, `len` characters long, padded to the spec width.
+fn padded(out: &mut dyn Output, spec: &Spec, len: usize, default: Align, body: impl FnOnce(&mut dyn Output)) {
+    let padding = spec.width.saturating_sub(len);
+    let (before, after) = match spec.align.unwrap_or(default) {
+        Align::Left => (0, padding),
+        Align::Right => (padding, 0),
+        Align::Center => (padding / 2, padding - padding / 2),
+    };
+    fill(out, spec.fill, before);
+    body(out);
+    fill(out, spec.fill, after);
+}
+
+// ──────────────────────────────────────────────
+//  itoa — number → string on a stack buffer
+// ──────────────────────────────────────────────
+
+const ITOA_BUF_SIZE: usize = 64; // 64-bit binary
+
+/// Writes the digits of `val` at the end of `buf`, returning where they start.
+fn u64_to_base(mut val: u64, base: u32, uppercase: bool, buf: &mut [u8; ITOA_BUF_SIZE]) -> usize {
+    if val == 0 {
+        unsafe { *buf.get_unchecked_mut(ITOA_BUF_SIZE - 1) = b'0'; }
+        return ITOA_BUF_SIZE - 1;
+    }
+    // Lets the compiler drop the division-by-zero panic path
+    let base = base.max(2);
+    let mut i = ITOA_BUF_SIZE;
+    while val > 0 {
+        i -= 1;
+        let (quotient, digit) = number::div_rem_u64(val, base);
+        let digit = digit as u8;
+        unsafe {
+            *buf.get_unchecked_mut(i) = if digit < 10 {
+                b'0' + digit
+            } else if uppercase {
+                b'A' + (digit - 10)
+            } else {
+                b'a' + (digit - 10)
+            };
+        }
+        val = quotient;
+    }
+    i
+}
+
+fn write_number(out: &mut dyn Output, negative: bool, magnitude: u64, spec: &Spec) {
+    let (base, uppercase, prefix) = spec.radix();
+    let mut buf = [0u8; ITOA_BUF_SIZE];
+    let start = u64_to_base(magnitude, base, uppercase, &mut buf);
+    let digits = unsafe { buf.get_unchecked(start..) };
+    let sign: &[u8] = if negative { b"-" } else if spec.plus { b"+" } else { b"" };
+    let len = sign.len() + prefix.len() + digits.len();
+
+    let emit = |out: &mut dyn Output, zeros: usize| {
+        for &c in sign.iter().chain(prefix.as_bytes()) {
+            out.raw(c);
+        }
+        fill(out, b'0', zeros);
+        for &c in digits {
+            out.raw(c);
+        }
+    };
+    if spec.zero {
+        // Zero padding goes between the sign/prefix and the digits
+        emit(out, spec.width.saturating_sub(len));
+    } else {
+        padded(out, spec, len, Align::Right, |out| emit(out, 0));
+    }
+}
+
+fn write_text(out: &mut dyn Output, text: &[u8], spec: &Spec) {
+    if spec.kind == Kind::Debug {
+        return write_quoted(out, text, b'"', spec);
+    }
+    let text = match spec.precision {
+        Some(max) if max < text.len() => unsafe { text.get_unchecked(..max) },
+        _ => text,
+    };
+    padded(out, spec, text.len(), Align::Left, |out| out.text(text));
+}
+
+/// Escape sequence for `c` inside a `quote`-delimited debug string.
+fn escape(c: u8, quote: u8) -> Option<&'static [u8]> {
+    match c {
+        b'\n' => Some(b"\\n"),
+        b'\t' => Some(b"\\t"),
+        b'\r' => Some(b"\\r"),
+        0 => Some(b"\\0"),
+        b'\\' => Some(b"\\\\"),
+        b'"' if quote == b'"' => Some(b"\\\""),
+        b'\'' if quote == b'\'' => Some(b"\\'"),
+        _ => None,
+    }
+}
+
+fn escaped_len(c: u8, quote: u8) -> usize {
+    match escape(c, quote) {
+        Some(seq) => seq.len(),
+        None if c.is_ascii_graphic() || c == b' ' => 1,
+        None => 4,
+    }
+}
+
+/// `{:?}` of text: quoted, with control characters escaped.
+fn write_quoted(out: &mut dyn Output, text: &[u8], quote: u8, spec: &Spec) {
+    let len = 2 + text.iter().map(|&c| escaped_len(c, quote)).sum::<usize>();
+    padded(out, spec, len, Align::Left, |out| {
+        out.raw(quote);
+        for &c in text {
+            match escape(c, quote) {
+                Some(seq) => seq.iter().for_each(|&e| out.raw(e)),
+                None if c.is_ascii_graphic() || c == b' ' => out.raw(c),
+                None => {
+                    const HEX: &[u8; 16] = b"0123456789abcdef";
+                    out.raw(b'\\');
+                    out.raw(b'x');
+                    out.raw(unsafe { *HEX.get_unchecked((c >> 4) as usize) });
+                    out.raw(unsafe { *HEX.get_unchecked((c & 0xF) as usize) });
+                }
+            }
+        }
+        out.raw(quote);
+    });
+}
+
+/// Runs a `KDisplay` / `KDebug` implementation, padded to the spec width.
+fn write_custom(out: &mut dyn Output, spec: &Spec, fmt: impl Fn(&mut Formatter)) {
+    let alternate = spec.alternate;
+    let len = if spec.width > 0 {
+        let mut counter = Counter(0);
+        fmt(&mut Formatter { out: &mut counter, alternate });
+        counter.0
+    } else {
+        0
+    };
+    padded(out, spec, len, Align::Left, |out| fmt(&mut Formatter { out, alternate }));
+}
+
+// ──────────────────────────────────────────────
+//  Format string parser
+// ──────────────────────────────────────────────
+
+fn write_arg(out: &mut dyn Output, arg: &PrintArg, spec: &Spec) {
+    match *arg {
+        PrintArg::Str(s)     => write_text(out, s.as_bytes(), spec),
+        PrintArg::Bytes(b)   => write_text(out, b, spec),
+        PrintArg::Char(c) if spec.kind == Kind::Debug => {
+            write_quoted(out, core::slice::from_ref(&c), b'\'', spec)
+        }
+        PrintArg::Char(c)    => padded(out, spec, 1, Align::Left, |out| out.raw(c)),
+        PrintArg::I32(v)     => write_number(out, v < 0, v.unsigned_abs() as u64, spec),
+        PrintArg::U32(v)     => write_number(out, false, v as u64, spec),
+        PrintArg::Usize(v)   => write_number(out, false, v as u64, spec),
+        PrintArg::U64(v)     => write_number(out, false, v, spec),
+        PrintArg::I64(v)     => write_number(out, v < 0, v.unsigned_abs(), spec),
+        PrintArg::Bool(v)    => {
+            let text: &[u8] = if v { b"true" } else { b"false" };
+            padded(out, spec, text.len(), Align::Left, |out| out.text(text))
+        }
+        PrintArg::Ptr(v)     => write_number(out, false, v as u32 as u64, spec),
+        PrintArg::Display(v) => write_custom(out, spec, |f| v.fmt(f)),
+        PrintArg::Debug(v)   => write_custom(out, spec, |f| v.fmt(f)),
+        #[cfg(feature = "core-fmt")]
+        PrintArg::Fmt(v)     => write_custom(out, spec, |f| {
+            let _ = fmt::Write::write_fmt(f, v);
+        }),
+    }
+}
+
+/// Resolves the argument part of a placeholder (before any `:`) to an
+/// index into `args`. Named arguments are the last `names.len()` ones.
+fn select(content: &[u8], next: &mut usize, args: usize, names: &[&str]) -> Option<usize> {
+    let end = content.iter().position(|&c| c == b':').unwrap_or(content.len());
+    let selector = unsafe { content.get_unchecked(..end) };
+    if selector.is_empty() {
+        let idx = *next;
+        *next += 1;
+        return Some(idx);
+    }
+    if selector.iter().all(|c| c.is_ascii_digit()) {
+        let mut i = 0;
+        return parse_decimal(selector, &mut i);
+    }
+    let named = names.iter().position(|name| {
+        let name = name.as_bytes();
+        name.len() == selector.len() && name.iter().zip(selector.iter()).all(|(a, b)| a == b)
+    })?;
+    Some(args.checked_sub(names.len())? + named)
+}
+
+/// Renders `fmt` with `args` into `out`; `names` are the names of the
+/// trailing `name = value` arguments. Placeholders without a matching
+/// argument print nothing and extra arguments are ignored.
+pub fn format(fmt: &str, args: &[PrintArg], names: &[&str], out: &mut dyn Output) {
+    let bytes = fmt.as_bytes();
+    let len = bytes.len();
+    let mut i: usize = 0;
+    let mut next_arg: usize = 0;
+
+    while i < len {
+        let ch = unsafe { *bytes.get_unchecked(i) };
+
+        if ch == b'{' {
+            if i + 1 < len && unsafe { *bytes.get_unchecked(i + 1) } == b'{' {
+                out.raw(b'{');
+                i += 2;
+                continue;
+            }
+            let spec_start = i + 1;
+            let mut j = spec_start;
+            while j < len && unsafe { *bytes.get_unchecked(j) } != b'}' {
+                j += 1;
+            }
+            let spec = parse_spec(bytes, spec_start, j);
+            let content = unsafe { bytes.get_unchecked(spec_start..j) };
+            if let Some(arg) = select(content, &mut next_arg, args.len(), names).and_then(|idx| args.get(idx)) {
+                write_arg(out, arg, &spec);
+            }
+            i = j + 1;
+            continue;
+        }
+        if ch == b'}' && i + 1 < len && unsafe { *bytes.get_unchecked(i + 1) } == b'}' {
+            out.raw(b'}');
+            i += 2;
+            continue;
+        }
+        // Regular character — needs control-char interpretation (\n, \t, etc.)
+        out.text(core::slice::from_ref(&ch));
+        i += 1;
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Compile-time check
+// ──────────────────────────────────────────────
+
+// Everything here is `#[inline]` so it is only generated where used, i.e.
+// never in the kernel image: the bounds checks would pull in core's panic
+// handlers, which `make link` does not provide.
+
+#[inline]
+const fn is_align(c: u8) -> bool {
+    c == b'<' || c == b'>' || c == b'^'
+}
+
+/// Validates the part of a placeholder after the `:`, `s[start..end]`.
+#[inline]
+const fn check_spec(s: &[u8], start: usize, end: usize) {
+    let mut i = start;
+    if end - start >= 2 && is_align(s[start + 1]) {
+        if !s[start].is_ascii() {
+            panic!("format string: the fill character must be ASCII");
+        }
+        i += 2;
+    } else if end > start && is_align(s[start]) {
+        i += 1;
+    }
+    if i < end && (s[i] == b'+' || s[i] == b'-') {
+        i += 1;
+    }
+    if i < end && s[i] == b'#' {
+        i += 1;
+    }
+    while i < end && s[i].is_ascii_digit() {
+        i += 1;
+    }
+    if i < end && s[i] == b'.' {
+        i += 1;
+        if !(i < end && s[i].is_ascii_digit()) {
+            panic!("format string: missing precision after `.`");
+        }
+        while i < end && s[i].is_ascii_digit() {
+            i += 1;
+        }
+    }
+    if i < end && matches!(s[i], b'x' | b'X' | b'b' | b'o' | b'p' | b'?') {
+        i += 1;
+    }
+    if i != end {
+        panic!("format string: unknown format specifier");
+    }
+}
+
+/// Returns `true` if `s[start..end]` is `name`.
+#[inline]
+const fn name_is(s: &[u8], start: usize, end: usize, name: &str) -> bool {
+    let name = name.as_bytes();
+    if name.len() != end - start {
+        return false;
+    }
+    let mut i = 0;
+    while i < name.len() {
+        if s[start + i] != name[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Resolves the argument part of a placeholder, `s[start..end]`, to an
+/// argument index, like `select` does at run time.
+#[inline]
+const fn check_selector(s: &[u8], start: usize, end: usize, next: &mut usize, positional: usize, names: &[&str]) -> usize {
+    if start == end {
+        let idx = *next;
+        *next += 1;
+        return idx;
+    }
+    if s[start].is_ascii_digit() {
+        let mut idx = 0;
+        let mut i = start;
+        while i < end {
+            if !s[i].is_ascii_digit() {
+                panic!("format string: invalid argument index");
+            }
+            idx = idx * 10 + (s[i] - b'0') as usize;
+            i += 1;
+        }
+        return idx;
+    }
+    let mut n = 0;
+    while n < names.len() {
+        if name_is(s, start, end, names[n]) {
+            return positional + n;
+        }
+        n += 1;
+    }
+    panic!("format string: unknown named argument");
+}
+
+/// Checks a format string against its arguments: `positional` unnamed
+/// ones followed by `names`. Meant for const evaluation, where a panic is
+/// a compile error; the printing macros call it for every format string.
+///
+/// Rejects unmatched braces, unknown specifiers, placeholders without an
+/// argument and arguments that no placeholder uses.
+#[inline]
+pub const fn check(fmt: &str, positional: usize, names: &[&str]) {
+    let s = fmt.as_bytes();
+    let total = positional + names.len();
+    if total > 64 {
+        panic!("format string: more than 64 arguments");
+    }
+    let mut used: u64 = 0;
+    let mut next = 0;
+    let mut i = 0;
+
+    while i < s.len() {
+        if s[i] == b'}' {
+            if i + 1 < s.len() && s[i + 1] == b'}' {
+                i += 2;
+                continue;
+            }
+            panic!("format string: unmatched `}}` (write `}}}}` for a literal brace)");
+        }
+        if s[i] != b'{' {
+            i += 1;
+            continue;
+        }
+        if i + 1 < s.len() && s[i + 1] == b'{' {
+            i += 2;
+            continue;
+        }
+
+        let start = i + 1;
+        let mut colon = start;
+        while colon < s.len() && s[colon] != b':' && s[colon] != b'}' {
+            colon += 1;
+        }
+        let mut end = colon;
+        while end < s.len() && s[end] != b'}' {
+            end += 1;
+        }
+        if end == s.len() {
+            panic!("format string: unmatched `{{` (write `{{{{` for a literal brace)");
+        }
+
+        let idx = check_selector(s, start, colon, &mut next, positional, names);
+        if idx >= total {
+            panic!("format string: placeholder without a matching argument");
+        }
+        used |= 1 << idx;
+        if colon < end {
+            check_spec(s, colon + 1, end);
+        }
+        i = end + 1;
+    }
+
+    if total > 0 && used != u64::MAX >> (64 - total) {
+        panic!("format string: argument never used");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collects the output in a fixed buffer.
+    fn render(fmt: &str, args: &[PrintArg]) -> ([u8; 160], usize) {
+        render_named(fmt, args, &[])
+    }
+
+    macro_rules! check {
+        ($expected:expr, $fmt:expr $(, $arg:expr)*) => {{
+            let (data, len) = render($fmt, &[$(PrintArg::from($arg)),*]);
+            assert_eq!(core::str::from_utf8(&data[..len]).unwrap(), $expected);
+        }};
+    }
+
+    // ── parse_spec ──
+
+    #[test]
+    fn parse_spec_empty() {
+        assert_eq!(parse_spec(b"", 0, 0), Spec::DEFAULT);
+        assert_eq!(parse_spec(b":", 0, 1), Spec::DEFAULT);
+    }
+
+    #[test]
+    fn parse_spec_full() {
+        let fmt = b":*^+#012.3x";
+        let spec = parse_spec(fmt, 0, fmt.len());
+        assert_eq!(spec.fill, b'*');
+        assert_eq!(spec.align, Some(Align::Center));
+        assert!(spec.plus && spec.alternate && spec.zero);
+        assert_eq!(spec.width, 12);
+        assert_eq!(spec.precision, Some(3));
+        assert_eq!(spec.kind, Kind::Hex);
+    }
+
+    #[test]
+    fn parse_spec_align_without_fill() {
+        let spec = parse_spec(b":<5", 0, 3);
+        assert_eq!(spec.fill, b' ');
+        assert_eq!(spec.align, Some(Align::Left));
+        assert_eq!(spec.width, 5);
+    }
+
+    #[test]
+    fn parse_spec_fill_is_align_char() {
+        let spec = parse_spec(b":>>4", 0, 4);
+        assert_eq!(spec.fill, b'>');
+        assert_eq!(spec.align, Some(Align::Right));
+        assert_eq!(spec.width, 4);
+    }
+
+    // ── basic types ──
+
+    #[test]
+    fn format_literals_and_braces() {
+        check!("a {} b", "a {{}} b");
+        check!("no args: ", "no args: {}");
+    }
+
+    #[test]
+    fn format_default() {
+        check!("42 -7 abc x true", "{} {} {} {} {}", 42_u32, -7_i32, "abc", 'x', true);
+    }
+
+    #[test]
+    fn format_bases() {
+        check!("ff FF 101 17", "{:x} {:X} {:b} {:o}", 255_u32, 255_u32, 5_u32, 15_u32);
+        check!("0xff 0XFF 0b101 0o17", "{:#x} {:#X} {:#b} {:#o}", 255_u32, 255_u32, 5_u32, 15_u32);
+    }
+
+    #[test]
+    fn format_i32_min() {
+        check!("-2147483648", "{}", i32::MIN);
+    }
+
+    // ── width and alignment ──
+
+    #[test]
+    fn format_width_default_alignment() {
+        check!("[   42]", "[{:5}]", 42_u32);
+        check!("[ab   ]", "[{:5}]", "ab");
+    }
+
+    #[test]
+    fn format_explicit_alignment() {
+        check!("[42   ]", "[{:<5}]", 42_u32);
+        check!("[   ab]", "[{:>5}]", "ab");
+        check!("[ ab  ]", "[{:^5}]", "ab");
+        check!("[ 42 ]", "[{:^4}]", 42_u32);
+    }
+
+    #[test]
+    fn format_custom_fill() {
+        check!("[***ab]", "[{:*>5}]", "ab");
+        check!("[-x--]", "[{:-^4}]", 'x');
+        check!("[7....]", "[{:.<5}]", 7_u32);
+    }
+
+    #[test]
+    fn format_width_smaller_than_value() {
+        check!("abcdef", "{:3}", "abcdef");
+        check!("123456", "{:2}", 123456_u32);
+    }
+
+    // ── zero padding ──
+
+    #[test]
+    fn format_zero_padding() {
+        check!("000000ff", "{:08x}", 255_u32);
+        check!("0x000800", "{:#08x}", 0x800_u32);
+        check!("0x00000800", "{:#010x}", 0x800_u32);
+        check!("-0042", "{:05}", -42_i32);
+    }
+
+    #[test]
+    fn format_zero_padding_ignores_alignment() {
+        check!("00042", "{:<05}", 42_u32);
+    }
+
+    // ── sign ──
+
+    #[test]
+    fn format_plus_sign() {
+        check!("+5 -5 +0", "{:+} {:+} {:+}", 5_i32, -5_i32, 0_u32);
+        check!("+0005", "{:+05}", 5_i32);
+        check!("[  +5]", "[{:>+4}]", 5_i32);
+    }
+
+    // ── precision ──
+
+    #[test]
+    fn format_precision_truncates_strings() {
+        check!("abc", "{:.3}", "abcdef");
+        check!("ab", "{:.3}", "ab");
+        check!("", "{:.0}", "abc");
+        check!("[ab   ]", "[{:5.2}]", b"abcdef" as &[u8]);
+    }
+
+    #[test]
+    fn format_precision_ignored_for_numbers() {
+        check!("1234", "{:.2}", 1234_u32);
+    }
+
+    // ── 64-bit integers ──
+
+    #[test]
+    fn format_u64() {
+        check!("18446744073709551615", "{}", u64::MAX);
+        check!("ffffffffffffffff", "{:x}", u64::MAX);
+        check!("0x0000000100000000", "{:#018x}", 1_u64 << 32);
+        check!("1000000000000", "{}", 1_000_000_000_000_u64);
+    }
+
+    #[test]
+    fn format_u64_binary() {
+        let (data, len) = render("{:b}", &[PrintArg::from(u64::MAX)]);
+        assert_eq!(len, 64);
+        assert!(data[..len].iter().all(|&c| c == b'1'));
+    }
+
+    #[test]
+    fn format_i64() {
+        check!("-9223372036854775808", "{}", i64::MIN);
+        check!("+9223372036854775807", "{:+}", i64::MAX);
+        check!("-0000000042", "{:011}", -42_i64);
+    }
+
+    // ── debug ──
+
+    #[test]
+    fn format_debug_text() {
+        check!("\"tacos\"", "{:?}", "tacos");
+        check!("\"a\\n\\\"b\\\"\"", "{:?}", "a\n\"b\"");
+        check!("\"\\x01\"", "{:?}", b"\x01" as &[u8]);
+        check!("'x' '\\''", "{:?} {:?}", 'x', '\'');
+        check!("[\"ab\"  ]", "[{:6?}]", "ab");
+    }
+
+    #[test]
+    fn format_debug_numbers_and_bools() {
+        check!("42 -1 true", "{:?} {:?} {:?}", 42_u32, -1_i32, true);
+    }
+
+    // ── KDisplay / KDebug ──
+
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    impl KDisplay for Point {
+        fn fmt(&self, f: &mut Formatter) {
+            crate::kwrite!(f, "({}, {})", self.x, self.y);
+        }
+    }
+
+    impl KDebug for Point {
+        fn fmt(&self, f: &mut Formatter) {
+            if f.alternate() {
+                crate::kwrite!(f, "Point {{\n    x: {},\n    y: {},\n}}", self.x, self.y);
+            } else {
+                crate::kwrite!(f, "Point {{ x: {}, y: {} }}", self.x, self.y);
+            }
+        }
+    }
+
+    #[test]
+    fn format_display_trait() {
+        let p = Point { x: 1, y: -2 };
+        check!("at (1, -2)", "at {}", &p);
+        check!("[(1, -2)   ]", "[{:10}]", &p);
+        check!("[   (1, -2)]", "[{:>10}]", &p);
+    }
+
+    #[test]
+    fn format_debug_trait() {
+        let p = Point { x: 3, y: 4 };
+        check!("Point { x: 3, y: 4 }", "{:?}", PrintArg::debug(&p));
+        check!("Point {\n    x: 3,\n    y: 4,\n}", "{:#?}", PrintArg::debug(&p));
+    }
+
+    // ── positional and named arguments ──
+
+    fn render_named(fmt: &str, args: &[PrintArg], names: &[&str]) -> ([u8; 160], usize) {
+        let mut data = [0; 160];
+        let len = BufWriter::new(&mut data).format(fmt, args, names).expect("test output fits").len();
+        (data, len)
+    }
+
+    #[test]
+    fn format_positional() {
+        check!("b a b", "{1} {0} {1}", "a", "b");
+        check!("ff 255", "{0:x} {0}", 255_u32);
+        check!("x y x", "{} {} {0}", "x", "y");
+    }
+
+    #[test]
+    fn format_named() {
+        let (data, len) = render_named(
+            "{addr:#06x} ({size} bytes) {}",
+            &[PrintArg::from("ok"), PrintArg::from(0x800_u32), PrintArg::from(56_u32)],
+            &["addr", "size"],
+        );
+        assert_eq!(&data[..len], b"0x0800 (56 bytes) ok");
+    }
+
+    // ── check ──
+
+    #[test]
+    fn check_accepts_valid_strings() {
+        check("plain", 0, &[]);
+        check("{{}} {} {:#010x} {:*^9} {:+.3?}", 4, &[]);
+        check("{1} {0}", 2, &[]);
+        check("{name:>8} {}", 1, &["name"]);
+        check("{0} {name} {name}", 1, &["name"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "placeholder without a matching argument")]
+    fn check_missing_argument() {
+        check("{} {}", 1, &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "argument never used")]
+    fn check_unused_argument() {
+        check("{}", 2, &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "placeholder without a matching argument")]
+    fn check_index_out_of_range() {
+        check("{2}", 2, &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown named argument")]
+    fn check_unknown_name() {
+        check("{nope}", 0, &["name"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown format specifier")]
+    fn check_unknown_specifier() {
+        check("{:q}", 1, &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "unmatched `{`")]
+    fn check_unclosed_brace() {
+        check("{:x", 1, &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "unmatched `}`")]
+    fn check_stray_closing_brace() {
+        check("}", 0, &[]);
+    }
+
+    // ── kformat ──
+
+    #[test]
+    fn kformat_fits() {
+        let mut buf = [0u8; 16];
+        assert_eq!(kformat!(buf, "{}x{}", 80_u32, 25_u32), Ok(&b"80x25"[..]));
+        let mut exact = [0u8; 5];
+        assert_eq!(kformat!(exact, "{:05}", 42_u32), Ok(&b"00042"[..]));
+    }
+
+    #[test]
+    fn kformat_truncates() {
+        let mut buf = [0u8; 4];
+        let result = kformat!(buf, "{:#010x}", 0x800_u32);
+        assert_eq!(result, Err(Truncated { written: b"0x00", needed: 10 }));
+    }
+
+    #[test]
+    fn kformat_into_slice() {
+        let mut buf = [0u8; 16];
+        let slice = &mut buf[4..];
+        assert_eq!(kformat!(slice, "{name}", name = "taco"), Ok(&b"taco"[..]));
+    }
+
+    #[test]
+    fn buf_writer_core_fmt() {
+        use core::fmt::Write;
+        let mut buf = [0u8; 8];
+        let mut w = BufWriter::new(&mut buf);
+        assert!(write!(w, "{}-{}", 1, 2).is_ok());
+        assert_eq!(w.as_bytes(), b"1-2");
+        assert!(w.write_str("too long").is_err());
+        assert!(w.is_truncated());
+    }
+
+    #[cfg(feature = "core-fmt")]
+    #[test]
+    fn format_args_argument() {
+        #[derive(Debug)]
+        #[allow(dead_code)]
+        struct Pair(u8, bool);
+        check!("Pair(1, true)", "{}", format_args!("{:?}", Pair(1, true)));
+        check!("[ Pair(2, false)]", "[{:>15}]", format_args!("{:?}", Pair(2, false)));
+    }
+}
@@ -1,7 +1,11 @@
-/// Line-editing input buffer.
-///
-/// Fixed-size buffer with cursor position, supporting
-/// insert, delete, and cursor movement.
+//! Line-editing input buffer.
+//!
+//! Fixed-size buffer with cursor position, supporting
+//! insert, delete, cursor movement and Emacs-style editing:
+//! word motion, and kill/yank through a one-entry kill buffer.
+
+use crate::io::format::{Formatter, KDebug, KDisplay};
+use crate::kwrite;
 
 const BUFFER_SIZE: usize = 78;
 
@@ -9,6 +13,14 @@ pub struct InputBuffer {
     pub buffer: [u8; BUFFER_SIZE],
     pub len: usize,
     pub pos: usize,
+    yank: [u8; BUFFER_SIZE],
+    yank_len: usize,
+}
+
+impl Default for InputBuffer {
+    fn default() -> Self {
+        InputBuffer::new()
+    }
 }
 
 impl InputBuffer {
@@ -17,9 +29,16 @@ impl InputBuffer {
             buffer: [0; BUFFER_SIZE],
             len: 0,
             pos: 0,
+            yank: [0; BUFFER_SIZE],
+            yank_len: 0,
         }
     }
 
+    #[inline(always)]
+    fn at(&self, i: usize) -> u8 {
+        unsafe { *self.buffer.get_unchecked(i) }
+    }
+
     /// Inserts `c` at cursor. Returns `false` if full.
     pub fn insert_char(&mut self, c: u8, max_len: usize) -> bool {
         let max = max_len.min(BUFFER_SIZE - 1);
@@ -67,6 +86,16 @@ impl InputBuffer {
         true
     }
 
+    /// Replaces the content with `line` (truncated), cursor at the end.
+    pub fn set(&mut self, line: &[u8], max_len: usize) {
+        let len = line.len().min(max_len).min(BUFFER_SIZE - 1);
+        for i in 0..len {
+            unsafe { *self.buffer.get_unchecked_mut(i) = *line.get_unchecked(i); }
+        }
+        self.len = len;
+        self.pos = len;
+    }
+
     /// Returns `true` if cursor can move left.
     pub fn can_move_left(&self) -> bool {
         self.pos > 0
@@ -91,6 +120,102 @@ impl InputBuffer {
         }
     }
 
+    /// Moves cursor to the start of the line.
+    pub fn move_home(&mut self) {
+        self.pos = 0;
+    }
+
+    /// Moves cursor to the end of the line.
+    pub fn move_end(&mut self) {
+        self.pos = self.len;
+    }
+
+    /// Moves cursor to the start of the current or previous word.
+    pub fn move_word_left(&mut self) {
+        while self.pos > 0 && !self.at(self.pos - 1).is_ascii_alphanumeric() {
+            self.pos -= 1;
+        }
+        while self.pos > 0 && self.at(self.pos - 1).is_ascii_alphanumeric() {
+            self.pos -= 1;
+        }
+    }
+
+    /// Moves cursor to the end of the current or next word.
+    pub fn move_word_right(&mut self) {
+        while self.pos < self.len && !self.at(self.pos).is_ascii_alphanumeric() {
+            self.pos += 1;
+        }
+        while self.pos < self.len && self.at(self.pos).is_ascii_alphanumeric() {
+            self.pos += 1;
+        }
+    }
+
+    /// Removes the character under the cursor (delete).
+    pub fn delete_char(&mut self) -> bool {
+        if self.pos >= self.len {
+            return false;
+        }
+        self.pos += 1;
+        self.remove_char()
+    }
+
+    /// Removes `from..to`, saving it in the kill buffer. Cursor ends at `from`.
+    fn kill_range(&mut self, from: usize, to: usize) -> bool {
+        if from >= to {
+            return false;
+        }
+        let count = to - from;
+        for i in 0..count {
+            unsafe { *self.yank.get_unchecked_mut(i) = self.at(from + i); }
+        }
+        self.yank_len = count;
+
+        let mut i = from;
+        while i + count < self.len {
+            unsafe { *self.buffer.get_unchecked_mut(i) = self.at(i + count); }
+            i += 1;
+        }
+        self.len -= count;
+        self.pos = from;
+        true
+    }
+
+    /// Kills from cursor to end of line (Ctrl+K).
+    pub fn kill_to_end(&mut self) -> bool {
+        self.kill_range(self.pos, self.len)
+    }
+
+    /// Kills from start of line to cursor (Ctrl+U).
+    pub fn kill_to_start(&mut self) -> bool {
+        self.kill_range(0, self.pos)
+    }
+
+    /// Kills the whitespace-delimited word before the cursor (Ctrl+W).
+    pub fn kill_word_before(&mut self) -> bool {
+        let mut start = self.pos;
+        while start > 0 && self.at(start - 1) == b' ' {
+            start -= 1;
+        }
+        while start > 0 && self.at(start - 1) != b' ' {
+            start -= 1;
+        }
+        self.kill_range(start, self.pos)
+    }
+
+    /// Inserts the kill buffer at cursor (Ctrl+Y). Returns `false` if
+    /// nothing could be inserted.
+    pub fn yank(&mut self, max_len: usize) -> bool {
+        let mut inserted = false;
+        for i in 0..self.yank_len {
+            let c = unsafe { *self.yank.get_unchecked(i) };
+            if !self.insert_char(c, max_len) {
+                break;
+            }
+            inserted = true;
+        }
+        inserted
+    }
+
     /// Returns the buffer content and resets state.
     pub fn flush(&mut self) -> &[u8] {
         let len = self.len.min(BUFFER_SIZE);
@@ -116,58 +241,339 @@ impl InputBuffer {
     }
 }
 
+/// The line being edited.
+impl KDisplay for InputBuffer {
+    fn fmt(&self, f: &mut Formatter) {
+        f.write_bytes(self.get_buffer());
+    }
+}
+
+impl KDebug for InputBuffer {
+    fn fmt(&self, f: &mut Formatter) {
+        let yank = unsafe { self.yank.get_unchecked(..self.yank_len.min(BUFFER_SIZE)) };
+        kwrite!(
+            f,
+            "InputBuffer {{ text: {:?}, len: {}, pos: {}, yank: {:?} }}",
+            self.get_buffer(),
+            self.len,
+            self.pos,
+            yank
+        );
+    }
+}
+
 // ──────────────────────────────────────────────
 //  Global instance + free-function wrappers
 // ──────────────────────────────────────────────
 
 static mut INPUT: InputBuffer = InputBuffer::new();
 
+fn input() -> &'static mut InputBuffer {
+    unsafe { &mut *core::ptr::addr_of_mut!(INPUT) }
+}
+
+fn input_ref() -> &'static InputBuffer {
+    unsafe { &*core::ptr::addr_of!(INPUT) }
+}
+
 /// Inserts `c` at cursor.
 pub fn insert_char(c: u8, max_len: usize) -> bool {
-    unsafe { (*&raw mut INPUT).insert_char(c, max_len) }
+    input().insert_char(c, max_len)
 }
 
 /// Removes the character before the cursor.
 pub fn remove_char() -> bool {
-    unsafe { (*&raw mut INPUT).remove_char() }
+    input().remove_char()
+}
+
+/// Replaces the content with `line`, cursor at the end.
+pub fn set(line: &[u8], max_len: usize) {
+    input().set(line, max_len)
 }
 
 /// Returns `true` if cursor can move left.
 pub fn can_move_left() -> bool {
-    unsafe { (*&raw const INPUT).can_move_left() }
+    input_ref().can_move_left()
 }
 
 /// Returns `true` if cursor can move right.
 pub fn can_move_right() -> bool {
-    unsafe { (*&raw const INPUT).can_move_right() }
+    input_ref().can_move_right()
 }
 
 /// Moves cursor one position left.
 pub fn move_left() {
-    unsafe { (*&raw mut INPUT).move_left(); }
+    input().move_left();
 }
 
 /// Moves cursor one position right.
 pub fn move_right() {
-    unsafe { (*&raw mut INPUT).move_right(); }
+    input().move_right();
+}
+
+/// Moves cursor to the start of the line.
+pub fn move_home() {
+    input().move_home();
+}
+
+/// Moves cursor to the end of the line.
+pub fn move_end() {
+    input().move_end();
+}
+
+/// Moves cursor to the start of the current or previous word.
+pub fn move_word_left() {
+    input().move_word_left();
+}
+
+/// Moves cursor to the end of the current or next word.
+pub fn move_word_right() {
+    input().move_word_right();
+}
+
+/// Removes the character under the cursor.
+pub fn delete_char() -> bool {
+    input().delete_char()
+}
+
+/// Kills from cursor to end of line.
+pub fn kill_to_end() -> bool {
+    input().kill_to_end()
+}
+
+/// Kills from start of line to cursor.
+pub fn kill_to_start() -> bool {
+    input().kill_to_start()
+}
+
+/// Kills the word before the cursor.
+pub fn kill_word_before() -> bool {
+    input().kill_word_before()
+}
+
+/// Inserts the kill buffer at cursor.
+pub fn yank(max_len: usize) -> bool {
+    input().yank(max_len)
 }
 
 /// Returns the buffer content and resets state.
 pub fn flush() -> &'static [u8] {
-    unsafe { (*&raw mut INPUT).flush() }
+    input().flush()
 }
 
 /// Returns the active portion of the buffer.
 pub fn get_buffer() -> &'static [u8] {
-    unsafe { (*&raw const INPUT).get_buffer() }
+    input_ref().get_buffer()
 }
 
 /// Returns the number of characters in the buffer.
 pub fn get_len() -> usize {
-    unsafe { (*&raw const INPUT).get_len() }
+    input_ref().get_len()
 }
 
 /// Returns the current cursor position.
 pub fn get_pos() -> usize {
-    unsafe { (*&raw const INPUT).get_pos() }
-}
\ No newline at end of file
+    input_ref().get_pos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::format::{BufWriter, PrintArg};
+
+    const MAX: usize = 64;
+
+    fn with(text: &[u8]) -> InputBuffer {
+        let mut buf = InputBuffer::new();
+        for &c in text {
+            buf.insert_char(c, MAX);
+        }
+        buf
+    }
+
+    // ── insert / remove ──
+
+    #[test]
+    fn insert_appends() {
+        let buf = with(b"abc");
+        assert_eq!(buf.get_buffer(), b"abc");
+        assert_eq!(buf.get_pos(), 3);
+    }
+
+    #[test]
+    fn insert_in_middle() {
+        let mut buf = with(b"ac");
+        buf.move_left();
+        buf.insert_char(b'b', MAX);
+        assert_eq!(buf.get_buffer(), b"abc");
+        assert_eq!(buf.get_pos(), 2);
+    }
+
+    #[test]
+    fn insert_respects_max_len() {
+        let mut buf = with(b"ab");
+        assert!(!buf.insert_char(b'c', 2));
+        assert_eq!(buf.get_buffer(), b"ab");
+    }
+
+    #[test]
+    fn remove_char_before_cursor() {
+        let mut buf = with(b"abc");
+        buf.move_left();
+        assert!(buf.remove_char());
+        assert_eq!(buf.get_buffer(), b"ac");
+        assert_eq!(buf.get_pos(), 1);
+    }
+
+    // ── home / end ──
+
+    #[test]
+    fn home_and_end() {
+        let mut buf = with(b"hello");
+        buf.move_home();
+        assert_eq!(buf.get_pos(), 0);
+        buf.move_end();
+        assert_eq!(buf.get_pos(), 5);
+    }
+
+    // ── delete ──
+
+    #[test]
+    fn delete_under_cursor() {
+        let mut buf = with(b"abc");
+        buf.move_home();
+        assert!(buf.delete_char());
+        assert_eq!(buf.get_buffer(), b"bc");
+        assert_eq!(buf.get_pos(), 0);
+    }
+
+    #[test]
+    fn delete_at_end_does_nothing() {
+        let mut buf = with(b"abc");
+        assert!(!buf.delete_char());
+        assert_eq!(buf.get_buffer(), b"abc");
+    }
+
+    // ── kill / yank ──
+
+    #[test]
+    fn kill_to_end_and_yank() {
+        let mut buf = with(b"echo hello");
+        for _ in 0..5 {
+            buf.move_left();
+        }
+        assert!(buf.kill_to_end());
+        assert_eq!(buf.get_buffer(), b"echo ");
+        assert!(buf.yank(MAX));
+        assert_eq!(buf.get_buffer(), b"echo hello");
+        assert_eq!(buf.get_pos(), 10);
+    }
+
+    #[test]
+    fn kill_to_start_keeps_tail() {
+        let mut buf = with(b"echo hello");
+        for _ in 0..5 {
+            buf.move_left();
+        }
+        assert!(buf.kill_to_start());
+        assert_eq!(buf.get_buffer(), b"hello");
+        assert_eq!(buf.get_pos(), 0);
+        buf.move_end();
+        assert!(buf.yank(MAX));
+        assert_eq!(buf.get_buffer(), b"helloecho ");
+    }
+
+    #[test]
+    fn kill_nothing_keeps_kill_buffer() {
+        let mut buf = with(b"abc def");
+        assert!(buf.kill_word_before());
+        assert!(!buf.kill_to_end());
+        assert!(buf.yank(MAX));
+        assert_eq!(buf.get_buffer(), b"abc def");
+    }
+
+    #[test]
+    fn kill_word_before_skips_trailing_spaces() {
+        let mut buf = with(b"ls foo/bar  ");
+        assert!(buf.kill_word_before());
+        assert_eq!(buf.get_buffer(), b"ls ");
+    }
+
+    #[test]
+    fn kill_word_before_at_start_fails() {
+        let mut buf = with(b"abc");
+        buf.move_home();
+        assert!(!buf.kill_word_before());
+        assert_eq!(buf.get_buffer(), b"abc");
+    }
+
+    #[test]
+    fn yank_empty_does_nothing() {
+        let mut buf = with(b"abc");
+        assert!(!buf.yank(MAX));
+        assert_eq!(buf.get_buffer(), b"abc");
+    }
+
+    #[test]
+    fn yank_stops_when_full() {
+        let mut buf = with(b"abcdef");
+        buf.move_home();
+        buf.kill_to_end();
+        buf.insert_char(b'x', MAX);
+        assert!(buf.yank(4));
+        assert_eq!(buf.get_buffer(), b"xabc");
+    }
+
+    // ── word motion ──
+
+    #[test]
+    fn word_left_skips_punctuation() {
+        let mut buf = with(b"foo-bar  baz");
+        buf.move_word_left();
+        assert_eq!(buf.get_pos(), 9);
+        buf.move_word_left();
+        assert_eq!(buf.get_pos(), 4);
+        buf.move_word_left();
+        assert_eq!(buf.get_pos(), 0);
+        buf.move_word_left();
+        assert_eq!(buf.get_pos(), 0);
+    }
+
+    #[test]
+    fn word_right_stops_at_word_end() {
+        let mut buf = with(b"foo-bar  baz");
+        buf.move_home();
+        buf.move_word_right();
+        assert_eq!(buf.get_pos(), 3);
+        buf.move_word_right();
+        assert_eq!(buf.get_pos(), 7);
+        buf.move_word_right();
+        assert_eq!(buf.get_pos(), 12);
+        buf.move_word_right();
+        assert_eq!(buf.get_pos(), 12);
+    }
+
+    // ── KDisplay / KDebug ──
+
+    fn render<'a>(out: &'a mut [u8; 128], fmt: &str, arg: PrintArg) -> &'a [u8] {
+        BufWriter::new(out).format(fmt, &[arg], &[]).expect("test output fits")
+    }
+
+    #[test]
+    fn display_prints_line() {
+        let buf = with(b"echo hi");
+        let mut out = [0; 128];
+        assert_eq!(render(&mut out, "[{}]", (&buf).into()), b"[echo hi]");
+    }
+
+    #[test]
+    fn debug_prints_state() {
+        let mut buf = with(b"ab cd");
+        buf.kill_word_before();
+        let mut out = [0; 128];
+        assert_eq!(
+            render(&mut out, "{:?}", PrintArg::debug(&buf)),
+            b"InputBuffer { text: \"ab \", len: 3, pos: 3, yank: \"cd\" }"
+        );
+    }
+}
@@ -1,25 +1,63 @@
-/// Keyboard event dispatcher.
+/// Keyboard and mouse event dispatcher.
 ///
-/// Translates `KeyEvent`s into input-buffer mutations
-/// and display updates.
+/// Translates `KeyEvent`s and `MouseEvent`s into input-buffer
+/// mutations and display updates.
 
-use crate::io::{display, input_buffer};
-use crate::shell::console;
+use crate::io::{display, input_buffer, pointer};
+use crate::shell::{completion, console, history};
 use crate::drivers::keyboard::KeyEvent;
+use crate::drivers::mouse::MouseEvent;
+use crate::drivers::vga;
 
 /// Dispatches a keyboard event to the appropriate handler.
 pub fn handle_key_event(event: KeyEvent) {
+    // The mouse cursor is an inverted cell: keep it off-screen while drawing
+    pointer::hide();
+    if unsafe { SEARCH_ACTIVE } {
+        handle_search_key(event);
+    } else {
+        dispatch_key(event);
+    }
+    unsafe { LAST_WAS_TAB = event == KeyEvent::Tab; }
+    pointer::show();
+}
+
+/// Line-editing key bindings.
+fn dispatch_key(event: KeyEvent) {
     match event {
         KeyEvent::Char(c) => handle_insert(c),
         KeyEvent::Backspace => handle_delete(),
         KeyEvent::Enter => handle_enter(),
         KeyEvent::CtrlC => handle_ctrl_c(),
+        KeyEvent::CtrlR => handle_ctrl_r(),
+        KeyEvent::Tab => handle_tab(),
         KeyEvent::ArrowLeft => handle_arrow_left(),
         KeyEvent::ArrowRight => handle_arrow_right(),
+        KeyEvent::ArrowUp => handle_arrow_up(),
+        KeyEvent::ArrowDown => handle_arrow_down(),
+        KeyEvent::Home | KeyEvent::CtrlA => handle_home(),
+        KeyEvent::End | KeyEvent::CtrlE => handle_end(),
+        KeyEvent::Delete => handle_delete_forward(),
+        KeyEvent::CtrlK => handle_kill(input_buffer::kill_to_end),
+        KeyEvent::CtrlU => handle_kill(input_buffer::kill_to_start),
+        KeyEvent::CtrlW => handle_kill(input_buffer::kill_word_before),
+        KeyEvent::CtrlY => handle_yank(),
+        KeyEvent::CtrlL => handle_clear_screen(),
+        KeyEvent::AltB => handle_word_motion(input_buffer::move_word_left),
+        KeyEvent::AltF => handle_word_motion(input_buffer::move_word_right),
         _ => {}
     }
 }
 
+/// Dispatches a mouse event: pointer movement, selection and paste.
+pub fn handle_mouse_event(event: MouseEvent) {
+    if pointer::handle_event(event) {
+        pointer::hide();
+        handle_paste(pointer::clipboard());
+        pointer::show();
+    }
+}
+
 // ──────────────────────────────────────────────
 //  Input-line redraw
 // ──────────────────────────────────────────────
@@ -44,6 +82,31 @@ fn refresh_input_from(from: usize) {
     display::set_pos(pos + offset, y);
 }
 
+/// Redraws the input line from `from` after it shrank from `old_len`
+/// characters, clearing the leftover cells.
+fn refresh_input_shrunk(from: usize, old_len: usize) {
+    refresh_input_from(from);
+
+    let (_, y) = display::get_pos();
+    let offset = console::input_start_col();
+    for i in input_buffer::get_len()..old_len {
+        display::put_char_at(i + offset, y, b' ');
+    }
+}
+
+/// Moves the display cursor to the logical input position.
+fn sync_input_cursor() {
+    let (_, y) = display::get_pos();
+    display::set_pos(console::input_start_col() + input_buffer::get_pos(), y);
+}
+
+/// Replaces the whole input line and redraws it.
+fn replace_input(line: &[u8]) {
+    let old_len = input_buffer::get_len();
+    input_buffer::set(line, console::max_input_len());
+    refresh_input_shrunk(0, old_len);
+}
+
 // ──────────────────────────────────────────────
 //  Key event handlers
 // ──────────────────────────────────────────────
@@ -55,6 +118,17 @@ fn handle_insert(c: char) {
     }
 }
 
+/// Inserts pasted text at the cursor; line breaks become spaces.
+fn handle_paste(text: &[u8]) {
+    for &c in text {
+        match c {
+            b'\n' => handle_insert(' '),
+            0x20..=0x7E => handle_insert(c as char),
+            _ => {}
+        }
+    }
+}
+
 /// Deletes the character before the cursor and redraws.
 fn handle_delete() {
     if input_buffer::remove_char() {
@@ -73,6 +147,7 @@ fn handle_enter() {
 /// Discards input, prints ^C, shows prompt.
 fn handle_ctrl_c() {
     input_buffer::flush();
+    history::reset_navigation();
     display::put_str("^C\n");
     console::show_prompt();
 }
@@ -91,4 +166,234 @@ fn handle_arrow_right() {
         input_buffer::move_right();
         display::move_right();
     }
-}
\ No newline at end of file
+}
+
+/// Moves the input cursor to the start of the line.
+fn handle_home() {
+    input_buffer::move_home();
+    sync_input_cursor();
+}
+
+/// Moves the input cursor to the end of the line.
+fn handle_end() {
+    input_buffer::move_end();
+    sync_input_cursor();
+}
+
+/// Moves the input cursor by one word.
+fn handle_word_motion(motion: fn()) {
+    motion();
+    sync_input_cursor();
+}
+
+/// Deletes the character under the cursor and redraws.
+fn handle_delete_forward() {
+    if input_buffer::delete_char() {
+        refresh_input_from(input_buffer::get_pos());
+    }
+}
+
+/// Runs a kill command and redraws what is left of the line.
+fn handle_kill(kill: fn() -> bool) {
+    let old_len = input_buffer::get_len();
+    if kill() {
+        refresh_input_shrunk(input_buffer::get_pos(), old_len);
+    }
+}
+
+/// Inserts the kill buffer at the cursor and redraws.
+fn handle_yank() {
+    let from = input_buffer::get_pos();
+    if input_buffer::yank(console::max_input_len()) {
+        refresh_input_from(from);
+    }
+}
+
+/// Clears the screen and redraws the prompt with the current input.
+fn handle_clear_screen() {
+    display::clear_screen();
+    console::show_prompt();
+    refresh_input_from(0);
+}
+
+/// Recalls the previous history entry.
+fn handle_arrow_up() {
+    if let Some(line) = history::prev(input_buffer::get_buffer()) {
+        replace_input(line);
+    }
+}
+
+/// Recalls the next history entry (or the line being edited).
+fn handle_arrow_down() {
+    if let Some(line) = history::next() {
+        replace_input(line);
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Tab completion
+// ──────────────────────────────────────────────
+
+static mut LAST_WAS_TAB: bool = false;
+
+/// Completes the word before the cursor. A unique match is inserted
+/// with a trailing space; ambiguous matches are extended to their common
+/// prefix, and listed on a second consecutive Tab.
+fn handle_tab() {
+    let completions = completion::complete(input_buffer::get_buffer(), input_buffer::get_pos());
+    let items = completions.items();
+    if items.is_empty() {
+        return;
+    }
+
+    let typed = completions.prefix().len();
+    let common = completions.common_prefix();
+    if common.len() > typed {
+        let from = input_buffer::get_pos();
+        for &c in unsafe { common.get_unchecked(typed..) } {
+            input_buffer::insert_char(c, console::max_input_len());
+        }
+        if items.len() == 1 {
+            input_buffer::insert_char(b' ', console::max_input_len());
+        }
+        refresh_input_from(from);
+    } else if items.len() == 1 {
+        let from = input_buffer::get_pos();
+        if input_buffer::insert_char(b' ', console::max_input_len()) {
+            refresh_input_from(from);
+        }
+    } else if unsafe { LAST_WAS_TAB } {
+        display::new_line();
+        for &item in items {
+            display::put_bytes(item);
+            display::put_str("  ");
+        }
+        display::new_line();
+        console::show_prompt();
+        refresh_input_from(0);
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Reverse history search (Ctrl+R)
+//
+//  While active, the prompt line shows
+//    (reverse-i-search)`query': match
+//  and keys edit the query instead of the input buffer.
+// ──────────────────────────────────────────────
+
+const SEARCH_QUERY_SIZE: usize = 32;
+
+static mut SEARCH_ACTIVE: bool = false;
+static mut SEARCH_QUERY: [u8; SEARCH_QUERY_SIZE] = [0; SEARCH_QUERY_SIZE];
+static mut SEARCH_LEN: usize = 0;
+static mut SEARCH_MATCH: usize = 0; // History entry number, 0 = no match
+
+fn search_query() -> &'static [u8] {
+    unsafe { (*core::ptr::addr_of!(SEARCH_QUERY)).get_unchecked(..SEARCH_LEN) }
+}
+
+/// Enters reverse search mode with an empty query.
+fn handle_ctrl_r() {
+    unsafe {
+        SEARCH_ACTIVE = true;
+        SEARCH_LEN = 0;
+        SEARCH_MATCH = 0;
+    }
+    draw_search();
+}
+
+/// Writes `text` at `(x, y)` without wrapping. Returns the next column.
+fn draw_clipped(mut x: usize, y: usize, text: &[u8]) -> usize {
+    for &c in text {
+        if x + 1 >= vga::VGA_WIDTH {
+            break;
+        }
+        display::put_char_at(x, y, c);
+        x += 1;
+    }
+    x
+}
+
+/// Redraws the search line in place of the prompt.
+fn draw_search() {
+    let (_, y) = display::get_pos();
+    let start = console::prompt_start_col();
+    for x in start..vga::VGA_WIDTH {
+        display::put_char_at(x, y, b' ');
+    }
+
+    let label: &[u8] = if unsafe { SEARCH_MATCH } == 0 && unsafe { SEARCH_LEN } > 0 {
+        b"(failed reverse-i-search)`"
+    } else {
+        b"(reverse-i-search)`"
+    };
+    let mut x = draw_clipped(start, y, label);
+    x = draw_clipped(x, y, search_query());
+    let cursor = x;
+    x = draw_clipped(x, y, b"': ");
+    if let Some(line) = history::get(unsafe { SEARCH_MATCH }) {
+        draw_clipped(x, y, line);
+    }
+    display::set_pos(cursor, y);
+}
+
+/// Leaves search mode, loading the match (if any) into the input line.
+fn end_search() {
+    unsafe { SEARCH_ACTIVE = false; }
+    if let Some(line) = history::get(unsafe { SEARCH_MATCH }) {
+        input_buffer::set(line, console::max_input_len());
+    }
+    console::redraw_prompt();
+    refresh_input_from(0);
+}
+
+/// Key bindings while searching.
+fn handle_search_key(event: KeyEvent) {
+    match event {
+        KeyEvent::Char(c) => {
+            unsafe {
+                if SEARCH_LEN < SEARCH_QUERY_SIZE {
+                    *(*core::ptr::addr_of_mut!(SEARCH_QUERY)).get_unchecked_mut(SEARCH_LEN) = c as u8;
+                    SEARCH_LEN += 1;
+                }
+                SEARCH_MATCH = history::search(search_query(), usize::MAX).unwrap_or(0);
+            }
+            draw_search();
+        }
+        KeyEvent::Backspace => {
+            unsafe {
+                SEARCH_LEN = SEARCH_LEN.saturating_sub(1);
+                SEARCH_MATCH = if SEARCH_LEN == 0 {
+                    0
+                } else {
+                    history::search(search_query(), usize::MAX).unwrap_or(0)
+                };
+            }
+            draw_search();
+        }
+        KeyEvent::CtrlR => {
+            unsafe {
+                let before = if SEARCH_MATCH == 0 { usize::MAX } else { SEARCH_MATCH };
+                if let Some(n) = history::search(search_query(), before) {
+                    SEARCH_MATCH = n;
+                }
+            }
+            draw_search();
+        }
+        KeyEvent::CtrlC => {
+            unsafe {
+                SEARCH_ACTIVE = false;
+                SEARCH_MATCH = 0;
+            }
+            console::redraw_prompt();
+            refresh_input_from(0);
+            handle_ctrl_c();
+        }
+        _ => {
+            // Any other key accepts the match and is then handled normally
+            end_search();
+            dispatch_key(event);
+        }
+    }
+}
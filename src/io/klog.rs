@@ -1,38 +1,243 @@
-/// klog — Kernel log ring buffer for `dmesg`.
+/// klog — Kernel log for `dmesg`.
 ///
-/// Every byte written through `printk` is saved in that buffer.
+/// Every line written through `printk` is saved as a record in a
+/// `log_ring::Log`, which holds the ring buffer itself. A `Reader`
+/// follows a log record by record and is told how many records it missed
+/// if the writer dropped them first (`dmesg -w` prints that as a notice).
 ///
-/// `dmesg` dumps the current contents to the VGA screen.
-/// printk is pretty hard to implement (concurrency/deadlocks, reentrant calls, latency, crash -> ringbuffer missing messages, interfering with normal operations...)
-/// but since TacOS is single-threaded and doesn't have interrupts yet, we can get away with a very simple implementation.
+/// The log survives a warm reboot, like Linux's ramoops: it lives in one
+/// of two slots at a fixed physical address (`PERSIST_BASE`) that neither
+/// GRUB nor the kernel clears, sealed by a magic number and a checksum.
+/// At boot, `init` keeps a valid log from the previous boot in its slot
+/// (`dmesg --previous`) and starts the new one in the other slot. The
+/// slots are only used if the memory map shows them in RAM and no boot
+/// module or other boot information lies there; otherwise the log lives
+/// in the kernel's bss and is lost on reboot.
+///
+/// printk must work from anywhere, interrupt handlers included, and
+/// never lose a record. Records are therefore stored with interrupts
+/// masked: a handler's printk lands before or after the one it
+/// interrupted, never inside it. Printing to the screen is slow and
+/// touches the display cursor, so it is deferred: the console follows
+/// the log with its own `Reader` and `flush_console` catches it up, from
+/// printk itself outside interrupt context and from the shell loop. Once
+/// the kernel panics, `enter_panic_mode` makes every flush go through,
+/// whatever it interrupted.
 /// See this conference to understand the complexities of a real printk implementation
 ///  : https://www.youtube.com/watch?v=saPQZ_tnxwE
 
+use crate::cpu::irq;
+use crate::drivers::timer;
 use crate::io::display;
+use crate::io::log_ring::{Cursor, Log};
+use crate::multiboot;
+use crate::{eprintln, pr_warn, printkln};
+
+pub use crate::io::log_ring::{Level, Read, Record, DEFAULT_LEVEL, LINE_MAX};
+
+/// Physical address of the persistent log slots, above the kernel image
+/// (`linker.ld` checks it). No allocator may hand out
+/// `PERSIST_BASE..PERSIST_END`.
+pub const PERSIST_BASE: usize = 0x0040_0000;
+const SLOT_SIZE: usize = (core::mem::size_of::<Log>() + 0xFFF) & !0xFFF;
+pub const PERSIST_END: usize = PERSIST_BASE + 2 * SLOT_SIZE;
+
+static mut CURRENT: *mut Log = core::ptr::null_mut();
+static mut PREVIOUS: *const Log = core::ptr::null();
+/// The log when the persistent slots can't be used.
+static mut VOLATILE: Log = Log::zeroed();
+
+// The line being written byte by byte through `Sink::Klog`: committed on
+// newline or when full.
+static mut LINE: [u8; LINE_MAX] = [0; LINE_MAX];
+static mut LINE_LEN: usize = 0;
+static mut LINE_OPEN: bool = false;
+static mut LINE_TIMESTAMP: u64 = 0;
+static mut CONSOLE_LEVEL: u8 = DEFAULT_CONSOLE_LEVEL;
+
+// Console position in the log: records before it are on the screen, or
+// were below the console level.
+static mut CONSOLE: Option<Reader> = None;
+static mut CONSOLE_BUSY: bool = false; // A flush is running.
+static mut PANIC: bool = false;
+
+// ──────────────────────────────────────────────
+//  Levels
+// ──────────────────────────────────────────────
+
+/// Messages more severe than this level (below it) also go to the
+/// screen: by default errors and worse.
+pub const DEFAULT_CONSOLE_LEVEL: u8 = Level::Warning as u8;
+
+/// Console log level: messages of a lower level value are also printed
+/// on the screen. 0 silences the console, 8 shows everything.
+pub fn console_level() -> u8 {
+    unsafe { CONSOLE_LEVEL }
+}
+
+pub fn set_console_level(level: u8) {
+    unsafe { CONSOLE_LEVEL = level.min(Level::Debug as u8 + 1); }
+}
+
+/// Returns `true` if messages of `level` are shown on the screen.
+pub fn shows_on_console(level: Level) -> bool {
+    (level as u8) < console_level()
+}
+
+// ──────────────────────────────────────────────
+//  Persistent slots
+// ──────────────────────────────────────────────
+
+fn slot(idx: usize) -> *mut Log {
+    (PERSIST_BASE + idx * SLOT_SIZE) as *mut Log
+}
+
+/// Why the persistent slots can't be used, if they can't: they must lie
+/// in RAM and clear of everything the bootloader left in memory.
+fn check_slots() -> Option<&'static str> {
+    let (start, end) = (PERSIST_BASE as u64, PERSIST_END as u64);
+    let mut has_map = false;
+    let mut in_ram = false;
+    for region in multiboot::memory_map() {
+        has_map = true;
+        in_ram |= region.available && region.base <= start && region.base.saturating_add(region.len) >= end;
+    }
+    if !has_map {
+        in_ram = multiboot::upper_memory_kib().is_some_and(|kib| 0x10_0000 + kib as u64 * 1024 >= end);
+    }
+    if !in_ram {
+        return Some("not in RAM");
+    }
+    let mut overlap = false;
+    multiboot::for_each_boot_range(|range_start, range_end| {
+        overlap |= range_start < PERSIST_END && range_end > PERSIST_BASE;
+    });
+    if overlap {
+        return Some("boot information lies there");
+    }
+    None
+}
+
+/// Picks the slots of the previous and current logs. Runs on the first
+/// `printk` if not called earlier; call it first thing at boot, right
+/// after `multiboot::init`: the slots are checked against the boot
+/// information before anything is written there.
+pub fn init() {
+    unsafe {
+        if !CURRENT.is_null() {
+            return;
+        }
+        if let Some(reason) = check_slots() {
+            let log = &mut *core::ptr::addr_of_mut!(VOLATILE);
+            log.reset(1);
+            CURRENT = log;
+            pr_warn!(
+                "klog: {:#x}-{:#x} unusable ({}), the log will not survive a reboot",
+                PERSIST_BASE, PERSIST_END, reason
+            );
+            return;
+        }
+        let valid = [(*slot(0)).is_valid(), (*slot(1)).is_valid()];
+        let previous = match valid {
+            [false, false] => None,
+            [true, false] => Some(0),
+            [false, true] => Some(1),
+            // Two reboots or more: the newer one is the previous boot
+            [true, true] => Some(if (*slot(0)).boot() > (*slot(1)).boot() { 0 } else { 1 }),
+        };
+        let (current, boot) = match previous {
+            Some(idx) => (1 - idx, (*slot(idx)).boot().wrapping_add(1)),
+            None => (0, 1),
+        };
+        (*slot(current)).reset(boot);
+        CURRENT = slot(current);
+        if let Some(idx) = previous {
+            PREVIOUS = slot(idx);
+            let log = &*PREVIOUS;
+            printkln!("klog: kept {} records from boot {} (dmesg --previous)", log.records(), log.boot());
+        }
+    }
+}
 
-/// Ring buffer sized to one full VGA screen (25 rows * 80 cols) - 1 for cursor line.
-/// (The console doesn't support scrollback, useless to keep more data than what fits on screen.
-const KLOG_BUF_SIZE: usize = 24 * 80;
+fn current() -> &'static mut Log {
+    unsafe {
+        if CURRENT.is_null() {
+            init();
+        }
+        &mut *CURRENT
+    }
+}
 
-static mut BUF: [u8; KLOG_BUF_SIZE] = [0; KLOG_BUF_SIZE];
-static mut HEAD: usize = 0; // Write cursor — next position to write into.
-static mut TOTAL: usize = 0; // Total bytes ever written (to detect wrap-around).
+/// Log of the previous boot, if it survived the reboot.
+fn previous() -> Option<&'static Log> {
+    unsafe { PREVIOUS.as_ref() }
+}
+
+/// Returns `true` if a log from the previous boot was found.
+pub fn has_previous() -> bool {
+    previous().is_some()
+}
+
+/// Discards the log of the previous boot.
+pub fn forget_previous() {
+    unsafe {
+        if let Some(log) = (PREVIOUS as *mut Log).as_mut() {
+            log.invalidate();
+        }
+        PREVIOUS = core::ptr::null();
+    }
+}
 
 // ──────────────────────────────────────────────
 //  Write API (called from printk)
 // ──────────────────────────────────────────────
 
-/// Append a single byte to the kernel log buffer.
-#[inline]
-pub fn log_byte(c: u8) {
+/// Stores the text of one printk call at `level`, a record per line (see
+/// `Log::store_message`). Interrupts are masked meanwhile, so that a
+/// handler's printk can't change the ring halfway through.
+pub fn store_message(level: Level, text: &[u8]) {
+    let timestamp = timer::uptime_us();
+    irq::without(|| current().store_message(level, timestamp, text));
+}
+
+/// Stores the line being written, if any. Called with interrupts masked.
+fn commit() {
     unsafe {
-        let buf_ptr = core::ptr::addr_of_mut!(BUF);
-        *(*buf_ptr).get_unchecked_mut(HEAD) = c;
-        HEAD += 1;
-        if HEAD >= KLOG_BUF_SIZE {
-            HEAD = 0;
+        if !LINE_OPEN {
+            return;
+        }
+        let line = &*core::ptr::addr_of!(LINE);
+        current().store(DEFAULT_LEVEL, LINE_TIMESTAMP, line.get_unchecked(..LINE_LEN));
+        LINE_LEN = 0;
+        LINE_OPEN = false;
+    }
+}
+
+/// Append a single byte to the kernel log buffer, at the default level.
+/// The line becomes a record at its newline, or when `LINE_MAX` long.
+pub fn log_byte(c: u8) {
+    let was_enabled = irq::save_disable();
+    let committed = unsafe {
+        if !LINE_OPEN {
+            LINE_OPEN = true;
+            LINE_TIMESTAMP = timer::uptime_us();
+        }
+        if c == b'\n' {
+            commit();
+            true
+        } else {
+            let line = &mut *core::ptr::addr_of_mut!(LINE);
+            *line.get_unchecked_mut(LINE_LEN) = c;
+            LINE_LEN += 1;
+            if LINE_LEN == LINE_MAX {
+                commit();
+            }
+            LINE_LEN == 0
         }
-        TOTAL += 1;
+    };
+    irq::restore(was_enabled);
+    if committed {
+        flush_console();
     }
 }
 
@@ -54,33 +259,96 @@ pub fn log_bytes(bytes: &[u8]) {
 //  Read API (called by dmesg)
 // ──────────────────────────────────────────────
 
-/// Dump the entire kernel log to the VGA display.
-///
-/// If the buffer has not yet wrapped, we print `BUF[0..HEAD]`.
-/// If it has wrapped, we print from the oldest data (`HEAD`) forward through the ring, covering `KLOG_BUF_SIZE` bytes.
-pub fn dump() {
+/// Reads a log record by record, remembering its position.
+pub struct Reader {
+    log: *const Log,
+    cursor: Cursor,
+}
+
+impl Reader {
+    fn start(log: &Log) -> Self {
+        Reader { log, cursor: Cursor::start(log) }
+    }
+
+    /// A reader starting at the oldest record.
+    pub fn new() -> Self {
+        Reader::start(current())
+    }
+
+    /// A reader of the previous boot's log, if there is one.
+    pub fn previous() -> Option<Self> {
+        previous().map(Reader::start)
+    }
+
+    /// A reader that only sees records written from now on.
+    pub fn at_end() -> Self {
+        let log = current();
+        Reader { log, cursor: Cursor::end(log) }
+    }
+
+    /// Sequence number of the next record to read.
+    pub fn seq(&self) -> u64 {
+        self.cursor.seq()
+    }
+
+    /// Next record, copied into `buf`; `None` when caught up with the
+    /// writer.
+    pub fn read<'a>(&mut self, buf: &'a mut [u8; LINE_MAX]) -> Option<Read<'a>> {
+        // A handler's printk must not move the tail under the copy
+        irq::without(|| self.cursor.read(unsafe { &*self.log }, buf))
+    }
+}
+
+
+impl Default for Reader {
+    fn default() -> Self {
+        Reader::new()
+    }
+}
+
+/// Clear the kernel log buffer. Sequence numbers keep counting.
+pub fn clear() {
+    irq::without(|| current().clear());
+}
+
+// ──────────────────────────────────────────────
+//  Console
+// ──────────────────────────────────────────────
+
+/// Prints the records the console has not shown yet. Does nothing in
+/// interrupt context, or when it interrupted another flush: that one
+/// carries on with the new records. Panic mode lifts both limits.
+pub fn flush_console() {
     unsafe {
-        let buf_ptr = core::ptr::addr_of!(BUF);
-        if TOTAL <= KLOG_BUF_SIZE {
-            for i in 0..HEAD {
-                let c = *(*buf_ptr).get_unchecked(i);
-                display::write_byte(c, crate::drivers::vga::DEFAULT_COLOR);
-            }
-        } else {
-            // Wrapped — oldest byte is at HEAD, read the full ring
-            for i in 0..KLOG_BUF_SIZE {
-                let idx = (HEAD + i) % KLOG_BUF_SIZE;
-                let c = *(*buf_ptr).get_unchecked(idx);
-                display::write_byte(c, crate::drivers::vga::DEFAULT_COLOR);
+        if irq::in_interrupt() && !PANIC {
+            return;
+        }
+        let busy = irq::without(|| core::mem::replace(&mut *core::ptr::addr_of_mut!(CONSOLE_BUSY), true));
+        if busy && !PANIC {
+            return;
+        }
+        let console = (*core::ptr::addr_of_mut!(CONSOLE)).get_or_insert_with(Reader::new);
+        let mut buf = [0u8; LINE_MAX];
+        while let Some(read) = console.read(&mut buf) {
+            match read {
+                Read::Record(record) => {
+                    if shows_on_console(record.level) {
+                        display::put_bytes(record.text);
+                        display::put_bytes(b"\n");
+                    }
+                }
+                Read::Dropped(count) => eprintln!("[... {} messages lost before reaching the console ...]", count),
             }
         }
+        CONSOLE_BUSY = false;
     }
 }
 
-/// Clear the kernel log buffer.
-pub fn clear() {
+/// Enters panic mode and flushes the console: nothing will run to
+/// finish a flush the panic interrupted, so it is not waited for.
+pub fn enter_panic_mode() {
     unsafe {
-        HEAD = 0;
-        TOTAL = 0;
+        PANIC = true;
     }
+    flush_console();
 }
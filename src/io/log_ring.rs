@@ -0,0 +1,622 @@
+//! Kernel log ring buffer, the storage behind `klog`.
+//!
+//! Every line written through `printk` is saved as a record: a header
+//! (sequence number, level, timestamp, length) followed by the text,
+//! without its newline. Records never straddle the end of the buffer (a
+//! `WRAP` marker sends reading back to offset 0), and when space runs out
+//! the oldest records are dropped whole, so a reader never sees half a
+//! message.
+//!
+//! Sequence numbers grow for the whole uptime. A `Cursor` remembers the
+//! next one it wants; if the writer dropped it meanwhile, the reader is
+//! told how many records it missed (`dmesg -w` prints that as a notice).
+//!
+//! The buffer holds `1 << LOG_BUF_SHIFT` bytes, 16 KiB unless the kernel
+//! is built with `TACOS_LOG_BUF_SHIFT` set (10 to 20).
+//!
+//! A `Log` is plain memory with a magic number and checksums, so that it
+//! can sit at a fixed physical address and survive a reboot. Locking and
+//! placement are `klog`'s business.
+
+/// Log2 of the buffer size, from `TACOS_LOG_BUF_SHIFT` at build time.
+pub const LOG_BUF_SHIFT: usize = match option_env!("TACOS_LOG_BUF_SHIFT") {
+    Some(shift) => parse_shift(shift),
+    None => 14,
+};
+const KLOG_BUF_SIZE: usize = 1 << LOG_BUF_SHIFT;
+
+const fn parse_shift(s: &str) -> usize {
+    let s = s.as_bytes();
+    let mut value = 0;
+    let mut i = 0;
+    while i < s.len() {
+        if !s[i].is_ascii_digit() {
+            panic!("TACOS_LOG_BUF_SHIFT must be a number");
+        }
+        value = value * 10 + (s[i] - b'0') as usize;
+        i += 1;
+    }
+    if value < 10 || value > 20 {
+        panic!("TACOS_LOG_BUF_SHIFT must be between 10 and 20");
+    }
+    value
+}
+
+/// Longest record text; longer lines are split.
+pub const LINE_MAX: usize = 256;
+
+
+/// "KLOG"
+const MAGIC: u32 = 0x474F_4C4B;
+
+/// Record header, followed by `len` bytes of text. Stored little-endian
+/// as `seq` (8 bytes), `timestamp` (8), `len` (2), `level` (1) and a pad
+/// byte; records start on `RECORD_ALIGN` boundaries.
+struct Header {
+    seq: u64,
+    /// Microseconds since boot.
+    timestamp: u64,
+    /// Text length, or `WRAP`.
+    len: u16,
+    level: u8,
+}
+
+const HEADER_SIZE: usize = 20;
+const RECORD_ALIGN: usize = 4;
+/// `len` of the marker left where the next record did not fit before the
+/// end of the buffer: reading continues at offset 0.
+const WRAP: u16 = u16::MAX;
+
+/// A log ring with its metadata, as laid out in a persistent slot.
+#[repr(C)]
+pub struct Log {
+    magic: u32,
+    /// Boot number, to tell the previous log from an older one.
+    boot: u32,
+    head: usize, // Offset of the next record.
+    tail: usize, // Offset of the oldest record.
+    first_seq: u64, // Sequence number of the oldest record.
+    next_seq: u64, // Sequence number of the next record.
+    /// Position-weighted sum of `buf`, updated on every write.
+    data_sum: u32,
+    /// Checksum of the fields above, updated by `seal`.
+    checksum: u32,
+    buf: [u8; KLOG_BUF_SIZE],
+}
+
+// ──────────────────────────────────────────────
+//  Levels
+// ──────────────────────────────────────────────
+
+/// Message severity, most severe first (the Linux `KERN_*` values).
+#[derive(Copy, Clone, PartialEq, PartialOrd)]
+pub enum Level {
+    Emerg = 0,
+    Alert = 1,
+    Crit = 2,
+    Err = 3,
+    Warning = 4,
+    Notice = 5,
+    Info = 6,
+    Debug = 7,
+}
+
+/// Level of `printk!` / `printkln!`.
+pub const DEFAULT_LEVEL: Level = Level::Info;
+
+impl Level {
+    pub const ALL: [Level; 8] = [
+        Level::Emerg, Level::Alert, Level::Crit, Level::Err,
+        Level::Warning, Level::Notice, Level::Info, Level::Debug,
+    ];
+
+    /// Name used by `dmesg -l`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Emerg => "emerg",
+            Level::Alert => "alert",
+            Level::Crit => "crit",
+            Level::Err => "err",
+            Level::Warning => "warn",
+            Level::Notice => "notice",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+
+    pub fn from_name(name: &[u8]) -> Option<Level> {
+        Level::ALL.into_iter().find(|level| {
+            let candidate = level.name().as_bytes();
+            candidate.len() == name.len() && candidate.iter().zip(name).all(|(a, b)| a == b)
+        })
+    }
+
+    pub fn from_u8(value: u8) -> Option<Level> {
+        Level::ALL.get(value as usize).copied()
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Ring buffer
+// ──────────────────────────────────────────────
+
+fn record_size(len: usize) -> usize {
+    (HEADER_SIZE + len + RECORD_ALIGN - 1) & !(RECORD_ALIGN - 1)
+}
+
+impl Log {
+    /// An all-zero log, invalid until `reset`.
+    pub const fn zeroed() -> Self {
+        Log { magic: 0, boot: 0, head: 0, tail: 0, first_seq: 0, next_seq: 0, data_sum: 0, checksum: 0, buf: [0; KLOG_BUF_SIZE] }
+    }
+
+    /// Starts an empty log. The buffer is left as is, only summed.
+    pub fn reset(&mut self, boot: u32) {
+        self.magic = MAGIC;
+        self.boot = boot;
+        self.head = 0;
+        self.tail = 0;
+        self.first_seq = 0;
+        self.next_seq = 0;
+        self.data_sum = self.sum_data();
+        self.seal();
+    }
+
+    fn sum_data(&self) -> u32 {
+        self.buf.iter().enumerate().fold(0u32, |sum, (pos, &c)| {
+            sum.wrapping_add((c as u32).wrapping_mul(pos as u32 + 1))
+        })
+    }
+
+    fn compute_checksum(&self) -> u32 {
+        let words = [
+            self.boot,
+            self.head as u32,
+            self.tail as u32,
+            self.first_seq as u32,
+            (self.first_seq >> 32) as u32,
+            self.next_seq as u32,
+            (self.next_seq >> 32) as u32,
+            self.data_sum,
+        ];
+        words.iter().fold(MAGIC, |sum, &word| sum.rotate_left(5) ^ word)
+    }
+
+    /// Updates the checksum after a change.
+    fn seal(&mut self) {
+        self.checksum = self.compute_checksum();
+    }
+
+    /// Returns `true` if the slot holds an intact log.
+    pub fn is_valid(&self) -> bool {
+        self.magic == MAGIC
+            && self.head <= KLOG_BUF_SIZE
+            && self.tail <= KLOG_BUF_SIZE
+            && self.first_seq <= self.next_seq
+            && self.checksum == self.compute_checksum()
+            && self.data_sum == self.sum_data()
+    }
+
+    fn write_byte(&mut self, pos: usize, c: u8) {
+        let slot = unsafe { self.buf.get_unchecked_mut(pos) };
+        let delta = (c as u32).wrapping_sub(*slot as u32);
+        self.data_sum = self.data_sum.wrapping_add(delta.wrapping_mul(pos as u32 + 1));
+        *slot = c;
+    }
+
+    fn write_header(&mut self, pos: usize, header: &Header) {
+        let mut bytes = [0u8; HEADER_SIZE];
+        let fields = header.seq.to_le_bytes().into_iter()
+            .chain(header.timestamp.to_le_bytes())
+            .chain(header.len.to_le_bytes())
+            .chain([header.level, 0]);
+        for (byte, value) in bytes.iter_mut().zip(fields) {
+            *byte = value;
+        }
+        for (i, &c) in bytes.iter().enumerate() {
+            self.write_byte(pos + i, c);
+        }
+    }
+
+    /// Header at `pos`, `None` where reading wraps to offset 0 (a `WRAP`
+    /// marker, or too little room left for a header).
+    fn header_at(&self, pos: usize) -> Option<Header> {
+        if KLOG_BUF_SIZE - pos < HEADER_SIZE {
+            return None;
+        }
+        let byte = |i: usize| unsafe { *self.buf.get_unchecked(pos + i) };
+        let mut word = [0u8; 8];
+        for (i, b) in word.iter_mut().enumerate() {
+            *b = byte(i);
+        }
+        let seq = u64::from_le_bytes(word);
+        for (i, b) in word.iter_mut().enumerate() {
+            *b = byte(8 + i);
+        }
+        let timestamp = u64::from_le_bytes(word);
+        let len = u16::from_le_bytes([byte(16), byte(17)]);
+        if len == WRAP {
+            return None;
+        }
+        Some(Header { seq, timestamp, len, level: byte(18) })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.first_seq == self.next_seq
+    }
+
+    /// Boot number given to `reset`.
+    pub fn boot(&self) -> u32 {
+        self.boot
+    }
+
+    /// Number of records held.
+    pub fn records(&self) -> u64 {
+        self.next_seq - self.first_seq
+    }
+
+    /// Breaks the seal, so that the log is no longer valid.
+    pub fn invalidate(&mut self) {
+        self.magic = 0;
+    }
+
+    /// Drops the oldest record.
+    fn drop_oldest(&mut self) {
+        let header = match self.header_at(self.tail) {
+            Some(header) => header,
+            None => {
+                self.tail = 0;
+                self.header_at(0).unwrap_or(Header { seq: 0, timestamp: 0, len: 0, level: 0 })
+            }
+        };
+        self.tail += record_size(header.len as usize);
+        self.first_seq += 1;
+        if self.is_empty() {
+            self.head = 0;
+            self.tail = 0;
+        }
+    }
+
+    /// Makes room for `size` bytes and returns their offset.
+    fn reserve(&mut self, size: usize) -> usize {
+        loop {
+            if self.is_empty() {
+                self.head = 0;
+                self.tail = 0;
+                return 0;
+            }
+            if self.head > self.tail {
+                // Free space: head..end, then 0..tail
+                if KLOG_BUF_SIZE - self.head >= size {
+                    return self.head;
+                }
+                if KLOG_BUF_SIZE - self.head >= HEADER_SIZE {
+                    let marker = Header { seq: 0, timestamp: 0, len: WRAP, level: 0 };
+                    self.write_header(self.head, &marker);
+                }
+                self.head = 0;
+            }
+            // Free space: head..tail (none when they meet)
+            if self.head < self.tail && self.tail - self.head >= size {
+                return self.head;
+            }
+            self.drop_oldest();
+        }
+    }
+
+    /// Appends a record.
+    pub fn store(&mut self, level: Level, timestamp: u64, text: &[u8]) {
+        let size = record_size(text.len());
+        let pos = self.reserve(size);
+        let header = Header { seq: self.next_seq, timestamp, len: text.len() as u16, level: level as u8 };
+        self.write_header(pos, &header);
+        for (i, &c) in text.iter().enumerate() {
+            self.write_byte(pos + HEADER_SIZE + i, c);
+        }
+        self.head = pos + size;
+        self.next_seq += 1;
+        self.seal();
+    }
+
+    /// Stores the text of one printk call at `level`, a record per line.
+    /// A trailing newline only ends the last line; lines longer than
+    /// `LINE_MAX` are split.
+    pub fn store_message(&mut self, level: Level, timestamp: u64, text: &[u8]) {
+        let text = match text {
+            [line @ .., b'\n'] => line,
+            _ => text,
+        };
+        for line in text.split(|&c| c == b'\n') {
+            if line.is_empty() {
+                self.store(level, timestamp, line);
+            }
+            for chunk in line.chunks(LINE_MAX) {
+                self.store(level, timestamp, chunk);
+            }
+        }
+    }
+
+    /// Empties the log. Sequence numbers keep counting.
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.tail = 0;
+        self.first_seq = self.next_seq;
+        self.seal();
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Reading
+// ──────────────────────────────────────────────
+
+/// A record copied out of the log.
+pub struct Record<'a> {
+    pub seq: u64,
+    pub level: Level,
+    /// Microseconds since boot.
+    pub timestamp: u64,
+    pub text: &'a [u8],
+}
+
+/// What `Cursor::read` found.
+pub enum Read<'a> {
+    Record(Record<'a>),
+    /// Records overwritten before the reader got to them.
+    Dropped(u64),
+}
+
+/// Position of a reader in a log.
+pub struct Cursor {
+    seq: u64,
+    /// Offset of record `seq`, valid while it is not dropped.
+    pos: usize,
+}
+
+impl Cursor {
+    /// At the oldest record of `log`.
+    pub fn start(log: &Log) -> Self {
+        Cursor { seq: log.first_seq, pos: log.tail }
+    }
+
+    /// Past the newest record of `log`: only records written from now on
+    /// are read.
+    pub fn end(log: &Log) -> Self {
+        Cursor { seq: log.next_seq, pos: log.head }
+    }
+
+    /// Sequence number of the next record to read.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Next record of `log`, copied into `buf`; `None` when caught up with
+    /// the writer. `log` must not change during the call.
+    pub fn read<'a>(&mut self, log: &Log, buf: &'a mut [u8; LINE_MAX]) -> Option<Read<'a>> {
+        if self.seq < log.first_seq {
+            let dropped = log.first_seq - self.seq;
+            *self = Cursor::start(log);
+            return Some(Read::Dropped(dropped));
+        }
+        if self.seq >= log.next_seq {
+            return None;
+        }
+        if self.seq == log.first_seq {
+            // Covers a log emptied and restarted at offset 0
+            self.pos = log.tail;
+        }
+        let header = match log.header_at(self.pos) {
+            Some(header) => header,
+            None => {
+                self.pos = 0;
+                log.header_at(0)?
+            }
+        };
+        let len = header.len as usize;
+        if len > LINE_MAX || self.pos + record_size(len) > KLOG_BUF_SIZE {
+            // Damaged record: stop here
+            return None;
+        }
+        for (i, byte) in buf.iter_mut().enumerate().take(len) {
+            *byte = unsafe { *log.buf.get_unchecked(self.pos + HEADER_SIZE + i) };
+        }
+        self.pos += record_size(len);
+        self.seq += 1;
+        Some(Read::Record(Record {
+            seq: header.seq,
+            level: Level::from_u8(header.level).unwrap_or(DEFAULT_LEVEL),
+            timestamp: header.timestamp,
+            text: unsafe { buf.get_unchecked(..len) },
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT_LEN: usize = 100;
+
+    fn new_log() -> Log {
+        let mut log = Log::zeroed();
+        log.reset(1);
+        log
+    }
+
+    /// Text of record `seq`: `TEXT_LEN` copies of its low byte.
+    fn text(seq: u64) -> [u8; TEXT_LEN] {
+        [seq as u8; TEXT_LEN]
+    }
+
+    fn fill(log: &mut Log, count: u64) {
+        for _ in 0..count {
+            let seq = log.next_seq;
+            log.store(Level::Info, seq * 10, &text(seq));
+        }
+    }
+
+    /// Records that fit before the end of the buffer.
+    fn capacity() -> u64 {
+        (KLOG_BUF_SIZE / record_size(TEXT_LEN)) as u64
+    }
+
+    /// Reads the next record and checks that it is whole.
+    fn next_seq(cursor: &mut Cursor, log: &Log) -> Option<u64> {
+        let mut buf = [0u8; LINE_MAX];
+        match cursor.read(log, &mut buf)? {
+            Read::Record(record) => {
+                assert_eq!(record.text, text(record.seq));
+                assert_eq!(record.timestamp, record.seq * 10);
+                Some(record.seq)
+            }
+            Read::Dropped(count) => panic!("{} records dropped", count),
+        }
+    }
+
+    /// Lengths of the records of `log`, in order.
+    fn lengths(log: &Log, out: &mut [usize]) -> usize {
+        let mut cursor = Cursor::start(log);
+        let mut buf = [0u8; LINE_MAX];
+        let mut count = 0;
+        while let Some(Read::Record(record)) = cursor.read(log, &mut buf) {
+            out[count] = record.text.len();
+            count += 1;
+        }
+        count
+    }
+
+    // ── store / read ──
+
+    #[test]
+    fn reads_back_in_order() {
+        let mut log = new_log();
+        fill(&mut log, 5);
+        let mut cursor = Cursor::start(&log);
+        for seq in 0..5 {
+            assert_eq!(next_seq(&mut cursor, &log), Some(seq));
+        }
+        assert_eq!(next_seq(&mut cursor, &log), None);
+        assert!(log.is_valid());
+    }
+
+    #[test]
+    fn cursor_at_end_sees_new_records_only() {
+        let mut log = new_log();
+        fill(&mut log, 3);
+        let mut cursor = Cursor::end(&log);
+        assert_eq!(next_seq(&mut cursor, &log), None);
+        fill(&mut log, 1);
+        assert_eq!(next_seq(&mut cursor, &log), Some(3));
+    }
+
+    // ── wrap ──
+
+    #[test]
+    fn record_past_the_end_wraps_to_start() {
+        let mut log = new_log();
+        let n = capacity();
+        fill(&mut log, n);
+        let marker = log.head;
+        assert!(KLOG_BUF_SIZE - marker < record_size(TEXT_LEN));
+
+        fill(&mut log, 1);
+        // Left a marker, dropped the record at offset 0 to make room
+        assert!(log.header_at(marker).is_none());
+        assert_eq!(log.head, record_size(TEXT_LEN));
+        assert_eq!(log.first_seq, 1);
+
+        let mut cursor = Cursor::start(&log);
+        for seq in 1..=n {
+            assert_eq!(next_seq(&mut cursor, &log), Some(seq));
+        }
+        assert_eq!(next_seq(&mut cursor, &log), None);
+        assert!(log.is_valid());
+    }
+
+    // ── eviction ──
+
+    #[test]
+    fn full_log_drops_oldest_records_whole() {
+        let mut log = new_log();
+        fill(&mut log, 3 * capacity() + 7);
+        assert_eq!(log.next_seq, 3 * capacity() + 7);
+        assert!(log.records() <= capacity());
+        assert!(log.records() >= capacity() - 1);
+
+        let mut cursor = Cursor::start(&log);
+        let mut expected = log.first_seq;
+        while let Some(seq) = next_seq(&mut cursor, &log) {
+            assert_eq!(seq, expected);
+            expected += 1;
+        }
+        assert_eq!(expected, log.next_seq);
+        assert!(log.is_valid());
+    }
+
+    #[test]
+    fn lagging_reader_is_told_how_many_it_missed() {
+        let mut log = new_log();
+        let mut cursor = Cursor::start(&log);
+        fill(&mut log, 2 * capacity());
+        let mut buf = [0u8; LINE_MAX];
+        match cursor.read(&log, &mut buf) {
+            Some(Read::Dropped(count)) => assert_eq!(count, log.first_seq),
+            _ => panic!("expected dropped records"),
+        }
+        assert_eq!(cursor.seq(), log.first_seq);
+        assert_eq!(next_seq(&mut cursor, &log), Some(log.first_seq));
+    }
+
+    #[test]
+    fn clear_keeps_counting() {
+        let mut log = new_log();
+        fill(&mut log, 4);
+        let mut cursor = Cursor::start(&log);
+        log.clear();
+        assert_eq!(log.records(), 0);
+        fill(&mut log, 1);
+        let mut buf = [0u8; LINE_MAX];
+        assert!(matches!(cursor.read(&log, &mut buf), Some(Read::Dropped(4))));
+        assert_eq!(next_seq(&mut cursor, &log), Some(4));
+    }
+
+    // ── store_message ──
+
+    #[test]
+    fn long_line_is_split_at_line_max() {
+        let mut log = new_log();
+        let mut message = [b'x'; 2 * LINE_MAX + 89];
+        message[2 * LINE_MAX + 88] = b'\n';
+        log.store_message(Level::Info, 0, &message);
+        let mut lens = [0; 8];
+        assert_eq!(lengths(&log, &mut lens), 3);
+        assert_eq!(lens[..3], [LINE_MAX, LINE_MAX, 88]);
+    }
+
+    #[test]
+    fn one_record_per_line() {
+        let mut log = new_log();
+        log.store_message(Level::Warning, 0, b"one\n\nthree\n");
+        let mut lens = [0; 8];
+        assert_eq!(lengths(&log, &mut lens), 3);
+        assert_eq!(lens[..3], [3, 0, 5]);
+
+        let mut cursor = Cursor::start(&log);
+        let mut buf = [0u8; LINE_MAX];
+        match cursor.read(&log, &mut buf) {
+            Some(Read::Record(record)) => {
+                assert_eq!(record.text, b"one");
+                assert!(record.level == Level::Warning);
+            }
+            _ => panic!("expected a record"),
+        }
+    }
+
+    #[test]
+    fn line_of_exactly_line_max_is_one_record() {
+        let mut log = new_log();
+        log.store_message(Level::Info, 0, &[b'y'; LINE_MAX]);
+        let mut lens = [0; 8];
+        assert_eq!(lengths(&log, &mut lens), 1);
+        assert_eq!(lens[0], LINE_MAX);
+    }
+}
@@ -1,7 +1,18 @@
+#[cfg(target_os = "none")]
 pub mod display;
+pub mod format;
+#[cfg(target_os = "none")]
 pub mod print_engine;
 pub mod input_buffer;
+#[cfg(target_os = "none")]
 pub mod io_manager;
+#[cfg(target_os = "none")]
 pub mod klog;
+pub mod log_ring;
+#[cfg(target_os = "none")]
+pub mod pointer;
+#[cfg(target_os = "none")]
 pub mod print;
+#[cfg(target_os = "none")]
 pub mod printk;
+pub mod stream;
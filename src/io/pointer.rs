@@ -0,0 +1,149 @@
+/// Text-mode mouse pointer.
+///
+/// Draws a block cursor by swapping the colors of the cell under the
+/// mouse, and implements X11-style selection: drag with the left button
+/// to select, release to copy, click the middle (or right) button to
+/// paste into the input line.
+///
+/// The cursor is drawn by inverting a cell, so it must be hidden before
+/// anything else writes to the screen (`io_manager` wraps every key
+/// event in `hide()` / `show()`), otherwise the inversion would stick.
+
+use crate::drivers::mouse::{self, MouseEvent};
+use crate::drivers::vga;
+
+/// Mouse movement units per text cell.
+const MICKEYS_PER_COL: i32 = 8;
+const MICKEYS_PER_ROW: i32 = 16;
+
+/// A full-screen selection plus one line break per row.
+const CLIPBOARD_SIZE: usize = (vga::VGA_WIDTH + 1) * vga::VGA_HEIGHT;
+
+// Position in mickeys, clamped to the screen
+static mut POS_X: i32 = 0;
+static mut POS_Y: i32 = 0;
+static mut BUTTONS: u8 = 0;
+static mut DRAWN: bool = false;
+
+// Selection (linear cell indices), highlighted while dragging
+static mut SELECTING: bool = false;
+static mut SEL_ANCHOR: usize = 0;
+static mut SEL_END: usize = 0;
+
+static mut CLIPBOARD: [u8; CLIPBOARD_SIZE] = [0; CLIPBOARD_SIZE];
+static mut CLIPBOARD_LEN: usize = 0;
+
+/// Returns the cell under the pointer as `(x, y)`.
+pub fn get_cell() -> (usize, usize) {
+    unsafe {
+        ((POS_X / MICKEYS_PER_COL) as usize, (POS_Y / MICKEYS_PER_ROW) as usize)
+    }
+}
+
+fn cell_index() -> usize {
+    let (x, y) = get_cell();
+    y * vga::VGA_WIDTH + x
+}
+
+/// Inverts every cell between the two linear indices (inclusive).
+fn invert_range(a: usize, b: usize) {
+    let (from, to) = if a <= b { (a, b) } else { (b, a) };
+    for i in from..=to {
+        vga::invert_color_at(i % vga::VGA_WIDTH, i / vga::VGA_WIDTH);
+    }
+}
+
+/// Removes the block cursor from the screen.
+pub fn hide() {
+    unsafe {
+        if DRAWN {
+            let (x, y) = get_cell();
+            vga::invert_color_at(x, y);
+            DRAWN = false;
+        }
+    }
+}
+
+/// Draws the block cursor if a mouse is present.
+pub fn show() {
+    unsafe {
+        if !DRAWN && mouse::is_enabled() {
+            let (x, y) = get_cell();
+            vga::invert_color_at(x, y);
+            DRAWN = true;
+        }
+    }
+}
+
+/// Copies the selected cells into the clipboard, one `\n` per line
+/// break, trailing blanks of each line dropped.
+fn copy_selection(a: usize, b: usize) {
+    let (from, to) = if a <= b { (a, b) } else { (b, a) };
+    let clip = unsafe { &mut *core::ptr::addr_of_mut!(CLIPBOARD) };
+    let mut len = 0;
+    let mut i = from;
+    while i <= to {
+        let y = i / vga::VGA_WIDTH;
+        let line_end = ((y + 1) * vga::VGA_WIDTH - 1).min(to);
+        // Trim trailing spaces on this line
+        let mut last = line_end + 1;
+        while last > i && vga::read_char_at((last - 1) % vga::VGA_WIDTH, y) == b' ' {
+            last -= 1;
+        }
+        for j in i..last {
+            unsafe { *clip.get_unchecked_mut(len) = vga::read_char_at(j % vga::VGA_WIDTH, y); }
+            len += 1;
+        }
+        if line_end < to {
+            unsafe { *clip.get_unchecked_mut(len) = b'\n'; }
+            len += 1;
+        }
+        i = line_end + 1;
+    }
+    unsafe { CLIPBOARD_LEN = len; }
+}
+
+/// Returns the last copied selection.
+pub fn clipboard() -> &'static [u8] {
+    unsafe { (*core::ptr::addr_of!(CLIPBOARD)).get_unchecked(..CLIPBOARD_LEN) }
+}
+
+/// Moves the pointer and updates the selection.
+///
+/// Returns `true` when the user asked to paste the clipboard.
+pub fn handle_event(event: MouseEvent) -> bool {
+    hide();
+
+    unsafe {
+        let max_x = vga::VGA_WIDTH as i32 * MICKEYS_PER_COL - 1;
+        let max_y = vga::VGA_HEIGHT as i32 * MICKEYS_PER_ROW - 1;
+        POS_X = (POS_X + event.dx as i32).clamp(0, max_x);
+        POS_Y = (POS_Y - event.dy as i32).clamp(0, max_y);
+    }
+
+    let pressed = event.buttons & !unsafe { BUTTONS };
+    let released = unsafe { BUTTONS } & !event.buttons;
+    unsafe { BUTTONS = event.buttons; }
+
+    let here = cell_index();
+    unsafe {
+        if pressed & mouse::BUTTON_LEFT != 0 {
+            SELECTING = true;
+            SEL_ANCHOR = here;
+            SEL_END = here;
+            invert_range(SEL_ANCHOR, SEL_END);
+        } else if SELECTING && here != SEL_END {
+            invert_range(SEL_ANCHOR, SEL_END);
+            SEL_END = here;
+            invert_range(SEL_ANCHOR, SEL_END);
+        }
+        if released & mouse::BUTTON_LEFT != 0 && SELECTING {
+            invert_range(SEL_ANCHOR, SEL_END);
+            copy_selection(SEL_ANCHOR, SEL_END);
+            SELECTING = false;
+        }
+    }
+
+    show();
+    pressed & (mouse::BUTTON_MIDDLE | mouse::BUTTON_RIGHT) != 0
+}
@@ -1,15 +1,11 @@
 /// `print!` / `println!` — formatted output to VGA display.
+/// `eprint!` / `eprintln!` — same, but never redirected by the shell.
 
 /// Print to VGA display. Use for user-facing output.
 #[macro_export]
 macro_rules! print {
-    ($fmt:expr) => {
-        $crate::io::print_engine::write_display($fmt, &[])
-    };
-    ($fmt:expr, $($arg:expr),* $(,)?) => {
-        $crate::io::print_engine::write_display($fmt, &[
-            $($crate::io::print_engine::PrintArg::from($arg)),*
-        ])
+    ($fmt:expr $(, $($args:tt)*)?) => {
+        $crate::format_call!({ $crate::io::print_engine::write_display } $fmt; [] [] $($($args)*)?)
     };
 }
 
@@ -17,12 +13,24 @@ macro_rules! print {
 #[macro_export]
 macro_rules! println {
     () => { $crate::print!("\n") };
-    ($fmt:expr) => {
-        $crate::io::print_engine::write_display(concat!($fmt, "\n"), &[])
+    ($fmt:expr $(, $($args:tt)*)?) => {
+        $crate::format_call!({ $crate::io::print_engine::write_display } concat!($fmt, "\n"); [] [] $($($args)*)?)
+    };
+}
+
+/// Print an error message to VGA display, even when output is redirected.
+#[macro_export]
+macro_rules! eprint {
+    ($fmt:expr $(, $($args:tt)*)?) => {
+        $crate::format_call!({ $crate::io::print_engine::write_error } $fmt; [] [] $($($args)*)?)
     };
-    ($fmt:expr, $($arg:expr),* $(,)?) => {
-        $crate::io::print_engine::write_display(concat!($fmt, "\n"), &[
-            $($crate::io::print_engine::PrintArg::from($arg)),*
-        ])
+}
+
+/// Print an error message to VGA display with trailing newline.
+#[macro_export]
+macro_rules! eprintln {
+    () => { $crate::eprint!("\n") };
+    ($fmt:expr $(, $($args:tt)*)?) => {
+        $crate::format_call!({ $crate::io::print_engine::write_error } concat!($fmt, "\n"); [] [] $($($args)*)?)
     };
 }
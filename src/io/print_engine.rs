@@ -1,16 +1,16 @@
 /// Shared print engine for `print!` and `printk!`.
 ///
-/// Supports format specifiers:
-///   {}    — default (decimal for integers, as-is for strings/chars/bools)
-///   {:x}  — hexadecimal lowercase       {:#x} — with "0x" prefix
-///   {:X}  — hexadecimal uppercase       {:#X} — with "0X" prefix
-///   {:b}  — binary                      {:#b} — with "0b" prefix
-///   {:o}  — octal                       {:#o} — with "0o" prefix
-///   {:p}  — pointer (hex with "0x" prefix)
-///   {{    — literal '{'
-///   }}    — literal '}'
+/// Formatting itself lives in `io::format` (see there for the supported
+/// `{:...}` specifiers); this module routes the rendered text to the
+/// VGA display, the kernel log and shell streams.
 
-use crate::io::{display, klog};
+use core::fmt;
+
+use crate::io::format::{self, BufWriter, Output};
+use crate::io::klog::{self, Level};
+use crate::io::{display, stream};
+
+pub use crate::io::format::{Formatter, KDebug, KDisplay, PrintArg};
 
 // ──────────────────────────────────────────────
 //  Output sink — controls where output is sent
@@ -19,13 +19,13 @@ use crate::io::{display, klog};
 /// Selects which output backends receive the formatted text.
 ///
 /// To add a new backend (e.g. serial), add a variant here,
-/// update `to_display()` / `to_klog()` (and add `to_serial()`),
-/// then update the three `emit_*` helpers below. Nothing else changes.
+/// update `to_display()` / `to_klog()` / `to_stream()` (and add `to_serial()`),
+/// then update the two `emit_*` helpers below. Nothing else changes.
 #[derive(Copy, Clone, PartialEq)]
 pub enum Sink {
     Display,    // VGA display only (user-facing output: echo, dmesg dump, …)
-    Klog,       // Kernel log ring buffer only (silent logging, no screen output)
-    Kernel,     // VGA display AND kernel log ring buffer (kernel messages: printk)
+    Klog,       // Kernel log ring buffer (the console shows it per the console level)
+    Stream,     // Current command's output stream (shell pipe or named buffer)
 }
 
 impl Sink {
@@ -33,8 +33,8 @@ impl Sink {
     #[inline(always)]
     fn to_display(self) -> bool {
         match self {
-            Sink::Display | Sink::Kernel => true,
-            Sink::Klog => false,
+            Sink::Display => true,
+            Sink::Klog | Sink::Stream => false,
         }
     }
 
@@ -42,63 +42,19 @@ impl Sink {
     #[inline(always)]
     fn to_klog(self) -> bool {
         match self {
-            Sink::Klog | Sink::Kernel => true,
-            Sink::Display => false,
+            Sink::Klog => true,
+            Sink::Display | Sink::Stream => false,
         }
     }
-}
 
-// ──────────────────────────────────────────────
-//  PrintArg — type-erased argument (like va_arg)
-// ──────────────────────────────────────────────
-
-#[derive(Copy, Clone)]
-pub enum PrintArg<'a> {
-    Str(&'a str),
-    Bytes(&'a [u8]),
-    Char(u8),
-    I32(i32),
-    U32(u32),
-    Usize(usize),
-    Bool(bool),
-    Ptr(*const u32),
-}
-
-impl<'a> From<&'a str> for PrintArg<'a> {
-    fn from(v: &'a str) -> Self { PrintArg::Str(v) }
-}
-impl<'a> From<&'a [u8]> for PrintArg<'a> {
-    fn from(v: &'a [u8]) -> Self { PrintArg::Bytes(v) }
-}
-impl<'a> From<u8> for PrintArg<'a> {
-    fn from(v: u8) -> Self { PrintArg::U32(v as u32) }
-}
-impl<'a> From<u16> for PrintArg<'a> {
-    fn from(v: u16) -> Self { PrintArg::U32(v as u32) }
-}
-impl<'a> From<u32> for PrintArg<'a> {
-    fn from(v: u32) -> Self { PrintArg::U32(v) }
-}
-impl<'a> From<i8> for PrintArg<'a> {
-    fn from(v: i8) -> Self { PrintArg::I32(v as i32) }
-}
-impl<'a> From<i16> for PrintArg<'a> {
-    fn from(v: i16) -> Self { PrintArg::I32(v as i32) }
-}
-impl<'a> From<i32> for PrintArg<'a> {
-    fn from(v: i32) -> Self { PrintArg::I32(v) }
-}
-impl<'a> From<usize> for PrintArg<'a> {
-    fn from(v: usize) -> Self { PrintArg::Usize(v) }
-}
-impl<'a> From<bool> for PrintArg<'a> {
-    fn from(v: bool) -> Self { PrintArg::Bool(v) }
-}
-impl<'a> From<char> for PrintArg<'a> {
-    fn from(v: char) -> Self { PrintArg::Char(v as u8) }
-}
-impl<'a> From<&u32> for PrintArg<'a> {
-    fn from(v: &u32) -> Self { PrintArg::Ptr(v) }
+    /// Should this sink write to the command output stream?
+    #[inline(always)]
+    fn to_stream(self) -> bool {
+        match self {
+            Sink::Stream => true,
+            Sink::Display | Sink::Klog => false,
+        }
+    }
 }
 
 // ──────────────────────────────────────────────
@@ -110,13 +66,7 @@ impl<'a> From<&u32> for PrintArg<'a> {
 fn emit_raw(c: u8, sink: Sink) {
     if sink.to_display() { display::put_char(c); }
     if sink.to_klog()    { klog::log_byte(c); }
-}
-
-/// Emit a string (with control-char interpretation on the display side).
-#[inline]
-fn emit_str(s: &str, sink: Sink) {
-    if sink.to_display() { display::put_str(s); }
-    if sink.to_klog()    { klog::log_str(s); }
+    if sink.to_stream()  { stream::write_byte(c); }
 }
 
 /// Emit a byte slice (with control-char interpretation on the display side).
@@ -124,205 +74,69 @@ fn emit_str(s: &str, sink: Sink) {
 fn emit_bytes(b: &[u8], sink: Sink) {
     if sink.to_display() { display::put_bytes(b); }
     if sink.to_klog()    { klog::log_bytes(b); }
+    if sink.to_stream()  { stream::write_bytes(b); }
 }
 
-/// Emit a single byte with control-char interpretation (\n, \t, etc.).
-/// Used for literal characters from the format string.
-#[inline]
-fn emit_byte(c: u8, sink: Sink) {
-    if sink.to_display() { display::write_byte(c, crate::drivers::vga::DEFAULT_COLOR); }
-    if sink.to_klog()    { klog::log_byte(c); }
-}
-
-// ──────────────────────────────────────────────
-//  itoa — number → string on a stack buffer
-// ──────────────────────────────────────────────
-
-const ITOA_BUF_SIZE: usize = 34; // 32-bit binary + sign
-
-#[derive(Copy, Clone)]
-enum Spec {
-    Default,
-    Hex,
-    HexUpper,
-    Binary,
-    Octal,
-    HexAlt,
-    HexUpperAlt,
-    BinaryAlt,
-    OctalAlt,
-    Ptr,
-}
-
-impl Spec {
-    fn params(self) -> (u32, bool, &'static str) {
-        match self {
-            Spec::Default      => (10, false, ""),
-            Spec::Hex          => (16, false, ""),
-            Spec::HexUpper     => (16, true,  ""),
-            Spec::Binary       => ( 2, false, ""),
-            Spec::Octal        => ( 8, false, ""),
-            Spec::HexAlt       => (16, false, "0x"),
-            Spec::HexUpperAlt  => (16, true,  "0X"),
-            Spec::BinaryAlt    => ( 2, false, "0b"),
-            Spec::OctalAlt     => ( 8, false, "0o"),
-            Spec::Ptr          => (16, false, "0x"),
-        }
-    }
-}
-
-fn u32_to_base(mut val: u32, base: u32, uppercase: bool, buf: &mut [u8; ITOA_BUF_SIZE]) -> usize {
-    if val == 0 {
-        unsafe { *buf.get_unchecked_mut(ITOA_BUF_SIZE - 1) = b'0'; }
-        return ITOA_BUF_SIZE - 1;
-    }
-    let mut i = ITOA_BUF_SIZE;
-    while val > 0 {
-        i -= 1;
-        let digit = (val % base) as u8;
-        unsafe {
-            *buf.get_unchecked_mut(i) = if digit < 10 {
-                b'0' + digit
-            } else if uppercase {
-                b'A' + (digit - 10)
-            } else {
-                b'a' + (digit - 10)
-            };
-        }
-        val /= base;
-    }
-    i
-}
-
-fn emit_buf(buf: &[u8; ITOA_BUF_SIZE], start: usize, sink: Sink) {
-    let mut i = start;
-    while i < ITOA_BUF_SIZE {
-        emit_raw(unsafe { *buf.get_unchecked(i) }, sink);
-        i += 1;
+impl Output for Sink {
+    fn raw(&mut self, c: u8) {
+        emit_raw(c, *self);
     }
-}
 
-fn write_u32(val: u32, spec: Spec, sink: Sink) {
-    let (base, uppercase, prefix) = spec.params();
-    if !prefix.is_empty() {
-        emit_str(prefix, sink);
+    fn text(&mut self, bytes: &[u8]) {
+        emit_bytes(bytes, *self);
     }
-    let mut buf = [0u8; ITOA_BUF_SIZE];
-    let start = u32_to_base(val, base, uppercase, &mut buf);
-    emit_buf(&buf, start, sink);
 }
 
-fn write_i32(val: i32, spec: Spec, sink: Sink) {
-    if val < 0 {
-        emit_raw(b'-', sink);
-        write_u32(val.wrapping_neg() as u32, spec, sink);
-    } else {
-        write_u32(val as u32, spec, sink);
+/// Lets `write!` and `core::fmt`-based code print through a sink, e.g.
+/// `write!(Sink::Klog, ...)`. Only the `core-fmt` feature links the
+/// `core::fmt` machinery that `write!` needs.
+impl fmt::Write for Sink {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        emit_bytes(s.as_bytes(), *self);
+        Ok(())
     }
 }
 
 // ──────────────────────────────────────────────
-//  Format string parser
+//  Public entry points
 // ──────────────────────────────────────────────
 
-fn write_arg(arg: &PrintArg, spec: Spec, sink: Sink) {
-    match arg {
-        PrintArg::Str(s)    => emit_str(s, sink),
-        PrintArg::Bytes(b)  => emit_bytes(b, sink),
-        PrintArg::Char(c)   => emit_raw(*c, sink),
-        PrintArg::I32(v)    => write_i32(*v, spec, sink),
-        PrintArg::U32(v)    => write_u32(*v, spec, sink),
-        PrintArg::Usize(v)  => write_u32(*v as u32, spec, sink),
-        PrintArg::Bool(v)   => emit_str(if *v { "true" } else { "false" }, sink),
-        PrintArg::Ptr(v) => write_u32(*v as u32, spec, sink),
-    }
-}
-
-fn parse_spec(fmt: &[u8], start: usize, end: usize) -> Spec {
-    let len = end - start;
-    if len == 0 {
-        return Spec::Default;
-    }
-    if unsafe { *fmt.get_unchecked(start) } != b':' {
-        return Spec::Default;
-    }
-    if len == 2 {
-        return match unsafe { *fmt.get_unchecked(start + 1) } {
-            b'x' => Spec::Hex,
-            b'X' => Spec::HexUpper,
-            b'b' => Spec::Binary,
-            b'o' => Spec::Octal,
-            b'p' => Spec::Ptr,
-            _ => Spec::Default,
-        };
-    }
-    if len == 3 && unsafe { *fmt.get_unchecked(start + 1) } == b'#' {
-        return match unsafe { *fmt.get_unchecked(start + 2) } {
-            b'x' => Spec::HexAlt,
-            b'X' => Spec::HexUpperAlt,
-            b'b' => Spec::BinaryAlt,
-            b'o' => Spec::OctalAlt,
-            _ => Spec::Default,
+/// Writes formatted output to the current command output: the VGA display,
+/// or the pipe / named buffer the shell redirected it to.
+pub fn write_display(fmt: &str, args: &[PrintArg], names: &[&str]) {
+    let mut sink = if stream::is_redirected() { Sink::Stream } else { Sink::Display };
+    format::format(fmt, args, names, &mut sink);
+}
+
+/// Writes formatted output to VGA display only, ignoring shell redirections
+/// (error messages).
+pub fn write_error(fmt: &str, args: &[PrintArg], names: &[&str]) {
+    format::format(fmt, args, names, &mut Sink::Display);
+}
+
+/// Longest printk message; the rest is cut off.
+const PRINTK_MAX: usize = 1024;
+
+/// A `printk` call at a log level: the printk macros expand to
+/// `Printk(level).write(fmt, args, names)`. Each call ends the current
+/// log record, even without a trailing newline.
+pub struct Printk(pub Level);
+
+impl Printk {
+    /// Writes formatted output to the kernel log ring buffer, then
+    /// flushes the console (see `klog`).
+    ///
+    /// The message is formatted on the stack first and stored in one go,
+    /// so a printk from an interrupt handler can't end up in the middle
+    /// of it.
+    pub fn write(self, fmt: &str, args: &[PrintArg], names: &[&str]) {
+        let Printk(level) = self;
+        let mut buf = [0u8; PRINTK_MAX];
+        let text = match BufWriter::new(&mut buf).format(fmt, args, names) {
+            Ok(text) => text,
+            Err(truncated) => truncated.written,
         };
+        klog::store_message(level, text);
+        klog::flush_console();
     }
-    Spec::Default
-}
-
-/// Core print engine — parses format string and emits to the selected sink(s).
-fn format(fmt: &str, args: &[PrintArg], sink: Sink) {
-    let bytes = fmt.as_bytes();
-    let len = bytes.len();
-    let mut i: usize = 0;
-    let mut arg_idx: usize = 0;
-
-    while i < len {
-        let ch = unsafe { *bytes.get_unchecked(i) };
-
-        if ch == b'{' {
-            if i + 1 < len && unsafe { *bytes.get_unchecked(i + 1) } == b'{' {
-                emit_raw(b'{', sink);
-                i += 2;
-                continue;
-            }
-            let spec_start = i + 1;
-            let mut j = spec_start;
-            while j < len && unsafe { *bytes.get_unchecked(j) } != b'}' {
-                j += 1;
-            }
-            let spec = parse_spec(bytes, spec_start, j);
-            if arg_idx < args.len() {
-                write_arg(unsafe { args.get_unchecked(arg_idx) }, spec, sink);
-                arg_idx += 1;
-            }
-            i = j + 1;
-            continue;
-        }
-        if ch == b'}' && i + 1 < len && unsafe { *bytes.get_unchecked(i + 1) } == b'}' {
-            emit_raw(b'}', sink);
-            i += 2;
-            continue;
-        }
-        // Regular character — needs control-char interpretation (\n, \t, etc.)
-        emit_byte(ch, sink);
-        i += 1;
-    }
-}
-
-// ──────────────────────────────────────────────
-//  Public entry points
-// ──────────────────────────────────────────────
-
-/// Writes formatted output to VGA display only (user-facing output).
-pub fn write_display(fmt: &str, args: &[PrintArg]) {
-    format(fmt, args, Sink::Display);
-}
-
-/// Writes formatted output to kernel log ring buffer only (no screen output).
-pub fn write_klog(fmt: &str, args: &[PrintArg]) {
-    format(fmt, args, Sink::Klog);
-}
-
-/// Writes formatted output to both VGA display and kernel log ring buffer.
-pub fn write_kernel(fmt: &str, args: &[PrintArg]) {
-    format(fmt, args, Sink::Kernel);
 }
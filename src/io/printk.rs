@@ -1,17 +1,16 @@
 /// `printk!` / `printkln!` — formatted output to kernel log buffer.
 ///
 /// Output is stored in a ring buffer and can be retrieved with `dmesg`.
+///
+/// `printk!` logs at the default level (info). `pr_emerg!` … `pr_debug!`
+/// log a line at the named level. Messages below the console log level
+/// (`dmesg -n`) are also printed on the screen.
 
 /// Print to kernel log buffer only.
 #[macro_export]
 macro_rules! printk {
-    ($fmt:expr) => {
-        $crate::io::print_engine::write_klog($fmt, &[])
-    };
-    ($fmt:expr, $($arg:expr),* $(,)?) => {
-        $crate::io::print_engine::write_klog($fmt, &[
-            $($crate::io::print_engine::PrintArg::from($arg)),*
-        ])
+    ($fmt:expr $(, $($args:tt)*)?) => {
+        $crate::format_call!({ $crate::io::print_engine::Printk($crate::io::klog::DEFAULT_LEVEL).write } $fmt; [] [] $($($args)*)?)
     };
 }
 
@@ -19,12 +18,65 @@ macro_rules! printk {
 #[macro_export]
 macro_rules! printkln {
     () => { $crate::printk!("\n") };
-    ($fmt:expr) => {
-        $crate::io::print_engine::write_klog(concat!($fmt, "\n"), &[])
+    ($fmt:expr $(, $($args:tt)*)?) => {
+        $crate::format_call!({ $crate::io::print_engine::Printk($crate::io::klog::DEFAULT_LEVEL).write } concat!($fmt, "\n"); [] [] $($($args)*)?)
     };
-    ($fmt:expr, $($arg:expr),* $(,)?) => {
-        $crate::io::print_engine::write_klog(concat!($fmt, "\n"), &[
-            $($crate::io::print_engine::PrintArg::from($arg)),*
-        ])
+}
+
+/// Log a line at `$level`, a `klog::Level` variant name. Used by the
+/// `pr_*!` macros.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! printk_level {
+    ($level:ident, $fmt:expr $(, $($args:tt)*)?) => {
+        $crate::format_call!({ $crate::io::print_engine::Printk($crate::io::klog::Level::$level).write } concat!($fmt, "\n"); [] [] $($($args)*)?)
     };
 }
+
+/// System is unusable.
+#[macro_export]
+macro_rules! pr_emerg {
+    ($($args:tt)*) => { $crate::printk_level!(Emerg, $($args)*) };
+}
+
+/// Action must be taken immediately.
+#[macro_export]
+macro_rules! pr_alert {
+    ($($args:tt)*) => { $crate::printk_level!(Alert, $($args)*) };
+}
+
+/// Critical condition.
+#[macro_export]
+macro_rules! pr_crit {
+    ($($args:tt)*) => { $crate::printk_level!(Crit, $($args)*) };
+}
+
+/// Error condition.
+#[macro_export]
+macro_rules! pr_err {
+    ($($args:tt)*) => { $crate::printk_level!(Err, $($args)*) };
+}
+
+/// Warning condition.
+#[macro_export]
+macro_rules! pr_warn {
+    ($($args:tt)*) => { $crate::printk_level!(Warning, $($args)*) };
+}
+
+/// Normal but significant condition.
+#[macro_export]
+macro_rules! pr_notice {
+    ($($args:tt)*) => { $crate::printk_level!(Notice, $($args)*) };
+}
+
+/// Informational message.
+#[macro_export]
+macro_rules! pr_info {
+    ($($args:tt)*) => { $crate::printk_level!(Info, $($args)*) };
+}
+
+/// Debug-level message.
+#[macro_export]
+macro_rules! pr_debug {
+    ($($args:tt)*) => { $crate::printk_level!(Debug, $($args)*) };
+}
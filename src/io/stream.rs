@@ -0,0 +1,408 @@
+//! In-memory byte streams for shell pipelines and redirections.
+//!
+//! While a command runs, its standard output is either the VGA display
+//! or one of these streams:
+//!   - pipe streams, connecting `cmd1 | cmd2` (the output of a stage
+//!     becomes the standard input of the next one),
+//!   - named buffers, the target of `> name` / `>> name`, which outlive
+//!     the command line and can be read back with `cat name`.
+//!
+//! `print_engine` routes `print!` output here through `Sink::Stream`
+//! whenever `is_redirected()` is true.
+//!
+//! Pipes come from a small pool so that a pipeline can run inside a stage
+//! of another one (a function or loop whose output is piped); the shell
+//! saves the current redirections before setting up a pipeline and
+//! restores them afterwards.
+
+const STREAM_SIZE: usize = 4096;
+const PIPE_COUNT: usize = 6;
+
+/// Number of named buffer slots.
+pub const BUFFER_COUNT: usize = 8;
+const BUFFER_NAME_SIZE: usize = 16;
+
+pub struct Stream {
+    data: [u8; STREAM_SIZE],
+    len: usize,
+    truncated: bool,
+}
+
+impl Stream {
+    const fn new() -> Self {
+        Stream { data: [0; STREAM_SIZE], len: 0, truncated: false }
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+        self.truncated = false;
+    }
+
+    fn write_byte(&mut self, c: u8) {
+        if self.len < STREAM_SIZE {
+            unsafe { *self.data.get_unchecked_mut(self.len) = c; }
+            self.len += 1;
+        } else {
+            self.truncated = true;
+        }
+    }
+
+    /// Returns the bytes written so far.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { self.data.get_unchecked(..self.len) }
+    }
+
+    /// Returns `true` if writes were dropped because the stream was full.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+struct NamedBuffer {
+    name: [u8; BUFFER_NAME_SIZE],
+    name_len: usize,
+    stream: Stream,
+}
+
+impl NamedBuffer {
+    const fn new() -> Self {
+        NamedBuffer { name: [0; BUFFER_NAME_SIZE], name_len: 0, stream: Stream::new() }
+    }
+
+    fn name(&self) -> &[u8] {
+        unsafe { self.name.get_unchecked(..self.name_len) }
+    }
+}
+
+static mut PIPES: [Stream; PIPE_COUNT] = [const { Stream::new() }; PIPE_COUNT];
+static mut PIPE_IN_USE: [bool; PIPE_COUNT] = [false; PIPE_COUNT];
+static mut BUFFERS: [NamedBuffer; BUFFER_COUNT] = [const { NamedBuffer::new() }; BUFFER_COUNT];
+
+// Current standard output (null = display) and input (null = none)
+static mut STDOUT: *mut Stream = core::ptr::null_mut();
+static mut STDIN: *const Stream = core::ptr::null();
+
+fn find_buffer(name: &[u8]) -> Option<usize> {
+    let buffers = unsafe { &*core::ptr::addr_of!(BUFFERS) };
+    buffers.iter().position(|b| b.name_len > 0 && b.name() == name)
+}
+
+// ──────────────────────────────────────────────
+//  Write API (called from print_engine)
+// ──────────────────────────────────────────────
+
+/// Returns `true` if standard output currently goes to a stream.
+#[inline(always)]
+pub fn is_redirected() -> bool {
+    unsafe { !STDOUT.is_null() }
+}
+
+/// Appends a byte to the current standard output stream.
+#[inline]
+pub fn write_byte(c: u8) {
+    unsafe {
+        if !STDOUT.is_null() {
+            (*STDOUT).write_byte(c);
+        }
+    }
+}
+
+/// Returns `true` if the current standard output stream dropped writes.
+pub fn stdout_truncated() -> bool {
+    unsafe { !STDOUT.is_null() && (*STDOUT).is_truncated() }
+}
+
+/// Appends a byte slice to the current standard output stream.
+pub fn write_bytes(bytes: &[u8]) {
+    for &b in bytes {
+        write_byte(b);
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Redirection setup (called by the shell)
+// ──────────────────────────────────────────────
+
+/// Standard output and input of a command, as saved by `save()`.
+#[derive(Copy, Clone)]
+pub struct Redirections {
+    stdout: *mut Stream,
+    stdin: *const Stream,
+}
+
+/// Returns the current redirections.
+pub fn save() -> Redirections {
+    unsafe { Redirections { stdout: STDOUT, stdin: STDIN } }
+}
+
+/// Puts back redirections returned by `save()`.
+pub fn restore(saved: Redirections) {
+    unsafe {
+        STDOUT = saved.stdout;
+        STDIN = saved.stdin;
+    }
+}
+
+/// Sends output back to the display, detaches input and frees every pipe.
+pub fn reset() {
+    restore(Redirections { stdout: core::ptr::null_mut(), stdin: core::ptr::null() });
+    unsafe { *core::ptr::addr_of_mut!(PIPE_IN_USE) = [false; PIPE_COUNT]; }
+}
+
+/// Takes an empty pipe from the pool. Returns `None` if all are in use.
+pub fn open_pipe() -> Option<usize> {
+    let in_use = unsafe { &mut *core::ptr::addr_of_mut!(PIPE_IN_USE) };
+    let idx = in_use.iter().position(|&used| !used)?;
+    unsafe {
+        *in_use.get_unchecked_mut(idx) = true;
+        (*core::ptr::addr_of_mut!(PIPES)).get_unchecked_mut(idx).clear();
+    }
+    Some(idx)
+}
+
+/// Returns a pipe to the pool.
+pub fn close_pipe(idx: usize) {
+    if idx < PIPE_COUNT {
+        unsafe { *(*core::ptr::addr_of_mut!(PIPE_IN_USE)).get_unchecked_mut(idx) = false; }
+    }
+}
+
+/// Sends standard output to pipe `idx`.
+pub fn redirect_to_pipe(idx: usize) {
+    let idx = idx % PIPE_COUNT;
+    unsafe { STDOUT = (*core::ptr::addr_of_mut!(PIPES)).get_unchecked_mut(idx); }
+}
+
+/// Sends standard output to the named buffer, created if needed.
+/// Returns `false` if the name is invalid or every buffer is in use.
+pub fn redirect_to_buffer(name: &[u8], append: bool) -> bool {
+    if name.is_empty() || name.len() > BUFFER_NAME_SIZE {
+        return false;
+    }
+    let buffers = unsafe { &mut *core::ptr::addr_of_mut!(BUFFERS) };
+    let idx = match find_buffer(name) {
+        Some(idx) => idx,
+        None => {
+            let Some(idx) = buffers.iter().position(|b| b.name_len == 0) else {
+                return false;
+            };
+            let buffer = unsafe { buffers.get_unchecked_mut(idx) };
+            for (i, &c) in name.iter().enumerate() {
+                unsafe { *buffer.name.get_unchecked_mut(i) = c; }
+            }
+            buffer.name_len = name.len();
+            buffer.stream.clear();
+            idx
+        }
+    };
+    let buffer = unsafe { buffers.get_unchecked_mut(idx) };
+    if !append {
+        buffer.stream.clear();
+    }
+    unsafe { STDOUT = &mut buffer.stream; }
+    true
+}
+
+/// Makes pipe `idx` the standard input of the next command.
+pub fn set_stdin_pipe(idx: usize) {
+    let idx = idx % PIPE_COUNT;
+    unsafe { STDIN = (*core::ptr::addr_of!(PIPES)).get_unchecked(idx); }
+}
+
+// ──────────────────────────────────────────────
+//  Read API (called by builtins)
+// ──────────────────────────────────────────────
+
+/// Returns the standard input of the running command, if it has one.
+pub fn stdin() -> Option<&'static [u8]> {
+    unsafe {
+        if STDIN.is_null() {
+            None
+        } else {
+            Some((*STDIN).as_bytes())
+        }
+    }
+}
+
+/// Returns the content of a named buffer.
+pub fn buffer(name: &[u8]) -> Option<&'static Stream> {
+    let idx = find_buffer(name)?;
+    Some(unsafe { &(*core::ptr::addr_of!(BUFFERS)).get_unchecked(idx).stream })
+}
+
+/// Returns the name and stream of the `idx`-th buffer slot, if in use.
+pub fn buffer_at(idx: usize) -> Option<(&'static [u8], &'static Stream)> {
+    let buffers = unsafe { &*core::ptr::addr_of!(BUFFERS) };
+    let buffer = buffers.get(idx)?;
+    if buffer.name_len == 0 {
+        return None;
+    }
+    Some((buffer.name(), &buffer.stream))
+}
+
+/// Deletes a named buffer. Returns `false` if it does not exist.
+pub fn remove_buffer(name: &[u8]) -> bool {
+    let Some(idx) = find_buffer(name) else {
+        return false;
+    };
+    unsafe { (*core::ptr::addr_of_mut!(BUFFERS)).get_unchecked_mut(idx).name_len = 0; }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern crate std;
+
+    use std::sync::{Mutex, MutexGuard};
+
+    /// The streams are global: tests touching them take turns.
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    /// Locks the streams and starts from no redirection and no buffer.
+    fn fresh() -> MutexGuard<'static, ()> {
+        let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        reset();
+        for idx in 0..BUFFER_COUNT {
+            unsafe { (*core::ptr::addr_of_mut!(BUFFERS)).get_unchecked_mut(idx).name_len = 0; }
+        }
+        guard
+    }
+
+    fn buffer_text(name: &[u8]) -> &'static [u8] {
+        buffer(name).expect("no such buffer").as_bytes()
+    }
+
+    // ── pipes ──
+
+    #[test]
+    fn pipe_carries_output_to_stdin() {
+        let _guard = fresh();
+        let idx = open_pipe().unwrap();
+        redirect_to_pipe(idx);
+        assert!(is_redirected());
+        write_bytes(b"hello");
+        set_stdin_pipe(idx);
+        assert_eq!(stdin(), Some(&b"hello"[..]));
+    }
+
+    #[test]
+    fn closed_pipe_is_reused_empty() {
+        let _guard = fresh();
+        let first = open_pipe().unwrap();
+        let second = open_pipe().unwrap();
+        assert_ne!(first, second);
+        redirect_to_pipe(first);
+        write_bytes(b"old");
+        close_pipe(first);
+        assert_eq!(open_pipe(), Some(first));
+        set_stdin_pipe(first);
+        assert_eq!(stdin(), Some(&b""[..]));
+    }
+
+    #[test]
+    fn pool_runs_out_of_pipes() {
+        let _guard = fresh();
+        for _ in 0..PIPE_COUNT {
+            assert!(open_pipe().is_some());
+        }
+        assert_eq!(open_pipe(), None);
+        close_pipe(2);
+        assert_eq!(open_pipe(), Some(2));
+    }
+
+    // ── buffers ──
+
+    #[test]
+    fn redirect_truncates_and_append_appends() {
+        let _guard = fresh();
+        assert!(redirect_to_buffer(b"out", false));
+        write_bytes(b"one");
+        assert!(redirect_to_buffer(b"out", true));
+        write_bytes(b"two");
+        assert_eq!(buffer_text(b"out"), b"onetwo");
+        assert!(redirect_to_buffer(b"out", false));
+        write_bytes(b"three");
+        assert_eq!(buffer_text(b"out"), b"three");
+    }
+
+    #[test]
+    fn append_creates_a_missing_buffer() {
+        let _guard = fresh();
+        assert!(redirect_to_buffer(b"new", true));
+        write_bytes(b"x");
+        assert_eq!(buffer_text(b"new"), b"x");
+    }
+
+    #[test]
+    fn writes_past_stream_size_are_dropped() {
+        let _guard = fresh();
+        assert!(redirect_to_buffer(b"big", false));
+        for _ in 0..STREAM_SIZE {
+            write_byte(b'a');
+        }
+        assert!(!stdout_truncated());
+        write_byte(b'b');
+        assert!(stdout_truncated());
+        let stream = buffer(b"big").unwrap();
+        assert_eq!(stream.as_bytes().len(), STREAM_SIZE);
+        assert!(stream.as_bytes().iter().all(|&c| c == b'a'));
+        assert!(stream.is_truncated());
+        assert!(redirect_to_buffer(b"big", false));
+        assert!(!stdout_truncated());
+    }
+
+    #[test]
+    fn invalid_buffer_names_are_rejected() {
+        let _guard = fresh();
+        assert!(!redirect_to_buffer(b"", false));
+        assert!(!redirect_to_buffer(&[b'n'; BUFFER_NAME_SIZE + 1], false));
+        assert!(redirect_to_buffer(&[b'n'; BUFFER_NAME_SIZE], false));
+    }
+
+    #[test]
+    fn buffer_slots_run_out() {
+        let _guard = fresh();
+        for i in 0..BUFFER_COUNT {
+            assert!(redirect_to_buffer(&[b'a' + i as u8], false));
+        }
+        assert!(!redirect_to_buffer(b"z", false));
+        // An existing buffer is still writable
+        assert!(redirect_to_buffer(b"a", true));
+        assert!(remove_buffer(b"c"));
+        assert!(!remove_buffer(b"c"));
+        assert!(redirect_to_buffer(b"z", false));
+        assert_eq!(buffer_at(2).map(|(name, _)| name), Some(&b"z"[..]));
+    }
+
+    // ── save / restore / reset ──
+
+    #[test]
+    fn restore_puts_back_saved_redirections() {
+        let _guard = fresh();
+        assert!(redirect_to_buffer(b"outer", false));
+        let saved = save();
+        let idx = open_pipe().unwrap();
+        redirect_to_pipe(idx);
+        write_bytes(b"inner");
+        restore(saved);
+        write_bytes(b"outer");
+        assert_eq!(buffer_text(b"outer"), b"outer");
+        assert_eq!(stdin(), None);
+    }
+
+    #[test]
+    fn reset_goes_back_to_the_display_and_frees_pipes() {
+        let _guard = fresh();
+        for _ in 0..PIPE_COUNT {
+            open_pipe();
+        }
+        redirect_to_pipe(0);
+        set_stdin_pipe(1);
+        reset();
+        assert!(!is_redirected());
+        assert_eq!(stdin(), None);
+        assert_eq!(open_pipe(), Some(0));
+    }
+}
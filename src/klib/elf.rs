@@ -0,0 +1,319 @@
+//! ELF32 symbol lookup, to name the functions in stack traces.
+//!
+//! GRUB hands over the kernel's section headers and loads every section,
+//! `.symtab` and `.strtab` included (see `multiboot::kernel_symbols`).
+//! `Symbols::lookup` finds the function an address falls in, and
+//! `demangle` turns Rust's legacy symbol names
+//! (`_ZN5tacos2mm4slab7kmalloc17h…E`) back into paths
+//! (`tacos::mm::slab::kmalloc`).
+
+/// Size of a section header (`Elf32_Shdr`).
+pub const SECTION_HEADER_SIZE: usize = 40;
+/// Size of a symbol table entry (`Elf32_Sym`).
+const SYMBOL_SIZE: usize = 16;
+
+/// `sh_type` of the symbol table.
+const SHT_SYMTAB: u32 = 2;
+/// Symbol types, from `st_info`.
+const STT_NOTYPE: u8 = 0;
+const STT_FUNC: u8 = 2;
+
+/// The fields of a section header we use.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Section {
+    pub kind: u32,
+    /// Where the section was loaded, 0 if it was not.
+    pub addr: u32,
+    pub size: u32,
+    /// Index of a related section: the string table of a symbol table.
+    pub link: u32,
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    let word = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(<[u8; 4]>::try_from(word).ok()?))
+}
+
+/// Section `idx` of a section header table.
+pub fn section(headers: &[u8], idx: usize) -> Option<Section> {
+    let base = idx * SECTION_HEADER_SIZE;
+    Some(Section {
+        kind: u32_at(headers, base + 4)?,
+        addr: u32_at(headers, base + 12)?,
+        size: u32_at(headers, base + 20)?,
+        link: u32_at(headers, base + 24)?,
+    })
+}
+
+/// The sections of a section header table, in order.
+pub fn sections(headers: &[u8]) -> impl Iterator<Item = Section> + '_ {
+    (0..headers.len() / SECTION_HEADER_SIZE).filter_map(move |idx| section(headers, idx))
+}
+
+/// The symbol table and its string table.
+pub fn symbol_table(headers: &[u8]) -> Option<(Section, Section)> {
+    let symtab = sections(headers).find(|section| section.kind == SHT_SYMTAB)?;
+    Some((symtab, section(headers, symtab.link as usize)?))
+}
+
+/// A symbol table and the string table of its names.
+pub struct Symbols<'a> {
+    symtab: &'a [u8],
+    strtab: &'a [u8],
+}
+
+impl<'a> Symbols<'a> {
+    pub fn new(symtab: &'a [u8], strtab: &'a [u8]) -> Self {
+        Symbols { symtab, strtab }
+    }
+
+    /// NUL-terminated string at `offset` in the string table.
+    fn name(&self, offset: u32) -> &'a [u8] {
+        let rest = self.strtab.get(offset as usize..).unwrap_or(&[]);
+        let len = rest.iter().position(|&c| c == 0).unwrap_or(rest.len());
+        rest.get(..len).unwrap_or(&[])
+    }
+
+    /// Name of the function containing `addr` and the offset of `addr` in
+    /// it. Symbols without a size (assembly labels) cover the addresses up
+    /// to the next one, and only count when no sized function contains
+    /// `addr`.
+    pub fn lookup(&self, addr: u32) -> Option<(&'a [u8], u32)> {
+        let mut nearest: Option<(u32, u32)> = None;
+        for entry in self.symtab.chunks_exact(SYMBOL_SIZE) {
+            let (Some(name), Some(value), Some(size)) = (u32_at(entry, 0), u32_at(entry, 4), u32_at(entry, 8)) else {
+                continue;
+            };
+            let kind = entry.get(12).map_or(STT_NOTYPE, |info| info & 0xF);
+            if value == 0 || value > addr || (kind != STT_FUNC && kind != STT_NOTYPE) {
+                continue;
+            }
+            if size != 0 {
+                if kind == STT_FUNC && addr - value < size {
+                    return Some((self.name(name), addr - value));
+                }
+            } else if nearest.is_none_or(|(_, best)| value > best) {
+                nearest = Some((name, value));
+            }
+        }
+        nearest.map(|(name, value)| (self.name(name), addr - value))
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Demangling
+// ──────────────────────────────────────────────
+
+/// Escapes of the legacy mangling scheme.
+static ESCAPES: &[(&[u8], &[u8])] = &[
+    (b"$LT$", b"<"), (b"$GT$", b">"), (b"$RF$", b"&"), (b"$BP$", b"*"),
+    (b"$C$", b","), (b"$SP$", b"@"), (b"$u20$", b" "), (b"$u27$", b"'"),
+    (b"$u5b$", b"["), (b"$u5d$", b"]"), (b"$u7b$", b"{"), (b"$u7d$", b"}"),
+    (b"$u7e$", b"~"), (b"..", b"::"),
+];
+
+/// Appends bytes to a buffer, dropping what does not fit.
+struct Out<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+}
+
+impl Out<'_> {
+    fn push(&mut self, bytes: &[u8]) {
+        for &c in bytes {
+            if let Some(slot) = self.buf.get_mut(self.len) {
+                *slot = c;
+                self.len += 1;
+            }
+        }
+    }
+}
+
+/// `h` followed by 16 hex digits: the hash ending every legacy path.
+fn is_hash(component: &[u8]) -> bool {
+    component.len() == 17 && component.first() == Some(&b'h') && component.iter().skip(1).all(u8::is_ascii_hexdigit)
+}
+
+/// Writes the demangled form of a legacy Rust symbol name into `buf` and
+/// returns it, truncated to the buffer. Other names are copied as they
+/// are.
+pub fn demangle<'b>(name: &[u8], buf: &'b mut [u8]) -> &'b [u8] {
+    let mut out = Out { buf, len: 0 };
+    if !demangle_path(name, &mut out) {
+        out.len = 0;
+        out.push(name);
+    }
+    let len = out.len;
+    out.buf.get(..len).unwrap_or(&[])
+}
+
+/// `_ZN` then length-prefixed components then `E`. Returns `false` if
+/// `name` is not of that form.
+fn demangle_path(name: &[u8], out: &mut Out) -> bool {
+    let Some(mut rest) = name.strip_prefix(b"_ZN") else {
+        return false;
+    };
+    let mut first = true;
+    loop {
+        match rest.first() {
+            Some(b'E') => return true,
+            Some(c) if c.is_ascii_digit() => {}
+            _ => return false,
+        }
+        let digits = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+        let len = rest.iter().take(digits).fold(0usize, |len, &c| len.saturating_mul(10).saturating_add((c - b'0') as usize));
+        let Some(component) = rest.get(digits..digits.saturating_add(len)) else {
+            return false;
+        };
+        rest = rest.get(digits + len..).unwrap_or(&[]);
+        if is_hash(component) && rest.first() == Some(&b'E') {
+            continue;
+        }
+        if !first {
+            out.push(b"::");
+        }
+        first = false;
+        // A leading `_$` protects an escape at the start
+        let mut component = component.strip_prefix(b"_$").map_or(component, |_| component.get(1..).unwrap_or(&[]));
+        while let Some(&c) = component.first() {
+            match ESCAPES.iter().find(|(escape, _)| component.starts_with(escape)) {
+                Some((escape, text)) => {
+                    out.push(text);
+                    component = component.get(escape.len()..).unwrap_or(&[]);
+                }
+                None => {
+                    out.push(&[c]);
+                    component = component.get(1..).unwrap_or(&[]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: u32, value: u32, size: u32, kind: u8) -> [u8; SYMBOL_SIZE] {
+        let mut entry = [0u8; SYMBOL_SIZE];
+        entry[0..4].copy_from_slice(&name.to_le_bytes());
+        entry[4..8].copy_from_slice(&value.to_le_bytes());
+        entry[8..12].copy_from_slice(&size.to_le_bytes());
+        entry[12] = kind;
+        entry
+    }
+
+    fn header(kind: u32, addr: u32, size: u32, link: u32) -> [u8; SECTION_HEADER_SIZE] {
+        let mut header = [0u8; SECTION_HEADER_SIZE];
+        header[4..8].copy_from_slice(&kind.to_le_bytes());
+        header[12..16].copy_from_slice(&addr.to_le_bytes());
+        header[20..24].copy_from_slice(&size.to_le_bytes());
+        header[24..28].copy_from_slice(&link.to_le_bytes());
+        header
+    }
+
+    const STRTAB: &[u8] = b"\0alpha\0beta\0stub\0data\0";
+
+    /// Entries laid end to end.
+    fn bytes<const N: usize, const M: usize>(entries: &[[u8; N]; M]) -> &[u8] {
+        entries.as_flattened()
+    }
+
+    fn table() -> [[u8; SYMBOL_SIZE]; 5] {
+        [
+            symbol(0, 0, 0, 0),
+            symbol(1, 0x1000, 0x40, STT_FUNC),
+            symbol(7, 0x1040, 0x20, STT_FUNC | 0x10),
+            symbol(12, 0x2000, 0, STT_NOTYPE),
+            symbol(17, 0x1050, 0x100, 1),
+        ]
+    }
+
+    // ── sections ──
+
+    #[test]
+    fn finds_symbol_table() {
+        let headers = [header(0, 0, 0, 0), header(1, 0x100000, 0x2000, 0), header(3, 0x300000, 0x80, 0), header(2, 0x200000, 0x100, 2)];
+        let headers = bytes(&headers);
+        assert_eq!(sections(headers).count(), 4);
+        let (symtab, strtab) = symbol_table(headers).unwrap();
+        assert_eq!(symtab, Section { kind: 2, addr: 0x200000, size: 0x100, link: 2 });
+        assert_eq!(strtab.addr, 0x300000);
+    }
+
+    #[test]
+    fn no_symbol_table() {
+        let headers = [header(0, 0, 0, 0), header(1, 0x100000, 0x2000, 0)];
+        let headers = bytes(&headers);
+        assert_eq!(symbol_table(headers), None);
+        assert_eq!(symbol_table(&headers[..30]), None);
+    }
+
+    // ── lookup ──
+
+    #[test]
+    fn lookup_inside_function() {
+        let table = table();
+        let symbols = Symbols::new(bytes(&table), STRTAB);
+        assert_eq!(symbols.lookup(0x1000), Some((&b"alpha"[..], 0)));
+        assert_eq!(symbols.lookup(0x103F), Some((&b"alpha"[..], 0x3F)));
+        assert_eq!(symbols.lookup(0x1044), Some((&b"beta"[..], 4)));
+    }
+
+    #[test]
+    fn lookup_ignores_objects() {
+        let table = table();
+        let symbols = Symbols::new(bytes(&table), STRTAB);
+        // Past `beta`, inside the `data` object only
+        assert_eq!(symbols.lookup(0x1070), None);
+    }
+
+    #[test]
+    fn lookup_unsized_label() {
+        let table = table();
+        let symbols = Symbols::new(bytes(&table), STRTAB);
+        assert_eq!(symbols.lookup(0x2010), Some((&b"stub"[..], 0x10)));
+        assert_eq!(symbols.lookup(0x0FFF), None);
+    }
+
+    #[test]
+    fn lookup_bad_name_offset() {
+        let table = symbol(999, 0x1000, 0x10, STT_FUNC);
+        let symbols = Symbols::new(&table, STRTAB);
+        assert_eq!(symbols.lookup(0x1004), Some((&b""[..], 4)));
+    }
+
+    // ── demangle ──
+
+    fn check_demangle(name: &[u8], expected: &[u8]) {
+        let mut buf = [0u8; 128];
+        assert_eq!(demangle(name, &mut buf), expected);
+    }
+
+    #[test]
+    fn demangle_path() {
+        check_demangle(b"_ZN5tacos2mm4slab7kmalloc17h683e21c15bd43530E", b"tacos::mm::slab::kmalloc");
+    }
+
+    #[test]
+    fn demangle_escapes() {
+        check_demangle(
+            b"_ZN77_$LT$tacos..mm..heap..KernelAllocator$u20$as$u20$core..alloc..GlobalAlloc$GT$5alloc17h0123456789abcdefE",
+            b"<tacos::mm::heap::KernelAllocator as core::alloc::GlobalAlloc>::alloc",
+        );
+        check_demangle(b"_ZN4core3ptr27drop_in_place$LT$$RF$u8$GT$17h0123456789abcdefE", b"core::ptr::drop_in_place<&u8>");
+    }
+
+    #[test]
+    fn demangle_other_names() {
+        check_demangle(b"rust_main", b"rust_main");
+        check_demangle(b"_ZN5tacos", b"_ZN5tacos");
+        check_demangle(b"_ZN99tacosE", b"_ZN99tacosE");
+    }
+
+    #[test]
+    fn demangle_truncates() {
+        let mut buf = [0u8; 8];
+        assert_eq!(demangle(b"_ZN5tacos2mm4slab7kmalloc17h683e21c15bd43530E", &mut buf), b"tacos::m");
+    }
+}
@@ -1,27 +1,291 @@
-#[no_mangle]
-pub extern "C" fn memset(s: *mut u8, c: i32, n: usize) -> *mut u8 {
-    let mut i = 0;
-    unsafe {
-        while i < n {
-            *s.add(i) = c as u8;
-            i += 1;
+//! Memory primitives: the symbols the compiler emits calls to (`memset`,
+//! `memcpy`, `memmove`, `memcmp`, `bcmp`), and volatile variants for
+//! device memory.
+//!
+//! Short buffers go byte by byte. Longer ones are handled a 32-bit word at
+//! a time once the destination is aligned, provided the source shares its
+//! alignment: with `rep stosd` / `rep movsd` from `REP_MIN_WORDS` words,
+//! with a word loop below. Other copies use `rep movsb`. `memmove` copies
+//! backwards (with the direction flag set) when the destination overlaps
+//! the end of the source.
+//!
+//! The byte and word loops use volatile accesses so that the compiler
+//! cannot turn them back into calls to `memset` / `memcpy` — that is,
+//! into calls to themselves. The kernel exports the functions under their
+//! C names; host builds keep Rust names so the tests exercise this code
+//! without replacing the C library's.
+
+use core::arch::asm;
+use core::ptr::{read_volatile, write_volatile};
+
+/// Buffers shorter than this are handled byte by byte.
+const WORD_MIN_BYTES: usize = 8;
+/// Word runs from this long use the string instructions.
+const REP_MIN_WORDS: usize = 16;
+
+/// Bytes before `addr` reaches a 4-byte boundary.
+fn misalignment(addr: usize) -> usize {
+    addr.wrapping_neg() & 3
+}
+
+/// Runs string instructions with `ecx` = count, `esi` = source and
+/// `edi` = destination. On i686 LLVM keeps `esi` for itself, so the
+/// source goes through another register and `esi` is restored after.
+macro_rules! rep_string {
+    ($($insn:literal),+; count: $count:expr, src: $src:expr, dest: $dest:expr) => {
+        #[cfg(target_arch = "x86")]
+        asm!(
+            "xchg {src}, esi", $($insn,)+ "mov esi, {src}",
+            src = inout(reg) $src => _, inout("ecx") $count => _, inout("edi") $dest => _,
+            options(nostack),
+        );
+        #[cfg(not(target_arch = "x86"))]
+        asm!($($insn),+, inout("ecx") $count => _, inout("esi") $src => _, inout("edi") $dest => _, options(nostack));
+    };
+}
+
+// ──────────────────────────────────────────────
+//  Building blocks
+// ──────────────────────────────────────────────
+
+unsafe fn set_bytes(s: *mut u8, byte: u8, n: usize) {
+    for i in 0..n {
+        write_volatile(s.add(i), byte);
+    }
+}
+
+unsafe fn set_words(s: *mut u32, word: u32, words: usize) {
+    if words >= REP_MIN_WORDS {
+        asm!("rep stosd", inout("ecx") words => _, inout("edi") s => _, in("eax") word, options(nostack, preserves_flags));
+    } else {
+        for i in 0..words {
+            write_volatile(s.add(i), word);
         }
     }
-    s
 }
 
-#[no_mangle]
-pub extern "C" fn memcpy(dest: *mut u8, src: *const u8, n: usize) -> *mut u8 {
-    let mut i = 0;
-    unsafe {
-        while i < n {
-            *dest.add(i) = *src.add(i);
-            i += 1;
+unsafe fn copy_bytes_forward(dest: *mut u8, src: *const u8, n: usize) {
+    for i in 0..n {
+        write_volatile(dest.add(i), read_volatile(src.add(i)));
+    }
+}
+
+unsafe fn copy_bytes_backward(dest: *mut u8, src: *const u8, n: usize) {
+    for i in (0..n).rev() {
+        write_volatile(dest.add(i), read_volatile(src.add(i)));
+    }
+}
+
+/// Copies `n` bytes upwards: correct unless `dest` overlaps the end of `src`.
+unsafe fn copy_forward(dest: *mut u8, src: *const u8, n: usize) {
+    let head = misalignment(dest as usize);
+    if n < WORD_MIN_BYTES || head != misalignment(src as usize) {
+        if n < WORD_MIN_BYTES {
+            copy_bytes_forward(dest, src, n);
+        } else {
+            rep_string!("rep movsb"; count: n, src: src, dest: dest);
+        }
+        return;
+    }
+    copy_bytes_forward(dest, src, head);
+    let words = (n - head) / 4;
+    let (dest_words, src_words) = (dest.add(head) as *mut u32, src.add(head) as *const u32);
+    if words >= REP_MIN_WORDS {
+        rep_string!("rep movsd"; count: words, src: src_words, dest: dest_words);
+    } else {
+        for i in 0..words {
+            write_volatile(dest_words.add(i), read_volatile(src_words.add(i)));
+        }
+    }
+    let done = head + words * 4;
+    copy_bytes_forward(dest.add(done), src.add(done), n - done);
+}
+
+/// Copies `n` bytes downwards, from the last one: correct unless `src`
+/// overlaps the end of `dest`.
+unsafe fn copy_backward(dest: *mut u8, src: *const u8, n: usize) {
+    let tail = (dest as usize + n) & 3;
+    if n < WORD_MIN_BYTES || tail != (src as usize + n) & 3 {
+        if n < WORD_MIN_BYTES {
+            copy_bytes_backward(dest, src, n);
+        } else {
+            // The direction flag must be clear again on return
+            rep_string!("std", "rep movsb", "cld"; count: n, src: src.add(n - 1), dest: dest.add(n - 1));
+        }
+        return;
+    }
+    let body = n - tail;
+    copy_bytes_backward(dest.add(body), src.add(body), tail);
+    let words = body / 4;
+    let head = body - words * 4;
+    let (dest_words, src_words) = (dest.add(head) as *mut u32, src.add(head) as *const u32);
+    if words >= REP_MIN_WORDS {
+        rep_string!("std", "rep movsd", "cld"; count: words, src: src_words.add(words - 1), dest: dest_words.add(words - 1));
+    } else {
+        for i in (0..words).rev() {
+            write_volatile(dest_words.add(i), read_volatile(src_words.add(i)));
+        }
+    }
+    copy_bytes_backward(dest, src, head);
+}
+
+/// Index of the first differing word of two equally aligned buffers, or
+/// `words`.
+unsafe fn first_different_word(a: *const u32, b: *const u32, words: usize) -> usize {
+    (0..words).find(|&i| read_volatile(a.add(i)) != read_volatile(b.add(i))).unwrap_or(words)
+}
+
+/// `memcmp` result of the first differing byte in `n` bytes, or 0.
+unsafe fn compare_bytes(a: *const u8, b: *const u8, n: usize) -> i32 {
+    for i in 0..n {
+        let (x, y) = (read_volatile(a.add(i)), read_volatile(b.add(i)));
+        if x != y {
+            return x as i32 - y as i32;
         }
     }
+    0
+}
+
+// ──────────────────────────────────────────────
+//  C symbols
+// ──────────────────────────────────────────────
+
+/// Fills `n` bytes at `s` with the low byte of `c`; returns `s`.
+///
+/// # Safety
+///
+/// `s..s + n` must be valid for writes.
+#[cfg_attr(target_os = "none", no_mangle)]
+pub unsafe extern "C" fn memset(s: *mut u8, c: i32, n: usize) -> *mut u8 {
+    let byte = c as u8;
+    if n < WORD_MIN_BYTES {
+        set_bytes(s, byte, n);
+        return s;
+    }
+    let head = misalignment(s as usize);
+    set_bytes(s, byte, head);
+    let words = (n - head) / 4;
+    set_words(s.add(head) as *mut u32, u32::from_ne_bytes([byte; 4]), words);
+    let done = head + words * 4;
+    set_bytes(s.add(done), byte, n - done);
+    s
+}
+
+/// Copies `n` bytes from `src` to `dest`, which must not overlap;
+/// returns `dest`.
+///
+/// # Safety
+///
+/// `src..src + n` must be valid for reads, `dest..dest + n` for writes.
+#[cfg_attr(target_os = "none", no_mangle)]
+pub unsafe extern "C" fn memcpy(dest: *mut u8, src: *const u8, n: usize) -> *mut u8 {
+    copy_forward(dest, src, n);
     dest
 }
 
+/// Copies `n` bytes from `src` to `dest`, which may overlap; returns
+/// `dest`.
+///
+/// # Safety
+///
+/// `src..src + n` must be valid for reads, `dest..dest + n` for writes.
+#[cfg_attr(target_os = "none", no_mangle)]
+pub unsafe extern "C" fn memmove(dest: *mut u8, src: *const u8, n: usize) -> *mut u8 {
+    if (dest as usize).wrapping_sub(src as usize) >= n {
+        // `dest` below `src`, or past its end
+        copy_forward(dest, src, n);
+    } else {
+        copy_backward(dest, src, n);
+    }
+    dest
+}
+
+/// Compares `n` bytes: negative, zero or positive as the first differing
+/// byte of `a` is below, equal to or above that of `b`, as unsigned bytes.
+///
+/// # Safety
+///
+/// Both ranges must be valid for reads.
+#[cfg_attr(target_os = "none", no_mangle)]
+pub unsafe extern "C" fn memcmp(a: *const u8, b: *const u8, n: usize) -> i32 {
+    let head = misalignment(a as usize);
+    if n < WORD_MIN_BYTES || head != misalignment(b as usize) {
+        return compare_bytes(a, b, n);
+    }
+    let result = compare_bytes(a, b, head);
+    if result != 0 {
+        return result;
+    }
+    let words = (n - head) / 4;
+    let same = first_different_word(a.add(head) as *const u32, b.add(head) as *const u32, words);
+    // The differing word, or the tail
+    let done = head + same * 4;
+    compare_bytes(a.add(done), b.add(done), n - done)
+}
+
+/// Returns 0 if the `n` bytes are equal, non-zero otherwise. Cheaper than
+/// `memcmp` when only equality matters.
+///
+/// # Safety
+///
+/// Both ranges must be valid for reads.
+#[cfg_attr(target_os = "none", no_mangle)]
+pub unsafe extern "C" fn bcmp(a: *const u8, b: *const u8, n: usize) -> i32 {
+    let head = misalignment(a as usize);
+    if n < WORD_MIN_BYTES || head != misalignment(b as usize) {
+        return compare_bytes(a, b, n);
+    }
+    let words = (n - head) / 4;
+    let done = head + words * 4;
+    let same_words = first_different_word(a.add(head) as *const u32, b.add(head) as *const u32, words) == words;
+    (compare_bytes(a, b, head) != 0 || !same_words || compare_bytes(a.add(done), b.add(done), n - done) != 0) as i32
+}
+
+// ──────────────────────────────────────────────
+//  Volatile variants (device memory)
+// ──────────────────────────────────────────────
+
+/// `memset` with volatile accesses only, in ascending order: 32-bit ones
+/// where aligned, byte ones around them.
+///
+/// # Safety
+///
+/// `s..s + n` must be valid for writes.
+pub unsafe fn memset_volatile(s: *mut u8, c: u8, n: usize) {
+    let head = misalignment(s as usize).min(n);
+    set_bytes(s, c, head);
+    let words = (n - head) / 4;
+    let word_ptr = s.add(head) as *mut u32;
+    for i in 0..words {
+        write_volatile(word_ptr.add(i), u32::from_ne_bytes([c; 4]));
+    }
+    let done = head + words * 4;
+    set_bytes(s.add(done), c, n - done);
+}
+
+/// Copies with volatile accesses only, in ascending order: 32-bit ones
+/// when both sides are aligned alike, byte ones otherwise. Overlap is
+/// fine when `dest` is below `src`, as when scrolling a frame buffer.
+///
+/// # Safety
+///
+/// `src..src + n` must be valid for reads, `dest..dest + n` for writes.
+pub unsafe fn memcpy_volatile(dest: *mut u8, src: *const u8, n: usize) {
+    let head = misalignment(dest as usize).min(n);
+    if head != misalignment(src as usize).min(n) {
+        copy_bytes_forward(dest, src, n);
+        return;
+    }
+    copy_bytes_forward(dest, src, head);
+    let words = (n - head) / 4;
+    let (dest_words, src_words) = (dest.add(head) as *mut u32, src.add(head) as *const u32);
+    for i in 0..words {
+        write_volatile(dest_words.add(i), read_volatile(src_words.add(i)));
+    }
+    let done = head + words * 4;
+    copy_bytes_forward(dest.add(done), src.add(done), n - done);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -31,21 +295,21 @@ mod tests {
     #[test]
     fn memset_fill_zero() {
         let mut buf = [0xFFu8; 16];
-        memset(buf.as_mut_ptr(), 0, buf.len());
+        unsafe { memset(buf.as_mut_ptr(), 0, buf.len()) };
         assert!(buf.iter().all(|&b| b == 0));
     }
 
     #[test]
     fn memset_fill_value() {
         let mut buf = [0u8; 16];
-        memset(buf.as_mut_ptr(), 0xAB, buf.len());
+        unsafe { memset(buf.as_mut_ptr(), 0xAB, buf.len()) };
         assert!(buf.iter().all(|&b| b == 0xAB));
     }
 
     #[test]
     fn memset_partial() {
         let mut buf = [0u8; 16];
-        memset(buf.as_mut_ptr(), 0xFF, 4);
+        unsafe { memset(buf.as_mut_ptr(), 0xFF, 4) };
         assert_eq!(&buf[..4], &[0xFF; 4]);
         assert_eq!(&buf[4..], &[0u8; 12]);
     }
@@ -53,7 +317,7 @@ mod tests {
     #[test]
     fn memset_zero_len() {
         let mut buf = [0xAA; 8];
-        memset(buf.as_mut_ptr(), 0, 0);
+        unsafe { memset(buf.as_mut_ptr(), 0, 0) };
         assert!(buf.iter().all(|&b| b == 0xAA));
     }
 
@@ -61,7 +325,7 @@ mod tests {
     fn memset_truncates_c_to_u8() {
         let mut buf = [0u8; 4];
         // 0x1FF should be truncated to 0xFF
-        memset(buf.as_mut_ptr(), 0x1FF, buf.len());
+        unsafe { memset(buf.as_mut_ptr(), 0x1FF, buf.len()) };
         assert!(buf.iter().all(|&b| b == 0xFF));
     }
 
@@ -71,7 +335,7 @@ mod tests {
     fn memcpy_basic() {
         let src = [1u8, 2, 3, 4, 5];
         let mut dst = [0u8; 5];
-        memcpy(dst.as_mut_ptr(), src.as_ptr(), 5);
+        unsafe { memcpy(dst.as_mut_ptr(), src.as_ptr(), 5) };
         assert_eq!(dst, src);
     }
 
@@ -79,7 +343,7 @@ mod tests {
     fn memcpy_partial() {
         let src = [10u8, 20, 30, 40, 50];
         let mut dst = [0u8; 5];
-        memcpy(dst.as_mut_ptr(), src.as_ptr(), 3);
+        unsafe { memcpy(dst.as_mut_ptr(), src.as_ptr(), 3) };
         assert_eq!(&dst[..3], &[10, 20, 30]);
         assert_eq!(&dst[3..], &[0, 0]);
     }
@@ -88,7 +352,7 @@ mod tests {
     fn memcpy_zero_len() {
         let src = [1u8, 2, 3];
         let mut dst = [0xFFu8; 3];
-        memcpy(dst.as_mut_ptr(), src.as_ptr(), 0);
+        unsafe { memcpy(dst.as_mut_ptr(), src.as_ptr(), 0) };
         assert!(dst.iter().all(|&b| b == 0xFF));
     }
 
@@ -96,7 +360,7 @@ mod tests {
     fn memcpy_large() {
         let src: [u8; 256] = core::array::from_fn(|i| i as u8);
         let mut dst = [0u8; 256];
-        memcpy(dst.as_mut_ptr(), src.as_ptr(), 256);
+        unsafe { memcpy(dst.as_mut_ptr(), src.as_ptr(), 256) };
         assert_eq!(dst, src);
     }
 
@@ -104,14 +368,159 @@ mod tests {
     fn memcpy_returns_dest() {
         let src = [1u8, 2];
         let mut dst = [0u8; 2];
-        let ret = memcpy(dst.as_mut_ptr(), src.as_ptr(), 2);
+        let ret = unsafe { memcpy(dst.as_mut_ptr(), src.as_ptr(), 2) };
         assert_eq!(ret, dst.as_mut_ptr());
     }
 
     #[test]
     fn memset_returns_s() {
         let mut buf = [0u8; 4];
-        let ret = memset(buf.as_mut_ptr(), 0, 4);
+        let ret = unsafe { memset(buf.as_mut_ptr(), 0, 4) };
         assert_eq!(ret, buf.as_mut_ptr());
     }
-}
\ No newline at end of file
+
+    // ── memmove ──────────────────────────────────────────
+
+    /// `memmove` within a 200-byte pattern, checked against `copy_within`.
+    fn check_memmove(src: usize, dest: usize, n: usize) {
+        let mut buf: [u8; 200] = core::array::from_fn(|i| i as u8);
+        let mut expected = buf;
+        expected.copy_within(src..src + n, dest);
+        let base = buf.as_mut_ptr();
+        let ret = unsafe { memmove(base.add(dest), base.add(src), n) };
+        assert_eq!(ret, unsafe { base.add(dest) });
+        assert_eq!(buf, expected, "src {src} dest {dest} n {n}");
+    }
+
+    #[test]
+    fn memmove_overlap_forward() {
+        for n in [0, 1, 7, 8, 13, 64, 150] {
+            for (src, dest) in [(0, 1), (0, 4), (3, 5), (2, 9), (1, 33)] {
+                check_memmove(src, dest, n);
+            }
+        }
+    }
+
+    #[test]
+    fn memmove_overlap_backward() {
+        for n in [0, 1, 7, 8, 13, 64, 150] {
+            for (src, dest) in [(1, 0), (4, 0), (5, 3), (9, 2), (33, 1)] {
+                check_memmove(src, dest, n);
+            }
+        }
+    }
+
+    #[test]
+    fn memmove_disjoint() {
+        check_memmove(0, 100, 100);
+        check_memmove(100, 0, 100);
+        check_memmove(3, 120, 77);
+    }
+
+    // ── alignments ───────────────────────────────────────
+
+    #[test]
+    fn memcpy_all_alignments() {
+        let src: [u8; 160] = core::array::from_fn(|i| (i * 7) as u8);
+        for src_off in 0..4 {
+            for dest_off in 0..4 {
+                for n in [5, 8, 9, 31, 64, 65, 150] {
+                    let mut dst = [0u8; 160];
+                    unsafe { memcpy(dst.as_mut_ptr().add(dest_off), src.as_ptr().add(src_off), n) };
+                    assert_eq!(&dst[dest_off..dest_off + n], &src[src_off..src_off + n]);
+                    assert!(dst[..dest_off].iter().chain(&dst[dest_off + n..]).all(|&b| b == 0));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn memset_all_alignments() {
+        for off in 0..4 {
+            for n in [5, 8, 9, 63, 64, 100] {
+                let mut buf = [0u8; 112];
+                unsafe { memset(buf.as_mut_ptr().add(off), 0x5A, n) };
+                assert!(buf[off..off + n].iter().all(|&b| b == 0x5A));
+                assert!(buf[..off].iter().chain(&buf[off + n..]).all(|&b| b == 0));
+            }
+        }
+    }
+
+    // ── memcmp / bcmp ────────────────────────────────────
+
+    #[test]
+    fn memcmp_equal() {
+        let a: [u8; 100] = core::array::from_fn(|i| i as u8);
+        let b = a;
+        assert_eq!(unsafe { memcmp(a.as_ptr(), b.as_ptr(), 100) }, 0);
+        assert_eq!(unsafe { memcmp(a.as_ptr(), b.as_ptr(), 0) }, 0);
+    }
+
+    #[test]
+    fn memcmp_sign() {
+        for at in [0, 3, 4, 17, 98] {
+            let a = [0x10u8; 100];
+            let mut b = a;
+            b[at] = 0x90;
+            assert!(unsafe { memcmp(a.as_ptr(), b.as_ptr(), 100) } < 0);
+            assert!(unsafe { memcmp(b.as_ptr(), a.as_ptr(), 100) } > 0);
+            // Only the first difference counts
+            b[99] = 0;
+            if at < 99 {
+                assert!(unsafe { memcmp(a.as_ptr(), b.as_ptr(), 100) } < 0);
+            }
+        }
+    }
+
+    #[test]
+    fn memcmp_is_unsigned() {
+        let (a, b) = ([0x80u8; 12], [0x7Fu8; 12]);
+        assert!(unsafe { memcmp(a.as_ptr(), b.as_ptr(), 12) } > 0);
+    }
+
+    #[test]
+    fn memcmp_misaligned() {
+        let a: [u8; 64] = core::array::from_fn(|i| i as u8);
+        let mut b = [0u8; 65];
+        b[1..].copy_from_slice(&a);
+        assert_eq!(unsafe { memcmp(a.as_ptr(), b.as_ptr().add(1), 64) }, 0);
+        b[40] = 0xFF;
+        assert!(unsafe { memcmp(a.as_ptr(), b.as_ptr().add(1), 64) } < 0);
+    }
+
+    #[test]
+    fn bcmp_equality() {
+        let a: [u8; 50] = core::array::from_fn(|i| i as u8);
+        let mut b = a;
+        assert_eq!(unsafe { bcmp(a.as_ptr(), b.as_ptr(), 50) }, 0);
+        for at in [0, 2, 25, 49] {
+            b[at] ^= 1;
+            assert_ne!(unsafe { bcmp(a.as_ptr(), b.as_ptr(), 50) }, 0);
+            b[at] ^= 1;
+        }
+    }
+
+    // ── volatile ─────────────────────────────────────────
+
+    #[test]
+    fn volatile_variants() {
+        let src: [u8; 40] = core::array::from_fn(|i| i as u8 + 1);
+        for off in 0..4 {
+            let mut dst = [0u8; 44];
+            unsafe { memcpy_volatile(dst.as_mut_ptr().add(off), src.as_ptr(), 40) };
+            assert_eq!(&dst[off..off + 40], &src);
+            unsafe { memset_volatile(dst.as_mut_ptr().add(off), 0xEE, 3) };
+            assert_eq!(&dst[off..off + 3], &[0xEE; 3]);
+            assert_eq!(dst[off + 3], src[3]);
+        }
+    }
+
+    #[test]
+    fn memcpy_volatile_scrolls_down() {
+        // Overlapping with dest below src, as in a frame buffer scroll
+        let mut buf: [u8; 40] = core::array::from_fn(|i| i as u8);
+        unsafe { memcpy_volatile(buf.as_mut_ptr(), buf.as_ptr().add(8), 32) };
+        let expected: [u8; 32] = core::array::from_fn(|i| i as u8 + 8);
+        assert_eq!(&buf[..32], &expected);
+    }
+}
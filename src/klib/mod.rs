@@ -1,5 +1,7 @@
-#[cfg(target_os = "none")]
+pub mod elf;
 pub mod memory;
 #[cfg(target_os = "none")]
 pub mod stack;
+pub mod number;
 pub mod string;
+pub mod time;
@@ -0,0 +1,176 @@
+/// Parses an unsigned number written in decimal, or in hexadecimal, binary
+/// or octal with a `0x`, `0b` or `0o` prefix (either case). Returns `None`
+/// for an empty, malformed or overflowing number.
+pub fn parse_u64(s: &[u8]) -> Option<u64> {
+    let (radix, digits) = match s {
+        [b'0', b'x' | b'X', rest @ ..] => (16, rest),
+        [b'0', b'b' | b'B', rest @ ..] => (2, rest),
+        [b'0', b'o' | b'O', rest @ ..] => (8, rest),
+        _ => (10, s),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    let mut value: u64 = 0;
+    for &c in digits {
+        let digit = match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'f' => c - b'a' + 10,
+            b'A'..=b'F' => c - b'A' + 10,
+            _ => return None,
+        } as u64;
+        if digit >= radix {
+            return None;
+        }
+        value = value.checked_mul(radix)?.checked_add(digit)?;
+    }
+    Some(value)
+}
+
+/// Same as `parse_u64`, for numbers that must fit in 32 bits.
+pub fn parse_u32(s: &[u8]) -> Option<u32> {
+    let value = parse_u64(s)?;
+    if value > u32::MAX as u64 { None } else { Some(value as u32) }
+}
+
+/// Same as `parse_u64`, with an optional leading `-` or `+`.
+pub fn parse_i64(s: &[u8]) -> Option<i64> {
+    match s {
+        [b'-', rest @ ..] => {
+            let value = parse_u64(rest)?;
+            if value > i64::MAX as u64 + 1 { None } else { Some((value as i64).wrapping_neg()) }
+        }
+        [b'+', rest @ ..] => i64::try_from(parse_u64(rest)?).ok(),
+        _ => i64::try_from(parse_u64(s)?).ok(),
+    }
+}
+
+/// Divides a 64-bit number by a 32-bit one, returning quotient and
+/// remainder.
+///
+/// `/` and `%` on `u64` call `__udivdi3` / `__umoddi3`, which the kernel
+/// does not link. A divisor below 2^16 (a number base, say) takes a long
+/// division by 16-bit digits, which only divides 32-bit values; others a
+/// shift-and-subtract loop, which only needs 64-bit shifts and compares.
+/// A zero divisor is treated as 1.
+pub fn div_rem_u64(n: u64, d: u32) -> (u64, u32) {
+    let d = d.max(1);
+    if n >> 32 == 0 {
+        return ((n as u32 / d) as u64, n as u32 % d);
+    }
+    if d <= 0xFFFF {
+        let mut quotient: u64 = 0;
+        let mut rem: u32 = 0;
+        for shift in [48, 32, 16, 0] {
+            // rem < d < 2^16, so this fits in 32 bits
+            let part = (rem << 16) | ((n >> shift) as u32 & 0xFFFF);
+            quotient |= ((part / d) as u64) << shift;
+            rem = part % d;
+        }
+        return (quotient, rem);
+    }
+    let mut quotient: u64 = 0;
+    let mut rem: u64 = 0;
+    for bit in (0..64).rev() {
+        rem = (rem << 1) | ((n >> bit) & 1);
+        if rem >= d as u64 {
+            rem -= d as u64;
+            quotient |= 1 << bit;
+        }
+    }
+    (quotient, rem as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ── parse_u64 ──
+
+    #[test]
+    fn parse_decimal() {
+        assert_eq!(parse_u64(b"0"), Some(0));
+        assert_eq!(parse_u64(b"2048"), Some(2048));
+    }
+
+    #[test]
+    fn parse_hex() {
+        assert_eq!(parse_u64(b"0x800"), Some(0x800));
+        assert_eq!(parse_u64(b"0XB8000"), Some(0xB8000));
+        assert_eq!(parse_u64(b"0xdeadBEEF"), Some(0xDEAD_BEEF));
+    }
+
+    #[test]
+    fn parse_binary() {
+        assert_eq!(parse_u64(b"0b1010"), Some(10));
+        assert_eq!(parse_u64(b"0b2"), None);
+    }
+
+    #[test]
+    fn parse_octal() {
+        assert_eq!(parse_u64(b"0o755"), Some(0o755));
+        assert_eq!(parse_u64(b"0o8"), None);
+    }
+
+    #[test]
+    fn parse_leading_zero_is_decimal() {
+        assert_eq!(parse_u64(b"0755"), Some(755));
+    }
+
+    #[test]
+    fn parse_rejects_garbage() {
+        assert_eq!(parse_u64(b""), None);
+        assert_eq!(parse_u64(b"0x"), None);
+        assert_eq!(parse_u64(b"12a"), None);
+        assert_eq!(parse_u64(b" 1"), None);
+    }
+
+    #[test]
+    fn parse_overflow() {
+        assert_eq!(parse_u64(b"18446744073709551615"), Some(u64::MAX));
+        assert_eq!(parse_u64(b"18446744073709551616"), None);
+    }
+
+    // ── parse_u32 ──
+
+    #[test]
+    fn parse_u32_range() {
+        assert_eq!(parse_u32(b"0xFFFFFFFF"), Some(u32::MAX));
+        assert_eq!(parse_u32(b"0x100000000"), None);
+    }
+
+    // ── parse_i64 ──
+
+    #[test]
+    fn parse_i64_signs() {
+        assert_eq!(parse_i64(b"-42"), Some(-42));
+        assert_eq!(parse_i64(b"+0x10"), Some(16));
+        assert_eq!(parse_i64(b"-9223372036854775808"), Some(i64::MIN));
+        assert_eq!(parse_i64(b"9223372036854775808"), None);
+        assert_eq!(parse_i64(b"-"), None);
+    }
+
+    // ── div_rem_u64 ──
+
+    #[test]
+    fn div_rem_small() {
+        assert_eq!(div_rem_u64(1_234_567, 1_000_000), (1, 234_567));
+        assert_eq!(div_rem_u64(5, 0), (5, 0));
+    }
+
+    #[test]
+    fn div_rem_large() {
+        assert_eq!(div_rem_u64(u64::MAX, 1_000_000), (u64::MAX / 1_000_000, (u64::MAX % 1_000_000) as u32));
+        assert_eq!(div_rem_u64(10_000 << 32, 2_000_000_007), ((10_000 << 32) / 2_000_000_007, ((10_000_u64 << 32) % 2_000_000_007) as u32));
+        assert_eq!(div_rem_u64(1 << 40, u32::MAX), ((1 << 40) / u32::MAX as u64, ((1_u64 << 40) % u32::MAX as u64) as u32));
+    }
+
+    #[test]
+    fn div_rem_by_small_divisors() {
+        for d in [2, 10, 16, 0xFFFF] {
+            for n in [u64::MAX, 1 << 32, 0x1234_5678_9ABC_DEF0] {
+                assert_eq!(div_rem_u64(n, d), (n / d as u64, (n % d as u64) as u32));
+            }
+        }
+    }
+}
@@ -11,10 +11,14 @@
 /// can be mapped to function names if symbols are available.
 
 use core::arch::asm;
+use crate::klib::elf;
+use crate::multiboot;
 use crate::println;
 
 /// Maximum number of frames to walk (prevents infinite loops).
 const MAX_FRAMES: usize = 20;
+/// Longest demangled function name `symbolize` returns.
+pub const SYMBOL_MAX: usize = 96;
 
 /// Reads the current EBP register value.
 #[inline(always)]
@@ -28,7 +32,7 @@ pub fn get_ebp() -> u32 {
 
 /// Reads the current ESP register value.
 #[inline(always)]
-fn get_esp() -> u32 {
+pub fn get_esp() -> u32 {
     let esp: u32;
     unsafe {
         asm!("mov {}, esp", out(reg) esp);
@@ -46,9 +50,9 @@ pub fn print_stack() {
     let ebp = get_ebp();
 
     println!("=== Kernel Stack Trace ===");
-    println!("  ESP: {:#x}\n  EBP: {:#x}\n", esp, ebp);
-    println!("  Frame  EBP         Return Addr");
-    println!("  -----  ----------  -----------");
+    println!("  ESP: {:#010x}\n  EBP: {:#010x}\n", esp, ebp);
+    println!("  Frame  EBP         Return Addr  Function");
+    println!("  -----  ----------  -----------  --------");
 
     let mut current_ebp = ebp;
     let mut frame: usize = 0;
@@ -58,12 +62,23 @@ pub fn print_stack() {
         let saved_ebp = unsafe { *(current_ebp as *const u32) };
         let return_addr = unsafe { *((current_ebp + 4) as *const u32) };
 
-        println!(
-            "  [{}]    {:#x}    {:#x}",
-            frame as u32,
-            current_ebp,
-            return_addr
-        );
+        let mut name = [0; SYMBOL_MAX];
+        match symbolize(return_addr, &mut name) {
+            Some((function, offset)) => println!(
+                "  {:<5}  {:#010x}  {:#010x}   {}+{:#x}",
+                frame as u32,
+                current_ebp,
+                return_addr,
+                function,
+                offset
+            ),
+            None => println!(
+                "  {:<5}  {:#010x}  {:#010x}",
+                frame as u32,
+                current_ebp,
+                return_addr
+            ),
+        }
 
         // Sanity check: EBP should increase as we walk up the stack
         // (stack grows downward, so older frames have higher addresses)
@@ -82,3 +97,38 @@ pub fn print_stack() {
 
     println!("=== End Stack Trace ({} frames) ===", frame as u32);
 }
+
+/// Fills `frames` with the return addresses of the calling functions,
+/// innermost first, skipping the `skip` innermost ones. Returns the
+/// number of addresses stored.
+///
+/// Walks the same EBP chain as `print_stack`, with the same sanity check,
+/// so it stops at a null or non-increasing frame pointer.
+#[inline(never)]
+pub fn backtrace(frames: &mut [u32], skip: usize) -> usize {
+    let mut current_ebp = get_ebp();
+    let mut skip = skip;
+    let mut count = 0;
+    while current_ebp != 0 && current_ebp % 4 == 0 && count < frames.len() {
+        let saved_ebp = unsafe { *(current_ebp as *const u32) };
+        let return_addr = unsafe { *((current_ebp + 4) as *const u32) };
+        if skip > 0 {
+            skip -= 1;
+        } else {
+            unsafe { *frames.get_unchecked_mut(count) = return_addr; }
+            count += 1;
+        }
+        if saved_ebp <= current_ebp {
+            break;
+        }
+        current_ebp = saved_ebp;
+    }
+    count
+}
+
+/// Demangled name of the function containing `addr`, written to `buf`,
+/// and the offset of `addr` in it. `None` without a symbol table.
+pub fn symbolize(addr: u32, buf: &mut [u8]) -> Option<(&[u8], u32)> {
+    let (name, offset) = multiboot::kernel_symbols()?.lookup(addr)?;
+    Some((elf::demangle(name, buf), offset))
+}
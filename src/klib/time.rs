@@ -0,0 +1,130 @@
+//! Calendar dates and Unix time.
+//!
+//! Times are seconds since 1970-01-01 00:00:00 UTC held in a `u32`,
+//! which lasts until 2106 and keeps the arithmetic 32-bit (the kernel
+//! does not link 64-bit division). The date conversions are Howard
+//! Hinnant's `days_from_civil` / `civil_from_days`, restricted to 1970
+//! and later.
+
+use crate::io::format::{Formatter, KDisplay};
+use crate::kwrite;
+
+const SECS_PER_DAY: u32 = 86_400;
+/// Days from 0000-03-01 to 1970-01-01 in the proleptic Gregorian calendar.
+const EPOCH_SHIFT: u32 = 719_468;
+/// Days in a 400-year cycle.
+const DAYS_PER_ERA: u32 = 146_097;
+
+/// A date and time of day, as read from the RTC.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct DateTime {
+    pub year: u32,
+    /// 1-12
+    pub month: u8,
+    /// 1-31
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+/// Days since 1970-01-01.
+fn days_from_civil(year: u32, month: u32, day: u32) -> u32 {
+    // Years start in March so the leap day ends the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let month_from_march = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * DAYS_PER_ERA + day_of_era - EPOCH_SHIFT
+}
+
+/// (year, month, day) of a day counted from 1970-01-01.
+fn civil_from_days(days: u32) -> (u32, u32, u32) {
+    let days = days + EPOCH_SHIFT;
+    let era = days / DAYS_PER_ERA;
+    let day_of_era = days - era * DAYS_PER_ERA;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as u32;
+    (year, month, day)
+}
+
+impl DateTime {
+    /// Seconds since the Unix epoch. The date must be 1970 or later.
+    pub fn to_unix(&self) -> u32 {
+        let days = days_from_civil(self.year, self.month as u32, self.day as u32);
+        days * SECS_PER_DAY + self.hour as u32 * 3600 + self.minute as u32 * 60 + self.second as u32
+    }
+
+    pub fn from_unix(secs: u32) -> Self {
+        let (year, month, day) = civil_from_days(secs / SECS_PER_DAY);
+        let rem = secs % SECS_PER_DAY;
+        DateTime {
+            year,
+            month: month as u8,
+            day: day as u8,
+            hour: (rem / 3600) as u8,
+            minute: (rem / 60 % 60) as u8,
+            second: (rem % 60) as u8,
+        }
+    }
+}
+
+/// `2026-10-18 12:34:56`
+impl KDisplay for DateTime {
+    fn fmt(&self, f: &mut Formatter) {
+        kwrite!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: u32, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> DateTime {
+        DateTime { year, month, day, hour, minute, second }
+    }
+
+    // ── to_unix ──
+
+    #[test]
+    fn to_unix_epoch() {
+        assert_eq!(date(1970, 1, 1, 0, 0, 0).to_unix(), 0);
+    }
+
+    #[test]
+    fn to_unix_dates() {
+        assert_eq!(date(2026, 10, 18, 12, 34, 56).to_unix(), 1_792_326_896);
+        assert_eq!(date(2024, 2, 29, 23, 59, 59).to_unix(), 1_709_251_199);
+        assert_eq!(date(2106, 2, 7, 6, 28, 15).to_unix(), u32::MAX);
+    }
+
+    // ── from_unix ──
+
+    #[test]
+    fn from_unix_dates() {
+        assert_eq!(DateTime::from_unix(0), date(1970, 1, 1, 0, 0, 0));
+        assert_eq!(DateTime::from_unix(1_709_251_199), date(2024, 2, 29, 23, 59, 59));
+        assert_eq!(DateTime::from_unix(1_709_251_200), date(2024, 3, 1, 0, 0, 0));
+        assert_eq!(DateTime::from_unix(u32::MAX), date(2106, 2, 7, 6, 28, 15));
+    }
+
+    #[test]
+    fn round_trip_every_month_end() {
+        for year in [1970, 1999, 2000, 2023, 2024, 2100] {
+            for month in 1..=12 {
+                let t = date(year, month, 28, 23, 59, 59);
+                assert_eq!(DateTime::from_unix(t.to_unix()), t);
+            }
+        }
+    }
+}
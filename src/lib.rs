@@ -1,15 +1,21 @@
 #![no_std]
 #![allow(dead_code)]  // temporary solution to avoid warnings for unused functions
 
-// Hardware-dependent modules — only compiled for the bare-metal target (os = "none")
+extern crate alloc;
+
+// Hardware-dependent modules — only compiled for the bare-metal target (os = "none").
+// `mm` and `shell` gate their submodules one by one, keeping the pure ones on the host.
+#[cfg(target_os = "none")]
+pub mod cpu;
 #[cfg(target_os = "none")]
 pub mod drivers;
 #[cfg(target_os = "none")]
 pub mod gdt;
+pub mod mm;
 #[cfg(target_os = "none")]
-pub mod io;
-#[cfg(target_os = "none")]
+pub mod multiboot;
 pub mod shell;
 
 // Pure-logic modules (always compiled, testable on host)
+pub mod io;
 pub mod klib;
@@ -1,17 +1,57 @@
 #![no_std]
 #![no_main]
 
+use core::arch::asm;
 use core::panic::PanicInfo;
-use tacos::printkln;
+use tacos::cpu::irq;
+use tacos::io::klog;
+use tacos::mm::kstack;
+use tacos::{pr_emerg, pr_warn, printkln};
 
 #[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
-    loop {}
+fn panic(info: &PanicInfo) -> ! {
+    irq::disable();
+    match info.location() {
+        Some(location) => pr_emerg!("Kernel panic at {}:{}", location.file(), location.line()),
+        None => pr_emerg!("Kernel panic"),
+    }
+    #[cfg(feature = "core-fmt")]
+    pr_emerg!("{}", format_args!("{}", info.message()));
+    klog::enter_panic_mode();
+    loop {
+        unsafe { asm!("cli", "hlt"); }
+    }
 }
 
 #[no_mangle]
-pub extern "C" fn rust_main() -> ! {
+pub extern "C" fn rust_main(magic: u32, info: u32) -> ! {
+    // Before any printk: klog checks its slots against the boot information
+    let multiboot = tacos::multiboot::init(magic, info);
+    tacos::io::klog::init();
     printkln!("Welcome to {} TacOS!", 42);
+    if !multiboot {
+        pr_warn!("multiboot: bad magic {:#x}, boot modules unavailable", magic);
+    }
+    tacos::cpu::init();
+    tacos::mm::init();
+    tacos::drivers::timer::init();
     tacos::gdt::init();
+    tacos::cpu::idt::init();
+    tacos::drivers::pic::init();
+    tacos::drivers::ps2::init();
+    tacos::drivers::mouse::init();
+    irq::enable();
+
+    // Leave the boot stack for one with a guard page
+    match kstack::alloc("kernel", kstack::KERNEL_STACK_SIZE) {
+        Some(stack) => kstack::run_on(&stack, run_shell),
+        None => {
+            pr_warn!("kstack: no guarded stack, staying on the boot stack");
+            tacos::shell::run();
+        }
+    }
+}
+
+extern "C" fn run_shell() -> ! {
     tacos::shell::run();
 }
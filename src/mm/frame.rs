@@ -0,0 +1,177 @@
+/// Physical page frame allocator.
+///
+/// A bitmap with one bit per 4 KiB frame of the first `MAX_MEMORY` bytes,
+/// set while the frame is free. `init` frees what the bootloader reports
+/// as RAM, then takes back the first megabyte (BIOS data, VGA memory,
+/// GRUB's structures), the kernel image, the boot information and
+/// modules, and the persistent klog slots, which must reach the next boot
+/// untouched.
+///
+/// The kernel reaches physical memory at the same address, so a frame's
+/// address is also a pointer to it.
+
+use crate::cpu::irq;
+use crate::io::klog;
+use crate::multiboot;
+use crate::{pr_warn, printkln};
+
+pub const PAGE_SIZE: usize = 4096;
+/// Memory above this address is left unused.
+pub const MAX_MEMORY: usize = 1 << 30;
+pub const MAX_FRAMES: usize = MAX_MEMORY / PAGE_SIZE;
+
+/// Start of the kernel image, where GRUB loads it (see `linker.ld`).
+const KERNEL_START: usize = 0x0010_0000;
+
+extern "C" {
+    /// End of the kernel image and its bss, page-aligned (see `linker.ld`).
+    static __kernel_end: u8;
+}
+
+static mut BITMAP: [u32; MAX_FRAMES / 32] = [0; MAX_FRAMES / 32];
+static mut TOTAL: usize = 0; // Frames the allocator manages.
+static mut FREE: usize = 0;
+static mut NEXT: usize = 0; // Where the next search starts.
+static mut END: usize = 0; // End of the highest RAM in use.
+
+/// Frame counts, for reports.
+#[derive(Copy, Clone)]
+pub struct Stats {
+    pub total: usize,
+    pub free: usize,
+}
+
+fn is_free(frame: usize) -> bool {
+    unsafe { *(*core::ptr::addr_of!(BITMAP)).get_unchecked(frame / 32) & (1 << (frame % 32)) != 0 }
+}
+
+fn set_free(frame: usize, free: bool) {
+    unsafe {
+        let word = (*core::ptr::addr_of_mut!(BITMAP)).get_unchecked_mut(frame / 32);
+        if free {
+            *word |= 1 << (frame % 32);
+        } else {
+            *word &= !(1 << (frame % 32));
+        }
+    }
+}
+
+/// Frees the whole frames of `start..end`.
+fn add_memory(start: u64, end: u64) {
+    let end = end.min(MAX_MEMORY as u64) as usize;
+    let start = start.min(end as u64) as usize;
+    let first = start.div_ceil(PAGE_SIZE);
+    unsafe { END = END.max(end / PAGE_SIZE * PAGE_SIZE); }
+    for frame in first..end / PAGE_SIZE {
+        if !is_free(frame) {
+            set_free(frame, true);
+            unsafe { FREE += 1; }
+        }
+    }
+}
+
+/// Takes back every frame that `start..end` touches.
+fn reserve(start: usize, end: usize) {
+    let end = end.min(MAX_MEMORY);
+    for frame in start / PAGE_SIZE..end.div_ceil(PAGE_SIZE) {
+        if is_free(frame) {
+            set_free(frame, false);
+            unsafe { FREE -= 1; }
+        }
+    }
+}
+
+/// Builds the bitmap from the bootloader's memory map. Call once, after
+/// `multiboot::init`.
+pub fn init() {
+    let mut has_map = false;
+    for region in multiboot::memory_map().filter(|region| region.available) {
+        has_map = true;
+        add_memory(region.base, region.base.saturating_add(region.len));
+    }
+    if !has_map {
+        match multiboot::upper_memory_kib() {
+            Some(kib) => add_memory(0x10_0000, 0x10_0000 + kib as u64 * 1024),
+            None => {
+                pr_warn!("frame: no memory information from the bootloader");
+                return;
+            }
+        }
+    }
+
+    let kernel_end = core::ptr::addr_of!(__kernel_end) as usize;
+    reserve(0, KERNEL_START);
+    reserve(KERNEL_START, kernel_end);
+    reserve(klog::PERSIST_BASE, klog::PERSIST_END);
+    multiboot::for_each_boot_range(reserve);
+
+    unsafe {
+        TOTAL = FREE;
+        NEXT = kernel_end / PAGE_SIZE;
+    }
+    printkln!("frame: {} KiB free for allocation", stats().free * (PAGE_SIZE / 1024));
+}
+
+/// Allocates `count` contiguous frames starting on a multiple of `align`
+/// frames, and returns the address of the first one.
+pub fn alloc_contiguous(count: usize, align: usize) -> Option<usize> {
+    let align = align.max(1);
+    let count = count.max(1);
+    irq::without(|| unsafe {
+        let start = NEXT.div_ceil(align) * align;
+        // From the hint to the end, then from the start
+        let found = find_run(start, MAX_FRAMES, count, align).or_else(|| find_run(0, start, count, align))?;
+        for frame in found..found + count {
+            set_free(frame, false);
+        }
+        FREE -= count;
+        NEXT = found + count;
+        Some(found * PAGE_SIZE)
+    })
+}
+
+/// First run of `count` free frames starting in `from..to`.
+fn find_run(from: usize, to: usize, count: usize, align: usize) -> Option<usize> {
+    let mut frame = from;
+    while frame < to && frame + count <= MAX_FRAMES {
+        match (frame..frame + count).find(|&f| !is_free(f)) {
+            None => return Some(frame),
+            // Skip past the frame in use
+            Some(used) => frame = (used + 1).div_ceil(align) * align,
+        }
+    }
+    None
+}
+
+/// Allocates one frame.
+pub fn alloc() -> Option<usize> {
+    alloc_contiguous(1, 1)
+}
+
+/// Frees `count` frames from `addr`, as returned by `alloc_contiguous`.
+pub fn free_contiguous(addr: usize, count: usize) {
+    irq::without(|| {
+        for frame in addr / PAGE_SIZE..addr / PAGE_SIZE + count {
+            if frame >= MAX_FRAMES || is_free(frame) {
+                pr_warn!("frame: free of unallocated frame {:#010x}", frame * PAGE_SIZE);
+                continue;
+            }
+            set_free(frame, true);
+            unsafe { FREE += 1; }
+        }
+    });
+}
+
+/// Frees a frame returned by `alloc`.
+pub fn free(addr: usize) {
+    free_contiguous(addr, 1);
+}
+
+/// End of usable physical memory, at most `MAX_MEMORY`.
+pub fn memory_end() -> usize {
+    unsafe { END }
+}
+
+pub fn stats() -> Stats {
+    unsafe { Stats { total: TOTAL, free: FREE } }
+}
@@ -0,0 +1,51 @@
+/// The kernel's global allocator, for `alloc` types (`Box`, `Vec`, …).
+///
+/// Requests up to `slab::KMALLOC_MAX` bytes, alignment included, come
+/// from the kmalloc caches; bigger ones take whole contiguous frames.
+/// `dealloc` gets the same layout back, so it knows which path to undo.
+/// With `heap-debug`, kmalloc blocks are only aligned on
+/// `slab_debug::ALIGN`, so more demanding layouts take frames too.
+/// `memleak` tracks the blocks of both kinds.
+
+use core::alloc::{GlobalAlloc, Layout};
+
+use crate::mm::frame::{self, PAGE_SIZE};
+use crate::mm::memleak;
+use crate::mm::slab;
+use crate::mm::{size_class, slab_debug};
+
+pub struct KernelAllocator;
+
+#[global_allocator]
+static ALLOCATOR: KernelAllocator = KernelAllocator;
+
+/// Size of the kmalloc object serving `layout`, or `None` for frames.
+fn kmalloc_size(layout: &Layout) -> Option<usize> {
+    size_class::heap_class(layout.size(), layout.align(), slab_debug::ENABLED.then_some(slab_debug::ALIGN))
+}
+
+unsafe impl GlobalAlloc for KernelAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        match kmalloc_size(&layout) {
+            // The exact size with heap-debug, for a redzone right after the data
+            Some(size) => slab::kmalloc(if slab_debug::ENABLED { layout.size() } else { size }),
+            None => {
+                let pages = layout.size().div_ceil(PAGE_SIZE);
+                let align = layout.align().div_ceil(PAGE_SIZE);
+                let ptr = frame::alloc_contiguous(pages, align).map_or(core::ptr::null_mut(), |addr| addr as *mut u8);
+                memleak::track(ptr, layout.size());
+                ptr
+            }
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        match kmalloc_size(&layout) {
+            Some(_) => slab::kfree(ptr),
+            None => {
+                memleak::untrack(ptr);
+                frame::free_contiguous(ptr as usize, layout.size().div_ceil(PAGE_SIZE));
+            }
+        }
+    }
+}
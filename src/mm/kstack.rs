@@ -0,0 +1,135 @@
+/// Kernel stacks: guard pages, overflow checks and usage watermarks.
+///
+/// Stacks from `alloc` are vmalloc areas, so the page below each one is
+/// never mapped: a stack that overflows faults there instead of silently
+/// overwriting whatever lies below. The double fault handler asks
+/// `overflowed` whether the faulting address or stack pointer lies in a
+/// guard page, to report the overflow by name.
+///
+/// Every stack is painted with `STACK_PAINT` before use. Words still
+/// holding the pattern were never written, so the lowest overwritten word
+/// is the high-water mark that `stackusage` reports. The boot stack from
+/// `boot.s` (painted there) and the double fault stack are registered too,
+/// without a guard page.
+
+use crate::mm::frame::PAGE_SIZE;
+use crate::mm::vmalloc;
+
+/// Fill pattern of unused stack words. Also in `boot.s`.
+pub const STACK_PAINT: u32 = 0x57AC_57AC;
+/// Size of the stack the kernel moves to once vmalloc works.
+pub const KERNEL_STACK_SIZE: usize = 16 * 1024;
+const MAX_STACKS: usize = 8;
+
+extern "C" {
+    /// The boot stack, see `boot.s`.
+    static stack_bottom: u8;
+    static stack_top: u8;
+}
+
+/// A registered kernel stack.
+#[derive(Copy, Clone)]
+pub struct Stack {
+    pub name: &'static str,
+    /// Lowest address.
+    pub base: usize,
+    pub size: usize,
+    /// An unmapped page lies right below `base`.
+    pub guarded: bool,
+}
+
+static mut STACKS: [Option<Stack>; MAX_STACKS] = [None; MAX_STACKS];
+
+impl Stack {
+    /// Address just past the stack, its initial stack pointer.
+    pub fn top(&self) -> usize {
+        self.base + self.size
+    }
+
+    /// Most bytes ever in use: from the lowest overwritten word to the top.
+    pub fn max_used(&self) -> usize {
+        let words = self.base as *const u32;
+        let unused = (0..self.size / 4)
+            .take_while(|&i| unsafe { core::ptr::read_volatile(words.add(i)) } == STACK_PAINT)
+            .count();
+        self.size - unused * 4
+    }
+
+    /// Returns `true` if `addr` lies in the stack's guard page.
+    pub fn guard_contains(&self, addr: usize) -> bool {
+        self.guarded && addr < self.base && self.base - addr <= PAGE_SIZE
+    }
+}
+
+/// Registers the boot stack.
+pub fn init() {
+    let base = core::ptr::addr_of!(stack_bottom) as usize;
+    let top = core::ptr::addr_of!(stack_top) as usize;
+    register(Stack { name: "boot", base, size: top - base, guarded: false });
+}
+
+/// Adds a stack to the table `stackusage` reports. Returns `false` if
+/// the table is full.
+pub fn register(stack: Stack) -> bool {
+    let stacks = unsafe { &mut *core::ptr::addr_of_mut!(STACKS) };
+    match stacks.iter_mut().find(|slot| slot.is_none()) {
+        Some(slot) => {
+            *slot = Some(stack);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Fills `base..base + size` with `STACK_PAINT`. The memory must not be
+/// in use.
+pub fn paint(base: usize, size: usize) {
+    let words = base as *mut u32;
+    for i in 0..size / 4 {
+        unsafe { core::ptr::write_volatile(words.add(i), STACK_PAINT); }
+    }
+}
+
+/// Allocates a painted `size`-byte stack with a guard page below it.
+#[track_caller]
+pub fn alloc(name: &'static str, size: usize) -> Option<Stack> {
+    let size = size.div_ceil(PAGE_SIZE) * PAGE_SIZE;
+    let base = vmalloc::vmalloc(size) as usize;
+    if base == 0 {
+        return None;
+    }
+    paint(base, size);
+    let stack = Stack { name, base, size, guarded: true };
+    if !register(stack) {
+        vmalloc::vfree(base as *mut u8);
+        return None;
+    }
+    Some(stack)
+}
+
+/// Switches to `stack` and runs `f` on it. The current stack is left
+/// as is and never returned to.
+pub fn run_on(stack: &Stack, f: extern "C" fn() -> !) -> ! {
+    unsafe {
+        core::arch::asm!(
+            "mov esp, {top}",
+            // End of the frame chain for stack traces
+            "xor ebp, ebp",
+            "call {f}",
+            top = in(reg) stack.top(),
+            f = in(reg) f,
+            options(noreturn),
+        );
+    }
+}
+
+/// The stack whose guard page holds `addr`, if any.
+pub fn overflowed(addr: usize) -> Option<Stack> {
+    stacks().find(|stack| stack.guard_contains(addr))
+}
+
+/// The registered stacks, in registration order.
+pub fn stacks() -> impl Iterator<Item = Stack> {
+    let stacks = unsafe { &*core::ptr::addr_of!(STACKS) };
+    stacks.iter().flatten().copied()
+}
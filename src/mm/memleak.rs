@@ -0,0 +1,252 @@
+/// Memory leak detector, after Linux's kmemleak.
+///
+/// Every `kmalloc` block and every large `alloc` request is recorded with
+/// the stack trace of its allocation, in a hash table of `Object`s from
+/// the `memleak_object` cache (large requests, which are not slab
+/// objects, in a list of their own).
+///
+/// `scan` looks for pointers to the blocks the way a conservative garbage
+/// collector does: every aligned word of the kernel's data and bss and of
+/// the kernel stacks that holds an address inside a block marks it
+/// referenced, and the contents of each referenced block are scanned in
+/// turn. Blocks left unreferenced can no longer be freed: suspected
+/// leaks. A stale word that looks like a pointer hides a leak; a block
+/// only referenced from memory the scan skips (objects of named caches,
+/// vmalloc areas, raw frames) shows up as one. `clear` marks the current
+/// suspects as known, so later scans only report new ones.
+
+use crate::cpu::irq;
+use crate::klib::stack;
+use crate::mm::kstack;
+use crate::mm::slab::{self, CacheId};
+
+pub const TRACE_DEPTH: usize = 6;
+const HASH_BITS: u32 = 9;
+const BUCKETS: usize = 1 << HASH_BITS;
+
+/// Object flags.
+const REFERENCED: u32 = 1 << 0;
+/// Reported by the last scan.
+const REPORTED: u32 = 1 << 1;
+/// Reported before a `clear`, no longer reported.
+const IGNORED: u32 = 1 << 2;
+
+extern "C" {
+    /// Start of the kernel's data and end of its bss (see `linker.ld`).
+    static __data_start: u8;
+    static __bss_end: u8;
+}
+
+/// A tracked block.
+struct Object {
+    start: usize,
+    size: usize,
+    /// Start of the kmalloc object holding the block, 0 for a large one.
+    key: usize,
+    flags: u32,
+    /// Return addresses of the allocation, innermost first, 0 past the end.
+    trace: [u32; TRACE_DEPTH],
+    /// Next object in the same bucket, or in the large list.
+    next: *mut Object,
+    /// Next object whose contents are still to scan.
+    gray: *mut Object,
+}
+
+/// A suspected leak, for reports.
+pub struct Leak {
+    pub start: usize,
+    pub size: usize,
+    pub trace: [u32; TRACE_DEPTH],
+}
+
+/// Counters.
+#[derive(Copy, Clone)]
+pub struct Stats {
+    /// Blocks tracked.
+    pub tracked: usize,
+    /// Blocks allocated while no `Object` could be.
+    pub untracked: usize,
+}
+
+static mut TABLE: [*mut Object; BUCKETS] = [core::ptr::null_mut(); BUCKETS];
+static mut LARGE: *mut Object = core::ptr::null_mut();
+static mut OBJECT_CACHE: Option<CacheId> = None;
+static mut TRACKED: usize = 0;
+static mut UNTRACKED: usize = 0;
+/// Addresses of all blocks ever tracked lie in `LOWEST..HIGHEST`.
+static mut LOWEST: usize = usize::MAX;
+static mut HIGHEST: usize = 0;
+
+/// Creates the cache of `Object`s. Allocations made before are not
+/// tracked.
+pub fn init() {
+    let cache = slab::create_cache("memleak_object", core::mem::size_of::<Object>(), core::mem::align_of::<Object>(), None);
+    unsafe { OBJECT_CACHE = cache; }
+}
+
+/// List the object of a block goes in.
+fn list(key: usize) -> &'static mut *mut Object {
+    unsafe {
+        if key == 0 {
+            return &mut *core::ptr::addr_of_mut!(LARGE);
+        }
+        let hash = (key as u32).wrapping_mul(0x9E37_79B9) >> (32 - HASH_BITS);
+        (*core::ptr::addr_of_mut!(TABLE)).get_unchecked_mut(hash as usize)
+    }
+}
+
+/// Start of the kmalloc object holding `ptr`, 0 for anything else.
+fn key_of(ptr: usize) -> usize {
+    slab::kmalloc_object_of(ptr as *mut u8).map_or(0, |(object, _)| object as usize)
+}
+
+/// Records a new block of `size` bytes and the trace of its allocation.
+/// Null is ignored.
+#[inline(never)]
+pub fn track(ptr: *mut u8, size: usize) {
+    if ptr.is_null() {
+        return;
+    }
+    let mut trace = [0; TRACE_DEPTH];
+    // From the caller of `track`
+    stack::backtrace(&mut trace, 1);
+    irq::without(|| unsafe {
+        let Some(cache) = OBJECT_CACHE else {
+            return;
+        };
+        let object = slab::cache_alloc(cache) as *mut Object;
+        if object.is_null() {
+            UNTRACKED += 1;
+            return;
+        }
+        let start = ptr as usize;
+        let key = key_of(start);
+        let list = list(key);
+        object.write(Object { start, size, key, flags: 0, trace, next: *list, gray: core::ptr::null_mut() });
+        *list = object;
+        TRACKED += 1;
+        LOWEST = LOWEST.min(start);
+        HIGHEST = HIGHEST.max(start + size.max(1));
+    });
+}
+
+/// Forgets a block about to be freed. Untracked blocks are ignored.
+pub fn untrack(ptr: *mut u8) {
+    if ptr.is_null() {
+        return;
+    }
+    irq::without(|| unsafe {
+        let Some(cache) = OBJECT_CACHE else {
+            return;
+        };
+        let mut link: *mut *mut Object = list(key_of(ptr as usize));
+        while let Some(object) = (*link).as_mut() {
+            if object.start == ptr as usize {
+                *link = object.next;
+                slab::cache_free(cache, object as *mut Object as *mut u8);
+                TRACKED -= 1;
+                return;
+            }
+            link = &mut object.next;
+        }
+    });
+}
+
+/// Calls `f` for every tracked object.
+fn for_each_object(mut f: impl FnMut(&mut Object)) {
+    unsafe {
+        let table = &*core::ptr::addr_of!(TABLE);
+        for &head in table.iter().chain(core::iter::once(&*core::ptr::addr_of!(LARGE))) {
+            let mut next = head;
+            while let Some(object) = next.as_mut() {
+                next = object.next;
+                f(object);
+            }
+        }
+    }
+}
+
+/// The tracked block `addr` points into.
+fn find(addr: usize) -> Option<&'static mut Object> {
+    let key = key_of(addr);
+    let mut next = *list(key);
+    while let Some(object) = unsafe { next.as_mut() } {
+        if object.key == key && addr >= object.start && addr - object.start < object.size.max(1) {
+            return Some(object);
+        }
+        next = object.next;
+    }
+    None
+}
+
+/// Marks the blocks the words of `start..end` point into, and queues them
+/// on `gray` to be scanned in turn.
+fn scan_range(start: usize, end: usize, gray: &mut *mut Object) {
+    let word = core::mem::size_of::<usize>();
+    let (lowest, highest) = unsafe { (LOWEST, HIGHEST) };
+    let mut addr = start.next_multiple_of(word);
+    while addr + word <= end {
+        let value = unsafe { core::ptr::read_volatile(addr as *const usize) };
+        if (lowest..highest).contains(&value) {
+            if let Some(object) = find(value) {
+                if object.flags & REFERENCED == 0 {
+                    object.flags |= REFERENCED;
+                    object.gray = *gray;
+                    *gray = object;
+                }
+            }
+        }
+        addr += word;
+    }
+}
+
+/// Scans memory for references to the tracked blocks and calls `report`
+/// for each unreferenced one not ignored. Returns the number reported.
+pub fn scan(mut report: impl FnMut(&Leak)) -> usize {
+    irq::without(|| {
+        for_each_object(|object| object.flags &= !(REFERENCED | REPORTED));
+
+        let mut gray = core::ptr::null_mut();
+        let data = core::ptr::addr_of!(__data_start) as usize;
+        let bss_end = core::ptr::addr_of!(__bss_end) as usize;
+        scan_range(data, bss_end, &mut gray);
+        // Only the live part of the current stack
+        let esp = stack::get_esp() as usize;
+        for stack in kstack::stacks() {
+            let from = if (stack.base..stack.top()).contains(&esp) { esp } else { stack.base };
+            scan_range(from, stack.top(), &mut gray);
+        }
+        while let Some(object) = unsafe { gray.as_mut() } {
+            gray = object.gray;
+            scan_range(object.start, object.start + object.size, &mut gray);
+        }
+
+        let mut leaks = 0;
+        for_each_object(|object| {
+            if object.flags & (REFERENCED | IGNORED) == 0 {
+                object.flags |= REPORTED;
+                leaks += 1;
+                report(&Leak { start: object.start, size: object.size, trace: object.trace });
+            }
+        });
+        leaks
+    })
+}
+
+/// Stops reporting the blocks the last scan reported. Returns how many.
+pub fn clear() -> usize {
+    irq::without(|| {
+        let mut cleared = 0;
+        for_each_object(|object| {
+            if object.flags & REPORTED != 0 {
+                object.flags = (object.flags & !REPORTED) | IGNORED;
+                cleared += 1;
+            }
+        });
+        cleared
+    })
+}
+
+pub fn stats() -> Stats {
+    unsafe { Stats { tracked: TRACKED, untracked: UNTRACKED } }
+}
@@ -0,0 +1,30 @@
+#[cfg(target_os = "none")]
+pub mod frame;
+#[cfg(target_os = "none")]
+pub mod heap;
+#[cfg(target_os = "none")]
+pub mod kstack;
+#[cfg(target_os = "none")]
+pub mod memleak;
+#[cfg(target_os = "none")]
+pub mod paging;
+#[cfg(target_os = "none")]
+pub mod slab;
+pub mod size_class;
+#[cfg(target_os = "none")]
+pub mod slab_debug;
+#[cfg(target_os = "none")]
+pub mod vmalloc;
+
+/// Sets up the frame allocator, the kmalloc caches, leak tracking, paging,
+/// vmalloc and the kernel stack table. Call after `multiboot::init` and
+/// `cpu::init`.
+#[cfg(target_os = "none")]
+pub fn init() {
+    frame::init();
+    slab::init();
+    memleak::init();
+    paging::init();
+    vmalloc::init();
+    kstack::init();
+}
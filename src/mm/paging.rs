@@ -0,0 +1,180 @@
+/// Paging setup and queries.
+///
+/// `init` turns paging on with physical memory identity-mapped up to
+/// `frame::memory_end()` (4 MiB pages when the CPU has PSE), so physical
+/// addresses keep working as pointers. Kernel virtual memory above
+/// `frame::MAX_MEMORY` is mapped a page at a time with `map`, for vmalloc.
+/// Until `init` runs, or without a frame allocator, paging stays off and
+/// every physical address is reachable.
+///
+/// The queries let code that dereferences user-supplied addresses (the
+/// memory builtins) refuse unmapped ones, instead of taking a page fault,
+/// which is fatal.
+///
+/// The walk reads the page directory and page tables through their
+/// physical addresses, so it assumes they are identity-mapped. Only the
+/// classic 32-bit two-level format is understood; with PAE enabled every
+/// address is reported as unmapped.
+
+use crate::cpu::regs::{self, CR0_PG, CR4_PAE, CR4_PGE, CR4_PSE};
+use crate::mm::frame::{self, PAGE_SIZE};
+use crate::{pr_warn, printkln};
+
+const ENTRY_PRESENT: u32 = 1 << 0;
+const ENTRY_WRITABLE: u32 = 1 << 1;
+const ENTRY_LARGE: u32 = 1 << 7;
+const ENTRY_GLOBAL: u32 = 1 << 8;
+const FRAME_MASK: u32 = 0xFFFF_F000;
+const ENTRIES: usize = 1024;
+const LARGE_PAGE_SIZE: usize = PAGE_SIZE * ENTRIES;
+
+/// The kernel page directory, once `init` has built it.
+static mut DIRECTORY: *mut u32 = core::ptr::null_mut();
+
+/// Returns `true` if CR0.PG is set.
+pub fn is_enabled() -> bool {
+    regs::read_cr0() & CR0_PG != 0
+}
+
+/// Returns `true` if reading `addr` will not fault.
+pub fn is_mapped(addr: usize) -> bool {
+    if !is_enabled() {
+        return true;
+    }
+    let cr4 = regs::read_cr4();
+    if cr4 & CR4_PAE != 0 {
+        return false;
+    }
+
+    let directory = (regs::read_cr3() & FRAME_MASK) as *const u32;
+    let pde = unsafe { core::ptr::read_volatile(directory.add(addr >> 22)) };
+    if pde & ENTRY_PRESENT == 0 {
+        return false;
+    }
+    if pde & ENTRY_LARGE != 0 && cr4 & CR4_PSE != 0 {
+        return true;
+    }
+
+    let table = (pde & FRAME_MASK) as *const u32;
+    let pte = unsafe { core::ptr::read_volatile(table.add((addr >> 12) & 0x3FF)) };
+    pte & ENTRY_PRESENT != 0
+}
+
+/// Returns `true` if every byte of `addr..addr + len` is mapped.
+/// A range that wraps around the address space is never mapped.
+pub fn is_range_mapped(addr: usize, len: usize) -> bool {
+    if len == 0 {
+        return true;
+    }
+    let Some(last) = addr.checked_add(len - 1) else {
+        return false;
+    };
+    let mut page = addr & !(PAGE_SIZE - 1);
+    loop {
+        if !is_mapped(page) {
+            return false;
+        }
+        match page.checked_add(PAGE_SIZE) {
+            Some(next) if next <= last => page = next,
+            _ => return true,
+        }
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Setup and mapping
+// ──────────────────────────────────────────────
+
+/// A zeroed frame for a page directory or table.
+fn zeroed_frame() -> Option<*mut u32> {
+    let table = frame::alloc()? as *mut u32;
+    unsafe { core::ptr::write_bytes(table, 0, ENTRIES); }
+    Some(table)
+}
+
+/// Builds the kernel page directory and turns paging on. Call once,
+/// after `frame::init` and `cpu::init` (which enables PSE and PGE).
+pub fn init() {
+    let end = frame::memory_end().div_ceil(LARGE_PAGE_SIZE) * LARGE_PAGE_SIZE;
+    let Some(directory) = zeroed_frame() else {
+        pr_warn!("paging: no memory for the page directory, paging stays off");
+        return;
+    };
+    let cr4 = regs::read_cr4();
+    let global = if cr4 & CR4_PGE != 0 { ENTRY_GLOBAL } else { 0 };
+    unsafe {
+        DIRECTORY = directory;
+        for addr in (0..end).step_by(LARGE_PAGE_SIZE) {
+            if cr4 & CR4_PSE != 0 {
+                *directory.add(addr / LARGE_PAGE_SIZE) = addr as u32 | ENTRY_PRESENT | ENTRY_WRITABLE | ENTRY_LARGE | global;
+                continue;
+            }
+            for page in (addr..addr + LARGE_PAGE_SIZE).step_by(PAGE_SIZE) {
+                if !map_flags(page, page, global) {
+                    pr_warn!("paging: no memory for page tables, paging stays off");
+                    DIRECTORY = core::ptr::null_mut();
+                    return;
+                }
+            }
+        }
+    }
+    regs::write_cr3(directory as u32);
+    regs::write_cr0(regs::read_cr0() | CR0_PG);
+    printkln!("paging: enabled, {} MiB identity-mapped", end >> 20);
+}
+
+/// Page table entry of `virt`, allocating its page table if `create`.
+fn entry(virt: usize, create: bool) -> Option<*mut u32> {
+    unsafe {
+        if DIRECTORY.is_null() {
+            return None;
+        }
+        let pde = DIRECTORY.add(virt / LARGE_PAGE_SIZE);
+        if *pde & ENTRY_PRESENT == 0 {
+            if !create {
+                return None;
+            }
+            *pde = zeroed_frame()? as u32 | ENTRY_PRESENT | ENTRY_WRITABLE;
+        }
+        if *pde & ENTRY_LARGE != 0 {
+            // Part of the identity map
+            return None;
+        }
+        let table = (*pde & FRAME_MASK) as *mut u32;
+        Some(table.add((virt / PAGE_SIZE) % ENTRIES))
+    }
+}
+
+fn map_flags(virt: usize, phys: usize, flags: u32) -> bool {
+    match entry(virt, true) {
+        Some(pte) => {
+            unsafe { *pte = (phys as u32 & FRAME_MASK) | ENTRY_PRESENT | ENTRY_WRITABLE | flags; }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Maps the page at `virt` to the frame at `phys`, writable, kernel only.
+/// Returns `false` if paging is off or a page table can't be allocated.
+pub fn map(virt: usize, phys: usize) -> bool {
+    let mapped = map_flags(virt, phys, 0);
+    if mapped {
+        regs::invlpg(virt);
+    }
+    mapped
+}
+
+/// Unmaps the page at `virt` and returns the frame it was mapped to.
+pub fn unmap(virt: usize) -> Option<usize> {
+    let pte = entry(virt, false)?;
+    unsafe {
+        if *pte & ENTRY_PRESENT == 0 {
+            return None;
+        }
+        let phys = (*pte & FRAME_MASK) as usize;
+        *pte = 0;
+        regs::invlpg(virt);
+        Some(phys)
+    }
+}
@@ -0,0 +1,98 @@
+//! Size classes of the kmalloc caches, shared by `slab` and `heap`.
+//!
+//! kmalloc cache `i` holds objects of `KMALLOC_MIN << i` bytes, aligned on
+//! their size. A request goes to the smallest class that fits it, and an
+//! address inside a slab maps back to its object by rounding down to a
+//! multiple of the object size from the first object.
+
+pub const KMALLOC_MIN: usize = 8;
+pub const KMALLOC_MAX: usize = 2048;
+
+/// Index of the smallest of the first `caches` kmalloc caches that holds
+/// `size` bytes, or `None` if none does.
+pub fn kmalloc_index(size: usize, caches: usize) -> Option<usize> {
+    let class = size.max(KMALLOC_MIN).checked_next_power_of_two()?;
+    let idx = (class.trailing_zeros() - KMALLOC_MIN.trailing_zeros()) as usize;
+    (idx < caches).then_some(idx)
+}
+
+/// Start of the object of `size` bytes that `addr` points into, objects
+/// being laid out back to back from `first`.
+pub fn object_start(first: usize, addr: usize, size: usize) -> usize {
+    first + (addr - first) / size.max(1) * size
+}
+
+/// Size of the kmalloc object serving a global allocator request of
+/// `size` bytes aligned on `align`, or `None` when it needs whole frames.
+/// `debug_align` is the alignment of blocks under `heap-debug`, if on:
+/// more demanding layouts take frames.
+pub fn heap_class(size: usize, align: usize, debug_align: Option<usize>) -> Option<usize> {
+    if debug_align.is_some_and(|debug_align| align > debug_align) {
+        return None;
+    }
+    // Objects of a power-of-two size are aligned on that size
+    let size = size.max(align).checked_next_power_of_two()?;
+    (size <= KMALLOC_MAX).then_some(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ── kmalloc_index ──
+
+    #[test]
+    fn small_requests_use_the_first_cache() {
+        assert_eq!(kmalloc_index(0, 9), Some(0));
+        assert_eq!(kmalloc_index(1, 9), Some(0));
+        assert_eq!(kmalloc_index(8, 9), Some(0));
+    }
+
+    #[test]
+    fn requests_round_up_to_a_power_of_two() {
+        assert_eq!(kmalloc_index(9, 9), Some(1));
+        assert_eq!(kmalloc_index(100, 9), Some(4));
+        assert_eq!(kmalloc_index(128, 9), Some(4));
+        assert_eq!(kmalloc_index(2048, 9), Some(8));
+    }
+
+    #[test]
+    fn requests_past_the_last_cache_fail() {
+        assert_eq!(kmalloc_index(2049, 9), None);
+        assert_eq!(kmalloc_index(2049, 10), Some(9));
+        assert_eq!(kmalloc_index(usize::MAX, 10), None);
+    }
+
+    // ── object_start ──
+
+    #[test]
+    fn object_start_rounds_down_to_an_object() {
+        assert_eq!(object_start(0x1040, 0x1040, 64), 0x1040);
+        assert_eq!(object_start(0x1040, 0x107F, 64), 0x1040);
+        assert_eq!(object_start(0x1040, 0x1080, 64), 0x1080);
+        assert_eq!(object_start(0x1010, 0x1234, 48), 0x1010 + 11 * 48);
+    }
+
+    // ── heap_class ──
+
+    #[test]
+    fn heap_class_covers_size_and_alignment() {
+        assert_eq!(heap_class(1, 1, None), Some(1));
+        assert_eq!(heap_class(24, 8, None), Some(32));
+        assert_eq!(heap_class(8, 256, None), Some(256));
+        assert_eq!(heap_class(2048, 8, None), Some(2048));
+    }
+
+    #[test]
+    fn heap_class_sends_big_requests_to_frames() {
+        assert_eq!(heap_class(2049, 8, None), None);
+        assert_eq!(heap_class(16, 4096, None), None);
+        assert_eq!(heap_class(usize::MAX, 1, None), None);
+    }
+
+    #[test]
+    fn heap_class_with_debug_alignment() {
+        assert_eq!(heap_class(16, 32, Some(64)), Some(32));
+        assert_eq!(heap_class(16, 128, Some(64)), None);
+    }
+}
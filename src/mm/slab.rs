@@ -0,0 +1,438 @@
+/// Slab allocator: caches of fixed-size objects carved out of page frames.
+///
+/// A cache hands out objects of one size. It takes memory from the frame
+/// allocator in slabs of `1 << order` contiguous frames, aligned on their
+/// size. A slab starts with a `Slab` header and a stack of free object
+/// indices; the objects follow. `kfree` finds the slab of an object by
+/// looking for the nearest slab start below it in `HEADS`, and the cache
+/// from the slab header.
+///
+/// `kmalloc` uses the smallest power-of-two cache from `KMALLOC_MIN` to
+/// `KMALLOC_MAX` bytes that fits. Subsystems create named caches for their
+/// own objects with `create_cache`. The optional constructor runs once per
+/// object, when its slab is set up, and freeing leaves objects untouched:
+/// callers give them back in their constructed state (Linux's
+/// `kmem_cache_create` contract).
+///
+/// Each cache keeps its slabs in three lists: full, partial and free. One
+/// free slab is kept for the next allocation, further ones go back to the
+/// frame allocator.
+///
+/// With the `heap-debug` feature, `kmalloc` and `kfree` go through
+/// `slab_debug`, which adds redzones and poisoning around the objects.
+/// `memleak` records every `kmalloc` block until it is freed.
+
+use crate::cpu::irq;
+use crate::mm::frame::{self, MAX_FRAMES, PAGE_SIZE};
+use crate::mm::{memleak, size_class, slab_debug};
+use crate::pr_err;
+
+pub use crate::mm::size_class::{KMALLOC_MAX, KMALLOC_MIN};
+/// kmalloc-8 … kmalloc-2048, the first caches, and kmalloc-4096 for the
+/// debug headers and redzones of the biggest requests.
+const KMALLOC_CACHES: usize = if slab_debug::ENABLED { 10 } else { 9 };
+const MAX_CACHES: usize = 32;
+/// Slabs grow until they hold this many objects, or reach `MAX_ORDER`.
+const MIN_OBJECTS: usize = 8;
+const MAX_ORDER: usize = 3;
+
+/// Start of a slab: the header, then `capacity` free indices.
+#[repr(C)]
+struct Slab {
+    cache: *mut Cache,
+    prev: *mut Slab,
+    next: *mut Slab,
+    /// Objects handed out; indices `0..capacity - inuse` are free.
+    inuse: usize,
+}
+
+const HEADER_SIZE: usize = core::mem::size_of::<Slab>();
+
+/// A named cache of objects of one size.
+pub struct Cache {
+    name: &'static str,
+    /// Object size, rounded up to `align`.
+    size: usize,
+    align: usize,
+    order: usize,
+    /// Objects per slab.
+    capacity: usize,
+    /// Offset of the first object in a slab.
+    offset: usize,
+    ctor: Option<fn(*mut u8)>,
+    full: *mut Slab,
+    partial: *mut Slab,
+    free: *mut Slab,
+    /// Objects handed out.
+    active: usize,
+    slabs: usize,
+}
+
+impl Cache {
+    const EMPTY: Cache = Cache {
+        name: "",
+        size: 0,
+        align: 0,
+        order: 0,
+        capacity: 0,
+        offset: 0,
+        ctor: None,
+        full: core::ptr::null_mut(),
+        partial: core::ptr::null_mut(),
+        free: core::ptr::null_mut(),
+        active: 0,
+        slabs: 0,
+    };
+}
+
+/// Handle of a cache from `create_cache`.
+#[derive(Copy, Clone)]
+pub struct CacheId(usize);
+
+/// Statistics of a cache, for `slabinfo`.
+pub struct CacheInfo {
+    pub name: &'static str,
+    pub size: usize,
+    pub active: usize,
+    pub total: usize,
+    pub per_slab: usize,
+    pub pages_per_slab: usize,
+    pub slabs: usize,
+}
+
+static mut CACHES: [Cache; MAX_CACHES] = [Cache::EMPTY; MAX_CACHES];
+static mut CACHE_COUNT: usize = 0;
+/// Bit set on the first frame of every slab.
+static mut HEADS: [u32; MAX_FRAMES / 32] = [0; MAX_FRAMES / 32];
+
+static KMALLOC_NAMES: [&str; 10] = [
+    "kmalloc-8", "kmalloc-16", "kmalloc-32", "kmalloc-64", "kmalloc-128",
+    "kmalloc-256", "kmalloc-512", "kmalloc-1024", "kmalloc-2048", "kmalloc-4096",
+];
+
+/// Creates the kmalloc caches. Call once, after `frame::init`.
+pub fn init() {
+    for (i, &name) in KMALLOC_NAMES.iter().take(KMALLOC_CACHES).enumerate() {
+        let size = KMALLOC_MIN << i;
+        let ctor = if slab_debug::ENABLED { slab_debug::constructor(size) } else { None };
+        create_cache(name, size, size, ctor);
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Slab heads
+// ──────────────────────────────────────────────
+
+fn set_head(addr: usize, head: bool) {
+    let frame = addr / PAGE_SIZE;
+    unsafe {
+        let word = (*core::ptr::addr_of_mut!(HEADS)).get_unchecked_mut(frame / 32);
+        if head {
+            *word |= 1 << (frame % 32);
+        } else {
+            *word &= !(1 << (frame % 32));
+        }
+    }
+}
+
+fn is_head(addr: usize) -> bool {
+    let frame = addr / PAGE_SIZE;
+    frame < MAX_FRAMES && unsafe { *(*core::ptr::addr_of!(HEADS)).get_unchecked(frame / 32) & (1 << (frame % 32)) != 0 }
+}
+
+/// The slab `ptr` points into. Slabs are aligned on their size, so the
+/// first slab start found going up the orders is the one.
+fn slab_of(ptr: *mut u8) -> Option<*mut Slab> {
+    let addr = ptr as usize;
+    (0..=MAX_ORDER).find_map(|order| {
+        let base = addr & !((PAGE_SIZE << order) - 1);
+        if !is_head(base) {
+            return None;
+        }
+        let slab = base as *mut Slab;
+        let cache = unsafe { &*(*slab).cache };
+        let inside = addr - base >= cache.offset && addr - base < PAGE_SIZE << cache.order;
+        inside.then_some(slab)
+    })
+}
+
+// ──────────────────────────────────────────────
+//  Slab lists
+// ──────────────────────────────────────────────
+
+fn push(list: &mut *mut Slab, slab: *mut Slab) {
+    unsafe {
+        (*slab).prev = core::ptr::null_mut();
+        (*slab).next = *list;
+        if let Some(head) = list.as_mut() {
+            head.prev = slab;
+        }
+    }
+    *list = slab;
+}
+
+fn unlink(list: &mut *mut Slab, slab: *mut Slab) {
+    unsafe {
+        let (prev, next) = ((*slab).prev, (*slab).next);
+        match prev.as_mut() {
+            Some(prev) => prev.next = next,
+            None => *list = next,
+        }
+        if let Some(next) = next.as_mut() {
+            next.prev = prev;
+        }
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Caches
+// ──────────────────────────────────────────────
+
+fn align_up(value: usize, align: usize) -> usize {
+    (value + align - 1) & !(align - 1)
+}
+
+impl Cache {
+    fn free_indices(&self, slab: *mut Slab) -> *mut u16 {
+        unsafe { (slab as *mut u8).add(HEADER_SIZE) as *mut u16 }
+    }
+
+    fn object(&self, slab: *mut Slab, idx: usize) -> *mut u8 {
+        unsafe { (slab as *mut u8).add(self.offset + idx * self.size) }
+    }
+
+    /// Picks the slab order and the object layout in it. Returns `false`
+    /// if not a single object fits in a slab.
+    fn set_geometry(&mut self) -> bool {
+        for order in 0..=MAX_ORDER {
+            let slab_size = PAGE_SIZE << order;
+            let mut capacity = (slab_size - HEADER_SIZE) / (self.size + 2).max(1);
+            while capacity > 0 && align_up(HEADER_SIZE + 2 * capacity, self.align) + capacity * self.size > slab_size {
+                capacity -= 1;
+            }
+            self.order = order;
+            self.capacity = capacity;
+            self.offset = align_up(HEADER_SIZE + 2 * capacity, self.align);
+            if capacity >= MIN_OBJECTS {
+                break;
+            }
+        }
+        self.capacity > 0
+    }
+
+    /// Takes a new slab from the frame allocator and constructs its objects.
+    fn grow(&mut self) -> Option<*mut Slab> {
+        let pages = 1 << self.order;
+        let slab = frame::alloc_contiguous(pages, pages)? as *mut Slab;
+        unsafe {
+            (*slab).cache = self;
+            (*slab).inuse = 0;
+            let indices = self.free_indices(slab);
+            for idx in 0..self.capacity {
+                // Lowest index on top of the stack
+                *indices.add(idx) = (self.capacity - 1 - idx) as u16;
+                if let Some(ctor) = self.ctor {
+                    ctor(self.object(slab, idx));
+                }
+            }
+        }
+        set_head(slab as usize, true);
+        self.slabs += 1;
+        Some(slab)
+    }
+
+    fn alloc(&mut self) -> *mut u8 {
+        let slab = if !self.partial.is_null() {
+            self.partial
+        } else {
+            let slab = if self.free.is_null() {
+                match self.grow() {
+                    Some(slab) => slab,
+                    None => return core::ptr::null_mut(),
+                }
+            } else {
+                let slab = self.free;
+                unlink(&mut self.free, slab);
+                slab
+            };
+            push(&mut self.partial, slab);
+            slab
+        };
+        unsafe {
+            (*slab).inuse += 1;
+            let idx = *self.free_indices(slab).add(self.capacity - (*slab).inuse);
+            if (*slab).inuse == self.capacity {
+                unlink(&mut self.partial, slab);
+                push(&mut self.full, slab);
+            }
+            self.active += 1;
+            self.object(slab, idx as usize)
+        }
+    }
+
+    fn free(&mut self, slab: *mut Slab, ptr: *mut u8) {
+        let idx = (ptr as usize - self.object(slab, 0) as usize) / self.size.max(1);
+        unsafe {
+            let was_full = (*slab).inuse == self.capacity;
+            *self.free_indices(slab).add(self.capacity - (*slab).inuse) = idx as u16;
+            (*slab).inuse -= 1;
+            self.active -= 1;
+            if was_full {
+                unlink(&mut self.full, slab);
+                push(&mut self.partial, slab);
+            }
+            if (*slab).inuse == 0 {
+                unlink(&mut self.partial, slab);
+                if self.free.is_null() {
+                    push(&mut self.free, slab);
+                } else {
+                    set_head(slab as usize, false);
+                    frame::free_contiguous(slab as usize, 1 << self.order);
+                    self.slabs -= 1;
+                }
+            }
+        }
+    }
+
+    fn info(&self) -> CacheInfo {
+        CacheInfo {
+            name: self.name,
+            size: self.size,
+            active: self.active,
+            total: self.slabs * self.capacity,
+            per_slab: self.capacity,
+            pages_per_slab: 1 << self.order,
+            slabs: self.slabs,
+        }
+    }
+}
+
+fn cache(id: CacheId) -> &'static mut Cache {
+    unsafe { (*core::ptr::addr_of_mut!(CACHES)).get_unchecked_mut(id.0) }
+}
+
+/// Creates a cache of `size`-byte objects aligned on `align` (a power of
+/// two). `ctor`, if any, initializes each object once, when its slab is
+/// allocated. Returns `None` when the cache table is full or an object
+/// does not fit in a slab.
+pub fn create_cache(name: &'static str, size: usize, align: usize, ctor: Option<fn(*mut u8)>) -> Option<CacheId> {
+    irq::without(|| unsafe {
+        if CACHE_COUNT == MAX_CACHES || !align.is_power_of_two() || align > PAGE_SIZE {
+            return None;
+        }
+        let id = CacheId(CACHE_COUNT);
+        let cache = cache(id);
+        *cache = Cache::EMPTY;
+        cache.name = name;
+        cache.align = align;
+        cache.size = align_up(size.max(1), align);
+        cache.ctor = ctor;
+        if !cache.set_geometry() {
+            return None;
+        }
+        CACHE_COUNT += 1;
+        Some(id)
+    })
+}
+
+/// Allocates an object from a cache. Returns null when out of memory.
+pub fn cache_alloc(id: CacheId) -> *mut u8 {
+    irq::without(|| cache(id).alloc())
+}
+
+/// Returns an object to the cache it came from.
+pub fn cache_free(id: CacheId, ptr: *mut u8) {
+    kfree_from(Some(id), ptr);
+}
+
+/// Allocates `size` bytes, aligned on the power of two `size` rounds up
+/// to (at most `slab_debug::ALIGN` with `heap-debug`). Returns null when
+/// out of memory, or for more than `KMALLOC_MAX`.
+pub fn kmalloc(size: usize) -> *mut u8 {
+    if size > KMALLOC_MAX {
+        return core::ptr::null_mut();
+    }
+    let ptr = if slab_debug::ENABLED { slab_debug::alloc(size) } else { kmalloc_object(size) };
+    memleak::track(ptr, size);
+    ptr
+}
+
+/// Frees an object from `kmalloc` or any cache. Null is ignored.
+pub fn kfree(ptr: *mut u8) {
+    memleak::untrack(ptr);
+    if slab_debug::ENABLED {
+        slab_debug::free(ptr);
+    } else {
+        kfree_from(None, ptr);
+    }
+}
+
+/// `kmalloc` without the debug layer: an object from the smallest kmalloc
+/// cache that fits `size`.
+pub(crate) fn kmalloc_object(size: usize) -> *mut u8 {
+    match size_class::kmalloc_index(size, KMALLOC_CACHES) {
+        Some(idx) if unsafe { CACHE_COUNT } >= KMALLOC_CACHES => cache_alloc(CacheId(idx)),
+        _ => core::ptr::null_mut(),
+    }
+}
+
+/// `kfree` without the debug layer.
+pub(crate) fn kfree_object(ptr: *mut u8) {
+    kfree_from(None, ptr);
+}
+
+/// Start and size of the kmalloc object `ptr` points into, or `None` if
+/// `ptr` is not in a kmalloc cache.
+pub(crate) fn kmalloc_object_of(ptr: *mut u8) -> Option<(*mut u8, usize)> {
+    let slab = slab_of(ptr)?;
+    let cache = unsafe { &*(*slab).cache };
+    let idx = (cache as *const Cache as usize - core::ptr::addr_of!(CACHES) as usize) / core::mem::size_of::<Cache>();
+    if idx >= KMALLOC_CACHES {
+        return None;
+    }
+    let start = size_class::object_start(cache.object(slab, 0) as usize, ptr as usize, cache.size);
+    Some((start as *mut u8, cache.size))
+}
+
+fn kfree_from(expected: Option<CacheId>, ptr: *mut u8) {
+    if ptr.is_null() {
+        return;
+    }
+    irq::without(|| {
+        let Some(slab) = slab_of(ptr) else {
+            pr_err!("slab: free of {:#010x}, not a slab object", ptr as usize);
+            return;
+        };
+        let cache = unsafe { &mut *(*slab).cache };
+        if let Some(id) = expected {
+            if !core::ptr::eq(cache, self::cache(id)) {
+                pr_err!("slab: {:#010x} freed to {}, belongs to {}", ptr as usize, self::cache(id).name, cache.name);
+                return;
+            }
+        }
+        let misplaced = (ptr as usize - cache.object(slab, 0) as usize) % cache.size.max(1) != 0;
+        if misplaced || unsafe { (*slab).inuse } == 0 {
+            pr_err!("slab: bad free of {:#010x} in {}", ptr as usize, cache.name);
+            return;
+        }
+        cache.free(slab, ptr);
+    });
+}
+
+/// Size of the object `ptr` points to, as allocated (its cache's size,
+/// or the requested size with `heap-debug`).
+pub fn ksize(ptr: *mut u8) -> Option<usize> {
+    if slab_debug::ENABLED {
+        if let Some(size) = slab_debug::size_of(ptr) {
+            return Some(size);
+        }
+    }
+    slab_of(ptr).map(|slab| unsafe { (*(*slab).cache).size })
+}
+
+/// Calls `f` with the statistics of every cache.
+pub fn for_each_cache(mut f: impl FnMut(CacheInfo)) {
+    for idx in 0..unsafe { CACHE_COUNT } {
+        f(cache(CacheId(idx)).info());
+    }
+}
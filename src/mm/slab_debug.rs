@@ -0,0 +1,262 @@
+/// Heap debugging for `kmalloc` / `kfree`, built with the `heap-debug`
+/// feature (`FEATURES=heap-debug` for make).
+///
+/// Every block gets a header and redzones inside a bigger kmalloc object:
+///
+///   [header | left redzone | block (size bytes) | right redzone to the end]
+///
+/// The header records the state of the block and the stack traces of its
+/// allocation and of its last free. Redzones are filled with
+/// `REDZONE_BYTE` and new blocks with `ALLOC_POISON`, so reads of
+/// uninitialized memory stand out. `kfree` checks both redzones, fills
+/// everything past the header with `FREE_POISON` and keeps the object in a
+/// quarantine of `QUARANTINE_LEN` objects before the slab gets it back.
+///
+/// The poison is checked again when an object leaves the quarantine and
+/// when `kmalloc` hands it out, so writes through a dangling pointer are
+/// caught. The kmalloc caches have a constructor that poisons their
+/// objects, which makes fresh slabs pass that check. Each problem is
+/// logged at error level with the traces of the block, and counted.
+///
+/// Only `kmalloc` blocks are covered: objects of named caches and large
+/// `alloc` requests, which take whole frames, are left alone.
+
+use crate::cpu::irq;
+use crate::klib::{memory, stack};
+use crate::mm::slab;
+use crate::pr_err;
+
+/// Whether `kmalloc` and `kfree` go through this layer.
+pub const ENABLED: bool = cfg!(feature = "heap-debug");
+/// Alignment of the blocks: the offset of a block in its object.
+pub const ALIGN: usize = PREFIX;
+
+const TRACE_DEPTH: usize = 5;
+const REDZONE: usize = 16;
+const HEADER_SIZE: usize = core::mem::size_of::<Header>();
+/// Offset of the block in the object.
+const PREFIX: usize = HEADER_SIZE + REDZONE;
+const QUARANTINE_LEN: usize = 128;
+
+const REDZONE_BYTE: u8 = 0xCC;
+const ALLOC_POISON: u8 = 0x5A;
+const FREE_POISON: u8 = 0x6B;
+
+/// Header states.
+const LIVE: u32 = 0xA110_CA7E;
+const QUARANTINED: u32 = 0xDEAD_0B1E;
+const RELEASED: u32 = 0xF4EE_B10C;
+
+#[repr(C)]
+struct Header {
+    state: u32,
+    /// Size requested, 0 for an object never allocated.
+    size: u32,
+    /// Return addresses, innermost first, 0 past the end.
+    alloc_trace: [u32; TRACE_DEPTH],
+    free_trace: [u32; TRACE_DEPTH],
+}
+
+const _: () = assert!(PREFIX.is_power_of_two());
+
+/// Counters, for `slabinfo`.
+#[derive(Copy, Clone)]
+pub struct Stats {
+    pub quarantined: usize,
+    pub errors: usize,
+}
+
+static mut QUARANTINE: [*mut u8; QUARANTINE_LEN] = [core::ptr::null_mut(); QUARANTINE_LEN];
+static mut QUARANTINE_NEXT: usize = 0;
+static mut QUARANTINED_COUNT: usize = 0;
+static mut ERRORS: usize = 0;
+
+fn header(object: *mut u8) -> &'static mut Header {
+    unsafe { &mut *(object as *mut Header) }
+}
+
+/// Size of the kmalloc object holding a `size`-byte block.
+fn object_size(size: usize) -> usize {
+    (PREFIX + size + REDZONE).next_power_of_two()
+}
+
+/// Offset of the first of `len` bytes from `start` that is not `byte`.
+fn first_mismatch(start: *const u8, len: usize, byte: u8) -> Option<usize> {
+    (0..len).find(|&i| unsafe { *start.add(i) } != byte)
+}
+
+// ──────────────────────────────────────────────
+//  Reports
+// ──────────────────────────────────────────────
+
+fn print_trace(label: &str, trace: &[u32]) {
+    if trace.first().copied().unwrap_or(0) == 0 {
+        return;
+    }
+    pr_err!("  {}:", label);
+    for &addr in trace.iter().take_while(|&&addr| addr != 0) {
+        let mut name = [0; stack::SYMBOL_MAX];
+        match stack::symbolize(addr, &mut name) {
+            Some((function, offset)) => pr_err!("    {:#010x} {}+{:#x}", addr, function, offset),
+            None => pr_err!("    {:#010x}", addr),
+        }
+    }
+}
+
+/// Logs what is known of the block in `object`, after the line that
+/// describes the problem, and counts the error.
+fn report_block(object: *mut u8) {
+    let header = header(object);
+    if matches!(header.state, LIVE | QUARANTINED | RELEASED) && header.alloc_trace[0] != 0 {
+        pr_err!("  block {:#010x}, {} bytes", object as usize + PREFIX, header.size);
+        print_trace("allocated at", &header.alloc_trace);
+        print_trace("freed at", &header.free_trace);
+    }
+    unsafe { ERRORS += 1; }
+}
+
+/// Checks that nothing past the header of a free object was written.
+fn check_poison(object: *mut u8, object_size: usize) {
+    let header = header(object);
+    if header.state != QUARANTINED && header.state != RELEASED {
+        pr_err!("heap-debug: header of free object {:#010x} overwritten", object as usize);
+        report_block(object);
+    } else if let Some(offset) = first_mismatch(unsafe { object.add(HEADER_SIZE) }, object_size - HEADER_SIZE, FREE_POISON) {
+        let addr = object as usize + HEADER_SIZE + offset;
+        pr_err!("heap-debug: use after free: {:#010x} written after the block was freed", addr);
+        report_block(object);
+    }
+}
+
+/// Checks the redzones of a live block.
+fn check_redzones(object: *mut u8, object_size: usize) {
+    let header = header(object);
+    let block = object as usize + PREFIX;
+    if let Some(offset) = first_mismatch(unsafe { object.add(HEADER_SIZE) }, REDZONE, REDZONE_BYTE) {
+        pr_err!("heap-debug: redzone overwritten {} bytes before block {:#010x}", REDZONE - offset, block);
+        report_block(object);
+    }
+    let end = PREFIX + header.size as usize;
+    if let Some(offset) = first_mismatch(unsafe { object.add(end) }, object_size - end, REDZONE_BYTE) {
+        pr_err!("heap-debug: redzone overwritten {} bytes after block {:#010x}", offset, block);
+        report_block(object);
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Allocation
+// ──────────────────────────────────────────────
+
+/// Constructor of the kmalloc cache of `size`-byte objects: poisons them
+/// as if freed. `None` for caches too small to hold a block.
+pub fn constructor(size: usize) -> Option<fn(*mut u8)> {
+    match size {
+        128 => Some(prepare::<128>),
+        256 => Some(prepare::<256>),
+        512 => Some(prepare::<512>),
+        1024 => Some(prepare::<1024>),
+        2048 => Some(prepare::<2048>),
+        4096 => Some(prepare::<4096>),
+        _ => None,
+    }
+}
+
+fn prepare<const SIZE: usize>(object: *mut u8) {
+    let header = header(object);
+    header.state = RELEASED;
+    header.size = 0;
+    header.alloc_trace = [0; TRACE_DEPTH];
+    header.free_trace = [0; TRACE_DEPTH];
+    unsafe { memory::memset(object.add(HEADER_SIZE), FREE_POISON as i32, SIZE - HEADER_SIZE); }
+}
+
+/// `kmalloc` with redzones: checks that the object was left alone while
+/// free and records the caller.
+#[inline(never)]
+pub fn alloc(size: usize) -> *mut u8 {
+    let object_size = object_size(size);
+    let object = slab::kmalloc_object(object_size);
+    if object.is_null() {
+        return object;
+    }
+    check_poison(object, object_size);
+
+    let header = header(object);
+    header.state = LIVE;
+    header.size = size as u32;
+    header.alloc_trace = [0; TRACE_DEPTH];
+    header.free_trace = [0; TRACE_DEPTH];
+    // From the caller of `alloc`
+    stack::backtrace(&mut header.alloc_trace, 1);
+    unsafe {
+        memory::memset(object.add(HEADER_SIZE), REDZONE_BYTE as i32, REDZONE);
+        memory::memset(object.add(PREFIX), ALLOC_POISON as i32, size);
+        memory::memset(object.add(PREFIX + size), REDZONE_BYTE as i32, object_size - PREFIX - size);
+        object.add(PREFIX)
+    }
+}
+
+/// `kfree` with checks: refuses double and invalid frees, checks the
+/// redzones, then poisons the object and quarantines it.
+#[inline(never)]
+pub fn free(ptr: *mut u8) {
+    if ptr.is_null() {
+        return;
+    }
+    let Some((object, object_size)) = slab::kmalloc_object_of(ptr) else {
+        // An object of a named cache, or not an object at all
+        slab::kfree_object(ptr);
+        return;
+    };
+    irq::without(|| {
+        let header = header(object);
+        if ptr as usize != object as usize + PREFIX || header.state != LIVE {
+            match header.state {
+                LIVE => pr_err!("heap-debug: invalid free of {:#010x}, inside a block", ptr as usize),
+                QUARANTINED | RELEASED => pr_err!("heap-debug: double free of {:#010x}", ptr as usize),
+                _ => pr_err!("heap-debug: invalid free of {:#010x}, not an allocated block", ptr as usize),
+            }
+            let mut trace = [0; TRACE_DEPTH];
+            stack::backtrace(&mut trace, 1);
+            print_trace("this free at", &trace);
+            report_block(object);
+            return;
+        }
+        check_redzones(object, object_size);
+
+        header.state = QUARANTINED;
+        stack::backtrace(&mut header.free_trace, 1);
+        unsafe { memory::memset(object.add(HEADER_SIZE), FREE_POISON as i32, object_size - HEADER_SIZE); }
+        quarantine(object);
+    });
+}
+
+/// Adds a freed object to the quarantine. When full, the oldest object is
+/// checked and goes back to its slab.
+fn quarantine(object: *mut u8) {
+    unsafe {
+        let slot = (*core::ptr::addr_of_mut!(QUARANTINE)).get_unchecked_mut(QUARANTINE_NEXT);
+        if QUARANTINED_COUNT == QUARANTINE_LEN {
+            let oldest = *slot;
+            let (_, object_size) = slab::kmalloc_object_of(oldest).unwrap_or((oldest, HEADER_SIZE));
+            check_poison(oldest, object_size);
+            header(oldest).state = RELEASED;
+            slab::kfree_object(oldest);
+        } else {
+            QUARANTINED_COUNT += 1;
+        }
+        *slot = object;
+        QUARANTINE_NEXT = (QUARANTINE_NEXT + 1) % QUARANTINE_LEN;
+    }
+}
+
+/// Requested size of the live block `ptr` points to.
+pub fn size_of(ptr: *mut u8) -> Option<usize> {
+    let (object, _) = slab::kmalloc_object_of(ptr)?;
+    let header = header(object);
+    (ptr as usize == object as usize + PREFIX && header.state == LIVE).then_some(header.size as usize)
+}
+
+pub fn stats() -> Stats {
+    unsafe { Stats { quarantined: QUARANTINED_COUNT, errors: ERRORS } }
+}
@@ -0,0 +1,157 @@
+/// vmalloc: virtually contiguous kernel allocations.
+///
+/// `vmalloc` takes single frames wherever the frame allocator finds them
+/// and maps them at consecutive addresses in `VMALLOC_START..VMALLOC_END`,
+/// above the identity map. Every area is followed by an unmapped guard
+/// page, so running off its end faults instead of corrupting the next
+/// area.
+///
+/// Areas are described by `Area` objects from the `vmap_area` slab cache,
+/// kept in a list sorted by address; a new area takes the first gap big
+/// enough. Each remembers the call site that allocated it, shown as its
+/// owner by `vmallocinfo`.
+
+use core::panic::Location;
+
+use crate::cpu::irq;
+use crate::mm::frame::{self, PAGE_SIZE};
+use crate::mm::paging;
+use crate::mm::slab::{self, CacheId};
+use crate::pr_err;
+
+pub const VMALLOC_START: usize = 0xE000_0000;
+pub const VMALLOC_END: usize = 0xF000_0000;
+
+struct Area {
+    start: usize,
+    /// Mapped pages, not counting the guard page.
+    pages: usize,
+    owner: &'static Location<'static>,
+    next: *mut Area,
+}
+
+/// Description of an area, for `vmallocinfo`.
+pub struct AreaInfo {
+    pub start: usize,
+    pub pages: usize,
+    /// Source location of the `vmalloc` call.
+    pub owner: &'static Location<'static>,
+}
+
+static mut AREAS: *mut Area = core::ptr::null_mut();
+static mut AREA_CACHE: Option<CacheId> = None;
+
+/// Creates the cache of area descriptors. Call once, after paging is on.
+pub fn init() {
+    let cache = slab::create_cache("vmap_area", core::mem::size_of::<Area>(), core::mem::align_of::<Area>(), None);
+    unsafe { AREA_CACHE = cache; }
+}
+
+/// Unmaps the first `pages` pages from `start` and frees their frames.
+fn unmap_range(start: usize, pages: usize) {
+    for page in 0..pages {
+        if let Some(phys) = paging::unmap(start + page * PAGE_SIZE) {
+            frame::free(phys);
+        }
+    }
+}
+
+/// Maps `pages` fresh frames from `start`. On failure, nothing stays mapped.
+fn map_range(start: usize, pages: usize) -> bool {
+    for page in 0..pages {
+        let virt = start + page * PAGE_SIZE;
+        let Some(phys) = frame::alloc() else {
+            unmap_range(start, page);
+            return false;
+        };
+        if !paging::map(virt, phys) {
+            frame::free(phys);
+            unmap_range(start, page);
+            return false;
+        }
+    }
+    true
+}
+
+/// Allocates `size` bytes of virtually contiguous memory, page-aligned.
+/// Returns null when out of memory or address space, when paging is off,
+/// or for a size of 0.
+#[track_caller]
+pub fn vmalloc(size: usize) -> *mut u8 {
+    let owner = Location::caller();
+    if size == 0 {
+        return core::ptr::null_mut();
+    }
+    let pages = size.div_ceil(PAGE_SIZE);
+    // The area and its guard page
+    let span = (pages + 1) * PAGE_SIZE;
+    irq::without(|| unsafe {
+        let Some(cache) = AREA_CACHE else {
+            return core::ptr::null_mut();
+        };
+
+        // First fit: the gap before `next`, or after the last area
+        let mut prev: *mut Area = core::ptr::null_mut();
+        let mut next = AREAS;
+        let mut start = VMALLOC_START;
+        while let Some(area) = next.as_ref() {
+            if area.start - start >= span {
+                break;
+            }
+            start = area.start + (area.pages + 1) * PAGE_SIZE;
+            prev = next;
+            next = area.next;
+        }
+        if VMALLOC_END - start < span {
+            return core::ptr::null_mut();
+        }
+
+        let area = slab::cache_alloc(cache) as *mut Area;
+        if area.is_null() {
+            return core::ptr::null_mut();
+        }
+        if !map_range(start, pages) {
+            slab::cache_free(cache, area as *mut u8);
+            return core::ptr::null_mut();
+        }
+        area.write(Area { start, pages, owner, next });
+        match prev.as_mut() {
+            Some(prev) => prev.next = area,
+            None => AREAS = area,
+        }
+        start as *mut u8
+    })
+}
+
+/// Frees an area from `vmalloc`: unmaps it and releases its frames.
+/// Null is ignored.
+pub fn vfree(ptr: *mut u8) {
+    if ptr.is_null() {
+        return;
+    }
+    irq::without(|| unsafe {
+        let Some(cache) = AREA_CACHE else {
+            return;
+        };
+        let mut link = core::ptr::addr_of_mut!(AREAS);
+        while let Some(area) = (*link).as_mut() {
+            if area.start == ptr as usize {
+                *link = area.next;
+                unmap_range(area.start, area.pages);
+                slab::cache_free(cache, area as *mut Area as *mut u8);
+                return;
+            }
+            link = &mut area.next;
+        }
+        pr_err!("vmalloc: vfree of {:#010x}, not a vmalloc area", ptr as usize);
+    });
+}
+
+/// Calls `f` for every area, in address order.
+pub fn for_each_area(mut f: impl FnMut(AreaInfo)) {
+    let mut next = unsafe { AREAS };
+    while let Some(area) = unsafe { next.as_ref() } {
+        f(AreaInfo { start: area.start, pages: area.pages, owner: area.owner });
+        next = area.next;
+    }
+}
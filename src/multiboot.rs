@@ -0,0 +1,263 @@
+/// Multiboot information handed over by the bootloader.
+///
+/// `boot.s` passes the magic value (EAX) and the physical address of the
+/// information structure (EBX) to `rust_main`, which hands them to `init`.
+/// Paging is off, so the structure and the modules it lists are read in
+/// place, where GRUB loaded them.
+
+use crate::klib::elf::{self, Symbols};
+
+/// Value of EAX when the kernel was loaded by a Multiboot bootloader.
+pub const BOOTLOADER_MAGIC: u32 = 0x2BAD_B002;
+
+/// `flags` bit: `mem_lower` / `mem_upper` are valid.
+const FLAG_MEM: u32 = 1 << 0;
+/// `flags` bit: `mods_count` / `mods_addr` are valid.
+const FLAG_MODS: u32 = 1 << 3;
+/// `flags` bit: `syms` holds the kernel's ELF section header table.
+const FLAG_ELF_SECTIONS: u32 = 1 << 5;
+/// `flags` bit: `mmap_length` / `mmap_addr` are valid.
+const FLAG_MMAP: u32 = 1 << 6;
+
+/// Start of the Multiboot information structure (fields we use).
+#[repr(C)]
+struct Info {
+    flags: u32,
+    mem_lower: u32,
+    mem_upper: u32,
+    boot_device: u32,
+    cmdline: u32,
+    mods_count: u32,
+    mods_addr: u32,
+    /// ELF section headers: count, entry size, address, string table index.
+    syms: [u32; 4],
+    mmap_length: u32,
+    mmap_addr: u32,
+}
+
+/// One entry of the BIOS memory map. `size` does not count itself.
+#[repr(C, packed)]
+struct RawMemoryEntry {
+    size: u32,
+    base: u64,
+    len: u64,
+    kind: u32,
+}
+
+/// `kind` of usable RAM.
+const MEMORY_AVAILABLE: u32 = 1;
+
+/// One entry of the module list.
+#[repr(C)]
+struct RawModule {
+    mod_start: u32,
+    mod_end: u32,
+    string: u32,
+    reserved: u32,
+}
+
+/// A file loaded by GRUB's `module` command.
+pub struct Module {
+    pub data: &'static [u8],
+    /// Arguments given after the file on the `module` line.
+    pub cmdline: &'static [u8],
+}
+
+static mut INFO: *const Info = core::ptr::null();
+
+/// Records the information structure. Returns `false` if the kernel was
+/// not started by a Multiboot bootloader.
+pub fn init(magic: u32, info: u32) -> bool {
+    if magic != BOOTLOADER_MAGIC || info == 0 {
+        return false;
+    }
+    unsafe { INFO = info as *const Info; }
+    true
+}
+
+/// Reads a NUL-terminated string.
+fn c_str(addr: u32) -> &'static [u8] {
+    if addr == 0 {
+        return &[];
+    }
+    let ptr = addr as *const u8;
+    let mut len = 0;
+    while unsafe { *ptr.add(len) } != 0 {
+        len += 1;
+    }
+    unsafe { core::slice::from_raw_parts(ptr, len) }
+}
+
+/// Number of modules loaded.
+pub fn module_count() -> usize {
+    unsafe {
+        if INFO.is_null() || (*INFO).flags & FLAG_MODS == 0 {
+            return 0;
+        }
+        (*INFO).mods_count as usize
+    }
+}
+
+/// Returns module `idx`.
+pub fn module(idx: usize) -> Option<Module> {
+    if idx >= module_count() {
+        return None;
+    }
+    unsafe {
+        let raw = &*((*INFO).mods_addr as *const RawModule).add(idx);
+        let len = raw.mod_end.saturating_sub(raw.mod_start) as usize;
+        Some(Module {
+            data: core::slice::from_raw_parts(raw.mod_start as *const u8, len),
+            cmdline: c_str(raw.string),
+        })
+    }
+}
+
+/// Name of a module: the last path component of the last word of its
+/// command line (`module /boot/init.sh init.sh` → `init.sh`).
+pub fn module_name(module: &Module) -> &'static [u8] {
+    let line = module.cmdline;
+    let mut end = line.len();
+    while end > 0 && unsafe { *line.get_unchecked(end - 1) } == b' ' {
+        end -= 1;
+    }
+    let mut start = end;
+    while start > 0 && !matches!(unsafe { *line.get_unchecked(start - 1) }, b' ' | b'/') {
+        start -= 1;
+    }
+    unsafe { line.get_unchecked(start..end) }
+}
+
+/// Returns the content of the module named `name`.
+pub fn find_module(name: &[u8]) -> Option<&'static [u8]> {
+    (0..module_count()).filter_map(module).find_map(|module| {
+        let found = module_name(&module);
+        let same = found.len() == name.len() && found.iter().zip(name.iter()).all(|(a, b)| a == b);
+        if same { Some(module.data) } else { None }
+    })
+}
+
+// ──────────────────────────────────────────────
+//  Memory map
+// ──────────────────────────────────────────────
+
+/// A range of physical memory reported by the bootloader.
+#[derive(Copy, Clone)]
+pub struct MemoryRegion {
+    pub base: u64,
+    pub len: u64,
+    /// Usable RAM, as opposed to reserved, ACPI or broken memory.
+    pub available: bool,
+}
+
+/// Iterator over the memory map.
+pub struct MemoryMap {
+    pos: u32,
+    end: u32,
+}
+
+impl Iterator for MemoryMap {
+    type Item = MemoryRegion;
+
+    fn next(&mut self) -> Option<MemoryRegion> {
+        if self.pos >= self.end {
+            return None;
+        }
+        let entry = unsafe { core::ptr::read_unaligned(self.pos as *const RawMemoryEntry) };
+        self.pos += entry.size + 4;
+        Some(MemoryRegion { base: entry.base, len: entry.len, available: entry.kind == MEMORY_AVAILABLE })
+    }
+}
+
+/// The BIOS memory map, empty if the bootloader passed none.
+pub fn memory_map() -> MemoryMap {
+    unsafe {
+        if INFO.is_null() || (*INFO).flags & FLAG_MMAP == 0 {
+            return MemoryMap { pos: 0, end: 0 };
+        }
+        MemoryMap { pos: (*INFO).mmap_addr, end: (*INFO).mmap_addr + (*INFO).mmap_length }
+    }
+}
+
+/// KiB of memory from 1 MiB up to the first hole, the fallback when
+/// there is no memory map.
+pub fn upper_memory_kib() -> Option<u32> {
+    unsafe {
+        if INFO.is_null() || (*INFO).flags & FLAG_MEM == 0 {
+            return None;
+        }
+        Some((*INFO).mem_upper)
+    }
+}
+
+/// Calls `reserve(start, end)` for every range of memory the boot
+/// information lives in: the structure itself, the memory map, the module
+/// list, the modules and their command lines, and the kernel's ELF section
+/// headers and sections.
+pub fn for_each_boot_range(mut reserve: impl FnMut(usize, usize)) {
+    unsafe {
+        if INFO.is_null() {
+            return;
+        }
+        let info = INFO as usize;
+        reserve(info, info + core::mem::size_of::<Info>());
+        if (*INFO).flags & FLAG_MMAP != 0 {
+            let mmap = (*INFO).mmap_addr as usize;
+            reserve(mmap, mmap + (*INFO).mmap_length as usize);
+        }
+        if module_count() > 0 {
+            let list = (*INFO).mods_addr as usize;
+            reserve(list, list + module_count() * core::mem::size_of::<RawModule>());
+        }
+        for module in (0..module_count()).filter_map(module) {
+            let data = module.data.as_ptr() as usize;
+            reserve(data, data + module.data.len());
+            if !module.cmdline.is_empty() {
+                let cmdline = module.cmdline.as_ptr() as usize;
+                // Include the NUL
+                reserve(cmdline, cmdline + module.cmdline.len() + 1);
+            }
+        }
+        let headers = section_headers();
+        if !headers.is_empty() {
+            let table = headers.as_ptr() as usize;
+            reserve(table, table + headers.len());
+        }
+        for section in elf::sections(headers).filter(|section| section.addr != 0) {
+            reserve(section.addr as usize, section.addr as usize + section.size as usize);
+        }
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Kernel symbols
+// ──────────────────────────────────────────────
+
+/// The kernel's ELF section header table, empty if the bootloader passed
+/// none.
+fn section_headers() -> &'static [u8] {
+    unsafe {
+        if INFO.is_null() || (*INFO).flags & FLAG_ELF_SECTIONS == 0 {
+            return &[];
+        }
+        let [count, size, addr, _] = (*INFO).syms;
+        if size as usize != elf::SECTION_HEADER_SIZE || addr == 0 {
+            return &[];
+        }
+        core::slice::from_raw_parts(addr as *const u8, count as usize * elf::SECTION_HEADER_SIZE)
+    }
+}
+
+/// The kernel's symbol table, if the bootloader loaded it.
+pub fn kernel_symbols() -> Option<Symbols<'static>> {
+    let (symtab, strtab) = elf::symbol_table(section_headers())?;
+    if symtab.addr == 0 || strtab.addr == 0 {
+        return None;
+    }
+    unsafe {
+        Some(Symbols::new(
+            core::slice::from_raw_parts(symtab.addr as *const u8, symtab.size as usize),
+            core::slice::from_raw_parts(strtab.addr as *const u8, strtab.size as usize),
+        ))
+    }
+}
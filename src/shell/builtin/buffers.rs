@@ -0,0 +1,39 @@
+use crate::io::stream;
+use crate::shell::completion::Completions;
+use crate::shell::getopt::{Args, OptSpec};
+use crate::shell::shell::{ExitStatus, EXIT_FAILURE, EXIT_SUCCESS};
+use crate::{eprintln, print, println};
+
+pub static OPTIONS: &[OptSpec] = &[
+    OptSpec { short: b'd', long: "--delete", value: Some("NAME"), help: "Delete buffer NAME" },
+];
+
+pub fn buffers(args: &Args) -> ExitStatus {
+    if let Some(name) = args.value(b'd') {
+        if !stream::remove_buffer(name) {
+            eprintln!("buffers: {}: no such buffer", name);
+            return EXIT_FAILURE;
+        }
+        return EXIT_SUCCESS;
+    }
+
+    for idx in 0..stream::BUFFER_COUNT {
+        if let Some((name, buffer)) = stream::buffer_at(idx) {
+            print!("{}: {} bytes", name, buffer.as_bytes().len());
+            if buffer.is_truncated() {
+                print!(" (truncated)");
+            }
+            println!();
+        }
+    }
+    EXIT_SUCCESS
+}
+
+/// Completes the names of existing buffers.
+pub fn complete(_: usize, out: &mut Completions) {
+    for idx in 0..stream::BUFFER_COUNT {
+        if let Some((name, _)) = stream::buffer_at(idx) {
+            out.offer(name);
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use crate::shell::builtin::read_input;
+use crate::shell::getopt::Args;
+use crate::shell::shell::{ExitStatus, EXIT_SUCCESS};
+use crate::print;
+
+pub fn cat(args: &Args) -> ExitStatus {
+    let operands = args.operands();
+    if operands.is_empty() {
+        match read_input(args.name(), None) {
+            Ok(text) => print!("{}", text),
+            Err(status) => return status,
+        }
+        return EXIT_SUCCESS;
+    }
+
+    let mut status = EXIT_SUCCESS;
+    for &name in operands {
+        match read_input(args.name(), Some(name)) {
+            Ok(text) => print!("{}", text),
+            Err(err) => status = err,
+        }
+    }
+    status
+}
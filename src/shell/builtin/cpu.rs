@@ -0,0 +1,149 @@
+/// `cpuinfo` and `regs`: CPU identification and register dumps.
+
+use crate::cpu::{cpuid, regs};
+use crate::shell::getopt::Args;
+use crate::shell::shell::{ExitStatus, EXIT_FAILURE, EXIT_SUCCESS};
+use crate::{eprintln, print, println};
+
+/// Column at which `cpuinfo` wraps the feature list.
+const WRAP_COLUMN: usize = 72;
+
+// ──────────────────────────────────────────────
+//  cpuinfo
+// ──────────────────────────────────────────────
+
+pub fn cpuinfo(_args: &Args) -> ExitStatus {
+    let Some(info) = cpuid::info() else {
+        eprintln!("cpuinfo: CPUID not supported");
+        return EXIT_FAILURE;
+    };
+
+    println!("vendor:    {}", info.vendor());
+    if !info.brand().is_empty() {
+        println!("brand:     {}", info.brand());
+    }
+    println!("family:    {} (model {}, stepping {})", info.family, info.model, info.stepping);
+    println!("leaves:    max {:#x}, extended {:#x}", info.max_leaf, info.max_ext_leaf);
+
+    print!("features: ");
+    let mut column = 10;
+    for feature in cpuid::FEATURES {
+        if !cpuid::has(feature) {
+            continue;
+        }
+        if column + 1 + feature.name.len() > WRAP_COLUMN {
+            print!("\n          ");
+            column = 10;
+        }
+        print!(" {}", feature.name);
+        column += 1 + feature.name.len();
+    }
+    println!();
+
+    print_cache_descriptors(info);
+    if info.is_vendor(b"GenuineIntel") && info.max_leaf >= 4 {
+        print_intel_caches();
+    } else if info.max_ext_leaf >= 0x8000_0006 {
+        print_amd_caches();
+    }
+    EXIT_SUCCESS
+}
+
+/// Raw one-byte cache/TLB descriptors from leaf 2.
+fn print_cache_descriptors(info: &cpuid::CpuInfo) {
+    if info.max_leaf < 2 {
+        return;
+    }
+    let r = cpuid::cpuid(2, 0);
+    print!("descriptors:");
+    for (i, reg) in [r.eax, r.ebx, r.ecx, r.edx].iter().enumerate() {
+        // Bit 31 set: the register holds no descriptors
+        if reg & (1 << 31) != 0 {
+            continue;
+        }
+        for (j, &byte) in reg.to_le_bytes().iter().enumerate() {
+            // The low byte of EAX is the iteration count, not a descriptor
+            if byte != 0 && !(i == 0 && j == 0) {
+                print!(" {:02x}", byte);
+            }
+        }
+    }
+    println!();
+}
+
+/// Deterministic cache parameters (leaf 4), one line per cache.
+fn print_intel_caches() {
+    for subleaf in 0..8 {
+        let r = cpuid::cpuid(4, subleaf);
+        let kind = match r.eax & 0x1F {
+            0 => break,
+            1 => "data",
+            2 => "instruction",
+            3 => "unified",
+            _ => "unknown",
+        };
+        let level = (r.eax >> 5) & 0x7;
+        let ways = ((r.ebx >> 22) & 0x3FF) + 1;
+        let partitions = ((r.ebx >> 12) & 0x3FF) + 1;
+        let line = (r.ebx & 0xFFF) + 1;
+        let sets = r.ecx + 1;
+        let size = ways * partitions * line * sets;
+        println!("cache:     L{} {} {} KiB, {}-way, {}-byte lines", level, kind, size / 1024, ways, line);
+    }
+}
+
+/// L1 and L2 cache sizes from the AMD extended leaves.
+fn print_amd_caches() {
+    let l1 = cpuid::cpuid(0x8000_0005, 0);
+    let l2 = cpuid::cpuid(0x8000_0006, 0);
+    println!("cache:     L1 data {} KiB, {}-byte lines", l1.ecx >> 24, l1.ecx & 0xFF);
+    println!("cache:     L1 instruction {} KiB, {}-byte lines", l1.edx >> 24, l1.edx & 0xFF);
+    println!("cache:     L2 unified {} KiB, {}-byte lines", l2.ecx >> 16, l2.ecx & 0xFF);
+}
+
+// ──────────────────────────────────────────────
+//  regs
+// ──────────────────────────────────────────────
+
+/// Prints the names of the set bits of `value`.
+fn print_bits(value: u32, names: &[(u32, &str)]) {
+    for &(bit, name) in names {
+        if value & (1 << bit) != 0 {
+            print!(" {}", name);
+        }
+    }
+}
+
+pub fn regs(_args: &Args) -> ExitStatus {
+    let cr0 = regs::read_cr0();
+    let cr4 = regs::read_cr4();
+    let eflags = regs::read_eflags();
+
+    print!("CR0     {:#010x}  ", cr0);
+    print_bits(cr0, regs::CR0_BITS);
+    println!();
+    println!("CR2     {:#010x}", regs::read_cr2());
+    println!("CR3     {:#010x}", regs::read_cr3());
+    print!("CR4     {:#010x}  ", cr4);
+    print_bits(cr4, regs::CR4_BITS);
+    println!();
+    print!("EFLAGS  {:#010x}  ", eflags);
+    print_bits(eflags, regs::EFLAGS_BITS);
+    println!(" IOPL={}", (eflags >> 12) & 0x3);
+
+    let [cs, ds, es, fs, gs, ss] = regs::read_selectors();
+    println!(
+        "CS={:#06x}  DS={:#06x}  ES={:#06x}  FS={:#06x}  GS={:#06x}  SS={:#06x}",
+        cs, ds, es, fs, gs, ss
+    );
+
+    if cpuid::has(&cpuid::TSC) {
+        println!("TSC     {}", regs::read_tsc());
+    }
+
+    let gdtr = regs::sgdt();
+    let idtr = regs::sidt();
+    println!("GDTR    base={:#010x}  limit={:#06x}  ({} entries)", gdtr.base, gdtr.limit, gdtr.entries());
+    println!("IDTR    base={:#010x}  limit={:#06x}  ({} entries)", idtr.base, idtr.limit, idtr.entries());
+    EXIT_SUCCESS
+}
@@ -1,14 +1,143 @@
-use crate::io::klog;
-use crate::println;
+use crate::drivers::timer;
+use crate::io::klog::{self, Level, Read, Reader, Record};
+use crate::klib::number;
+use crate::klib::time::DateTime;
+use crate::shell::getopt::{Args, OptSpec};
+use crate::shell::shell::{self, ExitStatus, EXIT_FAILURE, EXIT_INTERRUPTED, EXIT_SUCCESS, EXIT_USAGE};
+use crate::{eprintln, print, println};
 
-pub fn dmesg(argv: &'static [&'static [u8]]) {
-    // dmesg -c : dump and clear
-    let clear = argv.len() > 1 && unsafe { *argv.get_unchecked(1) } == b"-c";
+pub static OPTIONS: &[OptSpec] = &[
+    OptSpec { short: b'c', long: "--read-clear", value: None, help: "Clear the log after printing it" },
+    OptSpec { short: b'l', long: "--level", value: Some("LIST"), help: "Print only these levels (e.g. err,warn)" },
+    OptSpec { short: b'n', long: "--console-level", value: Some("LEVEL"), help: "Set the level of messages shown on screen" },
+    OptSpec { short: b'T', long: "--ctime", value: None, help: "Print human-readable timestamps" },
+    OptSpec { short: b'w', long: "--follow", value: None, help: "Wait for new messages until Ctrl+C" },
+    OptSpec { short: b'P', long: "--previous", value: None, help: "Print the log of the previous boot" },
+];
 
-    klog::dump();
+/// Bit `n` set: print messages of level `n`.
+type LevelMask = u8;
 
-    if clear {
+const LEVEL_NAMES: &str = "emerg, alert, crit, err, warn, notice, info, debug";
+
+/// `err,warn` → mask of those levels.
+fn parse_levels(list: &[u8]) -> Option<LevelMask> {
+    let mut mask = 0;
+    for name in list.split(|&c| c == b',') {
+        mask |= 1 << Level::from_name(name)? as u8;
+    }
+    Some(mask)
+}
+
+/// A number from 1 (emergencies only) to 8 (everything), like the
+/// console log level itself, or a level name meaning that level and
+/// anything more severe.
+fn parse_console_level(arg: &[u8]) -> Option<u8> {
+    if let Some(level) = Level::from_name(arg) {
+        return Some(level as u8 + 1);
+    }
+    number::parse_u32(arg).filter(|n| (1..=8).contains(n)).map(|n| n as u8)
+}
+
+fn print_record(record: &Record, mask: LevelMask, boot_time: Option<u32>) {
+    if mask & (1 << record.level as u8) == 0 {
+        return;
+    }
+    let (secs, micros) = number::div_rem_u64(record.timestamp, 1_000_000);
+    match boot_time {
+        Some(boot) => {
+            let time = DateTime::from_unix(boot.wrapping_add(secs as u32));
+            print!("[{}] ", &time);
+        }
+        None => print!("[{:5}.{:06}] ", secs, micros),
+    }
+    println!("{}", record.text);
+}
+
+/// Prints the records `reader` has not seen yet.
+fn print_new(reader: &mut Reader, mask: LevelMask, boot_time: Option<u32>) {
+    let mut buf = [0u8; klog::LINE_MAX];
+    while let Some(read) = reader.read(&mut buf) {
+        match read {
+            Read::Record(record) => print_record(&record, mask, boot_time),
+            Read::Dropped(count) => println!("[... {} records dropped ...]", count),
+        }
+    }
+}
+
+/// `dmesg --previous`: the log kept across the last warm reboot.
+fn previous(args: &Args, mask: LevelMask) -> ExitStatus {
+    if args.has(b'T') || args.has(b'w') {
+        eprintln!("dmesg: --previous cannot be combined with -T or -w");
+        return EXIT_USAGE;
+    }
+    let Some(mut reader) = Reader::previous() else {
+        eprintln!("dmesg: no log from a previous boot");
+        return EXIT_FAILURE;
+    };
+    print_new(&mut reader, mask, None);
+    if args.has(b'c') {
+        klog::forget_previous();
+        println!("[previous klog discarded]");
+    }
+    EXIT_SUCCESS
+}
+
+pub fn dmesg(args: &Args) -> ExitStatus {
+    if args.value(b'n').is_some() && [b'c', b'l', b'T', b'w', b'P'].iter().any(|&opt| args.has(opt)) {
+        eprintln!("dmesg: --console-level cannot be combined with other options");
+        return EXIT_USAGE;
+    }
+    if args.has(b'c') && args.has(b'w') {
+        eprintln!("dmesg: --read-clear cannot be combined with -w");
+        return EXIT_USAGE;
+    }
+    if let Some(arg) = args.value(b'n') {
+        let Some(level) = parse_console_level(arg) else {
+            eprintln!("dmesg: invalid level '{}' (1-8 or {})", arg, LEVEL_NAMES);
+            return EXIT_USAGE;
+        };
+        klog::set_console_level(level);
+        return EXIT_SUCCESS;
+    }
+
+    let mask = match args.value(b'l') {
+        None => LevelMask::MAX,
+        Some(list) => match parse_levels(list) {
+            Some(mask) => mask,
+            None => {
+                eprintln!("dmesg: unknown level in '{}' (levels: {})", list, LEVEL_NAMES);
+                return EXIT_USAGE;
+            }
+        },
+    };
+    let boot_time = match (args.has(b'T'), timer::boot_time()) {
+        (false, _) => None,
+        (true, Some(boot)) => Some(boot),
+        (true, None) => {
+            eprintln!("dmesg: real-time clock unavailable, cannot print wall-clock times");
+            return EXIT_FAILURE;
+        }
+    };
+
+    if args.has(b'P') {
+        return previous(args, mask);
+    }
+
+    let mut reader = Reader::new();
+    print_new(&mut reader, mask, boot_time);
+
+    if args.has(b'w') {
+        // Until Ctrl+C
+        while !shell::interrupted() {
+            print_new(&mut reader, mask, boot_time);
+            core::hint::spin_loop();
+        }
+        return EXIT_INTERRUPTED;
+    }
+    if args.has(b'c') {
         klog::clear();
         println!("[klog cleared]");
     }
+    EXIT_SUCCESS
 }
@@ -1,22 +1,22 @@
+use crate::shell::getopt::{Args, OptSpec};
+use crate::shell::shell::{ExitStatus, EXIT_SUCCESS};
 use crate::{print, println};
 
-pub fn echo(argv: &'static [&'static [u8]]) {
-    let mut start = 1;
-    let mut newline = true;
+pub static OPTIONS: &[OptSpec] = &[
+    OptSpec { short: b'n', long: "--no-newline", value: None, help: "Do not output the trailing newline" },
+];
 
-    if argv.len() > 1 && unsafe { *argv.get_unchecked(1) } == b"-n" {
-        start = 2;
-        newline = false;
-    }
-    let mut i = start;
-    while i < argv.len() {
-        if i > start {
+pub fn echo(args: &Args) -> ExitStatus {
+    let mut first = true;
+    for &arg in args.operands() {
+        if !first {
             print!(" ");
         }
-        print!("{}", unsafe { *argv.get_unchecked(i) });
-        i += 1;
+        print!("{}", arg);
+        first = false;
     }
-    if newline {
+    if !args.has(b'n') {
         println!();
     }
+    EXIT_SUCCESS
 }
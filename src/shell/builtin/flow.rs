@@ -0,0 +1,46 @@
+use crate::eprintln;
+use crate::shell::exec::{self, Flow};
+use crate::shell::getopt::Args;
+use crate::shell::shell::{last_status, ExitStatus, EXIT_FAILURE, EXIT_SUCCESS, EXIT_USAGE};
+
+pub fn break_loop(args: &Args) -> ExitStatus {
+    if !exec::in_loop() {
+        eprintln!("{}: only meaningful in a loop", args.name());
+        return EXIT_FAILURE;
+    }
+    exec::request(Flow::Break);
+    EXIT_SUCCESS
+}
+
+pub fn continue_loop(args: &Args) -> ExitStatus {
+    if !exec::in_loop() {
+        eprintln!("{}: only meaningful in a loop", args.name());
+        return EXIT_FAILURE;
+    }
+    exec::request(Flow::Continue);
+    EXIT_SUCCESS
+}
+
+/// `return [STATUS]`: leaves with STATUS, or with `$?` if none is given.
+pub fn return_call(args: &Args) -> ExitStatus {
+    if !exec::in_call() {
+        eprintln!("return: can only be used in a function or sourced script");
+        return EXIT_FAILURE;
+    }
+    let status = match args.operands().first() {
+        None => last_status(),
+        Some(&value) => {
+            let mut n: u32 = 0;
+            for &c in value {
+                if !c.is_ascii_digit() {
+                    eprintln!("return: {}: numeric argument required", value);
+                    return EXIT_USAGE;
+                }
+                n = n.saturating_mul(10).saturating_add((c - b'0') as u32);
+            }
+            (n % 256) as ExitStatus
+        }
+    };
+    exec::request(Flow::Return);
+    status
+}
@@ -0,0 +1,73 @@
+use crate::shell::builtin::{buffers, lines, read_input};
+use crate::shell::completion::Completions;
+use crate::shell::getopt::{Args, OptSpec};
+use crate::shell::shell::{ExitStatus, EXIT_FAILURE, EXIT_SUCCESS, EXIT_USAGE};
+use crate::{eprintln, print, println};
+
+pub static OPTIONS: &[OptSpec] = &[
+    OptSpec { short: b'i', long: "--ignore-case", value: None, help: "Ignore case when matching" },
+    OptSpec { short: b'v', long: "--invert-match", value: None, help: "Print lines that do not match" },
+    OptSpec { short: b'c', long: "--count", value: None, help: "Print only the number of matching lines" },
+    OptSpec { short: b'n', long: "--line-number", value: None, help: "Prefix each line with its number" },
+];
+
+fn same(a: u8, b: u8, ignore_case: bool) -> bool {
+    if ignore_case {
+        a.eq_ignore_ascii_case(&b)
+    } else {
+        a == b
+    }
+}
+
+fn contains(line: &[u8], pattern: &[u8], ignore_case: bool) -> bool {
+    if pattern.len() > line.len() {
+        return false;
+    }
+    for start in 0..=line.len() - pattern.len() {
+        let window = unsafe { line.get_unchecked(start..) };
+        if pattern.iter().zip(window.iter()).all(|(&p, &c)| same(p, c, ignore_case)) {
+            return true;
+        }
+    }
+    false
+}
+
+pub fn grep(args: &Args) -> ExitStatus {
+    let operands = args.operands();
+    let Some(&pattern) = operands.first() else {
+        eprintln!("grep: missing pattern");
+        return EXIT_USAGE;
+    };
+    let text = match read_input(args.name(), operands.get(1).copied()) {
+        Ok(text) => text,
+        Err(status) => return status,
+    };
+
+    let ignore_case = args.has(b'i');
+    let invert = args.has(b'v');
+    let mut matches = 0;
+    for (i, line) in lines(text).enumerate() {
+        if contains(line, pattern, ignore_case) == invert {
+            continue;
+        }
+        matches += 1;
+        if !args.has(b'c') {
+            if args.has(b'n') {
+                print!("{}:", i + 1);
+            }
+            println!("{}", line);
+        }
+    }
+
+    if args.has(b'c') {
+        println!("{}", matches);
+    }
+    if matches > 0 { EXIT_SUCCESS } else { EXIT_FAILURE }
+}
+
+/// Completes buffer names after the pattern.
+pub fn complete(arg_index: usize, out: &mut Completions) {
+    if arg_index >= 2 {
+        buffers::complete(arg_index, out);
+    }
+}
@@ -0,0 +1,24 @@
+use crate::shell::builtin::{lines, parse_count, read_input};
+use crate::shell::getopt::{Args, OptSpec};
+use crate::shell::shell::{ExitStatus, EXIT_SUCCESS};
+use crate::println;
+
+pub static OPTIONS: &[OptSpec] = &[
+    OptSpec { short: b'n', long: "--lines", value: Some("COUNT"), help: "Print the first COUNT lines (default 10)" },
+];
+
+pub fn head(args: &Args) -> ExitStatus {
+    let count = match parse_count(args.name(), args.value(b'n'), 10) {
+        Ok(count) => count,
+        Err(status) => return status,
+    };
+    let text = match read_input(args.name(), args.operands().first().copied()) {
+        Ok(text) => text,
+        Err(status) => return status,
+    };
+
+    for line in lines(text).take(count) {
+        println!("{}", line);
+    }
+    EXIT_SUCCESS
+}
@@ -0,0 +1,71 @@
+use crate::shell::completion::Completions;
+use crate::shell::getopt::{Args, Options};
+use crate::shell::shell::{find_command, Command, ExitStatus, COMMANDS, EXIT_FAILURE, EXIT_SUCCESS};
+use crate::{print, println};
+
+/// Width of the name column in the command list.
+const NAME_WIDTH: usize = 14;
+/// Width of the option column in `help <cmd>`.
+const OPTION_WIDTH: usize = 24;
+
+fn pad(written: usize, width: usize) {
+    for _ in written..width {
+        print!(" ");
+    }
+}
+
+pub fn help(args: &Args) -> ExitStatus {
+    let Some(&name) = args.operands().first() else {
+        println!("Available commands:");
+        for entry in COMMANDS.iter() {
+            print!("  {}", entry.name);
+            pad(entry.name.len(), NAME_WIDTH);
+            println!("{}", entry.summary);
+        }
+        println!("Type 'help COMMAND' or 'COMMAND --help' for details.");
+        return EXIT_SUCCESS;
+    };
+
+    match find_command(name) {
+        Some(entry) => {
+            print_help(entry);
+            EXIT_SUCCESS
+        }
+        None => {
+            println!("help: no help topics match '{}'", name);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Prints the usage, summary and option table of a command.
+pub fn print_help(entry: &Command) {
+    println!("Usage: {}", entry.usage);
+    println!("{}.", entry.summary);
+    println!();
+    let Options::Spec(spec) = entry.options else {
+        return;
+    };
+    println!("Options:");
+    for opt in spec.iter() {
+        print!("  -{}, {}", opt.short as char, opt.long);
+        let mut written = 4 + opt.long.len();
+        if let Some(value) = opt.value {
+            print!("={}", value);
+            written += 1 + value.len();
+        }
+        pad(written, OPTION_WIDTH);
+        println!("{}", opt.help);
+    }
+    print!("      --help");
+    pad(10, OPTION_WIDTH);
+    println!("Show this help");
+}
+
+pub fn complete(arg_index: usize, out: &mut Completions) {
+    if arg_index == 1 {
+        for entry in COMMANDS.iter() {
+            out.offer(entry.name);
+        }
+    }
+}
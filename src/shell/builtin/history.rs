@@ -0,0 +1,22 @@
+use crate::shell::getopt::{Args, OptSpec};
+use crate::shell::history;
+use crate::shell::shell::{ExitStatus, EXIT_SUCCESS};
+use crate::println;
+
+pub static OPTIONS: &[OptSpec] = &[
+    OptSpec { short: b'c', long: "--clear", value: None, help: "Forget every entry" },
+];
+
+pub fn history(args: &Args) -> ExitStatus {
+    if args.has(b'c') {
+        history::clear();
+        return EXIT_SUCCESS;
+    }
+
+    for n in history::first()..=history::last() {
+        if let Some(line) = history::get(n) {
+            println!("{}  {}", n, line);
+        }
+    }
+    EXIT_SUCCESS
+}
@@ -0,0 +1,327 @@
+/// Memory inspection builtins: `hexdump`, `peek*`, `poke*` and `memsearch`.
+///
+/// Addresses are physical (the kernel runs identity-mapped or without
+/// paging). Every access is checked with `mm::paging` first, so an
+/// unmapped address is refused instead of faulting the kernel.
+
+use crate::eprintln;
+use crate::klib::number;
+use crate::mm::paging;
+use crate::println;
+use crate::shell::getopt::{Args, OptSpec};
+use crate::shell::shell::{self, ExitStatus, EXIT_FAILURE, EXIT_INTERRUPTED, EXIT_SUCCESS, EXIT_USAGE};
+
+/// Bytes dumped by `hexdump` when no length is given.
+const DEFAULT_DUMP_LEN: usize = 128;
+/// Largest range `hexdump` accepts, to keep a typo from flooding the screen.
+const MAX_DUMP_LEN: usize = 64 * 1024;
+/// Matches printed by `memsearch` before it stops.
+const MAX_MATCHES: usize = 16;
+/// Longest pattern `memsearch` accepts.
+const MAX_PATTERN: usize = 64;
+/// Bytes `memsearch` scans between two checks for Ctrl+C.
+const INTERRUPT_CHECK: usize = 64 * 1024;
+
+pub static MEMSEARCH_OPTIONS: &[OptSpec] = &[
+    OptSpec { short: b's', long: "--string", value: None, help: "Treat the pattern as text even if it looks like bytes" },
+];
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Line under construction, printed in one go.
+struct Line {
+    buf: [u8; 80],
+    len: usize,
+}
+
+impl Line {
+    fn new() -> Self {
+        Line { buf: [0; 80], len: 0 }
+    }
+
+    fn push(&mut self, c: u8) {
+        if self.len < self.buf.len() {
+            unsafe { *self.buf.get_unchecked_mut(self.len) = c; }
+            self.len += 1;
+        }
+    }
+
+    /// Appends the low `digits` hex digits of `value`, zero-padded.
+    fn push_hex(&mut self, value: u32, digits: u32) {
+        for i in (0..digits).rev() {
+            let nibble = (value >> (i * 4)) & 0xF;
+            self.push(unsafe { *HEX_DIGITS.get_unchecked(nibble as usize) });
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        unsafe { self.buf.get_unchecked(..self.len) }
+    }
+}
+
+fn parse_number(cmd: &[u8], arg: &[u8]) -> Result<u32, ExitStatus> {
+    number::parse_u32(arg).ok_or_else(|| {
+        eprintln!("{}: invalid number: '{}'", cmd, arg);
+        EXIT_USAGE
+    })
+}
+
+/// Checks that `addr..addr + len` is inside the address space and mapped.
+fn check_range(cmd: &[u8], addr: u32, len: usize) -> Result<(), ExitStatus> {
+    // The range may end on the last byte of the address space
+    if len > 0 && (addr as usize).checked_add(len - 1).is_none() {
+        eprintln!("{}: {:#010x}: range wraps around the address space", cmd, addr);
+        return Err(EXIT_USAGE);
+    }
+    if !paging::is_range_mapped(addr as usize, len) {
+        eprintln!("{}: {:#010x}: address not mapped", cmd, addr);
+        return Err(EXIT_FAILURE);
+    }
+    Ok(())
+}
+
+fn usage_error(args: &Args) -> ExitStatus {
+    eprintln!("{0}: wrong number of arguments (see 'help {0}')", args.name());
+    EXIT_USAGE
+}
+
+/// Access width in bytes, from the digits ending the command name.
+fn width_of(name: &[u8]) -> usize {
+    match name.last() {
+        Some(b'8') => 1,
+        Some(b'6') => 2,
+        _ => 4,
+    }
+}
+
+fn unaligned_error(cmd: &[u8], addr: u32, width: usize) -> ExitStatus {
+    eprintln!("{}: {:#010x}: address not aligned to {} bytes", cmd, addr, width);
+    EXIT_USAGE
+}
+
+// ──────────────────────────────────────────────
+//  hexdump
+// ──────────────────────────────────────────────
+
+pub fn hexdump(args: &Args) -> ExitStatus {
+    let cmd = args.name();
+    let (addr, len) = match *args.operands() {
+        [addr] => (addr, None),
+        [addr, len] => (addr, Some(len)),
+        _ => return usage_error(args),
+    };
+    let addr = match parse_number(cmd, addr) {
+        Ok(addr) => addr,
+        Err(status) => return status,
+    };
+    let len = match len.map(|len| parse_number(cmd, len)) {
+        None => DEFAULT_DUMP_LEN,
+        Some(Ok(len)) => len as usize,
+        Some(Err(status)) => return status,
+    };
+    if len > MAX_DUMP_LEN {
+        eprintln!("{}: length {} too large (max {})", cmd, len, MAX_DUMP_LEN);
+        return EXIT_USAGE;
+    }
+    if let Err(status) = check_range(cmd, addr, len) {
+        return status;
+    }
+
+    // 00000800  ff ff 00 00 00 9a cf 00  ff ff 00 00 00 92 cf 00  |................|
+    let mut offset = 0;
+    while offset < len {
+        let count = (len - offset).min(16);
+        let base = addr as usize + offset;
+        let mut bytes = [0u8; 16];
+        for (i, byte) in bytes.iter_mut().enumerate().take(count) {
+            *byte = unsafe { core::ptr::read_volatile((base + i) as *const u8) };
+        }
+
+        let mut line = Line::new();
+        line.push_hex(base as u32, 8);
+        line.push(b' ');
+        for i in 0..16 {
+            line.push(b' ');
+            if i == 8 {
+                line.push(b' ');
+            }
+            if i < count {
+                line.push_hex(unsafe { *bytes.get_unchecked(i) } as u32, 2);
+            } else {
+                line.push(b' ');
+                line.push(b' ');
+            }
+        }
+        line.push(b' ');
+        line.push(b' ');
+        line.push(b'|');
+        for &byte in bytes.iter().take(count) {
+            line.push(if byte.is_ascii_graphic() || byte == b' ' { byte } else { b'.' });
+        }
+        line.push(b'|');
+        println!("{}", line.as_bytes());
+        offset += count;
+    }
+    EXIT_SUCCESS
+}
+
+// ──────────────────────────────────────────────
+//  peek / poke
+// ──────────────────────────────────────────────
+
+/// `peek8`, `peek16` and `peek32`: the width comes from the command name.
+pub fn peek(args: &Args) -> ExitStatus {
+    let cmd = args.name();
+    let [addr] = *args.operands() else {
+        return usage_error(args);
+    };
+    let addr = match parse_number(cmd, addr) {
+        Ok(addr) => addr,
+        Err(status) => return status,
+    };
+    let width = width_of(cmd);
+    if addr as usize % width != 0 {
+        return unaligned_error(cmd, addr, width);
+    }
+    if let Err(status) = check_range(cmd, addr, width) {
+        return status;
+    }
+
+    let value = unsafe {
+        match width {
+            1 => core::ptr::read_volatile(addr as *const u8) as u32,
+            2 => core::ptr::read_volatile(addr as *const u16) as u32,
+            _ => core::ptr::read_volatile(addr as *const u32),
+        }
+    };
+    match width {
+        1 => println!("{0:#04x} ({0})", value),
+        2 => println!("{0:#06x} ({0})", value),
+        _ => println!("{0:#010x} ({0})", value),
+    }
+    EXIT_SUCCESS
+}
+
+/// `poke8`, `poke16` and `poke32`: the width comes from the command name.
+pub fn poke(args: &Args) -> ExitStatus {
+    let cmd = args.name();
+    let [addr, value] = *args.operands() else {
+        return usage_error(args);
+    };
+    let addr = match parse_number(cmd, addr) {
+        Ok(addr) => addr,
+        Err(status) => return status,
+    };
+    let value_arg = value;
+    let value = match parse_number(cmd, value) {
+        Ok(value) => value,
+        Err(status) => return status,
+    };
+    let width = width_of(cmd);
+    if width < 4 && value >> (width * 8) != 0 {
+        eprintln!("{}: value '{}' does not fit in {} bits", cmd, value_arg, width * 8);
+        return EXIT_USAGE;
+    }
+    if addr as usize % width != 0 {
+        return unaligned_error(cmd, addr, width);
+    }
+    if let Err(status) = check_range(cmd, addr, width) {
+        return status;
+    }
+
+    unsafe {
+        match width {
+            1 => core::ptr::write_volatile(addr as *mut u8, value as u8),
+            2 => core::ptr::write_volatile(addr as *mut u16, value as u16),
+            _ => core::ptr::write_volatile(addr as *mut u32, value),
+        }
+    }
+    EXIT_SUCCESS
+}
+
+// ──────────────────────────────────────────────
+//  memsearch
+// ──────────────────────────────────────────────
+
+/// Builds the search pattern: byte values if every operand is a number
+/// below 256 (and `-s` is not given), otherwise the operands as text
+/// joined by spaces.
+fn build_pattern(args: &Args, words: &[&[u8]], pattern: &mut [u8; MAX_PATTERN]) -> Option<usize> {
+    let as_bytes = !args.has(b's')
+        && words.iter().all(|w| number::parse_u32(w).is_some_and(|v| v <= 0xFF));
+    let mut len = 0;
+    for (i, word) in words.iter().enumerate() {
+        if as_bytes {
+            *pattern.get_mut(len)? = number::parse_u32(word)? as u8;
+            len += 1;
+            continue;
+        }
+        if i > 0 {
+            *pattern.get_mut(len)? = b' ';
+            len += 1;
+        }
+        for &c in word.iter() {
+            *pattern.get_mut(len)? = c;
+            len += 1;
+        }
+    }
+    Some(len)
+}
+
+pub fn memsearch(args: &Args) -> ExitStatus {
+    let cmd = args.name();
+    let [start, len, ref words @ ..] = *args.operands() else {
+        return usage_error(args);
+    };
+    if words.is_empty() {
+        return usage_error(args);
+    }
+    let start = match parse_number(cmd, start) {
+        Ok(start) => start,
+        Err(status) => return status,
+    };
+    let len = match parse_number(cmd, len) {
+        Ok(len) => len as usize,
+        Err(status) => return status,
+    };
+
+    let mut pattern = [0u8; MAX_PATTERN];
+    let pattern_len = match build_pattern(args, words, &mut pattern) {
+        Some(0) => {
+            eprintln!("{}: empty pattern", cmd);
+            return EXIT_USAGE;
+        }
+        Some(n) => n,
+        None => {
+            eprintln!("{}: pattern longer than {} bytes", cmd, MAX_PATTERN);
+            return EXIT_USAGE;
+        }
+    };
+    let pattern = unsafe { pattern.get_unchecked(..pattern_len) };
+    if let Err(status) = check_range(cmd, start, len) {
+        return status;
+    }
+
+    let mut found = 0;
+    let mut pos = 0;
+    while pos + pattern_len <= len {
+        if pos % INTERRUPT_CHECK == 0 && shell::interrupted() {
+            eprintln!("^C");
+            return EXIT_INTERRUPTED;
+        }
+        let base = start as usize + pos;
+        let matches = pattern.iter().enumerate().all(|(i, &p)| {
+            unsafe { core::ptr::read_volatile((base + i) as *const u8) == p }
+        });
+        if matches {
+            if found == MAX_MATCHES {
+                println!("...");
+                break;
+            }
+            println!("{:#010x}", base);
+            found += 1;
+        }
+        pos += 1;
+    }
+    if found == 0 { EXIT_FAILURE } else { EXIT_SUCCESS }
+}
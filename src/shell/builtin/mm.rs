@@ -0,0 +1,292 @@
+/// `slabinfo`, `vmallocinfo` and `stackusage`: memory statistics;
+/// `memleak`, the front end of `mm::memleak`; and `membench`, which times
+/// the `klib::memory` primitives.
+
+use core::hint::black_box;
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::cpu::{cpuid, regs};
+use crate::drivers::timer;
+use crate::klib::{memory, number, stack};
+use crate::mm::{frame, kstack, memleak, slab, slab_debug, vmalloc};
+use crate::shell::getopt::Args;
+use crate::shell::shell::{self, ExitStatus, EXIT_FAILURE, EXIT_INTERRUPTED, EXIT_SUCCESS, EXIT_USAGE};
+use crate::{eprintln, print, println};
+
+// ──────────────────────────────────────────────
+//  slabinfo
+// ──────────────────────────────────────────────
+
+pub fn slabinfo(_args: &Args) -> ExitStatus {
+    println!("{:<16} {:>7} {:>7} {:>6} {:>8} {:>5} {:>6}", "name", "active", "total", "size", "per-slab", "pages", "slabs");
+    let mut pages = 0;
+    slab::for_each_cache(|cache| {
+        println!(
+            "{:<16} {:>7} {:>7} {:>6} {:>8} {:>5} {:>6}",
+            cache.name, cache.active, cache.total, cache.size, cache.per_slab, cache.pages_per_slab, cache.slabs
+        );
+        pages += cache.slabs * cache.pages_per_slab;
+    });
+    let frames = frame::stats();
+    println!(
+        "slabs use {} KiB; {} KiB free of {} KiB",
+        pages * (frame::PAGE_SIZE / 1024),
+        frames.free * (frame::PAGE_SIZE / 1024),
+        frames.total * (frame::PAGE_SIZE / 1024)
+    );
+    if slab_debug::ENABLED {
+        let debug = slab_debug::stats();
+        println!("heap debugging on: {} objects in quarantine, {} errors", debug.quarantined, debug.errors);
+    }
+    EXIT_SUCCESS
+}
+
+// ──────────────────────────────────────────────
+//  vmallocinfo
+// ──────────────────────────────────────────────
+
+pub fn vmallocinfo(_args: &Args) -> ExitStatus {
+    let mut pages = 0;
+    vmalloc::for_each_area(|area| {
+        let end = area.start + area.pages * frame::PAGE_SIZE;
+        println!(
+            "{:#010x}-{:#010x} {:>8} pages={:<5} {}:{}",
+            area.start, end, area.pages * frame::PAGE_SIZE, area.pages, area.owner.file(), area.owner.line()
+        );
+        pages += area.pages;
+    });
+    println!("{} KiB in vmalloc areas", pages * (frame::PAGE_SIZE / 1024));
+    EXIT_SUCCESS
+}
+
+// ──────────────────────────────────────────────
+//  stackusage
+// ──────────────────────────────────────────────
+
+pub fn stackusage(_args: &Args) -> ExitStatus {
+    let esp = stack::get_esp() as usize;
+    println!("{:<13} {:<23} {:>6} {:>6} {:>4}  guard", "stack", "range", "size", "max", "use");
+    for stack in kstack::stacks() {
+        let used = stack.max_used();
+        let current = if (stack.base..stack.top()).contains(&esp) { "  (current)" } else { "" };
+        println!(
+            "{:<13} {:#010x}-{:#010x} {:>6} {:>6} {:>3}%  {}{}",
+            stack.name,
+            stack.base,
+            stack.top(),
+            stack.size,
+            used,
+            used * 100 / stack.size.max(1),
+            if stack.guarded { "yes" } else { "no" },
+            current
+        );
+    }
+    EXIT_SUCCESS
+}
+
+// ──────────────────────────────────────────────
+//  memleak
+// ──────────────────────────────────────────────
+
+/// Bytes of each suspected leak `memleak scan` dumps.
+const LEAK_DUMP_LEN: usize = 16;
+
+fn print_leak(leak: &memleak::Leak) {
+    println!("unreferenced object {:#010x} ({} bytes):", leak.start, leak.size);
+    print!("  hex dump:");
+    for i in 0..leak.size.min(LEAK_DUMP_LEN) {
+        print!(" {:02x}", unsafe { *(leak.start as *const u8).add(i) });
+    }
+    println!();
+    println!("  backtrace:");
+    for &addr in leak.trace.iter().take_while(|&&addr| addr != 0) {
+        let mut name = [0; stack::SYMBOL_MAX];
+        match stack::symbolize(addr, &mut name) {
+            Some((function, offset)) => println!("    [<{:#010x}>] {}+{:#x}", addr, function, offset),
+            None => println!("    [<{:#010x}>]", addr),
+        }
+    }
+}
+
+/// `memleak scan` reports the suspected leaks and fails if there are any;
+/// `memleak clear` stops reporting those.
+pub fn memleak(args: &Args) -> ExitStatus {
+    match args.operands() {
+        [action] if *action == b"scan" => {
+            let leaks = memleak::scan(print_leak);
+            let stats = memleak::stats();
+            print!("{} blocks tracked, {} suspected leaks", stats.tracked, leaks);
+            if stats.untracked > 0 {
+                print!(" ({} blocks allocated untracked)", stats.untracked);
+            }
+            println!();
+            if leaks > 0 { EXIT_FAILURE } else { EXIT_SUCCESS }
+        }
+        [action] if *action == b"clear" => {
+            println!("{} suspected leaks will no longer be reported", memleak::clear());
+            EXIT_SUCCESS
+        }
+        _ => {
+            eprintln!("memleak: expected 'scan' or 'clear' (see 'help memleak')");
+            EXIT_USAGE
+        }
+    }
+}
+
+// ──────────────────────────────────────────────
+//  membench
+// ──────────────────────────────────────────────
+
+const MEMBENCH_DEFAULT_KIB: u32 = 64;
+const MEMBENCH_MAX_KIB: u32 = 4096;
+/// Each test runs this many times; the fastest run counts.
+const MEMBENCH_ROUNDS: usize = 4;
+
+/// Operation on `len` bytes of two buffers, each `len + PAGE_SIZE` long.
+type BenchFn = unsafe fn(dst: *mut u8, src: *mut u8, len: usize);
+
+struct Bench {
+    name: &'static str,
+    fast: BenchFn,
+    /// The same with volatile byte accesses, for comparison.
+    bytewise: BenchFn,
+}
+
+static BENCHES: &[Bench] = &[
+    Bench {
+        name: "memset",
+        fast: |dst, _, len| unsafe { memory::memset(dst, 0x5A, len); },
+        bytewise: |dst, _, len| unsafe {
+            for i in 0..len {
+                write_volatile(dst.add(i), 0x5A);
+            }
+        },
+    },
+    Bench {
+        name: "memcpy",
+        fast: |dst, src, len| unsafe { memory::memcpy(dst, src, len); },
+        bytewise: |dst, src, len| unsafe { copy_bytes(dst, src, len) },
+    },
+    Bench {
+        name: "memcpy+1",
+        fast: |dst, src, len| unsafe { memory::memcpy(dst, src.add(1), len); },
+        bytewise: |dst, src, len| unsafe { copy_bytes(dst, src.add(1), len) },
+    },
+    Bench {
+        name: "memmove",
+        // Overlapping, so the copy runs backwards
+        fast: |dst, _, len| unsafe { memory::memmove(dst.add(4), dst, len); },
+        bytewise: |dst, _, len| unsafe {
+            for i in (0..len).rev() {
+                write_volatile(dst.add(4 + i), read_volatile(dst.add(i)));
+            }
+        },
+    },
+    Bench {
+        name: "memcmp",
+        fast: |dst, src, len| unsafe { black_box(memory::memcmp(dst, src, len)); },
+        bytewise: |dst, src, len| unsafe { black_box(compare_bytes(dst, src, len)); },
+    },
+    Bench {
+        name: "bcmp",
+        fast: |dst, src, len| unsafe { black_box(memory::bcmp(dst, src, len)); },
+        bytewise: |dst, src, len| unsafe { black_box(compare_bytes(dst, src, len)); },
+    },
+];
+
+unsafe fn copy_bytes(dst: *mut u8, src: *const u8, len: usize) {
+    for i in 0..len {
+        write_volatile(dst.add(i), read_volatile(src.add(i)));
+    }
+}
+
+unsafe fn compare_bytes(a: *const u8, b: *const u8, len: usize) -> i32 {
+    for i in 0..len {
+        let (x, y) = (read_volatile(a.add(i)), read_volatile(b.add(i)));
+        if x != y {
+            return x as i32 - y as i32;
+        }
+    }
+    0
+}
+
+/// Fewest cycles `f` takes over `MEMBENCH_ROUNDS` runs. The buffers are
+/// made equal before each run, for the comparisons.
+fn time(f: BenchFn, dst: *mut u8, src: *mut u8, len: usize) -> u64 {
+    (0..MEMBENCH_ROUNDS)
+        .map(|_| unsafe {
+            memory::memcpy(dst, src, len);
+            let start = regs::read_tsc();
+            f(dst, src, len);
+            regs::read_tsc().wrapping_sub(start).max(1)
+        })
+        .min()
+        .unwrap_or(1)
+}
+
+/// Prints cycles per KiB and, with a calibrated TSC, MiB/s.
+fn print_rate(cycles: u64, len: usize) {
+    let per_kib = number::div_rem_u64(cycles * 1024, len as u32).0;
+    let khz = timer::tsc_khz();
+    if khz == 0 {
+        print!(" {:>9} {:>7}", per_kib, "-");
+        return;
+    }
+    let bytes_per_ms = number::div_rem_u64(len as u64 * khz as u64, cycles.min(u32::MAX as u64) as u32).0;
+    print!(" {:>9} {:>7}", per_kib, (bytes_per_ms * 1000) >> 20);
+}
+
+pub fn membench(args: &Args) -> ExitStatus {
+    let kib = match args.operands() {
+        [] => MEMBENCH_DEFAULT_KIB,
+        [arg] => match number::parse_u32(arg) {
+            Some(kib) if (1..=MEMBENCH_MAX_KIB).contains(&kib) => kib,
+            _ => {
+                eprintln!("membench: invalid size '{}' (1 to {} KiB)", *arg, MEMBENCH_MAX_KIB);
+                return EXIT_USAGE;
+            }
+        },
+        _ => {
+            eprintln!("membench: too many arguments");
+            return EXIT_USAGE;
+        }
+    };
+    if !cpuid::has(&cpuid::TSC) {
+        eprintln!("membench: needs a time-stamp counter");
+        return EXIT_FAILURE;
+    }
+    let len = kib as usize * 1024;
+    // Room for the offset source and destination of memcpy+1 and memmove
+    let dst = vmalloc::vmalloc(len + frame::PAGE_SIZE);
+    let src = vmalloc::vmalloc(len + frame::PAGE_SIZE);
+    if dst.is_null() || src.is_null() {
+        eprintln!("membench: out of memory");
+        vmalloc::vfree(dst);
+        vmalloc::vfree(src);
+        return EXIT_FAILURE;
+    }
+    for i in 0..len + frame::PAGE_SIZE {
+        unsafe { src.add(i).write(i as u8); }
+    }
+
+    println!("{} KiB buffers, best of {} runs", kib, MEMBENCH_ROUNDS);
+    println!("{:<9} {:^17} {:^17}", "", "klib", "byte loop");
+    println!("{:<9} {:>9} {:>7} {:>9} {:>7} {:>7}", "test", "cyc/KiB", "MiB/s", "cyc/KiB", "MiB/s", "speedup");
+    let mut status = EXIT_SUCCESS;
+    for bench in BENCHES {
+        if shell::interrupted() {
+            status = EXIT_INTERRUPTED;
+            break;
+        }
+        let fast = time(bench.fast, dst, src, len);
+        let bytewise = time(bench.bytewise, dst, src, len);
+        print!("{:<9}", bench.name);
+        print_rate(fast, len);
+        print_rate(bytewise, len);
+        let (whole, tenths) = number::div_rem_u64(number::div_rem_u64(bytewise * 10, fast.min(u32::MAX as u64) as u32).0, 10);
+        println!(" {:>5}.{}x", whole, tenths);
+    }
+    vmalloc::vfree(dst);
+    vmalloc::vfree(src);
+    status
+}
@@ -1,2 +1,98 @@
+pub mod buffers;
+pub mod cat;
+pub mod cpu;
 pub mod dmesg;
 pub mod echo;
+pub mod flow;
+pub mod grep;
+pub mod head;
+pub mod help;
+pub mod history;
+pub mod mem;
+pub mod mm;
+pub mod port;
+pub mod sort;
+pub mod source;
+pub mod tail;
+pub mod test;
+pub mod vars;
+pub mod wc;
+
+use crate::eprintln;
+use crate::io::stream;
+use crate::shell::shell::{ExitStatus, EXIT_FAILURE, EXIT_USAGE};
+
+/// Returns the text a filter command reads: the named buffer given as
+/// operand, or its standard input when there is none.
+///
+/// On error, prints a message prefixed with `cmd` and returns the status
+/// the command should exit with.
+pub fn read_input(cmd: &[u8], operand: Option<&[u8]>) -> Result<&'static [u8], ExitStatus> {
+    match operand {
+        Some(name) => match stream::buffer(name) {
+            Some(buffer) => Ok(buffer.as_bytes()),
+            None => {
+                eprintln!("{}: {}: no such buffer", cmd, name);
+                Err(EXIT_FAILURE)
+            }
+        },
+        None => match stream::stdin() {
+            Some(text) => Ok(text),
+            None => {
+                eprintln!("{}: no input (pipe a command into it or name a buffer)", cmd);
+                Err(EXIT_USAGE)
+            }
+        },
+    }
+}
+
+/// Iterator over the lines of a text, without their `\n`.
+/// A final newline does not start an extra empty line.
+pub struct Lines<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        if self.pos >= self.text.len() {
+            return None;
+        }
+        let start = self.pos;
+        let mut end = start;
+        while end < self.text.len() && unsafe { *self.text.get_unchecked(end) } != b'\n' {
+            end += 1;
+        }
+        self.pos = end + 1;
+        Some(unsafe { self.text.get_unchecked(start..end) })
+    }
+}
+
+pub fn lines(text: &[u8]) -> Lines<'_> {
+    Lines { text, pos: 0 }
+}
+
+/// Parses a line count given to `-n`.
+pub fn parse_count(cmd: &[u8], value: Option<&[u8]>, default: usize) -> Result<usize, ExitStatus> {
+    let Some(value) = value else {
+        return Ok(default);
+    };
+    let mut n: usize = 0;
+    for &c in value {
+        let digit = match c {
+            b'0'..=b'9' => (c - b'0') as usize,
+            _ => {
+                eprintln!("{}: invalid number of lines: '{}'", cmd, value);
+                return Err(EXIT_USAGE);
+            }
+        };
+        n = n.saturating_mul(10).saturating_add(digit);
+    }
+    if value.is_empty() {
+        eprintln!("{}: invalid number of lines: ''", cmd);
+        return Err(EXIT_USAGE);
+    }
+    Ok(n)
+}
@@ -0,0 +1,96 @@
+/// Port I/O builtins: `inb`, `inw`, `inl`, `outb`, `outw` and `outl`.
+///
+/// Raw access to the I/O space for poking at the CMOS (0x70/0x71), the
+/// PIT (0x40-0x43) or the 8042 (0x60/0x64) while bringing up a driver.
+/// Nothing is checked beyond the argument ranges: reading a status port
+/// can clear it, and a careless write can hang or reset the machine.
+
+use crate::drivers::port;
+use crate::eprintln;
+use crate::klib::number;
+use crate::println;
+use crate::shell::getopt::Args;
+use crate::shell::shell::{ExitStatus, EXIT_SUCCESS, EXIT_USAGE};
+
+/// Access width in bits, from the letter ending the command name.
+fn width_of(name: &[u8]) -> u32 {
+    match name.last() {
+        Some(b'b') => 8,
+        Some(b'w') => 16,
+        _ => 32,
+    }
+}
+
+fn parse_port(cmd: &[u8], arg: &[u8]) -> Result<u16, ExitStatus> {
+    match number::parse_u32(arg) {
+        Some(value) if value <= 0xFFFF => Ok(value as u16),
+        Some(_) => {
+            eprintln!("{}: port '{}' out of range (0-0xffff)", cmd, arg);
+            Err(EXIT_USAGE)
+        }
+        None => {
+            eprintln!("{}: invalid number: '{}'", cmd, arg);
+            Err(EXIT_USAGE)
+        }
+    }
+}
+
+fn usage_error(args: &Args) -> ExitStatus {
+    eprintln!("{0}: wrong number of arguments (see 'help {0}')", args.name());
+    EXIT_USAGE
+}
+
+/// `inb`, `inw` and `inl`: the width comes from the command name.
+pub fn port_in(args: &Args) -> ExitStatus {
+    let cmd = args.name();
+    let [port] = *args.operands() else {
+        return usage_error(args);
+    };
+    let port = match parse_port(cmd, port) {
+        Ok(port) => port,
+        Err(status) => return status,
+    };
+
+    let value = match width_of(cmd) {
+        8 => port::inb(port) as u32,
+        16 => port::inw(port) as u32,
+        _ => port::inl(port),
+    };
+    match width_of(cmd) {
+        8 => println!("{0:#04x} ({0})", value),
+        16 => println!("{0:#06x} ({0})", value),
+        _ => println!("{0:#010x} ({0})", value),
+    }
+    EXIT_SUCCESS
+}
+
+/// `outb`, `outw` and `outl`: the width comes from the command name.
+pub fn port_out(args: &Args) -> ExitStatus {
+    let cmd = args.name();
+    let [port, value] = *args.operands() else {
+        return usage_error(args);
+    };
+    let port = match parse_port(cmd, port) {
+        Ok(port) => port,
+        Err(status) => return status,
+    };
+    let width = width_of(cmd);
+    let value = match number::parse_u32(value) {
+        Some(v) if width == 32 || v >> width == 0 => v,
+        Some(_) => {
+            eprintln!("{}: value '{}' does not fit in {} bits", cmd, value, width);
+            return EXIT_USAGE;
+        }
+        None => {
+            eprintln!("{}: invalid number: '{}'", cmd, value);
+            return EXIT_USAGE;
+        }
+    };
+
+    match width {
+        8 => port::outb(port, value as u8),
+        16 => port::outw(port, value as u16),
+        _ => port::outl(port, value),
+    }
+    EXIT_SUCCESS
+}
@@ -0,0 +1,98 @@
+use core::cmp::Ordering;
+
+use crate::shell::builtin::{lines, read_input};
+use crate::shell::getopt::{Args, OptSpec};
+use crate::shell::shell::{ExitStatus, EXIT_FAILURE, EXIT_SUCCESS};
+use crate::{eprintln, println};
+
+/// Maximum number of lines `sort` can hold.
+const MAX_LINES: usize = 256;
+
+pub static OPTIONS: &[OptSpec] = &[
+    OptSpec { short: b'n', long: "--numeric-sort", value: None, help: "Compare leading numbers by value" },
+    OptSpec { short: b'r', long: "--reverse", value: None, help: "Reverse the order" },
+    OptSpec { short: b'u', long: "--unique", value: None, help: "Print only the first of equal lines" },
+];
+
+/// Value of the leading (optionally negative) decimal number, 0 if none.
+fn leading_number(line: &[u8]) -> i64 {
+    let mut i = 0;
+    while i < line.len() && unsafe { *line.get_unchecked(i) } == b' ' {
+        i += 1;
+    }
+    let negative = i < line.len() && unsafe { *line.get_unchecked(i) } == b'-';
+    if negative {
+        i += 1;
+    }
+    let mut n: i64 = 0;
+    while i < line.len() && unsafe { line.get_unchecked(i) }.is_ascii_digit() {
+        n = n.saturating_mul(10).saturating_add((unsafe { *line.get_unchecked(i) } - b'0') as i64);
+        i += 1;
+    }
+    if negative { -n } else { n }
+}
+
+fn compare(a: &[u8], b: &[u8], numeric: bool) -> Ordering {
+    if numeric {
+        let order = leading_number(a).cmp(&leading_number(b));
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+    // Byte-wise, shorter line first on a common prefix
+    a.cmp(b)
+}
+
+pub fn sort(args: &Args) -> ExitStatus {
+    let text = match read_input(args.name(), args.operands().first().copied()) {
+        Ok(text) => text,
+        Err(status) => return status,
+    };
+
+    let mut sorted: [&[u8]; MAX_LINES] = [&[]; MAX_LINES];
+    let mut count = 0;
+    for line in lines(text) {
+        if count >= MAX_LINES {
+            eprintln!("sort: too many lines (max {})", MAX_LINES);
+            return EXIT_FAILURE;
+        }
+        unsafe { *sorted.get_unchecked_mut(count) = line; }
+        count += 1;
+    }
+
+    // Insertion sort: stable, and inputs are at most a few hundred lines
+    let numeric = args.has(b'n');
+    let reverse = args.has(b'r');
+    for i in 1..count {
+        let line = unsafe { *sorted.get_unchecked(i) };
+        let mut j = i;
+        while j > 0 {
+            let prev = unsafe { *sorted.get_unchecked(j - 1) };
+            let mut order = compare(prev, line, numeric);
+            if reverse {
+                order = order.reverse();
+            }
+            if order != Ordering::Greater {
+                break;
+            }
+            unsafe { *sorted.get_unchecked_mut(j) = prev; }
+            j -= 1;
+        }
+        unsafe { *sorted.get_unchecked_mut(j) = line; }
+    }
+
+    let unique = args.has(b'u');
+    let mut last: Option<&[u8]> = None;
+    for &line in unsafe { sorted.get_unchecked(..count) } {
+        if unique {
+            if let Some(prev) = last {
+                if compare(prev, line, numeric) == Ordering::Equal {
+                    continue;
+                }
+            }
+            last = Some(line);
+        }
+        println!("{}", line);
+    }
+    EXIT_SUCCESS
+}
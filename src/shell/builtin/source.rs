@@ -0,0 +1,39 @@
+use alloc::vec::Vec;
+
+use crate::eprintln;
+use crate::shell::exec;
+use crate::shell::getopt::Args;
+use crate::shell::scripts::{self, Script};
+use crate::shell::shell::{ExitStatus, EXIT_FAILURE, EXIT_USAGE};
+
+/// Runs a script with the remaining operands as `$1`, `$2`, ...
+pub fn source(args: &Args) -> ExitStatus {
+    let operands = args.operands();
+    let Some(&name) = operands.first() else {
+        eprintln!("source: missing script name");
+        return EXIT_USAGE;
+    };
+    match scripts::find(name) {
+        Some(Script::Fixed(text)) => exec::run_script(text, operands),
+        Some(Script::Buffer(text)) => run_copy(name, text, operands),
+        None => {
+            eprintln!("source: {}: no such script", name);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Runs a copy of `text`, so that redirections into the buffer it comes
+/// from do not rewrite the script while it runs.
+fn run_copy(name: &[u8], text: &[u8], argv: &[&[u8]]) -> ExitStatus {
+    if text.is_empty() {
+        return exec::run_script(text, argv);
+    }
+    let mut copy = Vec::new();
+    if copy.try_reserve_exact(text.len()).is_err() {
+        eprintln!("source: {}: out of memory", name);
+        return EXIT_FAILURE;
+    }
+    copy.extend_from_slice(text);
+    exec::run_script(&copy, argv)
+}
@@ -0,0 +1,25 @@
+use crate::shell::builtin::{lines, parse_count, read_input};
+use crate::shell::getopt::{Args, OptSpec};
+use crate::shell::shell::{ExitStatus, EXIT_SUCCESS};
+use crate::println;
+
+pub static OPTIONS: &[OptSpec] = &[
+    OptSpec { short: b'n', long: "--lines", value: Some("COUNT"), help: "Print the last COUNT lines (default 10)" },
+];
+
+pub fn tail(args: &Args) -> ExitStatus {
+    let count = match parse_count(args.name(), args.value(b'n'), 10) {
+        Ok(count) => count,
+        Err(status) => return status,
+    };
+    let text = match read_input(args.name(), args.operands().first().copied()) {
+        Ok(text) => text,
+        Err(status) => return status,
+    };
+
+    let total = lines(text).count();
+    for line in lines(text).skip(total.saturating_sub(count)) {
+        println!("{}", line);
+    }
+    EXIT_SUCCESS
+}
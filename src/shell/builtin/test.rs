@@ -0,0 +1,100 @@
+use crate::eprintln;
+use crate::shell::getopt::Args;
+use crate::shell::shell::{ExitStatus, EXIT_FAILURE, EXIT_SUCCESS, EXIT_USAGE};
+
+fn integer(arg: &[u8]) -> Result<i64, ExitStatus> {
+    let (negative, digits) = match arg.split_first() {
+        Some((b'-', rest)) => (true, rest),
+        Some((b'+', rest)) => (false, rest),
+        _ => (false, arg),
+    };
+    if digits.is_empty() || !digits.iter().all(|c| c.is_ascii_digit()) {
+        eprintln!("test: {}: integer expression expected", arg);
+        return Err(EXIT_USAGE);
+    }
+    let mut n: i64 = 0;
+    for &c in digits {
+        n = n.saturating_mul(10).saturating_add((c - b'0') as i64);
+    }
+    Ok(if negative { -n } else { n })
+}
+
+fn unary(op: &[u8], arg: &[u8]) -> Result<bool, ExitStatus> {
+    match op {
+        b"-z" => Ok(arg.is_empty()),
+        b"-n" => Ok(!arg.is_empty()),
+        _ => {
+            eprintln!("test: {}: unary operator expected", op);
+            Err(EXIT_USAGE)
+        }
+    }
+}
+
+fn binary(left: &[u8], op: &[u8], right: &[u8]) -> Result<bool, ExitStatus> {
+    match op {
+        b"=" | b"==" => return Ok(left == right),
+        b"!=" => return Ok(left != right),
+        _ => {}
+    }
+    let compare: fn(i64, i64) -> bool = match op {
+        b"-eq" => |a, b| a == b,
+        b"-ne" => |a, b| a != b,
+        b"-lt" => |a, b| a < b,
+        b"-le" => |a, b| a <= b,
+        b"-gt" => |a, b| a > b,
+        b"-ge" => |a, b| a >= b,
+        _ => {
+            eprintln!("test: {}: binary operator expected", op);
+            return Err(EXIT_USAGE);
+        }
+    };
+    Ok(compare(integer(left)?, integer(right)?))
+}
+
+/// Evaluates an expression: `-o` binds loosest, then `-a`, then `!`.
+fn evaluate(args: &[&[u8]]) -> Result<bool, ExitStatus> {
+    for op in [b"-o", b"-a"] {
+        // An operator at either end is an operand (`test -a` is a string)
+        let split = args.iter().enumerate().position(|(i, &arg)| {
+            i > 0 && i + 1 < args.len() && arg == op
+        });
+        if let Some(i) = split {
+            let (left, right) = unsafe { (args.get_unchecked(..i), args.get_unchecked(i + 1..)) };
+            let left = evaluate(left)?;
+            let right = evaluate(right)?;
+            return Ok(if op == b"-o" { left || right } else { left && right });
+        }
+    }
+
+    match args {
+        [] => Ok(false),
+        [arg] => Ok(!arg.is_empty()),
+        [b"!", rest @ ..] => Ok(!evaluate(rest)?),
+        [op, arg] => unary(op, arg),
+        [left, op, right] => binary(left, op, right),
+        _ => {
+            eprintln!("test: too many arguments");
+            Err(EXIT_USAGE)
+        }
+    }
+}
+
+/// `test EXPRESSION` / `[ EXPRESSION ]`
+pub fn test(args: &Args) -> ExitStatus {
+    let mut operands = args.operands();
+    if args.name() == b"[" {
+        match operands.split_last() {
+            Some((&last, rest)) if last == b"]" => operands = rest,
+            _ => {
+                eprintln!("[: missing ']'");
+                return EXIT_USAGE;
+            }
+        }
+    }
+
+    match evaluate(operands) {
+        Ok(true) => EXIT_SUCCESS,
+        Ok(false) => EXIT_FAILURE,
+        Err(status) => status,
+    }
+}
@@ -0,0 +1,68 @@
+use crate::shell::completion::Completions;
+use crate::shell::getopt::{Args, OptSpec};
+use crate::shell::shell::{ExitStatus, EXIT_FAILURE, EXIT_SUCCESS};
+use crate::shell::vars;
+use crate::{eprintln, println};
+
+pub static EXPORT_OPTIONS: &[OptSpec] = &[
+    OptSpec { short: b'n', long: "--unexport", value: None, help: "Remove the export mark instead" },
+];
+
+pub fn set(_: &Args) -> ExitStatus {
+    for idx in 0..vars::MAX_VARS {
+        if let Some((name, value, _)) = vars::at(idx) {
+            println!("{}={}", name, value);
+        }
+    }
+    EXIT_SUCCESS
+}
+
+pub fn export(args: &Args) -> ExitStatus {
+    let operands = args.operands();
+    if operands.is_empty() {
+        for idx in 0..vars::MAX_VARS {
+            if let Some((name, value, true)) = vars::at(idx) {
+                println!("export {}={}", name, value);
+            }
+        }
+        return EXIT_SUCCESS;
+    }
+
+    let exported = !args.has(b'n');
+    let mut status = EXIT_SUCCESS;
+    for &operand in operands {
+        let (name, value) = match operand.iter().position(|&c| c == b'=') {
+            Some(eq) => unsafe { (operand.get_unchecked(..eq), Some(operand.get_unchecked(eq + 1..))) },
+            None => (operand, None),
+        };
+        let result = match value {
+            Some(value) => vars::set(name, value),
+            // Exporting an unset variable creates it empty
+            None if vars::get(name).is_none() && exported => vars::set(name, &[]),
+            None => Ok(()),
+        };
+        if let Err(err) = result {
+            eprintln!("export: {}: {}", name, vars::error_message(err));
+            status = EXIT_FAILURE;
+            continue;
+        }
+        vars::set_exported(name, exported);
+    }
+    status
+}
+
+pub fn unset(args: &Args) -> ExitStatus {
+    for &name in args.operands() {
+        vars::unset(name);
+    }
+    EXIT_SUCCESS
+}
+
+/// Completes the names of set variables.
+pub fn complete(_: usize, out: &mut Completions) {
+    for idx in 0..vars::MAX_VARS {
+        if let Some((name, _, _)) = vars::at(idx) {
+            out.offer(name);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use crate::shell::builtin::read_input;
+use crate::shell::getopt::{Args, OptSpec};
+use crate::shell::shell::{ExitStatus, EXIT_SUCCESS};
+use crate::{print, println};
+
+pub static OPTIONS: &[OptSpec] = &[
+    OptSpec { short: b'l', long: "--lines", value: None, help: "Print the line count" },
+    OptSpec { short: b'w', long: "--words", value: None, help: "Print the word count" },
+    OptSpec { short: b'c', long: "--bytes", value: None, help: "Print the byte count" },
+];
+
+pub fn wc(args: &Args) -> ExitStatus {
+    let text = match read_input(args.name(), args.operands().first().copied()) {
+        Ok(text) => text,
+        Err(status) => return status,
+    };
+
+    let mut lines = 0;
+    let mut words = 0;
+    let mut in_word = false;
+    for &c in text {
+        if c == b'\n' {
+            lines += 1;
+        }
+        if c == b' ' || c == b'\t' || c == b'\n' {
+            in_word = false;
+        } else if !in_word {
+            in_word = true;
+            words += 1;
+        }
+    }
+
+    // No option selects all three counts
+    let all = !args.has(b'l') && !args.has(b'w') && !args.has(b'c');
+    let counts = [(b'l', lines), (b'w', words), (b'c', text.len())];
+    let mut first = true;
+    for (opt, count) in counts {
+        if all || args.has(opt) {
+            if !first {
+                print!(" ");
+            }
+            print!("{}", count);
+            first = false;
+        }
+    }
+    println!();
+    EXIT_SUCCESS
+}
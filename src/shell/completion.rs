@@ -0,0 +1,136 @@
+/// Tab completion.
+///
+/// The word under the cursor is completed against command names when it
+/// is the first word of a command (at the start of the line or after
+/// `|`, `;`, `&&`, `||` or a keyword such as `then`), against buffer names after `>` / `>>`, against
+/// the command's long options when it starts with `-`, or against the
+/// candidates offered by the command's `complete` function otherwise.
+
+use crate::shell::builtin::buffers;
+use crate::shell::shell::{find_command, COMMANDS};
+
+const MAX_CANDIDATES: usize = 32;
+
+/// Argument completer: offers candidates for argument `arg_index` (1-based).
+pub type ArgCompleter = fn(arg_index: usize, out: &mut Completions);
+
+/// Candidates matching the word being completed.
+pub struct Completions<'a> {
+    prefix: &'a [u8],
+    items: [&'static [u8]; MAX_CANDIDATES],
+    count: usize,
+}
+
+impl<'a> Completions<'a> {
+    fn new(prefix: &'a [u8]) -> Self {
+        Completions { prefix, items: [&[]; MAX_CANDIDATES], count: 0 }
+    }
+
+    /// Offers a candidate; kept only if it starts with the typed prefix.
+    pub fn offer(&mut self, word: &'static [u8]) {
+        if self.count >= MAX_CANDIDATES || !starts_with(word, self.prefix) {
+            return;
+        }
+        unsafe { *self.items.get_unchecked_mut(self.count) = word; }
+        self.count += 1;
+    }
+
+    /// Offers every word of a static list.
+    pub fn offer_all(&mut self, words: &[&'static [u8]]) {
+        for &word in words {
+            self.offer(word);
+        }
+    }
+
+    /// The word being completed.
+    pub fn prefix(&self) -> &[u8] {
+        self.prefix
+    }
+
+    /// Matching candidates.
+    pub fn items(&self) -> &[&'static [u8]] {
+        unsafe { self.items.get_unchecked(..self.count) }
+    }
+
+    /// Longest prefix shared by every candidate.
+    pub fn common_prefix(&self) -> &'static [u8] {
+        let items = self.items();
+        let Some(&first) = items.first() else {
+            return &[];
+        };
+        let mut len = first.len();
+        for &item in items {
+            let mut i = 0;
+            while i < len && i < item.len()
+                && unsafe { *item.get_unchecked(i) == *first.get_unchecked(i) }
+            {
+                i += 1;
+            }
+            len = i;
+        }
+        unsafe { first.get_unchecked(..len) }
+    }
+}
+
+fn starts_with(word: &[u8], prefix: &[u8]) -> bool {
+    word.len() >= prefix.len()
+        && prefix.iter().zip(word.iter()).all(|(a, b)| a == b)
+}
+
+/// Collects candidates for the word ending at `cursor` in `line`.
+pub fn complete(line: &[u8], cursor: usize) -> Completions<'_> {
+    let line = unsafe { line.get_unchecked(..cursor.min(line.len())) };
+
+    let mut start = line.len();
+    while start > 0 && unsafe { *line.get_unchecked(start - 1) } != b' ' {
+        start -= 1;
+    }
+    let mut out = Completions::new(unsafe { line.get_unchecked(start..) });
+
+    // Words before the one being completed, counted from the current
+    // pipeline stage
+    let mut arg_index = 0;
+    let mut cmd_name: &[u8] = &[];
+    let mut after_redirect = false;
+    let mut i = 0;
+    while i < start {
+        if unsafe { *line.get_unchecked(i) } == b' ' {
+            i += 1;
+            continue;
+        }
+        let word_start = i;
+        while i < start && unsafe { *line.get_unchecked(i) } != b' ' {
+            i += 1;
+        }
+        let word = unsafe { line.get_unchecked(word_start..i) };
+        after_redirect = false;
+        match word {
+            b"|" | b"||" | b"&&" | b";" | b"then" | b"else" | b"do" | b"{" => arg_index = 0,
+            b">" | b">>" => after_redirect = true,
+            _ => {
+                if arg_index == 0 {
+                    cmd_name = word;
+                }
+                arg_index += 1;
+            }
+        }
+    }
+
+    if after_redirect {
+        buffers::complete(0, &mut out);
+    } else if arg_index == 0 {
+        for entry in COMMANDS.iter() {
+            out.offer(entry.name);
+        }
+    } else if let Some(entry) = find_command(cmd_name) {
+        if out.prefix().first() == Some(&b'-') {
+            for opt in entry.options.spec().iter().filter(|opt| !opt.long.is_empty()) {
+                out.offer(opt.long.as_bytes());
+            }
+            out.offer(b"--help");
+        } else if let Some(complete) = entry.complete {
+            complete(arg_index, &mut out);
+        }
+    }
+    out
+}
@@ -26,6 +26,22 @@ pub fn show_prompt() {
     display::put_str_colored(PROMPT, color);
 }
 
+/// Clears the current line from the prompt column and redraws the prompt.
+pub fn redraw_prompt() {
+    let (_, y) = display::get_pos();
+    let start = unsafe { PROMPT_START_COL };
+    for x in start..vga::VGA_WIDTH {
+        display::put_char_at(x, y, b' ');
+    }
+    display::set_pos(start, y);
+    show_prompt();
+}
+
+/// Returns the column where the prompt begins.
+pub fn prompt_start_col() -> usize {
+    unsafe { PROMPT_START_COL }
+}
+
 /// Returns the column where the input area begins (after the prompt).
 pub fn input_start_col() -> usize {
     unsafe { PROMPT_START_COL + PROMPT.len() }
@@ -0,0 +1,859 @@
+/// Shell interpreter.
+///
+/// Interactive lines, `source`d scripts, boot scripts and function bodies
+/// all run through `run_source`. The interpreter walks the token stream
+/// directly, executing commands as it parses them; every construct can
+/// also be parsed with `run == false`, which skips it without side
+/// effects. Loops seek back to their start for each iteration.
+///
+/// Grammar:
+///   list      := and_or ((';' | newline) and_or)*
+///   and_or    := pipeline (('&&' | '||') pipeline)*
+///   pipeline  := command ('|' command)*
+///   command   := simple | compound redirect* | NAME '(' ')' compound
+///   compound  := if | while | until | for | '{' list '}'
+///   simple    := (NAME=value)* word* with '>' / '>>' redirections
+///
+/// Operators are evaluated left to right: `a && b` runs `b` only if `a`
+/// succeeded, `a || b` only if it failed, and a skipped pipeline leaves
+/// `$?` as it was.
+///
+/// The output of each pipeline stage goes to a pipe stream that becomes
+/// the standard input of the next stage; the last stage writes wherever
+/// the pipeline itself writes. `> name` / `>> name` send the output of a
+/// command to a named buffer instead. Leading `NAME=value` words set shell
+/// variables, or only last while the command runs when one follows.
+///
+/// A source text is checked for syntax errors before any of it runs.
+
+use crate::eprintln;
+use crate::io::stream;
+use crate::shell::functions;
+use crate::shell::parser::{self, Lexer, Token};
+use crate::shell::shell::{
+    self, last_status, run_command, set_last_status, ExitStatus, EXIT_FAILURE, EXIT_INTERRUPTED, EXIT_SUCCESS,
+    EXIT_USAGE,
+};
+use crate::shell::vars::{self, Params};
+
+const MAX_ARGS: usize = 16;
+const MAX_ASSIGNS: usize = 4;
+/// Maximum number of words a `for` loop iterates over.
+const MAX_WORDS: usize = 32;
+/// Maximum nesting of function calls and sourced scripts.
+const MAX_CALL_DEPTH: usize = 16;
+
+/// Words that start a compound command.
+const COMPOUND_KEYWORDS: &[&[u8]] = &[b"if", b"while", b"until", b"for", b"{"];
+/// Words that may only appear inside a compound command.
+const CLOSING_KEYWORDS: &[&[u8]] = &[b"then", b"elif", b"else", b"fi", b"do", b"done", b"}"];
+
+/// How control leaves the commands being run.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Flow {
+    Normal,
+    /// `break`: leave the innermost loop.
+    Break,
+    /// `continue`: start the next iteration of the innermost loop.
+    Continue,
+    /// `return`: leave the running function or script.
+    Return,
+}
+
+static mut FLOW: Flow = Flow::Normal;
+static mut LOOP_DEPTH: usize = 0;
+static mut CALL_DEPTH: usize = 0;
+
+/// Requests a change of control flow (`break`, `continue`, `return`).
+pub fn request(flow: Flow) {
+    unsafe { FLOW = flow; }
+}
+
+fn flow() -> Flow {
+    unsafe { FLOW }
+}
+
+/// Returns `true` inside a `while`, `until` or `for` body.
+pub fn in_loop() -> bool {
+    unsafe { LOOP_DEPTH > 0 }
+}
+
+/// Returns `true` inside a function or sourced script.
+pub fn in_call() -> bool {
+    unsafe { CALL_DEPTH > 0 }
+}
+
+type Assignment = (&'static [u8], &'static [u8]);
+type Redirect = Option<(&'static [u8], bool)>;
+type Parsed<T = ()> = Result<T, ExitStatus>;
+
+fn is_one_of(raw: &[u8], words: &[&[u8]]) -> bool {
+    words.contains(&raw)
+}
+
+fn is_keyword(token: Token, keyword: &[u8]) -> bool {
+    matches!(token, Token::Word(raw) if raw == keyword)
+}
+
+fn syntax_error(token: Token) -> ExitStatus {
+    eprintln!("tacos: syntax error near unexpected token `{}'", token.text());
+    EXIT_USAGE
+}
+
+fn line_too_long() -> ExitStatus {
+    eprintln!("tacos: line too long");
+    EXIT_USAGE
+}
+
+/// A simple command, expanded.
+struct Simple {
+    argv: [&'static [u8]; MAX_ARGS],
+    argc: usize,
+    assigns: [Assignment; MAX_ASSIGNS],
+    assign_count: usize,
+    redirect: Redirect,
+}
+
+impl Simple {
+    const fn new() -> Self {
+        Simple {
+            argv: [&[]; MAX_ARGS],
+            argc: 0,
+            assigns: [(&[], &[]); MAX_ASSIGNS],
+            assign_count: 0,
+            redirect: None,
+        }
+    }
+
+    fn argv(&self) -> &[&'static [u8]] {
+        unsafe { self.argv.get_unchecked(..self.argc) }
+    }
+
+    fn assigns(&self) -> &[Assignment] {
+        unsafe { self.assigns.get_unchecked(..self.assign_count) }
+    }
+
+    fn push_arg(&mut self, arg: &'static [u8]) -> Parsed {
+        if self.argc >= MAX_ARGS {
+            eprintln!("tacos: too many arguments (max {})", MAX_ARGS);
+            return Err(EXIT_USAGE);
+        }
+        unsafe { *self.argv.get_unchecked_mut(self.argc) = arg; }
+        self.argc += 1;
+        Ok(())
+    }
+}
+
+struct Interp<'a> {
+    lexer: Lexer<'a>,
+}
+
+impl<'a> Interp<'a> {
+    fn new(source: &'a [u8]) -> Self {
+        Interp { lexer: Lexer::new(source) }
+    }
+
+    // ──────────────────────────────────────────────
+    //  Token helpers
+    // ──────────────────────────────────────────────
+
+    fn next(&mut self) -> Token<'a> {
+        self.lexer.next_token()
+    }
+
+    fn peek(&mut self) -> Token<'a> {
+        let pos = self.lexer.pos();
+        let token = self.lexer.next_token();
+        self.lexer.seek(pos);
+        token
+    }
+
+    fn skip_newlines(&mut self) {
+        while matches!(self.peek(), Token::Newline) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, keyword: &[u8]) -> Parsed {
+        let token = self.next();
+        if is_keyword(token, keyword) { Ok(()) } else { Err(syntax_error(token)) }
+    }
+
+    /// Whether commands should run: `run` and no pending break/continue/return.
+    fn active(run: bool) -> bool {
+        run && flow() == Flow::Normal
+    }
+
+    // ──────────────────────────────────────────────
+    //  Lists and pipelines
+    // ──────────────────────────────────────────────
+
+    /// Parses commands up to one of the `terms` keywords (not consumed) or
+    /// the end of input.
+    fn parse_list(&mut self, run: bool, terms: &[&[u8]]) -> Parsed {
+        loop {
+            self.skip_newlines();
+            match self.peek() {
+                Token::End => return Ok(()),
+                Token::Word(raw) if is_one_of(raw, terms) => return Ok(()),
+                _ => {}
+            }
+            self.parse_and_or(Self::active(run))?;
+            let token = self.peek();
+            match token {
+                Token::Semi | Token::Newline => {
+                    self.next();
+                }
+                Token::End => return Ok(()),
+                _ => return Err(syntax_error(token)),
+            }
+        }
+    }
+
+    fn parse_and_or(&mut self, run: bool) -> Parsed {
+        self.parse_pipeline(run)?;
+        loop {
+            let want_success = match self.peek() {
+                Token::AndIf => true,
+                Token::OrIf => false,
+                _ => return Ok(()),
+            };
+            self.next();
+            self.skip_newlines();
+            let succeeded = last_status() == EXIT_SUCCESS;
+            self.parse_pipeline(Self::active(run) && succeeded == want_success)?;
+        }
+    }
+
+    fn parse_pipeline(&mut self, run: bool) -> Parsed {
+        let saved = stream::save();
+        let mut input: Option<usize> = None;
+
+        let result = loop {
+            if run {
+                stream::restore(saved);
+                if let Some(pipe) = input {
+                    stream::set_stdin_pipe(pipe);
+                }
+            }
+            let output = self.parse_command(run);
+            if let Some(pipe) = input.take() {
+                stream::close_pipe(pipe);
+            }
+            match output {
+                Ok(pipe) => input = pipe,
+                Err(status) => break Err(status),
+            }
+            if !matches!(self.peek(), Token::Pipe) {
+                break Ok(());
+            }
+            self.next();
+            self.skip_newlines();
+        };
+
+        if let Some(pipe) = input {
+            stream::close_pipe(pipe);
+        }
+        if run {
+            stream::restore(saved);
+        }
+        result
+    }
+
+    /// Points standard output at a new pipe if the command just parsed is
+    /// followed by `|`, then at its redirection target if it has one.
+    ///
+    /// Returns the pipe, and `false` if the redirection failed and the
+    /// command must not run.
+    fn connect_output(&mut self, redirect: Redirect) -> Parsed<(Option<usize>, bool)> {
+        let mut pipe = None;
+        if matches!(self.peek(), Token::Pipe) {
+            let Some(idx) = stream::open_pipe() else {
+                eprintln!("tacos: too many nested pipelines");
+                return Err(EXIT_FAILURE);
+            };
+            stream::redirect_to_pipe(idx);
+            pipe = Some(idx);
+        }
+        if let Some((name, append)) = redirect {
+            if !stream::redirect_to_buffer(name, append) {
+                eprintln!("tacos: {}: cannot create buffer", name);
+                set_last_status(EXIT_FAILURE);
+                return Ok((pipe, false));
+            }
+        }
+        Ok((pipe, true))
+    }
+
+    /// Parses `>` / `>>` redirections; the last one wins.
+    fn parse_redirects(&mut self, run: bool) -> Parsed<Redirect> {
+        let mut redirect = None;
+        loop {
+            let token = self.peek();
+            let append = match token {
+                Token::RedirOut => false,
+                Token::RedirAppend => true,
+                _ => return Ok(redirect),
+            };
+            self.next();
+            let target = self.next();
+            let Token::Word(raw) = target else {
+                return Err(syntax_error(target));
+            };
+            if run {
+                let Some(name) = parser::expand_word(raw) else {
+                    return Err(line_too_long());
+                };
+                redirect = Some((name, append));
+            }
+        }
+    }
+
+    // ──────────────────────────────────────────────
+    //  Commands
+    // ──────────────────────────────────────────────
+
+    /// Parses one pipeline stage, running it if `run`.
+    /// Returns the pipe its output went to.
+    fn parse_command(&mut self, run: bool) -> Parsed<Option<usize>> {
+        let token = self.peek();
+        let Token::Word(raw) = token else {
+            return self.parse_simple(run);
+        };
+
+        if is_one_of(raw, COMPOUND_KEYWORDS) {
+            return self.parse_compound(run);
+        }
+        if is_one_of(raw, CLOSING_KEYWORDS) {
+            return Err(syntax_error(token));
+        }
+        if raw == b"function" {
+            self.next();
+            let name = self.next();
+            let Token::Word(name) = name else {
+                return Err(syntax_error(name));
+            };
+            if matches!(self.peek(), Token::LParen) {
+                self.next();
+                self.expect_rparen()?;
+            }
+            return self.parse_function(run, name);
+        }
+
+        // NAME ( ) defines a function
+        let pos = self.lexer.pos();
+        self.next();
+        if matches!(self.peek(), Token::LParen) {
+            self.next();
+            self.expect_rparen()?;
+            return self.parse_function(run, raw);
+        }
+        self.lexer.seek(pos);
+        self.parse_simple(run)
+    }
+
+    fn expect_rparen(&mut self) -> Parsed {
+        let token = self.next();
+        if matches!(token, Token::RParen) { Ok(()) } else { Err(syntax_error(token)) }
+    }
+
+    fn parse_simple(&mut self, run: bool) -> Parsed<Option<usize>> {
+        let mark = parser::mark();
+        let result = self.parse_simple_words(run);
+        let result = match result {
+            Ok(cmd) if run => self.run_simple(&cmd),
+            Ok(_) => Ok(None),
+            Err(status) => Err(status),
+        };
+        parser::release(mark);
+        result
+    }
+
+    /// Collects the words and redirections of a simple command, expanding
+    /// them if `run`.
+    fn parse_simple_words(&mut self, run: bool) -> Parsed<Simple> {
+        let mut cmd = Simple::new();
+        let mut words = 0;
+
+        loop {
+            let token = self.peek();
+            match token {
+                Token::Word(raw) => {
+                    self.next();
+                    words += 1;
+                    if run {
+                        self.expand_into(&mut cmd, raw)?;
+                    }
+                }
+                Token::RedirOut | Token::RedirAppend => {
+                    words += 1;
+                    if let Some(redirect) = self.parse_redirects(run)? {
+                        cmd.redirect = Some(redirect);
+                    }
+                }
+                _ => {
+                    if words == 0 {
+                        return Err(syntax_error(token));
+                    }
+                    return Ok(cmd);
+                }
+            }
+        }
+    }
+
+    fn expand_into(&mut self, cmd: &mut Simple, raw: &[u8]) -> Parsed {
+        // Leading NAME=value words are assignments
+        if cmd.argc == 0 {
+            if let Some((name, value)) = parser::split_assignment(raw) {
+                if cmd.assign_count >= MAX_ASSIGNS {
+                    eprintln!("tacos: too many assignments (max {})", MAX_ASSIGNS);
+                    return Err(EXIT_USAGE);
+                }
+                let (Some(name), Some(value)) = (parser::store(name), parser::expand_word(value)) else {
+                    return Err(line_too_long());
+                };
+                unsafe { *cmd.assigns.get_unchecked_mut(cmd.assign_count) = (name, value); }
+                cmd.assign_count += 1;
+                return Ok(());
+            }
+        }
+        if parser::is_all_params(raw) {
+            for n in 1..=vars::param_count() {
+                cmd.push_arg(vars::param(n).unwrap_or(&[]))?;
+            }
+            return Ok(());
+        }
+        let Some(word) = parser::expand_word(raw) else {
+            return Err(line_too_long());
+        };
+        if word.is_empty() && !parser::is_quoted(raw) {
+            return Ok(());
+        }
+        cmd.push_arg(word)
+    }
+
+    fn run_simple(&mut self, cmd: &Simple) -> Parsed<Option<usize>> {
+        let (pipe, ok) = self.connect_output(cmd.redirect)?;
+        if !ok {
+            return Ok(pipe);
+        }
+
+        let status = if cmd.argc > 0 {
+            run_with_assignments(cmd)
+        } else if assign(cmd.assigns()) {
+            EXIT_SUCCESS
+        } else {
+            EXIT_FAILURE
+        };
+        set_last_status(status);
+
+        if stream::stdout_truncated() {
+            match cmd.argv().first() {
+                Some(&name) => eprintln!("tacos: {}: output truncated", name),
+                None => eprintln!("tacos: output truncated"),
+            }
+        }
+        Ok(pipe)
+    }
+
+    // ──────────────────────────────────────────────
+    //  Compound commands
+    // ──────────────────────────────────────────────
+
+    fn parse_compound(&mut self, run: bool) -> Parsed<Option<usize>> {
+        if !run {
+            self.parse_compound_body(false)?;
+            self.parse_redirects(false)?;
+            return Ok(None);
+        }
+
+        // Skip the body once to find the redirections after it, so output
+        // is connected before anything inside runs
+        let mark = parser::mark();
+        let start = self.lexer.pos();
+        self.parse_compound_body(false)?;
+        let connected = match self.parse_redirects(true) {
+            Ok(redirect) => self.connect_output(redirect),
+            Err(status) => Err(status),
+        };
+        let result = match connected {
+            Ok((pipe, ok)) => {
+                self.lexer.seek(start);
+                let body = self.parse_compound_body(ok);
+                match body.and_then(|_| self.parse_redirects(false)) {
+                    Ok(_) => Ok(pipe),
+                    Err(status) => Err(status),
+                }
+            }
+            Err(status) => Err(status),
+        };
+        parser::release(mark);
+        result
+    }
+
+    fn parse_compound_body(&mut self, run: bool) -> Parsed {
+        let token = self.next();
+        let Token::Word(raw) = token else {
+            return Err(syntax_error(token));
+        };
+        match raw {
+            b"if" => self.parse_if(run),
+            b"while" => self.parse_while(run, false),
+            b"until" => self.parse_while(run, true),
+            b"for" => self.parse_for(run),
+            b"{" => {
+                self.parse_list(run, &[b"}"])?;
+                self.expect(b"}")
+            }
+            _ => Err(syntax_error(token)),
+        }
+    }
+
+    /// `if list; then list; [elif list; then list;]... [else list;] fi`
+    fn parse_if(&mut self, run: bool) -> Parsed {
+        self.parse_list(run, &[b"then"])?;
+        self.expect(b"then")?;
+        let mut taken = false;
+        let mut branch = Self::active(run) && last_status() == EXIT_SUCCESS;
+
+        loop {
+            self.parse_list(branch, &[b"elif", b"else", b"fi"])?;
+            taken |= branch;
+            let token = self.next();
+            if is_keyword(token, b"elif") {
+                let test = Self::active(run) && !taken;
+                self.parse_list(test, &[b"then"])?;
+                self.expect(b"then")?;
+                branch = test && Self::active(run) && last_status() == EXIT_SUCCESS;
+            } else if is_keyword(token, b"else") {
+                branch = Self::active(run) && !taken;
+                self.parse_list(branch, &[b"fi"])?;
+                taken |= branch;
+                self.expect(b"fi")?;
+                break;
+            } else if is_keyword(token, b"fi") {
+                break;
+            } else {
+                return Err(syntax_error(token));
+            }
+        }
+
+        if Self::active(run) && !taken {
+            set_last_status(EXIT_SUCCESS);
+        }
+        Ok(())
+    }
+
+    /// Ends a loop iteration. Returns `true` if the loop must stop.
+    fn end_iteration() -> Parsed<bool> {
+        if shell::interrupted() {
+            eprintln!("^C");
+            return Err(EXIT_INTERRUPTED);
+        }
+        match flow() {
+            Flow::Normal => Ok(false),
+            Flow::Continue => {
+                request(Flow::Normal);
+                Ok(false)
+            }
+            Flow::Break => {
+                request(Flow::Normal);
+                Ok(true)
+            }
+            Flow::Return => Ok(true),
+        }
+    }
+
+    /// `while list; do list; done` (or `until`, which loops while the
+    /// condition fails)
+    fn parse_while(&mut self, run: bool, until: bool) -> Parsed {
+        let cond = self.lexer.pos();
+        let mut status = EXIT_SUCCESS;
+        unsafe { LOOP_DEPTH += 1; }
+
+        let result = loop {
+            self.lexer.seek(cond);
+            if let Err(err) = self.parse_list(run, &[b"do"]).and_then(|_| self.expect(b"do")) {
+                break Err(err);
+            }
+            let go = Self::active(run) && (last_status() == EXIT_SUCCESS) != until;
+            if let Err(err) = self.parse_list(go, &[b"done"]).and_then(|_| self.expect(b"done")) {
+                break Err(err);
+            }
+            if !go {
+                break Ok(());
+            }
+            status = last_status();
+            match Self::end_iteration() {
+                Ok(false) => {}
+                Ok(true) => break Ok(()),
+                Err(err) => break Err(err),
+            }
+        };
+
+        unsafe { LOOP_DEPTH -= 1; }
+        if Self::active(run) {
+            set_last_status(status);
+        }
+        result
+    }
+
+    /// `for NAME [in word...]; do list; done`; without `in`, loops over
+    /// the positional parameters
+    fn parse_for(&mut self, run: bool) -> Parsed {
+        let token = self.next();
+        let Token::Word(name) = token else {
+            return Err(syntax_error(token));
+        };
+        if !vars::is_valid_name(name) {
+            return Err(syntax_error(token));
+        }
+
+        let mark = parser::mark();
+        let result = self.parse_for_words(run, name);
+        parser::release(mark);
+        result
+    }
+
+    fn parse_for_words(&mut self, run: bool, name: &[u8]) -> Parsed {
+        let mut words: [&'static [u8]; MAX_WORDS] = [&[]; MAX_WORDS];
+        let mut count = 0;
+        let mut push = |word: &'static [u8]| -> Parsed {
+            if count >= MAX_WORDS {
+                eprintln!("tacos: for: too many words (max {})", MAX_WORDS);
+                return Err(EXIT_USAGE);
+            }
+            unsafe { *words.get_unchecked_mut(count) = word; }
+            count += 1;
+            Ok(())
+        };
+
+        self.skip_newlines();
+        if is_keyword(self.peek(), b"in") {
+            self.next();
+            while let Token::Word(raw) = self.peek() {
+                self.next();
+                if !run {
+                    continue;
+                }
+                if parser::is_all_params(raw) {
+                    (1..=vars::param_count()).try_for_each(|n| push(vars::param(n).unwrap_or(&[])))?;
+                    continue;
+                }
+                let Some(word) = parser::expand_word(raw) else {
+                    return Err(line_too_long());
+                };
+                if !word.is_empty() || parser::is_quoted(raw) {
+                    push(word)?;
+                }
+            }
+        } else if run {
+            (1..=vars::param_count()).try_for_each(|n| push(vars::param(n).unwrap_or(&[])))?;
+        }
+        if matches!(self.peek(), Token::Semi) {
+            self.next();
+        }
+        self.skip_newlines();
+        self.expect(b"do")?;
+
+        let body = self.lexer.pos();
+        let mut status = EXIT_SUCCESS;
+        unsafe { LOOP_DEPTH += 1; }
+
+        let mut result = Ok(());
+        let mut ran = false;
+        for &word in unsafe { words.get_unchecked(..count) } {
+            if !Self::active(run) {
+                break;
+            }
+            if let Err(err) = vars::set(name, word) {
+                eprintln!("tacos: {}: {}", name, vars::error_message(err));
+                status = EXIT_FAILURE;
+                break;
+            }
+            self.lexer.seek(body);
+            ran = true;
+            if let Err(err) = self.parse_list(true, &[b"done"]).and_then(|_| self.expect(b"done")) {
+                result = Err(err);
+                break;
+            }
+            status = last_status();
+            match Self::end_iteration() {
+                Ok(false) => {}
+                Ok(true) => break,
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+        if result.is_ok() && !ran {
+            // No iteration ran: skip over the body
+            result = self.parse_list(false, &[b"done"]).and_then(|_| self.expect(b"done"));
+        }
+
+        unsafe { LOOP_DEPTH -= 1; }
+        if run && flow() != Flow::Return {
+            set_last_status(status);
+        }
+        result
+    }
+
+    /// Stores a function whose name and `()` were consumed.
+    fn parse_function(&mut self, run: bool, name: &[u8]) -> Parsed<Option<usize>> {
+        if !vars::is_valid_name(name) {
+            return Err(syntax_error(Token::Word(name)));
+        }
+        self.skip_newlines();
+        let start = self.lexer.pos();
+        let token = self.peek();
+        if !matches!(token, Token::Word(raw) if is_one_of(raw, COMPOUND_KEYWORDS)) {
+            return Err(syntax_error(token));
+        }
+        self.parse_compound(false)?;
+
+        if run {
+            let body = self.lexer.slice(start, self.lexer.pos());
+            let status = match functions::define(name, body) {
+                Ok(()) => EXIT_SUCCESS,
+                Err(err) => {
+                    eprintln!("tacos: {}: {}", name, functions::error_message(err));
+                    EXIT_FAILURE
+                }
+            };
+            set_last_status(status);
+        }
+        Ok(None)
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Running commands
+// ──────────────────────────────────────────────
+
+/// Sets shell variables, reporting failures. Returns `false` on error.
+fn assign(assigns: &[Assignment]) -> bool {
+    for &(name, value) in assigns {
+        if let Err(err) = vars::set(name, value) {
+            eprintln!("tacos: {}: {}", name, vars::error_message(err));
+            return false;
+        }
+    }
+    true
+}
+
+/// Runs a function or command.
+fn invoke(argv: &[&[u8]]) -> ExitStatus {
+    let Some(&name) = argv.first() else {
+        return EXIT_SUCCESS;
+    };
+    match functions::get(name) {
+        Some(body) => call(body, argv),
+        None => run_command(argv),
+    }
+}
+
+/// Runs one command with its prefix assignments in effect, then restores
+/// the previous values of the assigned variables.
+fn run_with_assignments(cmd: &Simple) -> ExitStatus {
+    if cmd.assign_count == 0 {
+        return invoke(cmd.argv());
+    }
+
+    let mut saved: [Option<&'static [u8]>; MAX_ASSIGNS] = [None; MAX_ASSIGNS];
+    for (i, &(name, _)) in cmd.assigns().iter().enumerate() {
+        let old = match vars::get(name) {
+            Some(value) => match parser::store(value) {
+                Some(copy) => Some(copy),
+                None => return line_too_long(),
+            },
+            None => None,
+        };
+        unsafe { *saved.get_unchecked_mut(i) = old; }
+    }
+
+    let status = if assign(cmd.assigns()) { invoke(cmd.argv()) } else { EXIT_FAILURE };
+
+    for (i, &(name, _)) in cmd.assigns().iter().enumerate() {
+        match unsafe { *saved.get_unchecked(i) } {
+            Some(old) => {
+                let _ = vars::set(name, old);
+            }
+            None => {
+                vars::unset(name);
+            }
+        }
+    }
+    status
+}
+
+/// Runs `source` with `argv` as positional parameters (`argv[0]` is `$0`).
+/// `return` leaves it.
+fn call(source: &[u8], argv: &[&[u8]]) -> ExitStatus {
+    if unsafe { CALL_DEPTH } >= MAX_CALL_DEPTH {
+        eprintln!("tacos: {}: maximum call depth exceeded", argv.first().copied().unwrap_or(&[]));
+        return EXIT_FAILURE;
+    }
+
+    // Parameters live in the arena until the caller releases its words
+    let mark = parser::mark();
+    let mut params: [&'static [u8]; vars::MAX_PARAMS] = [&[]; vars::MAX_PARAMS];
+    for (i, &arg) in argv.iter().take(vars::MAX_PARAMS).enumerate() {
+        let Some(arg) = parser::store(arg) else {
+            parser::release(mark);
+            return line_too_long();
+        };
+        unsafe { *params.get_unchecked_mut(i) = arg; }
+    }
+    let count = argv.len().min(vars::MAX_PARAMS);
+    let saved_params = vars::set_params(Params::from_argv(unsafe { params.get_unchecked(..count) }));
+    let saved_loops = unsafe { LOOP_DEPTH };
+    unsafe {
+        CALL_DEPTH += 1;
+        LOOP_DEPTH = 0;
+    }
+
+    let status = run_source(source);
+    if flow() == Flow::Return {
+        request(Flow::Normal);
+    }
+
+    unsafe {
+        CALL_DEPTH -= 1;
+        LOOP_DEPTH = saved_loops;
+    }
+    vars::set_params(saved_params);
+    parser::release(mark);
+    status
+}
+
+/// Checks the syntax of `source`, then runs it.
+/// Returns the status of the last command run.
+fn run_source(source: &[u8]) -> ExitStatus {
+    if let Err(status) = Interp::new(source).parse_list(false, &[]) {
+        set_last_status(status);
+        return status;
+    }
+    match Interp::new(source).parse_list(true, &[]) {
+        Ok(()) => last_status(),
+        Err(status) => {
+            set_last_status(status);
+            status
+        }
+    }
+}
+
+/// Runs one interactive command line.
+pub fn run_line(line: &[u8]) -> ExitStatus {
+    let status = run_source(line);
+    // An aborted line may leave redirections or control flow behind
+    stream::reset();
+    request(Flow::Normal);
+    status
+}
+
+/// Runs a script (`source NAME ARGS...`, boot scripts): `argv[0]` is its
+/// name, the rest its positional parameters.
+pub fn run_script(source: &[u8], argv: &[&[u8]]) -> ExitStatus {
+    call(source, argv)
+}
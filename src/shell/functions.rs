@@ -0,0 +1,81 @@
+/// Shell functions.
+///
+/// `name() { ...; }` stores the source text of the body, which is run by
+/// the interpreter each time the function is called. Bodies are copied
+/// because the line that defined them is overwritten by the next one.
+
+const MAX_FUNCTIONS: usize = 16;
+const NAME_SIZE: usize = 16;
+const BODY_SIZE: usize = 512;
+
+/// Why a function could not be defined.
+pub enum FunctionError {
+    /// The name or body does not fit in a slot.
+    TooLong,
+    /// Every slot is in use.
+    Full,
+}
+
+struct Function {
+    name: [u8; NAME_SIZE],
+    name_len: usize,
+    body: [u8; BODY_SIZE],
+    body_len: usize,
+}
+
+impl Function {
+    const fn new() -> Self {
+        Function { name: [0; NAME_SIZE], name_len: 0, body: [0; BODY_SIZE], body_len: 0 }
+    }
+
+    fn name(&self) -> &[u8] {
+        unsafe { self.name.get_unchecked(..self.name_len) }
+    }
+
+    fn body(&self) -> &[u8] {
+        unsafe { self.body.get_unchecked(..self.body_len) }
+    }
+}
+
+static mut FUNCTIONS: [Function; MAX_FUNCTIONS] = [const { Function::new() }; MAX_FUNCTIONS];
+
+fn find(name: &[u8]) -> Option<usize> {
+    let functions = unsafe { &*core::ptr::addr_of!(FUNCTIONS) };
+    functions.iter().position(|f| f.name_len > 0 && f.name() == name)
+}
+
+/// Defines or redefines a function.
+pub fn define(name: &[u8], body: &[u8]) -> Result<(), FunctionError> {
+    if name.len() > NAME_SIZE || body.len() > BODY_SIZE {
+        return Err(FunctionError::TooLong);
+    }
+    let functions = unsafe { &mut *core::ptr::addr_of_mut!(FUNCTIONS) };
+    let idx = match find(name) {
+        Some(idx) => idx,
+        None => functions.iter().position(|f| f.name_len == 0).ok_or(FunctionError::Full)?,
+    };
+    let function = unsafe { functions.get_unchecked_mut(idx) };
+    for (i, &c) in name.iter().enumerate() {
+        unsafe { *function.name.get_unchecked_mut(i) = c; }
+    }
+    function.name_len = name.len();
+    for (i, &c) in body.iter().enumerate() {
+        unsafe { *function.body.get_unchecked_mut(i) = c; }
+    }
+    function.body_len = body.len();
+    Ok(())
+}
+
+/// Returns the body of a function.
+pub fn get(name: &[u8]) -> Option<&'static [u8]> {
+    let idx = find(name)?;
+    Some(unsafe { (*core::ptr::addr_of!(FUNCTIONS)).get_unchecked(idx).body() })
+}
+
+/// Describes a `define` failure for error messages.
+pub fn error_message(err: FunctionError) -> &'static str {
+    match err {
+        FunctionError::TooLong => "name or body too long",
+        FunctionError::Full => "too many functions",
+    }
+}
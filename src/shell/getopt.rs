@@ -0,0 +1,334 @@
+//! getopt-style argument parser shared by every builtin.
+//!
+//! Each command declares its options as a static `OptSpec` table. The
+//! parser accepts, in any order with the operands:
+//!   -a -b        separate short flags
+//!   -ab          clustered short flags
+//!   -n5 / -n 5   short option with a value
+//!   --name       long flag
+//!   --name=5 / --name 5   long option with a value
+//!   --           end of options, everything after is an operand
+//!   -            a lone dash is an operand
+//!
+//! `--help` is recognized for every command unless it declares it.
+//!
+//! Commands whose operands may look like options (`test -n STRING`)
+//! declare `Options::Raw`: every argument is then an operand.
+
+/// Maximum number of options a command may declare.
+pub const MAX_OPTIONS: usize = 16;
+/// Maximum number of operands kept after parsing.
+pub const MAX_OPERANDS: usize = 32;
+
+/// One option accepted by a command.
+pub struct OptSpec {
+    /// Short form letter (`b'c'` for `-c`).
+    pub short: u8,
+    /// Long form including the dashes (`"--clear"`).
+    pub long: &'static str,
+    /// Name of the value for options taking one (`Some("LEVEL")`), `None` for flags.
+    pub value: Option<&'static str>,
+    /// One-line description shown by `help <cmd>`.
+    pub help: &'static str,
+}
+
+/// How a command takes its arguments.
+#[derive(Copy, Clone)]
+pub enum Options {
+    /// The options in the table, plus `--help`.
+    Spec(&'static [OptSpec]),
+    /// No option parsing: `-x` words are operands and `--help` is not
+    /// recognized, for commands that parse their own arguments.
+    Raw,
+}
+
+impl Options {
+    /// The declared options, none for `Raw`.
+    pub fn spec(self) -> &'static [OptSpec] {
+        match self {
+            Options::Spec(spec) => spec,
+            Options::Raw => &[],
+        }
+    }
+}
+
+/// Why parsing failed.
+pub enum OptError<'a> {
+    /// A short option letter that is not in the spec.
+    UnknownShort(u8),
+    /// A long option (with its dashes) that is not in the spec.
+    UnknownLong(&'a [u8]),
+    /// An option requiring a value was last on the line.
+    MissingValue(&'static OptSpec),
+    /// `--help` was given.
+    Help,
+}
+
+/// Parsed command line.
+pub struct Args<'a> {
+    argv: &'a [&'a [u8]],
+    spec: &'static [OptSpec],
+    found: [bool; MAX_OPTIONS],
+    values: [&'a [u8]; MAX_OPTIONS],
+    operands: [&'a [u8]; MAX_OPERANDS],
+    operand_count: usize,
+}
+
+impl<'a> Args<'a> {
+    /// Command name (`argv[0]`).
+    pub fn name(&self) -> &'a [u8] {
+        self.argv.first().copied().unwrap_or(&[])
+    }
+
+    /// Raw argument vector, options included.
+    pub fn argv(&self) -> &'a [&'a [u8]] {
+        self.argv
+    }
+
+    fn index_of(&self, short: u8) -> Option<usize> {
+        self.spec.iter().position(|opt| opt.short == short)
+    }
+
+    /// Returns `true` if option `-short` was given.
+    pub fn has(&self, short: u8) -> bool {
+        match self.index_of(short) {
+            Some(i) => unsafe { *self.found.get_unchecked(i) },
+            None => false,
+        }
+    }
+
+    /// Returns the value of option `-short`, if given.
+    pub fn value(&self, short: u8) -> Option<&'a [u8]> {
+        let i = self.index_of(short)?;
+        if unsafe { *self.found.get_unchecked(i) } {
+            Some(unsafe { *self.values.get_unchecked(i) })
+        } else {
+            None
+        }
+    }
+
+    /// Arguments that are not options, in order.
+    pub fn operands(&self) -> &[&'a [u8]] {
+        unsafe { self.operands.get_unchecked(..self.operand_count) }
+    }
+
+    fn set(&mut self, i: usize, value: &'a [u8]) {
+        if i < MAX_OPTIONS {
+            unsafe {
+                *self.found.get_unchecked_mut(i) = true;
+                *self.values.get_unchecked_mut(i) = value;
+            }
+        }
+    }
+
+    fn push_operand(&mut self, arg: &'a [u8]) {
+        if self.operand_count < MAX_OPERANDS {
+            unsafe { *self.operands.get_unchecked_mut(self.operand_count) = arg; }
+            self.operand_count += 1;
+        }
+    }
+}
+
+/// Parses `argv[1..]` against `options`.
+pub fn parse<'a>(options: Options, argv: &'a [&'a [u8]]) -> Result<Args<'a>, OptError<'a>> {
+    let spec = options.spec();
+    let mut args = Args {
+        argv,
+        spec,
+        found: [false; MAX_OPTIONS],
+        values: [&[]; MAX_OPTIONS],
+        operands: [&[]; MAX_OPERANDS],
+        operand_count: 0,
+    };
+
+    let mut i = 1;
+    let mut options_done = matches!(options, Options::Raw);
+    while i < argv.len() {
+        let arg = unsafe { *argv.get_unchecked(i) };
+        i += 1;
+
+        if options_done || arg.len() < 2 || unsafe { *arg.get_unchecked(0) } != b'-' {
+            args.push_operand(arg);
+            continue;
+        }
+
+        if unsafe { *arg.get_unchecked(1) } == b'-' {
+            // Long option
+            if arg.len() == 2 {
+                options_done = true;
+                continue;
+            }
+            let eq = arg.iter().position(|&c| c == b'=');
+            let name = unsafe { arg.get_unchecked(..eq.unwrap_or(arg.len())) };
+            let Some(idx) = spec.iter().position(|opt| opt.long.as_bytes() == name) else {
+                if name == b"--help" {
+                    return Err(OptError::Help);
+                }
+                return Err(OptError::UnknownLong(name));
+            };
+            let opt = unsafe { spec.get_unchecked(idx) };
+            let value: &[u8] = match (opt.value, eq) {
+                (None, _) => &[],
+                (Some(_), Some(eq)) => unsafe { arg.get_unchecked(eq + 1..) },
+                (Some(_), None) => {
+                    let Some(&next) = argv.get(i) else {
+                        return Err(OptError::MissingValue(opt));
+                    };
+                    i += 1;
+                    next
+                }
+            };
+            args.set(idx, value);
+            continue;
+        }
+
+        // Cluster of short options
+        let mut j = 1;
+        while j < arg.len() {
+            let c = unsafe { *arg.get_unchecked(j) };
+            let Some(idx) = spec.iter().position(|opt| opt.short == c) else {
+                return Err(OptError::UnknownShort(c));
+            };
+            let opt = unsafe { spec.get_unchecked(idx) };
+            j += 1;
+            if opt.value.is_none() {
+                args.set(idx, &[]);
+                continue;
+            }
+            // The rest of the cluster, or the next argument, is the value
+            let value = if j < arg.len() {
+                unsafe { arg.get_unchecked(j..) }
+            } else {
+                let Some(&next) = argv.get(i) else {
+                    return Err(OptError::MissingValue(opt));
+                };
+                i += 1;
+                next
+            };
+            args.set(idx, value);
+            break;
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SPEC: &[OptSpec] = &[
+        OptSpec { short: b'a', long: "--all", value: None, help: "" },
+        OptSpec { short: b'b', long: "--brief", value: None, help: "" },
+        OptSpec { short: b'n', long: "--lines", value: Some("N"), help: "" },
+    ];
+
+    fn parse_spec<'a>(argv: &'a [&'a [u8]]) -> Result<Args<'a>, OptError<'a>> {
+        parse(Options::Spec(SPEC), argv)
+    }
+
+    // ── short options ──
+
+    #[test]
+    fn separate_and_clustered_flags() {
+        let args = parse_spec(&[b"cmd", b"-a", b"-b"]).ok().unwrap();
+        assert!(args.has(b'a') && args.has(b'b'));
+        let args = parse_spec(&[b"cmd", b"-ab"]).ok().unwrap();
+        assert!(args.has(b'a') && args.has(b'b'));
+        assert!(!args.has(b'n'));
+        assert!(args.operands().is_empty());
+    }
+
+    #[test]
+    fn short_value_attached_or_separate() {
+        let args = parse_spec(&[b"cmd", b"-n5"]).ok().unwrap();
+        assert_eq!(args.value(b'n'), Some(&b"5"[..]));
+        let args = parse_spec(&[b"cmd", b"-n", b"5", b"file"]).ok().unwrap();
+        assert_eq!(args.value(b'n'), Some(&b"5"[..]));
+        assert_eq!(args.operands(), &[&b"file"[..]]);
+    }
+
+    #[test]
+    fn value_ends_a_cluster() {
+        let args = parse_spec(&[b"cmd", b"-an5b"]).ok().unwrap();
+        assert!(args.has(b'a'));
+        assert!(!args.has(b'b'));
+        assert_eq!(args.value(b'n'), Some(&b"5b"[..]));
+    }
+
+    #[test]
+    fn unknown_short_option() {
+        assert!(matches!(parse_spec(&[b"cmd", b"-ax"]), Err(OptError::UnknownShort(b'x'))));
+    }
+
+    // ── long options ──
+
+    #[test]
+    fn long_flag_and_values() {
+        let args = parse_spec(&[b"cmd", b"--all", b"--lines=7"]).ok().unwrap();
+        assert!(args.has(b'a'));
+        assert_eq!(args.value(b'n'), Some(&b"7"[..]));
+        let args = parse_spec(&[b"cmd", b"--lines", b"8"]).ok().unwrap();
+        assert_eq!(args.value(b'n'), Some(&b"8"[..]));
+        let args = parse_spec(&[b"cmd", b"--lines="]).ok().unwrap();
+        assert_eq!(args.value(b'n'), Some(&b""[..]));
+    }
+
+    #[test]
+    fn unknown_long_option() {
+        match parse_spec(&[b"cmd", b"--color=auto"]) {
+            Err(OptError::UnknownLong(name)) => assert_eq!(name, b"--color"),
+            _ => panic!("expected UnknownLong"),
+        }
+    }
+
+    #[test]
+    fn help_unless_declared() {
+        assert!(matches!(parse_spec(&[b"cmd", b"--help"]), Err(OptError::Help)));
+        static WITH_HELP: &[OptSpec] = &[OptSpec { short: b'h', long: "--help", value: None, help: "" }];
+        let args = parse(Options::Spec(WITH_HELP), &[b"cmd", b"--help"]).ok().unwrap();
+        assert!(args.has(b'h'));
+    }
+
+    // ── missing values ──
+
+    #[test]
+    fn missing_value_names_the_option() {
+        match parse_spec(&[b"cmd", b"-n"]) {
+            Err(OptError::MissingValue(opt)) => assert_eq!(opt.short, b'n'),
+            _ => panic!("expected MissingValue"),
+        }
+        assert!(matches!(parse_spec(&[b"cmd", b"-an"]), Err(OptError::MissingValue(_))));
+        assert!(matches!(parse_spec(&[b"cmd", b"--lines"]), Err(OptError::MissingValue(_))));
+    }
+
+    // ── operands ──
+
+    #[test]
+    fn double_dash_ends_options() {
+        let args = parse_spec(&[b"cmd", b"-a", b"--", b"-b", b"--lines"]).ok().unwrap();
+        assert!(args.has(b'a'));
+        assert!(!args.has(b'b'));
+        assert_eq!(args.operands(), &[&b"-b"[..], &b"--lines"[..]]);
+    }
+
+    #[test]
+    fn lone_dash_is_an_operand() {
+        let args = parse_spec(&[b"cmd", b"-", b"-a"]).ok().unwrap();
+        assert!(args.has(b'a'));
+        assert_eq!(args.operands(), &[&b"-"[..]]);
+    }
+
+    #[test]
+    fn operands_mix_with_options() {
+        let args = parse_spec(&[b"cmd", b"x", b"-a", b"y"]).ok().unwrap();
+        assert_eq!(args.name(), b"cmd");
+        assert_eq!(args.operands(), &[&b"x"[..], &b"y"[..]]);
+    }
+
+    #[test]
+    fn raw_keeps_every_argument() {
+        let args = parse(Options::Raw, &[b"test", b"-n", b"--help", b"--"]).ok().unwrap();
+        assert!(!args.has(b'n'));
+        assert_eq!(args.operands(), &[&b"-n"[..], &b"--help"[..], &b"--"[..]]);
+    }
+}
@@ -0,0 +1,420 @@
+//! Shell command history.
+//!
+//! A ring of the last `HISTORY_SIZE` command lines. Entries are numbered
+//! from 1 like in bash: entry `n` lives in slot `(n - 1) % HISTORY_SIZE`
+//! and stays valid until `HISTORY_SIZE` newer lines have been pushed.
+//!
+//! Also provides Up/Down navigation (with the line being edited saved as
+//! a scratch entry), reverse substring search for Ctrl+R, and `!!` / `!n`
+//! / `!-n` event expansion.
+
+const HISTORY_SIZE: usize = 32;
+const LINE_SIZE: usize = 80;
+const EXPAND_BUF_SIZE: usize = 256;
+
+static mut LINES: [[u8; LINE_SIZE]; HISTORY_SIZE] = [[0; LINE_SIZE]; HISTORY_SIZE];
+static mut LENS: [usize; HISTORY_SIZE] = [0; HISTORY_SIZE];
+static mut COUNT: usize = 0; // Number of the newest entry (total lines ever pushed)
+
+// Navigation state: entry currently recalled (COUNT + 1 = editing line)
+static mut NAV: usize = 1;
+static mut SCRATCH: [u8; LINE_SIZE] = [0; LINE_SIZE];
+static mut SCRATCH_LEN: usize = 0;
+
+static mut EXPAND_BUF: [u8; EXPAND_BUF_SIZE] = [0; EXPAND_BUF_SIZE];
+
+// ──────────────────────────────────────────────
+//  Storage
+// ──────────────────────────────────────────────
+
+/// Number of the oldest entry still stored.
+pub fn first() -> usize {
+    unsafe { COUNT.saturating_sub(HISTORY_SIZE) + 1 }
+}
+
+/// Number of the newest entry (0 if history is empty).
+pub fn last() -> usize {
+    unsafe { COUNT }
+}
+
+/// Returns entry `n`, if still stored.
+pub fn get(n: usize) -> Option<&'static [u8]> {
+    if n == 0 || n < first() || n > last() {
+        return None;
+    }
+    let slot = (n - 1) % HISTORY_SIZE;
+    unsafe {
+        let line = (*core::ptr::addr_of!(LINES)).get_unchecked(slot);
+        Some(line.get_unchecked(..*(*core::ptr::addr_of!(LENS)).get_unchecked(slot)))
+    }
+}
+
+/// Appends a line. Blank lines and repeats of the newest entry are skipped.
+/// Returns `false` if the line is longer than `LINE_SIZE` and was not
+/// stored.
+pub fn push(line: &[u8]) -> bool {
+    reset_navigation();
+    if line.iter().all(|&c| c == b' ') {
+        return true;
+    }
+    if line.len() > LINE_SIZE {
+        return false;
+    }
+    if let Some(newest) = get(last()) {
+        if newest == line {
+            return true;
+        }
+    }
+    let len = line.len();
+    unsafe {
+        let slot = COUNT % HISTORY_SIZE;
+        let dst = (*core::ptr::addr_of_mut!(LINES)).get_unchecked_mut(slot);
+        copy(dst, line, len);
+        *(*core::ptr::addr_of_mut!(LENS)).get_unchecked_mut(slot) = len;
+        COUNT += 1;
+        NAV = COUNT + 1;
+    }
+    true
+}
+
+/// Forgets every entry.
+pub fn clear() {
+    unsafe {
+        COUNT = 0;
+        NAV = 1;
+    }
+}
+
+fn copy(dst: &mut [u8; LINE_SIZE], src: &[u8], len: usize) {
+    for i in 0..len {
+        unsafe { *dst.get_unchecked_mut(i) = *src.get_unchecked(i); }
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Up / Down navigation
+// ──────────────────────────────────────────────
+
+/// Leaves navigation mode; the next `prev()` starts from the newest entry.
+pub fn reset_navigation() {
+    unsafe { NAV = COUNT + 1; }
+}
+
+/// Steps to the previous entry. `current` is the line being edited,
+/// saved the first time we leave it.
+pub fn prev(current: &[u8]) -> Option<&'static [u8]> {
+    let nav = unsafe { NAV };
+    if nav <= first() || last() == 0 {
+        return None;
+    }
+    if nav == last() + 1 {
+        let len = current.len().min(LINE_SIZE);
+        unsafe {
+            copy(&mut *core::ptr::addr_of_mut!(SCRATCH), current, len);
+            SCRATCH_LEN = len;
+        }
+    }
+    unsafe { NAV = nav - 1; }
+    get(nav - 1)
+}
+
+/// Steps to the next entry, or back to the saved editing line.
+pub fn next() -> Option<&'static [u8]> {
+    let nav = unsafe { NAV };
+    if nav > last() {
+        return None;
+    }
+    unsafe { NAV = nav + 1; }
+    if nav == last() {
+        return Some(unsafe { (*core::ptr::addr_of!(SCRATCH)).get_unchecked(..SCRATCH_LEN) });
+    }
+    get(nav + 1)
+}
+
+// ──────────────────────────────────────────────
+//  Reverse search (Ctrl+R)
+// ──────────────────────────────────────────────
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    if needle.len() > haystack.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i + needle.len() <= haystack.len() {
+        let mut j = 0;
+        while j < needle.len()
+            && unsafe { *haystack.get_unchecked(i + j) == *needle.get_unchecked(j) }
+        {
+            j += 1;
+        }
+        if j == needle.len() {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Finds the newest entry strictly older than `before` containing `query`.
+/// Returns its number.
+pub fn search(query: &[u8], before: usize) -> Option<usize> {
+    let mut n = before.min(last() + 1);
+    while n > first() {
+        n -= 1;
+        if let Some(line) = get(n) {
+            if contains(line, query) {
+                return Some(n);
+            }
+        }
+    }
+    None
+}
+
+// ──────────────────────────────────────────────
+//  Event expansion (!!, !n, !-n)
+// ──────────────────────────────────────────────
+
+fn parse_number(input: &[u8], mut i: usize) -> (usize, usize) {
+    let mut n: usize = 0;
+    while i < input.len() && unsafe { input.get_unchecked(i) }.is_ascii_digit() {
+        n = n.saturating_mul(10).saturating_add((unsafe { *input.get_unchecked(i) } - b'0') as usize);
+        i += 1;
+    }
+    (n, i)
+}
+
+/// Result of `expand`.
+pub enum Expansion {
+    /// The line has no history reference.
+    Unchanged(&'static [u8]),
+    /// The line with its references replaced, to be echoed like bash does.
+    Expanded(&'static [u8]),
+    /// The reference (`!n`, ...) of an event that is not stored.
+    NotFound(&'static [u8]),
+}
+
+/// Expands history references outside single quotes.
+// Not `contains`: it calls libcore's memchr, which the kernel does not link
+#[allow(clippy::manual_contains)]
+pub fn expand(input: &'static [u8]) -> Expansion {
+    if !input.iter().any(|&c| c == b'!') {
+        return Expansion::Unchanged(input);
+    }
+    let buf = unsafe { &mut *core::ptr::addr_of_mut!(EXPAND_BUF) };
+    let mut out = 0;
+    let mut i = 0;
+    let mut in_single = false;
+    let mut expanded = false;
+
+    while i < input.len() {
+        let c = unsafe { *input.get_unchecked(i) };
+        let next = if i + 1 < input.len() { unsafe { *input.get_unchecked(i + 1) } } else { 0 };
+
+        let event = if c == b'!' && !in_single {
+            match next {
+                b'!' => Some((last(), i + 2)),
+                b'0'..=b'9' => Some(parse_number(input, i + 1)),
+                b'-' => {
+                    let (back, end) = parse_number(input, i + 2);
+                    if end == i + 2 { None } else { Some(((last() + 1).saturating_sub(back), end)) }
+                }
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        match event {
+            Some((n, end)) => {
+                let Some(line) = get(n) else {
+                    return Expansion::NotFound(unsafe { input.get_unchecked(i..end) });
+                };
+                for &b in line {
+                    if out < EXPAND_BUF_SIZE {
+                        unsafe { *buf.get_unchecked_mut(out) = b; }
+                        out += 1;
+                    }
+                }
+                expanded = true;
+                i = end;
+            }
+            None => {
+                if c == b'\'' {
+                    in_single = !in_single;
+                }
+                if out < EXPAND_BUF_SIZE {
+                    unsafe { *buf.get_unchecked_mut(out) = c; }
+                    out += 1;
+                }
+                i += 1;
+            }
+        }
+    }
+
+    if !expanded {
+        return Expansion::Unchanged(input);
+    }
+    Expansion::Expanded(unsafe { (*core::ptr::addr_of!(EXPAND_BUF)).get_unchecked(..out) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern crate std;
+
+    use std::sync::{Mutex, MutexGuard};
+
+    /// History is global: tests take turns.
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    fn fresh() -> MutexGuard<'static, ()> {
+        let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        clear();
+        guard
+    }
+
+    fn push_numbered(count: usize) {
+        for n in 1..=count {
+            let line = [b'a' + (n % 26) as u8, b'0' + (n / 26 % 10) as u8, b'0' + (n % 10) as u8];
+            assert!(push(&line));
+        }
+    }
+
+    fn expanded(input: &'static [u8]) -> &'static [u8] {
+        match expand(input) {
+            Expansion::Expanded(line) => line,
+            _ => panic!("not expanded"),
+        }
+    }
+
+    // ── ring ──
+
+    #[test]
+    fn entries_are_numbered_from_one() {
+        let _guard = fresh();
+        assert_eq!(last(), 0);
+        assert_eq!(get(1), None);
+        assert!(push(b"ls"));
+        assert!(push(b"cat x"));
+        assert_eq!((first(), last()), (1, 2));
+        assert_eq!(get(1), Some(&b"ls"[..]));
+        assert_eq!(get(2), Some(&b"cat x"[..]));
+        assert_eq!(get(0), None);
+        assert_eq!(get(3), None);
+    }
+
+    #[test]
+    fn blank_lines_and_repeats_are_skipped() {
+        let _guard = fresh();
+        assert!(push(b"ls"));
+        assert!(push(b"ls"));
+        assert!(push(b"   "));
+        assert!(push(b""));
+        assert_eq!(last(), 1);
+        assert!(push(b"cd"));
+        assert!(push(b"ls"));
+        assert_eq!(last(), 3);
+    }
+
+    #[test]
+    fn ring_keeps_the_newest_entries() {
+        let _guard = fresh();
+        push_numbered(HISTORY_SIZE + 5);
+        assert_eq!((first(), last()), (6, HISTORY_SIZE + 5));
+        assert_eq!(get(5), None);
+        assert_eq!(get(6), Some(&[b'g', b'0', b'6'][..]));
+        assert!(get(HISTORY_SIZE + 5).is_some());
+    }
+
+    #[test]
+    fn long_lines_are_rejected() {
+        let _guard = fresh();
+        assert!(!push(&[b'x'; LINE_SIZE + 1]));
+        assert_eq!(last(), 0);
+        assert!(push(&[b'x'; LINE_SIZE]));
+        assert_eq!(get(1).map(<[u8]>::len), Some(LINE_SIZE));
+    }
+
+    // ── navigation ──
+
+    #[test]
+    fn prev_and_next_walk_back_to_the_edited_line() {
+        let _guard = fresh();
+        push(b"one");
+        push(b"two");
+        assert_eq!(prev(b"draft"), Some(&b"two"[..]));
+        assert_eq!(prev(b"two"), Some(&b"one"[..]));
+        assert_eq!(prev(b"one"), None);
+        assert_eq!(next(), Some(&b"two"[..]));
+        assert_eq!(next(), Some(&b"draft"[..]));
+        assert_eq!(next(), None);
+    }
+
+    #[test]
+    fn push_resets_navigation() {
+        let _guard = fresh();
+        assert_eq!(prev(b""), None);
+        push(b"one");
+        push(b"two");
+        prev(b"");
+        prev(b"");
+        push(b"three");
+        assert_eq!(prev(b""), Some(&b"three"[..]));
+    }
+
+    #[test]
+    fn prev_stops_at_the_oldest_stored_entry() {
+        let _guard = fresh();
+        push_numbered(HISTORY_SIZE + 1);
+        for _ in 0..HISTORY_SIZE {
+            assert!(prev(b"").is_some());
+        }
+        assert_eq!(prev(b""), None);
+    }
+
+    // ── search ──
+
+    #[test]
+    fn search_finds_older_matches() {
+        let _guard = fresh();
+        push(b"cat log");
+        push(b"ls");
+        push(b"grep log x");
+        assert_eq!(search(b"log", usize::MAX), Some(3));
+        assert_eq!(search(b"log", 3), Some(1));
+        assert_eq!(search(b"log", 1), None);
+        assert_eq!(search(b"", usize::MAX), Some(3));
+        assert_eq!(search(b"nope", usize::MAX), None);
+    }
+
+    // ── expand ──
+
+    #[test]
+    fn lines_without_events_are_unchanged() {
+        let _guard = fresh();
+        push(b"ls");
+        assert!(matches!(expand(b"echo hi"), Expansion::Unchanged(b"echo hi")));
+        assert!(matches!(expand(b"echo '!!' ! !x"), Expansion::Unchanged(b"echo '!!' ! !x")));
+    }
+
+    #[test]
+    fn events_are_replaced() {
+        let _guard = fresh();
+        push(b"ls");
+        push(b"cat x");
+        assert_eq!(expanded(b"!!"), b"cat x");
+        assert_eq!(expanded(b"!1 | wc"), b"ls | wc");
+        assert_eq!(expanded(b"!-2;!-1"), b"ls;cat x");
+        assert_eq!(expanded(b"echo '!!' !!"), b"echo '!!' cat x");
+    }
+
+    #[test]
+    fn missing_events_are_reported() {
+        let _guard = fresh();
+        assert!(matches!(expand(b"!!"), Expansion::NotFound(b"!!")));
+        push(b"ls");
+        assert!(matches!(expand(b"echo !7 x"), Expansion::NotFound(b"!7")));
+        assert!(matches!(expand(b"!-2"), Expansion::NotFound(b"!-2")));
+    }
+}
@@ -1,4 +1,20 @@
+#[cfg(target_os = "none")]
 pub mod builtin;
+#[cfg(target_os = "none")]
+pub mod completion;
+#[cfg(target_os = "none")]
 pub mod console;
+#[cfg(target_os = "none")]
+pub mod exec;
+#[cfg(target_os = "none")]
+pub mod functions;
+pub mod getopt;
+pub mod history;
+pub mod parser;
+#[cfg(target_os = "none")]
+pub mod scripts;
+#[cfg(target_os = "none")]
 pub mod shell;
+pub mod vars;
+#[cfg(target_os = "none")]
 pub use shell::{handle_command, run};
@@ -0,0 +1,572 @@
+//! Command line lexer and word expansion.
+//!
+//! The lexer splits a line into raw words and operators. A raw word keeps
+//! its quotes, backslashes and `$` references; `expand_word` resolves them
+//! into the word arena, where expanded words live until the caller
+//! releases them.
+//!
+//! Unquoted operators end the current word, so `dmesg|grep x>out` lexes
+//! the same as `dmesg | grep x > out`. A `#` at the start of a word begins
+//! a comment running to the end of the line.
+//!
+//! Expansion happens when a command is about to run, so `A=1; echo $A`
+//! sees the new value. Expanded values are not split into several words,
+//! except for a word that is exactly `$@` or `"$@"` (see `is_all_params`).
+
+use crate::shell::vars::{self, last_status};
+
+const ARENA_SIZE: usize = 2048;
+
+static mut ARENA: [u8; ARENA_SIZE] = [0; ARENA_SIZE];
+static mut TOP: usize = 0;
+
+/// A lexical token.
+#[derive(Copy, Clone)]
+pub enum Token<'a> {
+    /// A word, quotes and escapes still in place.
+    Word(&'a [u8]),
+    /// `|`
+    Pipe,
+    /// `>`
+    RedirOut,
+    /// `>>`
+    RedirAppend,
+    /// `;`
+    Semi,
+    /// `&&`
+    AndIf,
+    /// `||`
+    OrIf,
+    /// A lone `&` (background jobs are not supported).
+    Amp,
+    /// `(`
+    LParen,
+    /// `)`
+    RParen,
+    /// A line break.
+    Newline,
+    /// End of input.
+    End,
+}
+
+impl<'a> Token<'a> {
+    /// Source text of the token, for error messages.
+    pub fn text(&self) -> &'a [u8] {
+        match *self {
+            Token::Word(raw) => raw,
+            Token::Pipe => b"|",
+            Token::RedirOut => b">",
+            Token::RedirAppend => b">>",
+            Token::Semi => b";",
+            Token::AndIf => b"&&",
+            Token::OrIf => b"||",
+            Token::Amp => b"&",
+            Token::LParen => b"(",
+            Token::RParen => b")",
+            Token::Newline | Token::End => b"newline",
+        }
+    }
+}
+
+fn is_blank(c: u8) -> bool {
+    c == b' ' || c == b'\t' || c == b'\r'
+}
+
+fn is_operator(c: u8) -> bool {
+    matches!(c, b'|' | b'>' | b';' | b'&' | b'(' | b')' | b'\n')
+}
+
+/// Splits a command line into tokens.
+pub struct Lexer<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Lexer { input, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    /// Current position in the input, for `seek`.
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Moves back (or forward) to a position returned by `pos`.
+    pub fn seek(&mut self, pos: usize) {
+        self.pos = pos.min(self.input.len());
+    }
+
+    /// Input between two positions.
+    pub fn slice(&self, start: usize, end: usize) -> &'a [u8] {
+        let end = end.min(self.input.len());
+        unsafe { self.input.get_unchecked(start.min(end)..end) }
+    }
+
+    /// Returns the next token, `Token::End` once the input is exhausted.
+    pub fn next_token(&mut self) -> Token<'a> {
+        while self.peek().is_some_and(is_blank) {
+            self.pos += 1;
+        }
+        if self.peek() == Some(b'#') {
+            while self.peek().is_some_and(|c| c != b'\n') {
+                self.pos += 1;
+            }
+        }
+        let Some(c) = self.peek() else {
+            return Token::End;
+        };
+
+        match c {
+            b'\n' => {
+                self.pos += 1;
+                Token::Newline
+            }
+            b'(' => {
+                self.pos += 1;
+                Token::LParen
+            }
+            b')' => {
+                self.pos += 1;
+                Token::RParen
+            }
+            b'|' => {
+                self.pos += 1;
+                if self.peek() == Some(b'|') {
+                    self.pos += 1;
+                    return Token::OrIf;
+                }
+                Token::Pipe
+            }
+            b'&' => {
+                self.pos += 1;
+                if self.peek() == Some(b'&') {
+                    self.pos += 1;
+                    return Token::AndIf;
+                }
+                Token::Amp
+            }
+            b';' => {
+                self.pos += 1;
+                Token::Semi
+            }
+            b'>' => {
+                self.pos += 1;
+                if self.peek() == Some(b'>') {
+                    self.pos += 1;
+                    return Token::RedirAppend;
+                }
+                Token::RedirOut
+            }
+            _ => Token::Word(self.scan_word()),
+        }
+    }
+
+    /// Consumes a raw word. An unterminated quote runs to the end of input.
+    fn scan_word(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if is_blank(c) || is_operator(c) {
+                break;
+            }
+            self.pos += 1;
+            if c == b'"' || c == b'\'' {
+                while let Some(q) = self.peek() {
+                    self.pos += 1;
+                    if q == c {
+                        break;
+                    }
+                    if q == b'\\' && self.peek().is_some() {
+                        self.pos += 1;
+                    }
+                }
+            } else if c == b'\\' && self.peek().is_some() {
+                self.pos += 1;
+            }
+        }
+        unsafe { self.input.get_unchecked(start..self.pos) }
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Word arena
+// ──────────────────────────────────────────────
+
+/// Current top of the arena, to be passed back to `release`.
+pub fn mark() -> usize {
+    unsafe { TOP }
+}
+
+/// Frees every word expanded since `mark`.
+pub fn release(mark: usize) {
+    unsafe { TOP = mark.min(TOP); }
+}
+
+fn push(c: u8) -> bool {
+    unsafe {
+        if TOP >= ARENA_SIZE {
+            return false;
+        }
+        *(*core::ptr::addr_of_mut!(ARENA)).get_unchecked_mut(TOP) = c;
+        TOP += 1;
+    }
+    true
+}
+
+/// Byte a backslash escape stands for: a control character for `\n`,
+/// `\t`, `\r`, `\v` and `\0`, else the escaped character itself, so that
+/// `\$`, `\|` or `\ ` come out literally.
+fn escape_char(c: u8) -> u8 {
+    match c {
+        b'n' => b'\n',
+        b't' => b'\t',
+        b'r' => b'\r',
+        b'v' => 0x0B,
+        b'0' => 0,
+        _ => c,
+    }
+}
+
+/// Copies bytes into the arena.
+///
+/// Returns `None` if the arena is full.
+pub fn store(bytes: &[u8]) -> Option<&'static [u8]> {
+    let start = mark();
+    for &c in bytes {
+        if !push(c) {
+            release(start);
+            return None;
+        }
+    }
+    Some(unsafe { (*core::ptr::addr_of!(ARENA)).get_unchecked(start..TOP) })
+}
+
+fn push_all(bytes: &[u8]) -> bool {
+    bytes.iter().all(|&c| push(c))
+}
+
+fn push_number(n: usize) -> bool {
+    let mut digits = [0u8; 10];
+    let mut n = n;
+    let mut len = 0;
+    loop {
+        unsafe { *digits.get_unchecked_mut(len) = b'0' + (n % 10) as u8; }
+        len += 1;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    (0..len).rev().all(|i| push(unsafe { *digits.get_unchecked(i) }))
+}
+
+fn push_status() -> bool {
+    push_number(last_status() as usize)
+}
+
+/// `$@` / `$*` inside a larger word: parameters joined by spaces.
+fn push_all_params() -> bool {
+    (1..=vars::param_count()).all(|n| {
+        (n == 1 || push(b' ')) && push_all(vars::param(n).unwrap_or(&[]))
+    })
+}
+
+/// Expands the `$` reference starting at `raw[i]` (just after the `$`).
+/// Returns the index after the reference and whether the arena had room.
+fn expand_reference(raw: &[u8], i: usize) -> (usize, bool) {
+    let len = raw.len();
+    let Some(&c) = raw.get(i) else {
+        return (i, push(b'$'));
+    };
+
+    match c {
+        b'?' => return (i + 1, push_status()),
+        b'#' => return (i + 1, push_number(vars::param_count())),
+        b'@' | b'*' => return (i + 1, push_all_params()),
+        b'0'..=b'9' => return (i + 1, push_all(vars::param((c - b'0') as usize).unwrap_or(&[]))),
+        _ => {}
+    }
+
+    let (start, end, next) = if c == b'{' {
+        let mut end = i + 1;
+        while end < len && unsafe { *raw.get_unchecked(end) } != b'}' {
+            end += 1;
+        }
+        if end >= len {
+            // No closing brace: keep the text as is
+            return (i, push(b'$'));
+        }
+        (i + 1, end, end + 1)
+    } else if vars::is_name_start(c) {
+        let mut end = i + 1;
+        while end < len && vars::is_name_char(unsafe { *raw.get_unchecked(end) }) {
+            end += 1;
+        }
+        (i, end, end)
+    } else {
+        return (i, push(b'$'));
+    };
+
+    let name = unsafe { raw.get_unchecked(start..end) };
+    let ok = match name {
+        b"?" => push_status(),
+        b"#" => push_number(vars::param_count()),
+        b"@" | b"*" => push_all_params(),
+        [digit @ b'0'..=b'9'] => push_all(vars::param((digit - b'0') as usize).unwrap_or(&[])),
+        _ => push_all(vars::get(name).unwrap_or(&[])),
+    };
+    (next, ok)
+}
+
+/// Resolves quotes, escapes and `$NAME` / `${NAME}` / `$?` references of a
+/// raw word into the arena. Nothing is expanded inside single quotes.
+///
+/// Returns `None` if the arena is full.
+pub fn expand_word(raw: &[u8]) -> Option<&'static [u8]> {
+    let start = mark();
+    let len = raw.len();
+    let mut i = 0;
+
+    while i < len {
+        let c = unsafe { *raw.get_unchecked(i) };
+        i += 1;
+        let ok = if c == b'"' || c == b'\'' {
+            let mut ok = true;
+            while i < len && unsafe { *raw.get_unchecked(i) } != c {
+                let mut q = unsafe { *raw.get_unchecked(i) };
+                if q == b'$' && c == b'"' {
+                    let (next, pushed) = expand_reference(raw, i + 1);
+                    ok &= pushed;
+                    i = next;
+                    continue;
+                }
+                if q == b'\\' && i + 1 < len {
+                    i += 1;
+                    q = escape_char(unsafe { *raw.get_unchecked(i) });
+                }
+                ok &= push(q);
+                i += 1;
+            }
+            // Skip closing quote
+            i += 1;
+            ok
+        } else if c == b'$' {
+            let (next, ok) = expand_reference(raw, i);
+            i = next;
+            ok
+        } else if c == b'\\' && i < len {
+            let esc = escape_char(unsafe { *raw.get_unchecked(i) });
+            i += 1;
+            push(esc)
+        } else {
+            push(c)
+        };
+        if !ok {
+            release(start);
+            return None;
+        }
+    }
+    Some(unsafe { (*core::ptr::addr_of!(ARENA)).get_unchecked(start..TOP) })
+}
+
+/// Returns `true` if a raw word is `$@` or `"$@"`, which expands to one
+/// word per positional parameter.
+pub fn is_all_params(raw: &[u8]) -> bool {
+    matches!(raw, b"$@" | b"\"$@\"")
+}
+
+/// Returns `true` if a raw word contains quotes. An unquoted word that
+/// expands to nothing is dropped from the command line.
+pub fn is_quoted(raw: &[u8]) -> bool {
+    raw.iter().any(|&c| c == b'"' || c == b'\'')
+}
+
+/// Splits a raw `NAME=value` word into its name and raw value.
+pub fn split_assignment(raw: &[u8]) -> Option<(&[u8], &[u8])> {
+    let eq = raw.iter().position(|&c| c == b'=')?;
+    let name = unsafe { raw.get_unchecked(..eq) };
+    if !vars::is_valid_name(name) {
+        return None;
+    }
+    Some((name, unsafe { raw.get_unchecked(eq + 1..) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern crate std;
+
+    use std::string::String;
+    use std::sync::MutexGuard;
+    use std::vec::Vec;
+
+    /// Locks the variables (see `vars::tests::fresh`) and empties the arena.
+    fn fresh() -> MutexGuard<'static, ()> {
+        let guard = vars::tests::fresh();
+        release(0);
+        guard
+    }
+
+    /// Tokens of `input`, words as `w(raw)` and operators as their text.
+    fn lex(input: &[u8]) -> Vec<String> {
+        let mut lexer = Lexer::new(input);
+        let mut tokens = Vec::new();
+        loop {
+            let token = lexer.next_token();
+            let text = String::from_utf8_lossy(token.text()).into_owned();
+            match token {
+                Token::End => return tokens,
+                Token::Word(_) => tokens.push(std::format!("w({text})")),
+                Token::Newline => tokens.push(String::from("\\n")),
+                _ => tokens.push(text),
+            }
+        }
+    }
+
+    fn expand(raw: &[u8]) -> &'static [u8] {
+        expand_word(raw).expect("arena full")
+    }
+
+    // ── lexer ──
+
+    #[test]
+    fn operators_end_words() {
+        assert_eq!(lex(b"dmesg|grep x>out"), ["w(dmesg)", "|", "w(grep)", "w(x)", ">", "w(out)"]);
+        assert_eq!(lex(b"a||b&&c;d&"), ["w(a)", "||", "w(b)", "&&", "w(c)", ";", "w(d)", "&"]);
+        assert_eq!(lex(b"(a)>>b\nc"), ["(", "w(a)", ")", ">>", "w(b)", "\\n", "w(c)"]);
+    }
+
+    #[test]
+    fn quotes_and_escapes_stay_in_words() {
+        assert_eq!(lex(b"echo \"a | b\" 'c;d' e\\|f g\\ h"), ["w(echo)", "w(\"a | b\")", "w('c;d')", "w(e\\|f)", "w(g\\ h)"]);
+        assert_eq!(lex(b"echo \"open ;"), ["w(echo)", "w(\"open ;)"]);
+    }
+
+    #[test]
+    fn comments_run_to_end_of_line() {
+        assert_eq!(lex(b"a # b | c\nd"), ["w(a)", "\\n", "w(d)"]);
+        assert_eq!(lex(b"a#b"), ["w(a#b)"]);
+    }
+
+    #[test]
+    fn seek_goes_back() {
+        let mut lexer = Lexer::new(b"a b");
+        let pos = lexer.pos();
+        assert!(matches!(lexer.next_token(), Token::Word(b"a")));
+        lexer.seek(pos);
+        assert!(matches!(lexer.next_token(), Token::Word(b"a")));
+        assert!(matches!(lexer.next_token(), Token::Word(b"b")));
+        assert!(matches!(lexer.next_token(), Token::End));
+    }
+
+    // ── quoting ──
+
+    #[test]
+    fn quotes_are_removed() {
+        let _guard = fresh();
+        assert!(vars::set(b"A", b"1").is_ok());
+        assert_eq!(expand(b"\"a b\""), b"a b");
+        assert_eq!(expand(b"x\"$A\"y"), b"x1y");
+        assert_eq!(expand(b"'$A'"), b"$A");
+        assert_eq!(expand(b"\"it's\""), b"it's");
+    }
+
+    #[test]
+    fn escapes() {
+        let _guard = fresh();
+        assert_eq!(expand(b"a\\nb"), b"a\nb");
+        assert_eq!(expand(b"\"a\\tb\""), b"a\tb");
+        assert_eq!(expand(b"\\$HOME"), b"$HOME");
+        assert_eq!(expand(b"\"\\$HOME\""), b"$HOME");
+        assert_eq!(expand(b"a\\|b\\;c\\>d\\&e\\ f"), b"a|b;c>d&e f");
+        assert_eq!(expand(b"\\\\\\\""), b"\\\"");
+    }
+
+    // ── references ──
+
+    #[test]
+    fn variables() {
+        let _guard = fresh();
+        assert!(vars::set(b"NAME", b"tacos").is_ok());
+        assert_eq!(expand(b"$NAME"), b"tacos");
+        assert_eq!(expand(b"${NAME}s"), b"tacoss");
+        assert_eq!(expand(b"$NAMEs"), b"");
+        assert_eq!(expand(b"[$UNSET]"), b"[]");
+        assert_eq!(expand(b"${NAME"), b"${NAME");
+        assert_eq!(expand(b"$"), b"$");
+        assert_eq!(expand(b"$-"), b"$-");
+    }
+
+    #[test]
+    fn special_parameters() {
+        let _guard = fresh();
+        vars::set_last_status(130);
+        assert_eq!(expand(b"$?"), b"130");
+        assert_eq!(expand(b"${?}"), b"130");
+        let saved = vars::set_params(vars::Params::from_argv(&[b"f", b"one", b"two"]));
+        assert_eq!(expand(b"$#"), b"2");
+        assert_eq!(expand(b"${#}"), b"2");
+        assert_eq!(expand(b"$0:$1:${2}:$3"), b"f:one:two:");
+        assert_eq!(expand(b"[$@]"), b"[one two]");
+        assert_eq!(expand(b"\"$*\""), b"one two");
+        vars::set_params(saved);
+        assert_eq!(expand(b"$#"), b"0");
+    }
+
+    #[test]
+    fn all_params_words() {
+        assert!(is_all_params(b"$@"));
+        assert!(is_all_params(b"\"$@\""));
+        assert!(!is_all_params(b"x$@"));
+        assert!(!is_all_params(b"$*"));
+    }
+
+    // ── assignments ──
+
+    #[test]
+    fn assignments() {
+        assert_eq!(split_assignment(b"A=1"), Some((&b"A"[..], &b"1"[..])));
+        assert_eq!(split_assignment(b"A=\"x=y\""), Some((&b"A"[..], &b"\"x=y\""[..])));
+        assert_eq!(split_assignment(b"A="), Some((&b"A"[..], &b""[..])));
+        assert_eq!(split_assignment(b"=1"), None);
+        assert_eq!(split_assignment(b"1A=1"), None);
+        assert_eq!(split_assignment(b"echo"), None);
+    }
+
+    // ── arena ──
+
+    #[test]
+    fn arena_overflow_fails_and_frees() {
+        let _guard = fresh();
+        assert!(store(&[b'x'; ARENA_SIZE + 1]).is_none());
+        assert_eq!(mark(), 0);
+        assert!(store(&[b'x'; ARENA_SIZE - 1]).is_some());
+        assert!(expand_word(b"yz").is_none());
+        assert_eq!(mark(), ARENA_SIZE - 1);
+        assert_eq!(expand(b"y"), b"y");
+        assert!(store(b"").is_some());
+        assert!(expand_word(b"$?").is_none());
+        release(0);
+        assert_eq!(expand(b"yz"), b"yz");
+    }
+
+    #[test]
+    fn release_frees_later_words() {
+        let _guard = fresh();
+        expand(b"abc");
+        let middle = mark();
+        assert_eq!(middle, 3);
+        expand(b"de");
+        assert_eq!(mark(), 5);
+        release(middle);
+        assert_eq!(expand(b"f"), b"f");
+        assert_eq!(mark(), 4);
+        release(0);
+        assert_eq!(mark(), 0);
+    }
+}
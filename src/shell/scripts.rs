@@ -0,0 +1,56 @@
+/// Script lookup for `source`.
+///
+/// A script is found, in order, among the named output buffers, the
+/// scripts embedded in the kernel image, and the modules GRUB loaded
+/// (`module /boot/init.sh init.sh` in grub.cfg).
+///
+/// A buffer can be rewritten by the script it holds (`source log` with
+/// `echo ... > log` inside), so `source` runs a copy of it.
+
+use crate::io::stream;
+use crate::multiboot;
+use crate::shell::completion::Completions;
+
+/// Scripts built into the kernel.
+static EMBEDDED: &[(&[u8], &[u8])] = &[
+    (b"selftest.sh", include_bytes!("../../scripts/selftest.sh")),
+];
+
+/// Source text of a script.
+pub enum Script {
+    /// Embedded or boot module text, which never changes.
+    Fixed(&'static [u8]),
+    /// Contents of a named buffer, valid until the buffer is written.
+    Buffer(&'static [u8]),
+}
+
+/// Returns the source text of the script named `name`.
+pub fn find(name: &[u8]) -> Option<Script> {
+    if let Some(buffer) = stream::buffer(name) {
+        return Some(Script::Buffer(buffer.as_bytes()));
+    }
+    if let Some(&(_, text)) = EMBEDDED.iter().find(|(script, _)| *script == name) {
+        return Some(Script::Fixed(text));
+    }
+    multiboot::find_module(name).map(Script::Fixed)
+}
+
+/// Completes script names.
+pub fn complete(arg_index: usize, out: &mut Completions) {
+    if arg_index != 1 {
+        return;
+    }
+    for &(name, _) in EMBEDDED {
+        out.offer(name);
+    }
+    for idx in 0..multiboot::module_count() {
+        if let Some(module) = multiboot::module(idx) {
+            out.offer(multiboot::module_name(&module));
+        }
+    }
+    for idx in 0..stream::BUFFER_COUNT {
+        if let Some((name, _)) = stream::buffer_at(idx) {
+            out.offer(name);
+        }
+    }
+}
@@ -1,44 +1,505 @@
-use crate::drivers::keyboard;
+use crate::drivers::{keyboard, mouse, ps2};
+use crate::drivers::keyboard::KeyEvent;
+use crate::drivers::ps2::Ps2Byte;
 use crate::drivers::port::outb;
 use crate::io::io_manager;
+use crate::io::klog;
+use crate::io::print_engine::PrintArg;
+use crate::klib::number;
 use crate::shell::console;
 use crate::shell::builtin;
-use crate::{print, println, printkln};
+use crate::shell::completion::ArgCompleter;
+use crate::shell::exec;
+use crate::shell::getopt::{self, Args, OptError, Options};
+use crate::shell::history::{self, Expansion};
+pub use crate::shell::vars::{last_status, set_last_status};
+use crate::multiboot;
+use crate::{eprintln, kformat, print, println, printkln};
 use core::arch::asm;
 
 pub fn run() -> ! {
     printkln!("Entering shell...");
     console::show_welcome_message();
+
+    if let Some(script) = multiboot::find_module(b"init.sh") {
+        printkln!("Running boot script init.sh");
+        exec::run_script(script, &[b"init.sh"]);
+    }
     console::show_prompt();
 
     loop {
-        if let Some(event) = keyboard::get_key_event() {
-            io_manager::handle_key_event(event);
+        // Messages logged in interrupt context
+        klog::flush_console();
+        // Packets decoded by the IRQ12 handler
+        while let Some(event) = mouse::next_event() {
+            io_manager::handle_mouse_event(event);
+        }
+        // Mouse bytes only come through here while the mouse is off
+        if let Some(Ps2Byte::Keyboard(scancode)) = ps2::poll() {
+            if let Some(event) = keyboard::handle_scancode(scancode) {
+                io_manager::handle_key_event(event);
+            }
+        }
+    }
+}
+
+/// Drains PS/2 input while a command runs. Returns `true` if Ctrl+C was
+/// pressed; other keys and mouse movements are dropped.
+pub fn interrupted() -> bool {
+    let mut interrupted = false;
+    while mouse::next_event().is_some() {}
+    while let Some(byte) = ps2::poll() {
+        if let Ps2Byte::Keyboard(scancode) = byte {
+            if let Some(KeyEvent::CtrlC) = keyboard::handle_scancode(scancode) {
+                interrupted = true;
+            }
         }
     }
+    interrupted
 }
 
-type CmdHandler = fn(argv: &'static [&'static [u8]]);
+/// Exit status returned by every command handler.
+pub type ExitStatus = u8;
 
-struct Command {
-    name: &'static [u8],
-    handler: CmdHandler,
+pub const EXIT_SUCCESS: ExitStatus = 0;
+pub const EXIT_FAILURE: ExitStatus = 1;
+/// Bad options or operands.
+pub const EXIT_USAGE: ExitStatus = 2;
+/// No command with that name.
+pub const EXIT_NOT_FOUND: ExitStatus = 127;
+/// Interrupted by Ctrl+C.
+pub const EXIT_INTERRUPTED: ExitStatus = 130;
+
+type CmdHandler = fn(args: &Args) -> ExitStatus;
+
+pub struct Command {
+    pub name: &'static [u8],
+    /// One-line description shown by `help`.
+    pub summary: &'static str,
+    /// Synopsis shown by `help <cmd>` and on usage errors.
+    pub usage: &'static str,
+    pub options: Options,
+    pub handler: CmdHandler,
+    /// Completer for operands; options are completed from `options`.
+    pub complete: Option<ArgCompleter>,
 }
 
-static COMMANDS: &[Command] = &[
-    Command { name: b"help",     handler: |_| help() },
-    Command { name: b"echo",     handler: builtin::echo::echo },
-    Command { name: b"tacos",    handler: |_| tacos() },
-    Command { name: b"shutdown", handler: |_| shutdown() },
-    Command { name: b"halt",     handler: |_| shutdown() },
-    Command { name: b"reboot",   handler: |_| reboot() },
-    Command { name: b"format_test",   handler: |_| format_test() },
-    Command { name: b"stack",    handler: |_| crate::klib::stack::print_stack() },
-    Command { name: b"stack_test",    handler: |_| stack_test() },
-    Command { name: b"gdt",      handler: |_| crate::gdt::print_gdt() },
-    Command { name: b"dmesg",    handler: builtin::dmesg::dmesg },
+pub static COMMANDS: &[Command] = &[
+    Command {
+        name: b"help",
+        summary: "List commands or describe one",
+        usage: "help [COMMAND]",
+        options: Options::Spec(&[]),
+        handler: builtin::help::help,
+        complete: Some(builtin::help::complete),
+    },
+    Command {
+        name: b"echo",
+        summary: "Print arguments separated by spaces",
+        usage: "echo [-n] [STRING]...",
+        options: Options::Spec(builtin::echo::OPTIONS),
+        handler: builtin::echo::echo,
+        complete: None,
+    },
+    Command {
+        name: b"tacos",
+        summary: "Eat some tacos",
+        usage: "tacos",
+        options: Options::Spec(&[]),
+        handler: tacos,
+        complete: None,
+    },
+    Command {
+        name: b"shutdown",
+        summary: "Power off the machine (QEMU isa-debug-exit)",
+        usage: "shutdown",
+        options: Options::Spec(&[]),
+        handler: shutdown,
+        complete: None,
+    },
+    Command {
+        name: b"halt",
+        summary: "Same as shutdown",
+        usage: "halt",
+        options: Options::Spec(&[]),
+        handler: shutdown,
+        complete: None,
+    },
+    Command {
+        name: b"reboot",
+        summary: "Reset the CPU through the keyboard controller",
+        usage: "reboot",
+        options: Options::Spec(&[]),
+        handler: reboot,
+        complete: None,
+    },
+    Command {
+        name: b"format_test",
+        summary: "Exercise the print engine format specifiers",
+        usage: "format_test",
+        options: Options::Spec(&[]),
+        handler: format_test,
+        complete: None,
+    },
+    Command {
+        name: b"stack",
+        summary: "Print the kernel stack trace",
+        usage: "stack",
+        options: Options::Spec(&[]),
+        handler: |_| { crate::klib::stack::print_stack(); EXIT_SUCCESS },
+        complete: None,
+    },
+    Command {
+        name: b"stack_test",
+        summary: "Print a stack trace from nested frames (5 by default)",
+        usage: "stack_test [LEVELS]",
+        options: Options::Spec(&[]),
+        handler: stack_test,
+        complete: None,
+    },
+    Command {
+        name: b"gdt",
+        summary: "Print the Global Descriptor Table",
+        usage: "gdt",
+        options: Options::Spec(&[]),
+        handler: |_| { crate::gdt::print_gdt(); EXIT_SUCCESS },
+        complete: None,
+    },
+    Command {
+        name: b"cpuinfo",
+        summary: "Print CPU identification and features (CPUID)",
+        usage: "cpuinfo",
+        options: Options::Spec(&[]),
+        handler: builtin::cpu::cpuinfo,
+        complete: None,
+    },
+    Command {
+        name: b"regs",
+        summary: "Print control, flags, segment and descriptor-table registers",
+        usage: "regs",
+        options: Options::Spec(&[]),
+        handler: builtin::cpu::regs,
+        complete: None,
+    },
+    Command {
+        name: b"hexdump",
+        summary: "Dump memory as hex and ASCII",
+        usage: "hexdump ADDR [LEN]",
+        options: Options::Spec(&[]),
+        handler: builtin::mem::hexdump,
+        complete: None,
+    },
+    Command {
+        name: b"peek8",
+        summary: "Read a 8-bit value from memory",
+        usage: "peek8 ADDR",
+        options: Options::Spec(&[]),
+        handler: builtin::mem::peek,
+        complete: None,
+    },
+    Command {
+        name: b"peek16",
+        summary: "Read a 16-bit value from memory",
+        usage: "peek16 ADDR",
+        options: Options::Spec(&[]),
+        handler: builtin::mem::peek,
+        complete: None,
+    },
+    Command {
+        name: b"peek32",
+        summary: "Read a 32-bit value from memory",
+        usage: "peek32 ADDR",
+        options: Options::Spec(&[]),
+        handler: builtin::mem::peek,
+        complete: None,
+    },
+    Command {
+        name: b"poke8",
+        summary: "Write a 8-bit value to memory",
+        usage: "poke8 ADDR VALUE",
+        options: Options::Spec(&[]),
+        handler: builtin::mem::poke,
+        complete: None,
+    },
+    Command {
+        name: b"poke16",
+        summary: "Write a 16-bit value to memory",
+        usage: "poke16 ADDR VALUE",
+        options: Options::Spec(&[]),
+        handler: builtin::mem::poke,
+        complete: None,
+    },
+    Command {
+        name: b"poke32",
+        summary: "Write a 32-bit value to memory",
+        usage: "poke32 ADDR VALUE",
+        options: Options::Spec(&[]),
+        handler: builtin::mem::poke,
+        complete: None,
+    },
+    Command {
+        name: b"memsearch",
+        summary: "Find a byte sequence or string in memory",
+        usage: "memsearch [-s] START LEN BYTE...|STRING",
+        options: Options::Spec(builtin::mem::MEMSEARCH_OPTIONS),
+        handler: builtin::mem::memsearch,
+        complete: None,
+    },
+    Command {
+        name: b"slabinfo",
+        summary: "Print slab cache statistics",
+        usage: "slabinfo",
+        options: Options::Spec(&[]),
+        handler: builtin::mm::slabinfo,
+        complete: None,
+    },
+    Command {
+        name: b"vmallocinfo",
+        summary: "List vmalloc areas with their owners",
+        usage: "vmallocinfo",
+        options: Options::Spec(&[]),
+        handler: builtin::mm::vmallocinfo,
+        complete: None,
+    },
+    Command {
+        name: b"stackusage",
+        summary: "Print the high-water mark of each kernel stack",
+        usage: "stackusage",
+        options: Options::Spec(&[]),
+        handler: builtin::mm::stackusage,
+        complete: None,
+    },
+    Command {
+        name: b"memleak",
+        summary: "Report heap blocks nothing points to (scan), or forget those (clear)",
+        usage: "memleak scan|clear",
+        options: Options::Spec(&[]),
+        handler: builtin::mm::memleak,
+        complete: None,
+    },
+    Command {
+        name: b"membench",
+        summary: "Compare memset/memcpy/memmove/memcmp/bcmp with byte loops",
+        usage: "membench [KIB]",
+        options: Options::Spec(&[]),
+        handler: builtin::mm::membench,
+        complete: None,
+    },
+    Command {
+        name: b"inb",
+        summary: "Read a 8-bit value from an I/O port",
+        usage: "inb PORT",
+        options: Options::Spec(&[]),
+        handler: builtin::port::port_in,
+        complete: None,
+    },
+    Command {
+        name: b"inw",
+        summary: "Read a 16-bit value from an I/O port",
+        usage: "inw PORT",
+        options: Options::Spec(&[]),
+        handler: builtin::port::port_in,
+        complete: None,
+    },
+    Command {
+        name: b"inl",
+        summary: "Read a 32-bit value from an I/O port",
+        usage: "inl PORT",
+        options: Options::Spec(&[]),
+        handler: builtin::port::port_in,
+        complete: None,
+    },
+    Command {
+        name: b"outb",
+        summary: "Write a 8-bit value to an I/O port",
+        usage: "outb PORT VALUE",
+        options: Options::Spec(&[]),
+        handler: builtin::port::port_out,
+        complete: None,
+    },
+    Command {
+        name: b"outw",
+        summary: "Write a 16-bit value to an I/O port",
+        usage: "outw PORT VALUE",
+        options: Options::Spec(&[]),
+        handler: builtin::port::port_out,
+        complete: None,
+    },
+    Command {
+        name: b"outl",
+        summary: "Write a 32-bit value to an I/O port",
+        usage: "outl PORT VALUE",
+        options: Options::Spec(&[]),
+        handler: builtin::port::port_out,
+        complete: None,
+    },
+    Command {
+        name: b"dmesg",
+        summary: "Print the kernel log buffer",
+        usage: "dmesg [-cPTw] [-l LIST] | dmesg -n LEVEL",
+        options: Options::Spec(builtin::dmesg::OPTIONS),
+        handler: builtin::dmesg::dmesg,
+        complete: None,
+    },
+    Command {
+        name: b"history",
+        summary: "List previous command lines",
+        usage: "history [-c]",
+        options: Options::Spec(builtin::history::OPTIONS),
+        handler: builtin::history::history,
+        complete: None,
+    },
+    Command {
+        name: b"cat",
+        summary: "Print named buffers or standard input",
+        usage: "cat [BUFFER]...",
+        options: Options::Spec(&[]),
+        handler: builtin::cat::cat,
+        complete: Some(builtin::buffers::complete),
+    },
+    Command {
+        name: b"grep",
+        summary: "Print lines containing a pattern",
+        usage: "grep [-icnv] PATTERN [BUFFER]",
+        options: Options::Spec(builtin::grep::OPTIONS),
+        handler: builtin::grep::grep,
+        complete: Some(builtin::grep::complete),
+    },
+    Command {
+        name: b"head",
+        summary: "Print the first lines of the input",
+        usage: "head [-n COUNT] [BUFFER]",
+        options: Options::Spec(builtin::head::OPTIONS),
+        handler: builtin::head::head,
+        complete: Some(builtin::buffers::complete),
+    },
+    Command {
+        name: b"tail",
+        summary: "Print the last lines of the input",
+        usage: "tail [-n COUNT] [BUFFER]",
+        options: Options::Spec(builtin::tail::OPTIONS),
+        handler: builtin::tail::tail,
+        complete: Some(builtin::buffers::complete),
+    },
+    Command {
+        name: b"wc",
+        summary: "Count lines, words and bytes of the input",
+        usage: "wc [-lwc] [BUFFER]",
+        options: Options::Spec(builtin::wc::OPTIONS),
+        handler: builtin::wc::wc,
+        complete: Some(builtin::buffers::complete),
+    },
+    Command {
+        name: b"sort",
+        summary: "Print the lines of the input in order",
+        usage: "sort [-nru] [BUFFER]",
+        options: Options::Spec(builtin::sort::OPTIONS),
+        handler: builtin::sort::sort,
+        complete: Some(builtin::buffers::complete),
+    },
+    Command {
+        name: b"buffers",
+        summary: "List or delete named output buffers",
+        usage: "buffers [-d NAME]",
+        options: Options::Spec(builtin::buffers::OPTIONS),
+        handler: builtin::buffers::buffers,
+        complete: Some(builtin::buffers::complete),
+    },
+    Command {
+        name: b"source",
+        summary: "Run a script from a buffer, the kernel or a boot module",
+        usage: "source SCRIPT [ARG]...",
+        options: Options::Spec(&[]),
+        handler: builtin::source::source,
+        complete: Some(crate::shell::scripts::complete),
+    },
+    Command {
+        name: b"test",
+        summary: "Evaluate a string or integer comparison",
+        usage: "test EXPRESSION",
+        options: Options::Raw,
+        handler: builtin::test::test,
+        complete: None,
+    },
+    Command {
+        name: b"[",
+        summary: "Same as test, with a closing ]",
+        usage: "[ EXPRESSION ]",
+        options: Options::Raw,
+        handler: builtin::test::test,
+        complete: None,
+    },
+    Command {
+        name: b"true",
+        summary: "Do nothing, successfully",
+        usage: "true",
+        options: Options::Spec(&[]),
+        handler: |_| EXIT_SUCCESS,
+        complete: None,
+    },
+    Command {
+        name: b"false",
+        summary: "Do nothing, unsuccessfully",
+        usage: "false",
+        options: Options::Spec(&[]),
+        handler: |_| EXIT_FAILURE,
+        complete: None,
+    },
+    Command {
+        name: b"break",
+        summary: "Leave the innermost loop",
+        usage: "break",
+        options: Options::Spec(&[]),
+        handler: builtin::flow::break_loop,
+        complete: None,
+    },
+    Command {
+        name: b"continue",
+        summary: "Start the next iteration of the innermost loop",
+        usage: "continue",
+        options: Options::Spec(&[]),
+        handler: builtin::flow::continue_loop,
+        complete: None,
+    },
+    Command {
+        name: b"return",
+        summary: "Leave a function or sourced script",
+        usage: "return [STATUS]",
+        options: Options::Spec(&[]),
+        handler: builtin::flow::return_call,
+        complete: None,
+    },
+    Command {
+        name: b"set",
+        summary: "List shell variables",
+        usage: "set",
+        options: Options::Spec(&[]),
+        handler: builtin::vars::set,
+        complete: None,
+    },
+    Command {
+        name: b"export",
+        summary: "Mark variables as environment variables",
+        usage: "export [-n] [NAME[=VALUE]]...",
+        options: Options::Spec(builtin::vars::EXPORT_OPTIONS),
+        handler: builtin::vars::export,
+        complete: Some(builtin::vars::complete),
+    },
+    Command {
+        name: b"unset",
+        summary: "Remove shell variables",
+        usage: "unset NAME...",
+        options: Options::Spec(&[]),
+        handler: builtin::vars::unset,
+        complete: Some(builtin::vars::complete),
+    },
 ];
 
+/// Looks up a command by name.
+pub fn find_command(name: &[u8]) -> Option<&'static Command> {
+    COMMANDS.iter().find(|entry| starts_with(name, entry.name) && name.len() == entry.name.len())
+}
+
 fn starts_with(haystack: &[u8], needle: &[u8]) -> bool {
     if haystack.len() < needle.len() {
         return false;
@@ -58,159 +519,65 @@ pub fn handle_command(input: &'static [u8]) {
         return;
     }
 
-    let argv = parse_input(input);
-    if argv.is_empty() {
-        return;
-    }
-
-    let cmd_name = argv[0];
-
-    for entry in COMMANDS.iter() {
-        if starts_with(cmd_name, entry.name) && cmd_name.len() == entry.name.len() {
-            (entry.handler)(argv);
+    let input = match history::expand(input) {
+        Expansion::Unchanged(line) => line,
+        Expansion::Expanded(line) => {
+            println!("{}", line);
+            line
+        }
+        Expansion::NotFound(event) => {
+            println!("{}: event not found", event);
             return;
         }
+    };
+    if !history::push(input) {
+        eprintln!("history: line too long, not saved");
     }
-    println!("Unknown command: {}", cmd_name);
-}
 
-fn help() {
-    println!("Available commands:");
-    let mut first = true;
-    for entry in COMMANDS.iter() {
-        if first {
-            print!(" ");
-            first = false;
-        } else {
-            print!(", ");
-        }
-        print!("{}", entry.name);
-    }
-    println!();
+    exec::run_line(input);
 }
 
-const MAX_ARGS: usize = 16;
-const PARSE_BUF_SIZE: usize = 80;
-
-static mut PARSE_BUF: [u8; PARSE_BUF_SIZE] = [0; PARSE_BUF_SIZE];
-static mut ARGV: [&'static [u8]; MAX_ARGS] = [&[]; MAX_ARGS];
+/// Parses options against the command's spec and runs its handler.
+pub fn run_command(argv: &[&[u8]]) -> ExitStatus {
+    let Some(&cmd_name) = argv.first() else {
+        return EXIT_SUCCESS;
+    };
 
-/// Parses input into argv-style array.
-/// Returns slice of argument slices where argv[0] is command, argv[1..] are args.
-fn parse_input(input: &[u8]) -> &'static [&'static [u8]] {
-    let buf_ptr = core::ptr::addr_of_mut!(PARSE_BUF);
-    let argv_ptr = core::ptr::addr_of_mut!(ARGV);
-    let buf = unsafe { &mut *buf_ptr };
-    let argv = unsafe { &mut *argv_ptr };
+    let Some(entry) = find_command(cmd_name) else {
+        eprintln!("Unknown command: {}", cmd_name);
+        return EXIT_NOT_FOUND;
+    };
 
-    let mut out: usize = 0; // Position in PARSE_BUF
-    let mut argc: usize = 0; // Number of arguments
-    let mut i: usize = 0;
-    let len = input.len();
-
-    // Skip leading spaces
-    while i < len && input[i] == b' ' {
-        i += 1;
-    }
-
-    // Parse tokens
-    while i < len && argc < MAX_ARGS {
-        if input[i] == b' ' {
-            // Skip consecutive spaces
-            while i < len && input[i] == b' ' {
-                i += 1;
-            }
-        } else {
-            // Parse one token
-            let start = out;
-            i = parse_token(input, i, buf, &mut out);
-
-            // Store this argument
-            argv[argc] = unsafe { (*buf_ptr).get_unchecked(start..out) };
-            argc += 1;
+    match getopt::parse(entry.options, argv) {
+        Ok(args) => (entry.handler)(&args),
+        Err(OptError::Help) => {
+            builtin::help::print_help(entry);
+            EXIT_SUCCESS
         }
-    }
-
-    unsafe { (*argv_ptr).get_unchecked(..argc) }
-}
-
-/// Parses a single token from `input[i..]` into `buf[*out..]`.
-/// Handles quotes (single/double) and escape sequences.
-/// Stops at the first unquoted space or end of input.
-/// Returns the new position in `input`.
-fn parse_token(
-    input: &[u8],
-    mut i: usize,
-    buf: &mut [u8; PARSE_BUF_SIZE],
-    out: &mut usize,
-) -> usize {
-    let len = input.len();
-
-    while i < len && input[i] != b' ' {
-        if input[i] == b'"' || input[i] == b'\'' {
-            let quote = input[i];
-            i += 1;
-            while i < len && input[i] != quote {
-                if input[i] == b'\\' && i + 1 < len {
-                    i += 1;
-                    let esc = escape_char(input[i]);
-                    if esc != 0xFF && *out < PARSE_BUF_SIZE {
-                        buf[*out] = esc;
-                        *out += 1;
-                    }
-                } else if *out < PARSE_BUF_SIZE {
-                    buf[*out] = input[i];
-                    *out += 1;
-                }
-                i += 1;
+        Err(err) => {
+            match err {
+                OptError::UnknownShort(c) => eprintln!("{}: invalid option -- '{}'", entry.name, c as char),
+                OptError::UnknownLong(name) => eprintln!("{}: unrecognized option '{}'", entry.name, name),
+                OptError::MissingValue(opt) => eprintln!("{}: option '{}' requires an argument", entry.name, opt.long),
+                OptError::Help => {}
             }
-            // Skip closing quote
-            if i < len && input[i] == quote {
-                i += 1;
-            }
-        } else if input[i] == b'\\' && i + 1 < len {
-            i += 1;
-            let esc = escape_char(input[i]);
-            if esc != 0xFF && *out < PARSE_BUF_SIZE {
-                buf[*out] = esc;
-                *out += 1;
-            }
-            i += 1;
-        } else {
-            if *out < PARSE_BUF_SIZE {
-                buf[*out] = input[i];
-                *out += 1;
-            }
-            i += 1;
+            eprintln!("Usage: {}", entry.usage);
+            EXIT_USAGE
         }
     }
-    i
 }
 
-fn escape_char(c: u8) -> u8 {
-    match c {
-        b'n' => b'\n',
-        b't' => b'\t',
-        b'r' => b'\r',
-        b'v' => 0x0B,
-        b'\\' => b'\\',
-        b'\'' => b'\'',
-        b'"' => b'"',
-        b'0' => 0,
-        _ => 0xFF,
-    }
-}
-
-fn tacos() {
+fn tacos(_: &Args) -> ExitStatus {
     static mut TACOS_COUNTER: u8 = 1;
     println!("You ate {} tacos!\0", unsafe { TACOS_COUNTER });
     unsafe {
         TACOS_COUNTER += 3;
     }
+    EXIT_SUCCESS
 }
 
 /// A test function to demonstrate the formatting capabilities of `println!`.
-fn format_test() {
+fn format_test(_: &Args) -> ExitStatus {
     println!("=== format test ===");
     println!("String: {}", "Hello from TacOS");
     println!("Integer: {}", 42);
@@ -224,16 +591,35 @@ fn format_test() {
     println!("Octal alt of {} : {:#o}", 42_u32, 42_u32);
     println!("Bool: {} {}", true, false);
     println!("Multi: {} + {} = {}", 1, 2, 3);
+    println!("Zero padded: {:08x} {:#010x}", 0xBEEF_u32, 0x800_u32);
+    println!("Aligned: [{:<6}] [{:>6}] [{:^6}]", "left", "right", "mid");
+    println!("Fill: [{:*^9}] [{:->5}]", "taco", 42_u32);
+    println!("Sign: {:+} {:+} {:+05}", 7_i32, -7_i32, 7_i32);
+    println!("Precision: {:.4}", "truncated");
+    println!("64-bit: {} {:#x} {}", u64::MAX, 1_u64 << 40, i64::MIN);
+    println!("Debug: {:?} {:?} {:?}", "tab\there", 'q', PrintArg::debug(&KeyEvent::CtrlC));
+    println!("Display: {}", &KeyEvent::CtrlC);
+    println!("Positional: {1} {0} {1:#x}", 1_u32, 255_u32);
+    println!("Named: {name:>6} is {age} ({0})", "ok", name = "taco", age = 3_u32);
+    let mut buf = [0u8; 8];
+    match kformat!(buf, "{}x{}", 80_u32, 25_u32) {
+        Ok(text) => println!("kformat: {}", text),
+        Err(t) => println!("kformat: truncated to {} ({} needed)", t.written, t.needed),
+    }
+    #[cfg(feature = "core-fmt")]
+    println!("core::fmt: {}", format_args!("{:?}", (Some(3), "x")));
     print!("No newline...");
     println!(" done!");
     println!("Literal braces: {{}}");
     print!("Two new lines...\n\nDone!\n");
     println!("=== end test ===");
     println!();
+    EXIT_SUCCESS
 }
 
 /// A test function to demonstrate the kernel stack trace functionality.
-fn stack_test() {
+/// Enough levels overflow the stack into its guard page.
+fn stack_test(args: &Args) -> ExitStatus {
     #[inline(never)]
     fn recursive(n: u32) {
         if n == 0 {
@@ -248,10 +634,25 @@ fn stack_test() {
             }
         }
     }
-    recursive(5);
+    let levels = match args.operands() {
+        [] => 5,
+        [arg] => match number::parse_u32(arg) {
+            Some(levels) => levels,
+            None => {
+                eprintln!("stack_test: invalid number of levels '{}'", *arg);
+                return EXIT_USAGE;
+            }
+        },
+        _ => {
+            eprintln!("stack_test: too many arguments");
+            return EXIT_USAGE;
+        }
+    };
+    recursive(levels);
+    EXIT_SUCCESS
 }
 
-fn shutdown() {
+fn shutdown(_: &Args) -> ExitStatus {
     outb(0xF4, 0x00);
     loop {
         unsafe {
@@ -260,14 +661,11 @@ fn shutdown() {
     }
 }
 
-fn reboot() {
+fn reboot(_: &Args) -> ExitStatus {
     println!("Rebooting...");
 
-    let mut status = crate::drivers::port::inb(0x64);
-    while status & 0x02 != 0 {
-       status = crate::drivers::port::inb(0x64);
-    }
-    outb(0x64, 0xFE);
+    // Pulse the CPU reset line through the 8042 controller
+    ps2::command(0xFE);
     loop {
         unsafe {
             asm!("hlt");
@@ -0,0 +1,312 @@
+//! Shell variables.
+//!
+//! A fixed table of `NAME=value` pairs. Names follow the usual shell
+//! rules (`[A-Za-z_][A-Za-z0-9_]*`). There are no child processes, so
+//! `export` only marks a variable as part of the environment listed by
+//! `export`; every variable is visible to every command.
+//!
+//! Also holds the positional parameters `$0`..`$9` of the running script
+//! or function, and the exit status `$?`.
+
+/// Number of variable slots.
+pub const MAX_VARS: usize = 32;
+const NAME_SIZE: usize = 16;
+const VALUE_SIZE: usize = 64;
+
+/// Why a variable could not be set.
+pub enum VarError {
+    /// The name is not a valid identifier.
+    InvalidName,
+    /// The name or value does not fit in a slot.
+    TooLong,
+    /// Every slot is in use.
+    Full,
+}
+
+struct Var {
+    name: [u8; NAME_SIZE],
+    name_len: usize,
+    value: [u8; VALUE_SIZE],
+    value_len: usize,
+    exported: bool,
+}
+
+impl Var {
+    const fn new() -> Self {
+        Var { name: [0; NAME_SIZE], name_len: 0, value: [0; VALUE_SIZE], value_len: 0, exported: false }
+    }
+
+    fn name(&self) -> &[u8] {
+        unsafe { self.name.get_unchecked(..self.name_len) }
+    }
+
+    fn value(&self) -> &[u8] {
+        unsafe { self.value.get_unchecked(..self.value_len) }
+    }
+}
+
+static mut VARS: [Var; MAX_VARS] = [const { Var::new() }; MAX_VARS];
+
+/// Number of positional parameters, `$0` included.
+pub const MAX_PARAMS: usize = 10;
+
+/// Positional parameters: `list[0]` is `$0`.
+#[derive(Copy, Clone)]
+pub struct Params {
+    list: [&'static [u8]; MAX_PARAMS],
+    count: usize,
+}
+
+impl Params {
+    /// Builds parameters from an argument vector, `argv[0]` becoming `$0`.
+    /// Arguments past `$9` are dropped.
+    pub fn from_argv(argv: &[&'static [u8]]) -> Self {
+        let mut params = Params { list: [&[]; MAX_PARAMS], count: 0 };
+        for &arg in argv.iter().take(MAX_PARAMS) {
+            unsafe { *params.list.get_unchecked_mut(params.count) = arg; }
+            params.count += 1;
+        }
+        params
+    }
+}
+
+static mut PARAMS: Params = Params { list: [b"tacos"; MAX_PARAMS], count: 1 };
+
+fn find(name: &[u8]) -> Option<usize> {
+    let vars = unsafe { &*core::ptr::addr_of!(VARS) };
+    vars.iter().position(|v| v.name_len > 0 && v.name() == name)
+}
+
+/// Returns `true` if `c` may start a variable name.
+pub fn is_name_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_'
+}
+
+/// Returns `true` if `c` may appear in a variable name.
+pub fn is_name_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+/// Returns `true` if `name` is a valid variable name.
+pub fn is_valid_name(name: &[u8]) -> bool {
+    match name.split_first() {
+        Some((&first, rest)) => is_name_start(first) && rest.iter().all(|&c| is_name_char(c)),
+        None => false,
+    }
+}
+
+/// Returns the value of a variable.
+pub fn get(name: &[u8]) -> Option<&'static [u8]> {
+    let idx = find(name)?;
+    Some(unsafe { (*core::ptr::addr_of!(VARS)).get_unchecked(idx).value() })
+}
+
+/// Sets a variable, creating it if needed.
+pub fn set(name: &[u8], value: &[u8]) -> Result<(), VarError> {
+    if !is_valid_name(name) {
+        return Err(VarError::InvalidName);
+    }
+    if name.len() > NAME_SIZE || value.len() > VALUE_SIZE {
+        return Err(VarError::TooLong);
+    }
+    let vars = unsafe { &mut *core::ptr::addr_of_mut!(VARS) };
+    let idx = match find(name) {
+        Some(idx) => idx,
+        None => {
+            let Some(idx) = vars.iter().position(|v| v.name_len == 0) else {
+                return Err(VarError::Full);
+            };
+            let var = unsafe { vars.get_unchecked_mut(idx) };
+            for (i, &c) in name.iter().enumerate() {
+                unsafe { *var.name.get_unchecked_mut(i) = c; }
+            }
+            var.name_len = name.len();
+            var.exported = false;
+            idx
+        }
+    };
+    let var = unsafe { vars.get_unchecked_mut(idx) };
+    for (i, &c) in value.iter().enumerate() {
+        unsafe { *var.value.get_unchecked_mut(i) = c; }
+    }
+    var.value_len = value.len();
+    Ok(())
+}
+
+/// Removes a variable. Returns `false` if it was not set.
+pub fn unset(name: &[u8]) -> bool {
+    let Some(idx) = find(name) else {
+        return false;
+    };
+    unsafe { (*core::ptr::addr_of_mut!(VARS)).get_unchecked_mut(idx).name_len = 0; }
+    true
+}
+
+/// Marks or unmarks a variable as exported. Returns `false` if it is not set.
+pub fn set_exported(name: &[u8], exported: bool) -> bool {
+    let Some(idx) = find(name) else {
+        return false;
+    };
+    unsafe { (*core::ptr::addr_of_mut!(VARS)).get_unchecked_mut(idx).exported = exported; }
+    true
+}
+
+/// Returns the name, value and export flag of the `idx`-th slot, if in use.
+pub fn at(idx: usize) -> Option<(&'static [u8], &'static [u8], bool)> {
+    let vars = unsafe { &*core::ptr::addr_of!(VARS) };
+    let var = vars.get(idx)?;
+    if var.name_len == 0 {
+        return None;
+    }
+    Some((var.name(), var.value(), var.exported))
+}
+
+/// Describes a `set` failure for error messages.
+pub fn error_message(err: VarError) -> &'static str {
+    match err {
+        VarError::InvalidName => "not a valid identifier",
+        VarError::TooLong => "name or value too long",
+        VarError::Full => "too many variables",
+    }
+}
+
+// ──────────────────────────────────────────────
+//  Positional parameters
+// ──────────────────────────────────────────────
+
+/// Returns the current positional parameters.
+pub fn params() -> Params {
+    unsafe { PARAMS }
+}
+
+/// Replaces the positional parameters, returning the previous ones.
+pub fn set_params(params: Params) -> Params {
+    unsafe { core::mem::replace(&mut *core::ptr::addr_of_mut!(PARAMS), params) }
+}
+
+/// Returns `$n`.
+pub fn param(n: usize) -> Option<&'static [u8]> {
+    let params = unsafe { &*core::ptr::addr_of!(PARAMS) };
+    if n < params.count { Some(unsafe { *params.list.get_unchecked(n) }) } else { None }
+}
+
+/// `$#`: number of parameters, `$0` excluded.
+pub fn param_count() -> usize {
+    unsafe { PARAMS.count.saturating_sub(1) }
+}
+
+// ──────────────────────────────────────────────
+//  Exit status
+// ──────────────────────────────────────────────
+
+static mut LAST_STATUS: u8 = 0;
+
+/// Exit status of the last command run (`$?`).
+pub fn last_status() -> u8 {
+    unsafe { LAST_STATUS }
+}
+
+/// Records the exit status of the pipeline that just ran.
+pub fn set_last_status(status: u8) {
+    unsafe { LAST_STATUS = status; }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    extern crate std;
+
+    use std::sync::{Mutex, MutexGuard};
+
+    /// The variables are global, and word expansion reads them: tests of
+    /// either take turns.
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    /// Locks the variables and starts from an empty table, no parameters
+    /// and `$?` at 0.
+    pub(crate) fn fresh() -> MutexGuard<'static, ()> {
+        let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        for idx in 0..MAX_VARS {
+            unsafe { (*core::ptr::addr_of_mut!(VARS)).get_unchecked_mut(idx).name_len = 0; }
+        }
+        set_params(Params::from_argv(&[b"tacos"]));
+        set_last_status(0);
+        guard
+    }
+
+    // ── names ──
+
+    #[test]
+    fn valid_names() {
+        assert!(is_valid_name(b"A"));
+        assert!(is_valid_name(b"_x1"));
+        assert!(!is_valid_name(b""));
+        assert!(!is_valid_name(b"1A"));
+        assert!(!is_valid_name(b"A-B"));
+    }
+
+    // ── set / get / unset ──
+
+    #[test]
+    fn set_replaces_the_value() {
+        let _guard = fresh();
+        assert!(set(b"A", b"1").is_ok());
+        assert!(set(b"A", b"22").is_ok());
+        assert_eq!(get(b"A"), Some(&b"22"[..]));
+        assert_eq!(get(b"B"), None);
+        assert!(unset(b"A"));
+        assert!(!unset(b"A"));
+        assert_eq!(get(b"A"), None);
+    }
+
+    #[test]
+    fn export_flag_is_kept_across_sets() {
+        let _guard = fresh();
+        assert!(!set_exported(b"A", true));
+        assert!(set(b"A", b"1").is_ok());
+        assert!(set_exported(b"A", true));
+        assert!(set(b"A", b"2").is_ok());
+        assert_eq!(at(0), Some((&b"A"[..], &b"2"[..], true)));
+    }
+
+    // ── limits ──
+
+    #[test]
+    fn invalid_or_long_names_and_values_are_rejected() {
+        let _guard = fresh();
+        assert!(matches!(set(b"1A", b""), Err(VarError::InvalidName)));
+        assert!(matches!(set(&[b'N'; NAME_SIZE + 1], b""), Err(VarError::TooLong)));
+        assert!(matches!(set(b"A", &[b'v'; VALUE_SIZE + 1]), Err(VarError::TooLong)));
+        assert!(set(&[b'N'; NAME_SIZE], &[b'v'; VALUE_SIZE]).is_ok());
+    }
+
+    #[test]
+    fn table_fills_up() {
+        let _guard = fresh();
+        for i in 0..MAX_VARS {
+            assert!(set(&[b'V', b'A' + (i / 26) as u8, b'A' + (i % 26) as u8], b"x").is_ok());
+        }
+        assert!(matches!(set(b"MORE", b"x"), Err(VarError::Full)));
+        // Existing variables can still change
+        assert!(set(b"VAA", b"y").is_ok());
+        assert!(unset(b"VAB"));
+        assert!(set(b"MORE", b"x").is_ok());
+    }
+
+    // ── positional parameters ──
+
+    #[test]
+    fn params_stop_at_nine() {
+        let _guard = fresh();
+        let argv: [&'static [u8]; 12] = [b"f", b"1", b"2", b"3", b"4", b"5", b"6", b"7", b"8", b"9", b"10", b"11"];
+        let saved = set_params(Params::from_argv(&argv));
+        assert_eq!(param_count(), 9);
+        assert_eq!(param(0), Some(&b"f"[..]));
+        assert_eq!(param(9), Some(&b"9"[..]));
+        assert_eq!(param(10), None);
+        set_params(saved);
+        assert_eq!(param_count(), 0);
+        assert_eq!(param(1), None);
+    }
+}